@@ -0,0 +1,585 @@
+//! Staged data editing for the results grid
+
+use crate::app::{error_chain, App, ResultsTab};
+use crate::db::{
+    quote_ident, CellValue, ColumnDef, ColumnInfo, DmlExecutor, DmlStatement, QueryExecutor,
+    SchemaExplorer,
+};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use tiberius::Client;
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio_util::compat::Compat;
+
+/// Result of a background edit-mode operation
+pub enum EditOutcome {
+    /// Table metadata loaded, edit mode can start
+    Started(Box<EditSession>),
+    /// Staged changes were committed
+    Applied(u64),
+}
+
+/// Staged cell edits, row inserts and deletes for a single-table result
+pub struct EditSession {
+    pub schema: String,
+    pub table: String,
+    /// Table column for each result column (`None` for expressions/aliases)
+    pub columns: Vec<Option<ColumnDef>>,
+    /// Result column indices that make up the primary key
+    pub key_columns: Vec<usize>,
+    /// Row count before any staged inserts; rows past this are new
+    pub original_row_count: usize,
+    /// Staged cell values keyed by (row, column)
+    pub edits: HashMap<(usize, usize), CellValue>,
+    /// Rows staged for deletion
+    pub deleted: HashSet<usize>,
+    /// Input buffer while a cell is being edited
+    pub input: Option<String>,
+    /// Generated statements while the preview is open
+    pub preview: Option<Vec<DmlStatement>>,
+    /// Preview scroll offset
+    pub preview_scroll: usize,
+    /// Esc was pressed once with pending changes
+    pub confirm_discard: bool,
+}
+
+impl EditSession {
+    /// Resolve the table behind a query and load its key columns
+    pub async fn load(
+        client: &mut Client<Compat<TcpStream>>,
+        query: &str,
+        result_columns: &[ColumnInfo],
+        row_count: usize,
+    ) -> Result<Self> {
+        let table_ref = single_table(query).ok_or_else(|| {
+            anyhow::anyhow!("Edit mode needs a single-table SELECT (no joins, unions or subqueries)")
+        })?;
+        let (schema, table) = QueryExecutor::parse_table_name(&table_ref);
+        let schema = schema.unwrap_or_else(|| "dbo".to_string());
+
+        let table_columns = SchemaExplorer::get_columns(client, &schema, &table).await?;
+        if table_columns.is_empty() {
+            anyhow::bail!("Table [{}].[{}] not found in the current database", schema, table);
+        }
+
+        let columns: Vec<Option<ColumnDef>> = result_columns
+            .iter()
+            .map(|rc| {
+                table_columns
+                    .iter()
+                    .find(|tc| tc.name.eq_ignore_ascii_case(&rc.name))
+                    .cloned()
+            })
+            .collect();
+
+        let pk_names: Vec<&str> = table_columns
+            .iter()
+            .filter(|c| c.is_primary_key)
+            .map(|c| c.name.as_str())
+            .collect();
+        if pk_names.is_empty() {
+            anyhow::bail!("Table [{}].[{}] has no primary key", schema, table);
+        }
+
+        let mut key_columns = Vec::new();
+        for pk in &pk_names {
+            match columns
+                .iter()
+                .position(|c| c.as_ref().map(|c| c.name.as_str()) == Some(*pk))
+            {
+                Some(idx) => key_columns.push(idx),
+                None => anyhow::bail!("Primary key column [{}] is not in the result", pk),
+            }
+        }
+
+        Ok(Self {
+            schema,
+            table,
+            columns,
+            key_columns,
+            original_row_count: row_count,
+            edits: HashMap::new(),
+            deleted: HashSet::new(),
+            input: None,
+            preview: None,
+            preview_scroll: 0,
+            confirm_discard: false,
+        })
+    }
+
+    /// Fully quoted table name
+    pub fn table_name(&self) -> String {
+        format!("{}.{}", quote_ident(&self.schema), quote_ident(&self.table))
+    }
+
+    pub fn is_inserted(&self, row: usize) -> bool {
+        row >= self.original_row_count
+    }
+
+    pub fn is_deleted(&self, row: usize) -> bool {
+        self.deleted.contains(&row)
+    }
+
+    pub fn is_edited(&self, row: usize, col: usize) -> bool {
+        self.edits.contains_key(&(row, col))
+    }
+
+    /// Number of staged changes (edited cells, inserted and deleted rows)
+    pub fn change_count(&self, total_rows: usize) -> usize {
+        let inserted = total_rows.saturating_sub(self.original_row_count);
+        let edited = self
+            .edits
+            .keys()
+            .filter(|(row, _)| !self.is_inserted(*row))
+            .count();
+        let deleted = self.deleted.iter().filter(|r| !self.is_inserted(**r)).count();
+        edited + inserted + deleted
+    }
+
+    /// Check whether a result column can be written to
+    pub fn editable_column(&self, col: usize) -> std::result::Result<&ColumnDef, String> {
+        match self.columns.get(col) {
+            Some(Some(def)) if def.is_identity => Err(format!("[{}] is an identity column", def.name)),
+            Some(Some(def)) if def.is_computed => Err(format!("[{}] is a computed column", def.name)),
+            Some(Some(def)) => Ok(def),
+            _ => Err("Column is not part of the table".to_string()),
+        }
+    }
+
+    /// Stage a new value for a cell from user input
+    pub fn stage_cell(
+        &mut self,
+        rows: &[Vec<CellValue>],
+        row: usize,
+        col: usize,
+        text: &str,
+    ) -> std::result::Result<(), String> {
+        let def = self.editable_column(col)?;
+        let value = parse_cell_input(text, def)?;
+
+        let original = rows.get(row).and_then(|r| r.get(col));
+        let unchanged = original
+            .map(|o| !self.is_inserted(row) && o.to_string() == value.to_string())
+            .unwrap_or(false);
+
+        if unchanged {
+            self.edits.remove(&(row, col));
+        } else {
+            self.edits.insert((row, col), value);
+        }
+        Ok(())
+    }
+
+    /// Toggle deletion of a row
+    pub fn toggle_delete(&mut self, row: usize) {
+        if !self.deleted.remove(&row) {
+            self.deleted.insert(row);
+        }
+    }
+
+    /// Drop staged changes for a single row
+    pub fn revert_row(&mut self, row: usize) {
+        self.edits.retain(|(r, _), _| *r != row);
+        self.deleted.remove(&row);
+    }
+
+    /// Value to display for a cell, taking staged edits into account
+    pub fn display_value<'a>(&'a self, row: usize, col: usize, original: &'a CellValue) -> &'a CellValue {
+        self.edits.get(&(row, col)).unwrap_or(original)
+    }
+
+    /// Generate the parameterized DML batch for all staged changes
+    pub fn build_statements(&self, rows: &[Vec<CellValue>]) -> Vec<DmlStatement> {
+        let table = self.table_name();
+        let mut statements = Vec::new();
+
+        // Deletes first so key changes on other rows cannot collide with them
+        let mut deleted: Vec<usize> = self
+            .deleted
+            .iter()
+            .copied()
+            .filter(|r| !self.is_inserted(*r))
+            .collect();
+        deleted.sort_unstable();
+
+        for row in deleted {
+            let mut params = Vec::new();
+            let predicate = self.key_predicate(rows, row, &mut params);
+            statements.push(DmlStatement {
+                sql: format!("DELETE FROM {} WHERE {};", table, predicate),
+                params,
+                expect_single_row: true,
+            });
+        }
+
+        for row in 0..self.original_row_count {
+            if self.is_deleted(row) {
+                continue;
+            }
+
+            let mut cols: Vec<usize> = self
+                .edits
+                .keys()
+                .filter(|(r, _)| *r == row)
+                .map(|(_, c)| *c)
+                .collect();
+            if cols.is_empty() {
+                continue;
+            }
+            cols.sort_unstable();
+
+            let mut params = Vec::new();
+            let assignments: Vec<String> = cols
+                .iter()
+                .map(|c| {
+                    let name = self.column_name(*c);
+                    format!("{} = {}", name, push_param(&mut params, &self.edits[&(row, *c)]))
+                })
+                .collect();
+            let predicate = self.key_predicate(rows, row, &mut params);
+
+            statements.push(DmlStatement {
+                sql: format!("UPDATE {} SET {} WHERE {};", table, assignments.join(", "), predicate),
+                params,
+                expect_single_row: true,
+            });
+        }
+
+        for row in self.original_row_count..rows.len() {
+            if self.is_deleted(row) {
+                continue;
+            }
+
+            let mut cols: Vec<usize> = self
+                .edits
+                .keys()
+                .filter(|(r, _)| *r == row)
+                .map(|(_, c)| *c)
+                .collect();
+            cols.sort_unstable();
+
+            let sql_prefix = format!("INSERT INTO {}", table);
+            if cols.is_empty() {
+                statements.push(DmlStatement {
+                    sql: format!("{} DEFAULT VALUES;", sql_prefix),
+                    params: Vec::new(),
+                    expect_single_row: true,
+                });
+                continue;
+            }
+
+            let mut params = Vec::new();
+            let names: Vec<String> = cols.iter().map(|c| self.column_name(*c)).collect();
+            let values: Vec<String> = cols
+                .iter()
+                .map(|c| push_param(&mut params, &self.edits[&(row, *c)]))
+                .collect();
+
+            statements.push(DmlStatement {
+                sql: format!("{} ({}) VALUES ({});", sql_prefix, names.join(", "), values.join(", ")),
+                params,
+                expect_single_row: true,
+            });
+        }
+
+        statements
+    }
+
+    fn column_name(&self, col: usize) -> String {
+        self.columns
+            .get(col)
+            .and_then(|c| c.as_ref())
+            .map(|c| quote_ident(&c.name))
+            .unwrap_or_default()
+    }
+
+    /// Build `[pk1] = @Pn AND [pk2] = @Pm` from the row's original key values
+    fn key_predicate(&self, rows: &[Vec<CellValue>], row: usize, params: &mut Vec<CellValue>) -> String {
+        self.key_columns
+            .iter()
+            .map(|c| {
+                let value = rows
+                    .get(row)
+                    .and_then(|r| r.get(*c))
+                    .cloned()
+                    .unwrap_or(CellValue::Null);
+                format!("{} = {}", self.column_name(*c), push_param(params, &value))
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    }
+}
+
+/// Add a parameter and return its placeholder; NULL is inlined so it never needs a type
+fn push_param(params: &mut Vec<CellValue>, value: &CellValue) -> String {
+    if matches!(value, CellValue::Null) {
+        return "NULL".to_string();
+    }
+    params.push(value.clone());
+    format!("@P{}", params.len())
+}
+
+/// Parse user input into a cell value matching the column's SQL type
+fn parse_cell_input(text: &str, def: &ColumnDef) -> std::result::Result<CellValue, String> {
+    if text.eq_ignore_ascii_case("null") {
+        if !def.is_nullable {
+            return Err(format!("[{}] does not allow NULL", def.name));
+        }
+        return Ok(CellValue::Null);
+    }
+
+    let trimmed = text.trim();
+    match def.data_type.to_lowercase().as_str() {
+        "tinyint" | "smallint" | "int" | "bigint" => trimmed
+            .parse::<i64>()
+            .map(CellValue::Int)
+            .map_err(|_| format!("'{}' is not a valid integer", trimmed)),
+        "float" | "real" => trimmed
+            .parse::<f64>()
+            .map(CellValue::Float)
+            .map_err(|_| format!("'{}' is not a valid number", trimmed)),
+        "bit" => match trimmed.to_lowercase().as_str() {
+            "1" | "true" => Ok(CellValue::Bool(true)),
+            "0" | "false" => Ok(CellValue::Bool(false)),
+            _ => Err(format!("'{}' is not a valid bit (use 1/0)", trimmed)),
+        },
+        "binary" | "varbinary" | "image" => {
+            let hex = trimmed.trim_start_matches("0x").trim_start_matches("0X");
+            if !hex.len().is_multiple_of(2) {
+                return Err("Binary values need an even number of hex digits".to_string());
+            }
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                .collect::<std::result::Result<Vec<u8>, _>>()
+                .map(CellValue::Binary)
+                .map_err(|_| format!("'{}' is not valid hex", trimmed))
+        }
+        "date" | "datetime" | "datetime2" | "smalldatetime" | "time" | "datetimeoffset" => {
+            Ok(CellValue::DateTime(trimmed.to_string()))
+        }
+        _ => Ok(CellValue::String(text.to_string())),
+    }
+}
+
+/// Table of a query that reads exactly one table, as written after FROM; `None` for
+/// joins, unions, subqueries and old-style comma joins
+fn single_table(query: &str) -> Option<String> {
+    let tokens = sql_tokens(query);
+    let upper: Vec<String> = tokens.iter().map(|t| t.to_uppercase()).collect();
+    if upper.first().map(String::as_str) != Some("SELECT") {
+        return None;
+    }
+    let multi_table = ["JOIN", "UNION", "INTERSECT", "EXCEPT", "APPLY", "PIVOT", "UNPIVOT"];
+    if upper.iter().any(|t| multi_table.contains(&t.as_str())) || upper.iter().filter(|t| *t == "SELECT").count() > 1 {
+        return None;
+    }
+    let from = upper.iter().position(|t| t == "FROM")?;
+    let table = tokens.get(from + 1)?;
+    let is_name = table.starts_with(|c: char| c.is_alphanumeric() || matches!(c, '_' | '#' | '[' | '"'));
+    // A comma in the FROM clause, outside table hints, means an old-style join
+    let mut depth = 0usize;
+    for token in &upper[from + 2..] {
+        match token.as_str() {
+            "(" => depth += 1,
+            ")" => depth = depth.saturating_sub(1),
+            "," if depth == 0 => return None,
+            "WHERE" | "GROUP" | "ORDER" | "HAVING" | "OPTION" | "FOR" => break,
+            _ => {}
+        }
+    }
+    is_name.then(|| table.clone())
+}
+
+/// Words, quoted and dotted names (`[dbo].[My Table]` is one token) and single punctuation
+/// characters; comments are dropped and string literals become `'`
+fn sql_tokens(query: &str) -> Vec<String> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '-' && next == Some('-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '\'' {
+            i = skip_quoted(&chars, i, '\'');
+            tokens.push("'".to_string());
+        } else if c.is_alphanumeric() || matches!(c, '_' | '@' | '#' | '[' | '"') {
+            let start = i;
+            while i < chars.len() {
+                match chars[i] {
+                    '[' => i = skip_quoted(&chars, i, ']'),
+                    '"' => i = skip_quoted(&chars, i, '"'),
+                    c if c.is_alphanumeric() || matches!(c, '_' | '@' | '#' | '$' | '.') => i += 1,
+                    _ => break,
+                }
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else {
+            tokens.push(c.to_string());
+            i += 1;
+        }
+    }
+    tokens
+}
+
+/// Index just past the quoted text opening at `start`; a doubled `close` is part of the text
+fn skip_quoted(chars: &[char], start: usize, close: char) -> usize {
+    let mut i = start + 1;
+    while i < chars.len() {
+        if chars[i] == close {
+            if chars.get(i + 1) == Some(&close) {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    i
+}
+
+impl App {
+    /// Enter edit mode for the current result (loads table metadata in the background)
+    pub fn start_edit_mode(&mut self) {
+        if self.is_loading || self.edit_session.is_some() {
+            return;
+        }
+
+        let query = match &self.result_query {
+            Some(q) if !self.result.columns.is_empty() => q.clone(),
+            _ => {
+                self.error = Some("Run a single-table SELECT first".to_string());
+                return;
+            }
+        };
+
+        self.is_loading = true;
+        self.error = None;
+        self.spinner_frame = 0;
+        self.results_tab = ResultsTab::Data;
+
+        let (tx, rx) = oneshot::channel();
+        let client_arc = self.db.client();
+        let columns = self.result.columns.clone();
+        let row_count = self.result.rows.len();
+        self.pending_edit = Some(rx);
+
+        tokio::spawn(async move {
+            let mut client = client_arc.lock().await;
+            let result = EditSession::load(&mut client, &query, &columns, row_count).await;
+            let _ = tx.send(result.map(|session| EditOutcome::Started(Box::new(session))).map_err(|e| error_chain(&e)));
+        });
+    }
+
+    /// Run the previewed batch in a transaction
+    pub fn apply_edits(&mut self) {
+        let statements = match self.edit_session.as_ref().and_then(|s| s.preview.clone()) {
+            Some(statements) if !statements.is_empty() => statements,
+            _ => return,
+        };
+
+        self.is_loading = true;
+        self.error = None;
+        self.spinner_frame = 0;
+
+        let (tx, rx) = oneshot::channel();
+        let client_arc = self.db.client();
+        self.pending_edit = Some(rx);
+
+        tokio::spawn(async move {
+            let mut client = client_arc.lock().await;
+            let result = DmlExecutor::execute_batch(&mut client, &statements).await;
+            let _ = tx.send(result.map(EditOutcome::Applied).map_err(|e| error_chain(&e)));
+        });
+    }
+
+    /// Leave edit mode, dropping any staged inserts from the grid
+    pub fn discard_edits(&mut self) {
+        if let Some(session) = self.edit_session.take() {
+            self.result.rows.truncate(session.original_row_count);
+            self.result.row_count = self.result.rows.len();
+            self.refresh_result_view();
+        }
+    }
+
+    /// Check whether a background edit operation has finished
+    pub fn check_edit_completion(&mut self) {
+        let Some(rx) = self.pending_edit.as_mut() else {
+            return;
+        };
+
+        let outcome = match rx.try_recv() {
+            Ok(outcome) => outcome,
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => {
+                Err("Edit operation was interrupted".to_string())
+            }
+        };
+
+        self.is_loading = false;
+        self.pending_edit = None;
+
+        match outcome {
+            Ok(EditOutcome::Started(session)) => {
+                self.message = Some(format!(
+                    "Editing {} | Enter:Edit cell  o:Insert  d:Delete  u:Revert  p:Preview  Esc:Exit",
+                    session.table_name()
+                ));
+                self.edit_session = Some(*session);
+            }
+            Ok(EditOutcome::Applied(affected)) => {
+                self.discard_edits();
+                self.message = Some(format!("✓ Committed, {} row(s) affected", affected));
+                if let Some(query) = self.result_query.clone() {
+                    self.run_query(query);
+                }
+            }
+            Err(error_msg) => {
+                if let Some(session) = self.edit_session.as_mut() {
+                    session.preview = None;
+                }
+                self.error = Some(error_msg);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_table_finds_the_table_across_lines() {
+        assert_eq!(single_table("SELECT *\nFROM dbo.T").as_deref(), Some("dbo.T"));
+        assert_eq!(single_table("select id,\n\tname\nfrom [dbo].[My Table] t\nwhere id = 1").as_deref(), Some("[dbo].[My Table]"));
+        assert_eq!(single_table("SELECT SELECTED_FLAG FROM Accounts WITH (NOLOCK)").as_deref(), Some("Accounts"));
+        assert_eq!(single_table("SELECT 'a JOIN b' AS note -- UNION\nFROM T").as_deref(), Some("T"));
+    }
+
+    #[test]
+    fn single_table_rejects_more_than_one_table() {
+        assert_eq!(single_table("SELECT * FROM dbo.A a\nJOIN dbo.B b ON a.id = b.id"), None);
+        assert_eq!(single_table("SELECT * FROM A\nUNION ALL\nSELECT * FROM B"), None);
+        assert_eq!(single_table("SELECT * FROM A WHERE id IN (SELECT id FROM B)"), None);
+        assert_eq!(single_table("SELECT * FROM (SELECT 1 AS x) s"), None);
+        assert_eq!(single_table("SELECT * FROM A, B"), None);
+        assert_eq!(single_table("SELECT * FROM A AS a, B b"), None);
+        assert_eq!(single_table("SELECT * FROM A WITH (NOLOCK, INDEX(1)) WHERE id IN (1, 2)").as_deref(), Some("A"));
+        assert_eq!(single_table("SELECT * FROM A CROSS APPLY f(A.id)"), None);
+        assert_eq!(single_table("UPDATE A SET x = 1"), None);
+        assert_eq!(single_table("SELECT 1"), None);
+    }
+}
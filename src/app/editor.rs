@@ -0,0 +1,600 @@
+//! Text buffer behind the query editor: cursor, selection and undo/redo
+
+use crate::utils::{display_width, grapheme_width, visual_col};
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Spaces inserted for one level of indentation
+pub const INDENT: &str = "    ";
+
+/// Maximum number of undo steps kept
+const UNDO_LIMIT: usize = 500;
+
+/// What the last edit was, so runs of typing undo as one step
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EditKind {
+    Typing,
+    Backspace,
+    Delete,
+    Other,
+}
+
+/// How the anchor and cursor map to the selected text
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectionMode {
+    /// Between anchor and cursor (Shift+arrows)
+    #[default]
+    Exclusive,
+    /// Also covers the grapheme under the far end (vim visual mode)
+    Inclusive,
+    /// Whole lines (vim visual line mode)
+    Line,
+}
+
+/// Text and cursor before an edit
+#[derive(Clone, Debug)]
+struct Snapshot {
+    text: String,
+    cursor: usize,
+}
+
+/// Multi-line editor buffer
+///
+/// Positions are byte offsets into the text and always sit on a grapheme
+/// boundary. Columns are terminal display columns, so wide CJK characters count
+/// as two and combining marks as zero.
+#[derive(Clone, Debug, Default)]
+pub struct EditorBuffer {
+    text: String,
+    cursor: usize,
+    /// Other end of the selection; the selection runs between it and the cursor
+    anchor: Option<usize>,
+    /// Column to return to when moving vertically through shorter lines
+    goal_col: Option<usize>,
+    selection_mode: SelectionMode,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    last_edit: Option<EditKind>,
+    /// Open undo group; true once its snapshot has been taken
+    group: Option<bool>,
+}
+
+impl EditorBuffer {
+    /// Buffer with the cursor at the end of `text`
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self {
+            cursor: text.len(),
+            text,
+            ..Self::default()
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Cursor line and display column, in logical (typing) order
+    pub fn line_col(&self) -> (usize, usize) {
+        self.position_line_col(self.cursor)
+    }
+
+    /// Cursor line and on-screen column once right-to-left runs are reordered
+    pub fn visual_line_col(&self) -> (usize, usize) {
+        let start = self.line_start(self.cursor);
+        let line = &self.text[start..self.line_end(self.cursor)];
+        (self.line_col().0, visual_col(line, self.cursor - start))
+    }
+
+    fn position_line_col(&self, pos: usize) -> (usize, usize) {
+        let before = &self.text[..pos];
+        let line = before.matches('\n').count();
+        let col = display_width(&before[self.line_start(pos)..]);
+        (line, col)
+    }
+
+    // ----- Selection -----
+
+    pub fn has_selection(&self) -> bool {
+        self.selection().is_some()
+    }
+
+    /// Selected byte range, if any
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        let (lo, hi) = (anchor.min(self.cursor), anchor.max(self.cursor));
+        match self.selection_mode {
+            SelectionMode::Exclusive if lo == hi => None,
+            SelectionMode::Exclusive => Some(lo..hi),
+            SelectionMode::Inclusive => Some(lo..self.next_boundary(hi).max(lo)),
+            SelectionMode::Line => Some(self.line_start(lo)..self.line_end(hi)),
+        }
+    }
+
+    pub fn anchor(&self) -> Option<usize> {
+        self.anchor
+    }
+
+    /// Set or drop the selection anchor without moving the cursor
+    pub fn set_anchor(&mut self, anchor: Option<usize>) {
+        self.anchor = anchor.map(|a| a.min(self.text.len()));
+    }
+
+    pub fn set_selection_mode(&mut self, mode: SelectionMode) {
+        self.selection_mode = mode;
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selection().map(|r| &self.text[r])
+    }
+
+    /// Selected display columns on a given line, in logical order
+    pub fn selection_on_line(&self, line: usize) -> Option<(usize, usize)> {
+        let range = self.selection()?;
+        let (start_line, start_col) = self.position_line_col(range.start);
+        let (end_line, end_col) = self.position_line_col(range.end);
+        if line < start_line || line > end_line {
+            return None;
+        }
+
+        let line_len = self.text.split('\n').nth(line).map(display_width).unwrap_or(0);
+        let from = if line == start_line { start_col } else { 0 };
+        // Lines crossed by the selection show one extra cell for the newline
+        let to = if line == end_line { end_col } else { line_len + 1 };
+        Some((from, to))
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.text.len();
+        self.goal_col = None;
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.anchor = None;
+    }
+
+    // ----- Movement -----
+
+    /// Place the cursor, leaving any selection anchor in place
+    pub fn set_cursor(&mut self, pos: usize) {
+        self.cursor = pos.min(self.text.len());
+        self.goal_col = None;
+        self.last_edit = None;
+    }
+
+    /// Move the cursor, extending the selection when `select` is set
+    fn move_to(&mut self, pos: usize, select: bool) {
+        if select {
+            if self.anchor.is_none() {
+                self.anchor = Some(self.cursor);
+            }
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = pos.min(self.text.len());
+        self.last_edit = None;
+    }
+
+    pub fn move_left(&mut self, select: bool) {
+        // Collapse an existing selection to its start
+        let pos = match self.selection() {
+            Some(range) if !select => range.start,
+            _ => self.prev_boundary(self.cursor),
+        };
+        self.move_to(pos, select);
+        self.goal_col = None;
+    }
+
+    pub fn move_right(&mut self, select: bool) {
+        let pos = match self.selection() {
+            Some(range) if !select => range.end,
+            _ => self.next_boundary(self.cursor),
+        };
+        self.move_to(pos, select);
+        self.goal_col = None;
+    }
+
+    pub fn move_word_left(&mut self, select: bool) {
+        let pos = self.word_backward(self.cursor, false);
+        self.move_to(pos, select);
+        self.goal_col = None;
+    }
+
+    pub fn move_word_right(&mut self, select: bool) {
+        let pos = self.word_forward(self.cursor, false);
+        self.move_to(pos, select);
+        self.goal_col = None;
+    }
+
+    pub fn move_up(&mut self, select: bool) {
+        let (line, col) = self.line_col();
+        let goal = *self.goal_col.get_or_insert(col);
+        let pos = if line == 0 { 0 } else { self.position_at(line - 1, goal) };
+        self.move_to(pos, select);
+    }
+
+    pub fn move_down(&mut self, select: bool) {
+        let (line, col) = self.line_col();
+        let goal = *self.goal_col.get_or_insert(col);
+        let pos = if line + 1 >= self.line_count() {
+            self.text.len()
+        } else {
+            self.position_at(line + 1, goal)
+        };
+        self.move_to(pos, select);
+    }
+
+    /// Home: first non-blank char, or the line start if already there
+    pub fn move_line_start(&mut self, select: bool) {
+        let start = self.line_start(self.cursor);
+        let indent_end = start
+            + self.text[start..self.line_end(self.cursor)]
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(self.line_end(self.cursor) - start);
+        let pos = if self.cursor == indent_end { start } else { indent_end };
+        self.move_to(pos, select);
+        self.goal_col = None;
+    }
+
+    pub fn move_line_end(&mut self, select: bool) {
+        let pos = self.line_end(self.cursor);
+        self.move_to(pos, select);
+        self.goal_col = None;
+    }
+
+    pub fn move_doc_start(&mut self, select: bool) {
+        self.move_to(0, select);
+        self.goal_col = None;
+    }
+
+    pub fn move_doc_end(&mut self, select: bool) {
+        self.move_to(self.text.len(), select);
+        self.goal_col = None;
+    }
+
+    // ----- Editing -----
+
+    /// Type a character, replacing any selection
+    pub fn insert_char(&mut self, c: char) {
+        let range = self.selection().unwrap_or(self.cursor..self.cursor);
+        let kind = if range.is_empty() && !c.is_whitespace() { EditKind::Typing } else { EditKind::Other };
+        self.replace(range, &c.to_string(), kind);
+    }
+
+    /// Insert text (paste, schema names), replacing any selection
+    pub fn insert_str(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let range = self.selection().unwrap_or(self.cursor..self.cursor);
+        self.replace(range, &text, EditKind::Other);
+    }
+
+    /// New line that keeps the current indentation, one level deeper after '('
+    pub fn insert_newline(&mut self) {
+        let start = self.line_start(self.cursor);
+        let before = &self.text[start..self.cursor];
+        let mut indent: String = before.chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+        if before.trim_end().ends_with('(') {
+            indent.push_str(INDENT);
+        }
+        self.insert_str(&format!("\n{}", indent));
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(range) = self.selection() {
+            self.replace(range, "", EditKind::Other);
+            return;
+        }
+        if self.cursor == 0 {
+            return;
+        }
+
+        // Inside leading whitespace, remove back to the previous indent stop
+        let start = self.line_start(self.cursor);
+        let before = &self.text[start..self.cursor];
+        let from = if !before.is_empty() && before.chars().all(|c| c == ' ') {
+            let width = before.len();
+            let stop = (width - 1) / INDENT.len() * INDENT.len();
+            start + stop
+        } else {
+            self.prev_boundary(self.cursor)
+        };
+        self.replace(from..self.cursor, "", EditKind::Backspace);
+    }
+
+    pub fn delete(&mut self) {
+        if let Some(range) = self.selection() {
+            self.replace(range, "", EditKind::Other);
+        } else if self.cursor < self.text.len() {
+            let to = self.next_boundary(self.cursor);
+            let cursor = self.cursor;
+            self.replace(cursor..to, "", EditKind::Delete);
+        }
+    }
+
+    pub fn delete_word_back(&mut self) {
+        let range = self.selection().unwrap_or(self.word_backward(self.cursor, false)..self.cursor);
+        self.replace(range, "", EditKind::Other);
+    }
+
+    pub fn delete_word_forward(&mut self) {
+        let range = self.selection().unwrap_or(self.cursor..self.word_forward(self.cursor, false));
+        self.replace(range, "", EditKind::Other);
+    }
+
+    /// Remove the current line, or every line the selection touches
+    pub fn delete_lines(&mut self) {
+        let lines = self.selected_lines();
+        let mut range = lines.clone();
+        if range.end < self.text.len() {
+            range.end += 1; // trailing newline
+        } else if range.start > 0 {
+            range.start -= 1; // last line: take the newline before it
+        }
+        let (_, col) = self.line_col();
+        self.replace(range, "", EditKind::Other);
+        let line = self.line_col().0;
+        self.cursor = self.position_at(line, col);
+    }
+
+    /// Copy the current line (or selected lines) below itself
+    pub fn duplicate_lines(&mut self) {
+        let lines = self.selected_lines();
+        let block = self.text[lines.clone()].to_string();
+        let offset = self.cursor - lines.start;
+        self.replace(lines.end..lines.end, &format!("\n{}", block), EditKind::Other);
+        self.cursor = lines.end + 1 + offset.min(block.len());
+    }
+
+    /// Indent every line touched by the selection
+    pub fn indent_lines(&mut self) {
+        let lines = self.selected_lines();
+        let block = self.text[lines.clone()]
+            .split('\n')
+            .map(|l| format!("{}{}", INDENT, l))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.replace_keeping_selection(lines, &block);
+    }
+
+    /// Remove one indent level from every line touched by the selection
+    pub fn outdent_lines(&mut self) {
+        let lines = self.selected_lines();
+        let block = self.text[lines.clone()]
+            .split('\n')
+            .map(|l| {
+                let strip = l.chars().take(INDENT.len()).take_while(|c| *c == ' ').count();
+                l[strip..].to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.replace_keeping_selection(lines, &block);
+    }
+
+    /// Remove and return the selection
+    pub fn cut(&mut self) -> Option<String> {
+        let range = self.selection()?;
+        let text = self.text[range.clone()].to_string();
+        self.replace(range, "", EditKind::Other);
+        Some(text)
+    }
+
+    /// Replace the whole buffer as one undoable step
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        let len = self.text.len();
+        self.replace(0..len, &text, EditKind::Other);
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text(String::new());
+    }
+
+    /// Replace a byte range as one undoable edit
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) {
+        self.replace(range, text, EditKind::Other);
+    }
+
+    // ----- Undo / redo -----
+
+    /// Collect the following edits into a single undo step until `end_undo_group`
+    pub fn begin_undo_group(&mut self) {
+        self.group = Some(false);
+    }
+
+    pub fn end_undo_group(&mut self) {
+        self.group = None;
+        self.last_edit = None;
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(snapshot) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot { text: self.text.clone(), cursor: self.cursor }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.text = snapshot.text;
+        self.cursor = snapshot.cursor.min(self.text.len());
+        self.anchor = None;
+        self.goal_col = None;
+        self.last_edit = None;
+    }
+
+    /// Single entry point for every text change
+    fn replace(&mut self, range: Range<usize>, text: &str, kind: EditKind) {
+        if range.is_empty() && text.is_empty() {
+            return;
+        }
+
+        // Consecutive typing/deleting of the same kind undoes as one step
+        let coalesce = match self.group {
+            Some(taken) => {
+                self.group = Some(true);
+                taken
+            }
+            None => kind != EditKind::Other && self.last_edit == Some(kind),
+        };
+        if !coalesce {
+            self.undo.push(self.snapshot());
+            if self.undo.len() > UNDO_LIMIT {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+
+        self.text.replace_range(range.clone(), text);
+        self.cursor = range.start + text.len();
+        self.anchor = None;
+        self.goal_col = None;
+        self.last_edit = Some(kind);
+    }
+
+    /// Replace whole lines and select the result
+    fn replace_keeping_selection(&mut self, lines: Range<usize>, block: &str) {
+        self.replace(lines.clone(), block, EditKind::Other);
+        self.anchor = Some(lines.start);
+        self.cursor = lines.start + block.len();
+    }
+
+    // ----- Position helpers -----
+
+    pub fn line_count(&self) -> usize {
+        self.text.matches('\n').count() + 1
+    }
+
+    /// Start of the line containing `pos`
+    pub fn line_start(&self, pos: usize) -> usize {
+        self.text[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0)
+    }
+
+    /// End of the line containing `pos` (the newline, or the end of the text)
+    pub fn line_end(&self, pos: usize) -> usize {
+        self.text[pos..].find('\n').map(|i| pos + i).unwrap_or(self.text.len())
+    }
+
+    /// Byte range of the full lines covered by the cursor or selection (no trailing newline)
+    fn selected_lines(&self) -> Range<usize> {
+        let range = self.selection().unwrap_or(self.cursor..self.cursor);
+        // A selection ending at a line start does not include that line
+        let end = if range.end > range.start && self.line_start(range.end) == range.end {
+            range.end - 1
+        } else {
+            range.end
+        };
+        self.line_start(range.start)..self.line_end(end)
+    }
+
+    /// Byte offset of a line/display column pair, clamped to the line length
+    ///
+    /// A column inside a wide character lands on that character's start.
+    pub fn position_at(&self, line: usize, col: usize) -> usize {
+        let start = match line {
+            0 => 0,
+            _ => self
+                .text
+                .match_indices('\n')
+                .nth(line - 1)
+                .map(|(i, _)| i + 1)
+                .unwrap_or(self.text.len()),
+        };
+        let end = self.line_end(start);
+        let mut width = 0;
+        for (i, g) in self.text[start..end].grapheme_indices(true) {
+            width += grapheme_width(g);
+            if width > col {
+                return start + i;
+            }
+        }
+        end
+    }
+
+    pub fn prev_boundary(&self, pos: usize) -> usize {
+        self.text[..pos].graphemes(true).next_back().map(|g| pos - g.len()).unwrap_or(0)
+    }
+
+    pub fn next_boundary(&self, pos: usize) -> usize {
+        self.text[pos..].graphemes(true).next().map(|g| pos + g.len()).unwrap_or(pos)
+    }
+
+    /// Start of the word before `pos`; `big` treats any non-blank run as one word
+    pub fn word_backward(&self, pos: usize, big: bool) -> usize {
+        let mut graphemes = self.text[..pos].grapheme_indices(true).rev().peekable();
+        let mut result = pos;
+        // Skip whitespace, then a run of the same character class
+        while let Some(&(i, g)) = graphemes.peek() {
+            if grapheme_class(g, big) != 0 {
+                break;
+            }
+            result = i;
+            graphemes.next();
+        }
+        let class = graphemes.peek().map(|&(_, g)| grapheme_class(g, big));
+        while let Some(&(i, g)) = graphemes.peek() {
+            if Some(grapheme_class(g, big)) != class {
+                break;
+            }
+            result = i;
+            graphemes.next();
+        }
+        result
+    }
+
+    /// Start of the next word after `pos` (end of the current word plus blanks)
+    pub fn word_forward(&self, pos: usize, big: bool) -> usize {
+        let mut graphemes = self.text[pos..].grapheme_indices(true).peekable();
+        let mut result = pos;
+        let class = graphemes.peek().map(|&(_, g)| grapheme_class(g, big)).filter(|&k| k != 0);
+        if class.is_some() {
+            while let Some(&(i, g)) = graphemes.peek() {
+                if Some(grapheme_class(g, big)) != class {
+                    break;
+                }
+                result = pos + i + g.len();
+                graphemes.next();
+            }
+        }
+        while let Some(&(i, g)) = graphemes.peek() {
+            if grapheme_class(g, big) != 0 {
+                break;
+            }
+            result = pos + i + g.len();
+            graphemes.next();
+        }
+        result
+    }
+}
+
+/// 0 = whitespace, 1 = identifier chars, 2 = punctuation (by the base character)
+pub fn grapheme_class(g: &str, big: bool) -> u8 {
+    match g.chars().next() {
+        Some(c) if c.is_whitespace() => 0,
+        Some(_) if big => 1,
+        Some(c) if c.is_alphanumeric() || c == '_' || c == '@' || c == '#' => 1,
+        _ => 2,
+    }
+}
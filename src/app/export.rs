@@ -0,0 +1,573 @@
+//! Export dialog: target path with completion, format and text options
+
+use crate::app::{error_chain, App};
+use crate::db::{DbConnection, QueryExecutor};
+use crate::export::{create_writer, export_result, stream_query, ExportFormat, ExportOptions, ExportProgress, StreamOutcome, TextEncoding};
+use crate::utils::{format_bytes, format_duration, format_number};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::oneshot;
+
+/// Delimiters offered for CSV and TSV; any other character can be typed
+const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+/// Quote characters offered for CSV and TSV; `None` never quotes
+const QUOTES: [Option<char>; 3] = [Some('"'), Some('\''), None];
+
+/// Where the exported rows come from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportSource {
+    /// Rows already fetched into the Data tab
+    Grid,
+    /// Run the query again and stream its rows to the file
+    Query,
+}
+
+/// Fields of the export dialog, in order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportField {
+    Source,
+    Path,
+    Format,
+    Delimiter,
+    Quote,
+    Encoding,
+    Bom,
+    NullText,
+    Headers,
+    Table,
+}
+
+impl ExportField {
+    pub const ALL: [ExportField; 10] = [
+        ExportField::Source,
+        ExportField::Path,
+        ExportField::Format,
+        ExportField::Delimiter,
+        ExportField::Quote,
+        ExportField::Encoding,
+        ExportField::Bom,
+        ExportField::NullText,
+        ExportField::Headers,
+        ExportField::Table,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportField::Source => "Rows",
+            ExportField::Path => "File",
+            ExportField::Format => "Format",
+            ExportField::Delimiter => "Delimiter",
+            ExportField::Quote => "Quote",
+            ExportField::Encoding => "Encoding",
+            ExportField::Bom => "BOM",
+            ExportField::NullText => "NULL as",
+            ExportField::Headers => "Headers",
+            ExportField::Table => "Table",
+        }
+    }
+
+    /// Whether the field means anything for `format`
+    pub fn applies_to(&self, format: ExportFormat) -> bool {
+        match self {
+            ExportField::Source | ExportField::Path | ExportField::Format => true,
+            ExportField::Delimiter | ExportField::Quote => format.is_delimited(),
+            ExportField::Encoding | ExportField::Bom => format.is_text(),
+            ExportField::NullText | ExportField::Headers => {
+                format.is_delimited() || matches!(format, ExportFormat::Markdown | ExportFormat::Html)
+            }
+            ExportField::Table => format == ExportFormat::Sql,
+        }
+    }
+
+    /// Fields typed as text rather than picked from choices
+    pub fn is_text(&self) -> bool {
+        matches!(self, ExportField::Path | ExportField::NullText | ExportField::Table)
+    }
+}
+
+/// Popup asking where and how to export the current result
+#[derive(Clone, Debug)]
+pub struct ExportDialog {
+    pub options: ExportOptions,
+    pub source: ExportSource,
+    /// Query to re-run for `ExportSource::Query`; without one only the grid can be exported
+    pub query: Option<String>,
+    pub path: String,
+    pub field: ExportField,
+    completer: PathCompleter,
+    /// Target that exists; Enter again overwrites it
+    confirm_overwrite: Option<PathBuf>,
+    pub error: Option<String>,
+}
+
+impl ExportDialog {
+    fn new(options: ExportOptions, source: ExportSource, query: Option<String>) -> Self {
+        let name = format!(
+            "export_{}.{}",
+            chrono::Local::now().format("%Y%m%d_%H%M%S"),
+            options.format.extension()
+        );
+        let path = match options.dir.as_ref().filter(|dir| dir.is_dir()) {
+            Some(dir) => dir.join(name).display().to_string(),
+            None => name,
+        };
+        Self {
+            options,
+            source,
+            query,
+            path,
+            field: ExportField::Path,
+            completer: PathCompleter::default(),
+            confirm_overwrite: None,
+            error: None,
+        }
+    }
+
+    /// Fields shown for the chosen format
+    pub fn fields(&self) -> Vec<ExportField> {
+        ExportField::ALL
+            .into_iter()
+            .filter(|f| f.applies_to(self.options.format))
+            .filter(|f| *f != ExportField::Source || self.query.is_some())
+            .collect()
+    }
+
+    pub fn next_field(&mut self, delta: isize) {
+        let fields = self.fields();
+        let current = fields.iter().position(|f| *f == self.field).unwrap_or(0) as isize;
+        self.field = fields[(current + delta).rem_euclid(fields.len() as isize) as usize];
+    }
+
+    /// Step the chosen field through its values (booleans toggle)
+    pub fn cycle(&mut self, delta: isize) {
+        let options = &mut self.options;
+        match self.field {
+            ExportField::Source => {
+                self.source = match self.source {
+                    ExportSource::Grid => ExportSource::Query,
+                    ExportSource::Query => ExportSource::Grid,
+                };
+            }
+            ExportField::Format => {
+                let format = step(&ExportFormat::ALL, &options.format, delta);
+                options.set_format(format);
+                self.path = with_extension(&self.path, format);
+            }
+            ExportField::Delimiter => options.delimiter = step(&DELIMITERS, &options.delimiter, delta),
+            ExportField::Quote => options.quote = step(&QUOTES, &options.quote, delta),
+            ExportField::Encoding => options.encoding = step(&TextEncoding::ALL, &options.encoding, delta),
+            ExportField::Bom => options.bom = !options.bom,
+            ExportField::Headers => options.headers = !options.headers,
+            ExportField::Path | ExportField::NullText | ExportField::Table => {}
+        }
+        self.edited();
+    }
+
+    pub fn push(&mut self, c: char) {
+        match self.field {
+            ExportField::Path => self.path.push(c),
+            ExportField::NullText => self.options.null_text.push(c),
+            ExportField::Table => self.options.table.push(c),
+            ExportField::Delimiter => self.options.delimiter = c,
+            ExportField::Quote if c == ' ' => self.cycle(1),
+            ExportField::Quote => self.options.quote = Some(c),
+            _ => self.cycle(1),
+        }
+        self.edited();
+    }
+
+    pub fn pop(&mut self) {
+        match self.field {
+            ExportField::Path => {
+                self.path.pop();
+            }
+            ExportField::NullText => {
+                self.options.null_text.pop();
+            }
+            ExportField::Table => {
+                self.options.table.pop();
+            }
+            _ => {}
+        }
+        self.edited();
+    }
+
+    fn edited(&mut self) {
+        self.completer.reset();
+        self.confirm_overwrite = None;
+        self.error = None;
+    }
+
+    /// Tab on the path: complete a unique match, extend to the common prefix, then cycle matches
+    pub fn complete_path(&mut self) {
+        if let Err(error) = self.completer.complete(&mut self.path) {
+            self.error = Some(error);
+        }
+    }
+
+    /// Text of a choice field, for drawing
+    pub fn value(&self, field: ExportField) -> String {
+        let options = &self.options;
+        let yes_no = |v: bool| if v { "Yes" } else { "No" }.to_string();
+        match field {
+            ExportField::Source => match self.source {
+                ExportSource::Grid => "Result grid".to_string(),
+                ExportSource::Query => "Re-run query, stream to file".to_string(),
+            },
+            ExportField::Path => self.path.clone(),
+            ExportField::Format => options.format.label().to_string(),
+            ExportField::Delimiter => match options.delimiter {
+                '\t' => "Tab".to_string(),
+                ' ' => "Space".to_string(),
+                c => c.to_string(),
+            },
+            ExportField::Quote => options.quote.map_or("None".to_string(), |q| q.to_string()),
+            ExportField::Encoding => options.encoding.label().to_string(),
+            ExportField::Bom => yes_no(options.bom),
+            ExportField::NullText => options.null_text.clone(),
+            ExportField::Headers => yes_no(options.headers),
+            ExportField::Table => options.table.clone(),
+        }
+    }
+}
+
+/// Neighbour of `current` in `values`, wrapping around
+pub(crate) fn step<T: Copy + PartialEq>(values: &[T], current: &T, delta: isize) -> T {
+    let index = values.iter().position(|v| v == current).unwrap_or(0) as isize;
+    values[(index + delta).rem_euclid(values.len() as isize) as usize]
+}
+
+/// Tab completion of a typed path
+#[derive(Clone, Debug, Default)]
+pub struct PathCompleter {
+    /// Matches of the last Tab on an ambiguous path; further Tabs cycle them
+    completions: Vec<String>,
+    completion: usize,
+}
+
+impl PathCompleter {
+    /// Complete a unique match, extend to the common prefix, then cycle matches
+    pub fn complete(&mut self, path: &mut String) -> Result<(), String> {
+        if !self.completions.is_empty() {
+            self.completion = (self.completion + 1) % self.completions.len();
+            *path = self.completions[self.completion].clone();
+            return Ok(());
+        }
+        let matches = path_completions(path);
+        match matches.len() {
+            0 => return Err("No matching file or folder".to_string()),
+            1 => *path = matches[0].clone(),
+            _ => {
+                let common = common_prefix(&matches);
+                if common.len() > path.len() {
+                    *path = common;
+                } else {
+                    *path = matches[0].clone();
+                    self.completions = matches;
+                    self.completion = 0;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget the matches after the path was edited
+    pub fn reset(&mut self) {
+        self.completions.clear();
+    }
+}
+
+/// Swap an export extension (or add one) to match `format`
+fn with_extension(path: &str, format: ExportFormat) -> String {
+    let file = Path::new(path);
+    let known = file
+        .extension()
+        .map(|ext| ExportFormat::parse(&ext.to_string_lossy()).is_some())
+        .unwrap_or(true);
+    if path.is_empty() || path.ends_with(['/', '\\']) || !known {
+        return path.to_string();
+    }
+    file.with_extension(format.extension()).display().to_string()
+}
+
+/// `~` and `~/...` relative to the home folder
+pub(crate) fn expand_tilde(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            home.join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Paths starting with `input`, as typed, folders ending in a separator
+fn path_completions(input: &str) -> Vec<String> {
+    let (dir_part, prefix) = match input.rfind(['/', '\\']) {
+        Some(i) => input.split_at(i + 1),
+        None if input == "~" => return vec![format!("~{}", std::path::MAIN_SEPARATOR)],
+        None => ("", input),
+    };
+    let dir = if dir_part.is_empty() { PathBuf::from(".") } else { expand_tilde(dir_part) };
+    let Ok(read) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut matches: Vec<String> = read
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            let separator = if is_dir { std::path::MAIN_SEPARATOR.to_string() } else { String::new() };
+            Some(format!("{}{}{}", dir_part, name, separator))
+        })
+        .collect();
+    matches.sort();
+    matches
+}
+
+/// Longest common prefix, on character boundaries
+fn common_prefix(values: &[String]) -> String {
+    let Some((first, rest)) = values.split_first() else {
+        return String::new();
+    };
+    let mut end = first.len();
+    for value in rest {
+        end = first
+            .char_indices()
+            .zip(value.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(end.min(value.len()), |((i, _), _)| i.min(end));
+    }
+    first[..end].to_string()
+}
+
+/// Export streaming in the background: Esc cancels through `progress`
+pub struct ExportJob {
+    pub progress: Arc<ExportProgress>,
+    pub path: PathBuf,
+    pub started: Instant,
+    options: ExportOptions,
+    /// How the export ended, and why the connection could not be replaced afterwards
+    rx: oneshot::Receiver<(Result<StreamOutcome, String>, Option<String>)>,
+}
+
+impl ExportJob {
+    /// Rows written per second so far
+    pub fn rate(&self) -> f64 {
+        let secs = self.started.elapsed().as_secs_f64();
+        if secs > 0.0 { self.progress.rows() as f64 / secs } else { 0.0 }
+    }
+}
+
+impl App {
+    /// Open the export dialog with the last-used options, optionally switching format.
+    /// `ExportSource::Query` re-runs the editor's query; the grid source needs a result.
+    pub(crate) fn open_export_dialog(&mut self, source: ExportSource, format: Option<ExportFormat>) {
+        let query = match source {
+            ExportSource::Grid => self.result_query.clone(),
+            ExportSource::Query => Some(self.editor.text().to_string()),
+        }
+        .filter(|q| !q.trim().is_empty());
+        if source == ExportSource::Grid && self.result.columns.is_empty() {
+            self.error = Some("No results to export".to_string());
+            return;
+        }
+        if source == ExportSource::Query && query.is_none() {
+            self.error = Some("No query to export".to_string());
+            return;
+        }
+        let mut options = self.export_options.clone();
+        if let Some(format) = format {
+            options.set_format(format);
+        }
+        self.export_dialog = Some(ExportDialog::new(options, source, query));
+    }
+
+    /// Write the file; on success remember the options and the folder
+    pub(crate) fn confirm_export_dialog(&mut self) {
+        let Some(dialog) = self.export_dialog.as_mut() else {
+            return;
+        };
+        let typed = dialog.path.trim();
+        if typed.is_empty() {
+            dialog.error = Some("Enter a file name".to_string());
+            return;
+        }
+        let path = expand_tilde(typed);
+        if path.is_dir() {
+            dialog.error = Some(format!("{} is a folder", path.display()));
+            return;
+        }
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty() && !p.is_dir()) {
+            dialog.error = Some(format!("Folder {} does not exist", parent.display()));
+            return;
+        }
+        if path.exists() && dialog.confirm_overwrite.as_ref() != Some(&path) {
+            dialog.error = Some(format!("{} exists; Enter again to overwrite", path.display()));
+            dialog.confirm_overwrite = Some(path);
+            return;
+        }
+
+        if dialog.source == ExportSource::Query {
+            self.start_stream_export(path);
+            return;
+        }
+        if self.result.columns.is_empty() {
+            dialog.error = Some("No results to export; choose Re-run query".to_string());
+            return;
+        }
+
+        let table = self.result_query.as_deref().and_then(QueryExecutor::extract_table_name);
+        match export_result(&self.result, &path, &dialog.options, table.as_deref()) {
+            Ok(bytes) => {
+                let options = dialog.options.clone();
+                let summary = format!(
+                    "{} rows to {} ({})",
+                    self.result.rows.len(),
+                    path.display(),
+                    format_bytes(bytes)
+                );
+                self.message = Some(self.remember_export_options(options, &path, summary));
+                self.export_dialog = None;
+            }
+            Err(e) => dialog.error = Some(format!("Export failed: {}", e)),
+        }
+    }
+
+    /// Full path for a file saved without the export dialog: the last export folder,
+    /// or the working directory
+    pub(crate) fn export_target(&self, name: &str) -> PathBuf {
+        let path = match self.export_options.dir.as_ref().filter(|dir| dir.is_dir()) {
+            Some(dir) => dir.join(name),
+            None => PathBuf::from(name),
+        };
+        std::path::absolute(&path).unwrap_or(path)
+    }
+
+    /// Keep `options` and the file's folder for the next export; returns the status message
+    fn remember_export_options(&mut self, mut options: ExportOptions, path: &Path, summary: String) -> String {
+        options.dir = std::fs::canonicalize(path)
+            .ok()
+            .and_then(|p| p.parent().map(Path::to_path_buf));
+        let message = match options.save() {
+            Ok(()) => format!("✓ Exported {}", summary),
+            Err(e) => format!("✓ Exported {} (settings not saved: {})", summary, e),
+        };
+        self.export_options = options;
+        message
+    }
+
+    /// Re-run the dialog's query in the background, writing rows to `path` as they arrive
+    fn start_stream_export(&mut self, path: PathBuf) {
+        let Some(dialog) = self.export_dialog.as_mut() else {
+            return;
+        };
+        if !dialog.options.format.is_streaming() {
+            dialog.error = Some(format!(
+                "{} needs the rows in memory; stream to CSV, JSON Lines or Parquet instead",
+                dialog.options.format.label()
+            ));
+            return;
+        }
+        if self.is_loading {
+            dialog.error = Some("Wait for the running query to finish".to_string());
+            return;
+        }
+        let query = dialog.query.clone().unwrap_or_default();
+        let table = QueryExecutor::extract_table_name(&query);
+        let mut writer = match create_writer(&path, &dialog.options, table.as_deref()) {
+            Ok(writer) => writer,
+            Err(e) => {
+                dialog.error = Some(format!("Export failed: {}", e));
+                return;
+            }
+        };
+        let options = dialog.options.clone();
+        self.export_dialog = None;
+
+        self.is_loading = true;
+        self.error = None;
+        self.message = None;
+        self.spinner_frame = 0;
+
+        let progress = Arc::new(ExportProgress::default());
+        let (tx, rx) = oneshot::channel();
+        let client_arc = self.db.client();
+        let config = self.db.config.clone();
+        let task_progress = Arc::clone(&progress);
+
+        tokio::spawn(async move {
+            let mut client = client_arc.lock().await;
+            let outcome = stream_query(&mut client, &query, writer.as_mut(), &task_progress).await;
+            // The unread rest of the result is still on its way; a fresh connection stops the server sending it
+            let mut lost = None;
+            if !matches!(outcome, Ok(StreamOutcome::Finished)) {
+                match DbConnection::connect(&config).await {
+                    Ok(fresh) => *client = fresh,
+                    Err(e) => lost = Some(error_chain(&e)),
+                }
+            }
+            let _ = tx.send((outcome.map_err(|e| error_chain(&e)), lost));
+        });
+
+        self.pending_export = Some(ExportJob { progress, path, started: Instant::now(), options, rx });
+    }
+
+    /// Ask the running export to stop
+    pub(crate) fn cancel_export(&mut self) {
+        if let Some(job) = self.pending_export.as_ref() {
+            job.progress.cancel();
+        }
+    }
+
+    /// Check if a streamed export has finished; a cancelled or failed one leaves no partial file
+    pub fn check_export_completion(&mut self) {
+        let Some(job) = self.pending_export.as_mut() else {
+            return;
+        };
+        let (outcome, lost) = match job.rx.try_recv() {
+            Ok(done) => done,
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => (Err("Export was interrupted".to_string()), None),
+        };
+        let Some(job) = self.pending_export.take() else {
+            return;
+        };
+        self.is_loading = false;
+
+        let rows = job.progress.rows();
+        match outcome {
+            Ok(StreamOutcome::Finished) => {
+                let summary = format!(
+                    "{} rows to {} ({}) in {}",
+                    format_number(rows as i64),
+                    job.path.display(),
+                    format_bytes(job.progress.bytes()),
+                    format_duration(job.started.elapsed())
+                );
+                self.message = Some(self.remember_export_options(job.options, &job.path, summary));
+            }
+            Ok(StreamOutcome::Cancelled) => {
+                let _ = std::fs::remove_file(&job.path);
+                self.message = Some(format!("Export cancelled after {} rows; partial file removed", format_number(rows as i64)));
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&job.path);
+                self.error = Some(format!("Export failed after {} rows: {}", format_number(rows as i64), e));
+            }
+        }
+        // The old session still holds the unread rest of the result, so it cannot run anything else
+        if let Some(e) = lost {
+            self.db.connected = false;
+            let lost = format!("Connection lost, the next query reconnects: {}", e);
+            self.error = Some(match self.error.take() {
+                Some(error) => format!("{}; {}", error, lost),
+                None => lost,
+            });
+        }
+    }
+}
@@ -0,0 +1,357 @@
+//! Opening and saving the query as a .sql file
+
+use crate::app::{ActivePanel, App};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+/// How often the open file is checked for changes on disk
+const DISK_CHECK_INTERVAL_MS: u128 = 1000;
+
+/// The .sql file behind the query editor
+#[derive(Clone, Debug)]
+pub struct QueryFile {
+    pub path: PathBuf,
+    /// Text as last read from or written to disk
+    saved_text: String,
+    /// Modification time seen when the file was last read or written
+    disk_modified: Option<SystemTime>,
+    /// Changed by another program since; waiting for a reload answer
+    pub changed_on_disk: bool,
+}
+
+impl QueryFile {
+    fn new(path: PathBuf, saved_text: String) -> Self {
+        let disk_modified = modified_time(&path);
+        Self { path, saved_text, disk_modified, changed_on_disk: false }
+    }
+
+    /// File name for titles and messages
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Whether the browser picks a file to open or a name to save under
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileBrowserMode {
+    Open,
+    SaveAs,
+}
+
+/// One row of the file browser
+#[derive(Clone, Debug)]
+pub struct FileEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// Popup for choosing a .sql file
+#[derive(Clone, Debug)]
+pub struct FileBrowser {
+    pub mode: FileBrowserMode,
+    pub dir: PathBuf,
+    pub entries: Vec<FileEntry>,
+    pub selected: usize,
+    /// File name typed in save-as mode
+    pub input: String,
+    /// List every file, not only .sql
+    pub show_all: bool,
+    /// The list was navigated since the name was typed; Enter on a folder opens it
+    pub list_focus: bool,
+    /// Save-as target that exists; Enter again overwrites it
+    pub confirm_overwrite: Option<PathBuf>,
+    pub error: Option<String>,
+}
+
+impl FileBrowser {
+    pub fn new(mode: FileBrowserMode, dir: PathBuf, input: String) -> Self {
+        let mut browser = Self {
+            mode,
+            dir,
+            entries: Vec::new(),
+            selected: 0,
+            input,
+            show_all: false,
+            list_focus: mode == FileBrowserMode::Open,
+            confirm_overwrite: None,
+            error: None,
+        };
+        browser.refresh();
+        browser
+    }
+
+    /// Re-read the current directory: `..` first, then folders, then files
+    pub fn refresh(&mut self) {
+        self.entries.clear();
+        self.error = None;
+        if self.dir.parent().is_some() {
+            self.entries.push(FileEntry { name: "..".to_string(), is_dir: true, size: 0 });
+        }
+
+        let read = match std::fs::read_dir(&self.dir) {
+            Ok(read) => read,
+            Err(e) => {
+                self.error = Some(format!("{}: {}", self.dir.display(), e));
+                return;
+            }
+        };
+
+        let mut entries: Vec<FileEntry> = read
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let meta = entry.metadata().ok()?;
+                let visible = !name.starts_with('.')
+                    && (meta.is_dir() || self.show_all || is_sql_file(&name));
+                visible.then(|| FileEntry { name, is_dir: meta.is_dir(), size: meta.len() })
+            })
+            .collect();
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+        self.entries.extend(entries);
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+    }
+
+    pub fn selected_entry(&self) -> Option<&FileEntry> {
+        self.entries.get(self.selected)
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let last = self.entries.len() as isize - 1;
+        self.selected = (self.selected as isize + delta).clamp(0, last) as usize;
+        self.list_focus = true;
+        self.confirm_overwrite = None;
+        // Picking a file in save-as mode copies its name into the input
+        if self.mode == FileBrowserMode::SaveAs {
+            if let Some(entry) = self.selected_entry().filter(|e| !e.is_dir) {
+                self.input = entry.name.clone();
+            }
+        }
+    }
+
+    /// Enter a folder (`..` goes up)
+    pub fn enter_dir(&mut self, name: &str) {
+        let previous = self.dir.file_name().map(|n| n.to_string_lossy().to_string());
+        if name == ".." {
+            if let Some(parent) = self.dir.parent() {
+                self.dir = parent.to_path_buf();
+            }
+        } else {
+            self.dir.push(name);
+        }
+        self.selected = 0;
+        self.refresh();
+
+        // Going up keeps the folder we came from selected
+        if name == ".." {
+            if let Some(index) = previous.and_then(|p| self.entries.iter().position(|e| e.name == p)) {
+                self.selected = index;
+            }
+        }
+    }
+
+    pub fn push_input(&mut self, c: char) {
+        self.input.push(c);
+        self.list_focus = false;
+        self.confirm_overwrite = None;
+    }
+
+    pub fn pop_input(&mut self) {
+        self.input.pop();
+        self.list_focus = false;
+        self.confirm_overwrite = None;
+    }
+
+    pub fn go_up(&mut self) {
+        if self.dir.parent().is_some() {
+            self.enter_dir("..");
+        }
+    }
+
+    pub fn toggle_show_all(&mut self) {
+        self.show_all = !self.show_all;
+        self.refresh();
+    }
+
+    /// Full path for the typed save-as name, adding .sql when there is no extension
+    pub fn target_path(&self) -> Option<PathBuf> {
+        let name = self.input.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let mut path = self.dir.join(name);
+        if path.extension().is_none() {
+            path.set_extension("sql");
+        }
+        Some(path)
+    }
+}
+
+fn is_sql_file(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("sql"))
+}
+
+impl App {
+    /// Unsaved changes since the file was opened or written
+    pub fn is_query_modified(&self) -> bool {
+        self.query_file
+            .as_ref()
+            .is_some_and(|file| file.saved_text != self.editor.text())
+    }
+
+    /// Load a .sql file into the editor; a missing file starts a new, empty one
+    pub fn open_query_file(&mut self, path: PathBuf) {
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text.replace("\r\n", "\n"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                self.message = Some(format!("New file {}", path.display()));
+                String::new()
+            }
+            Err(e) => {
+                self.error = Some(format!("Cannot open {}: {}", path.display(), e));
+                return;
+            }
+        };
+
+        // Replacing the text is undoable, so unsaved work can be recovered with Ctrl+Z
+        self.editor.set_text(text.clone());
+        self.editor.set_cursor(0);
+        self.query_scroll_x = 0;
+        self.query_scroll_y = 0;
+        self.active_panel = ActivePanel::QueryEditor;
+        if path.exists() {
+            self.message = Some(format!("Opened {} ({} line(s))", path.display(), self.editor.line_count()));
+        }
+        self.query_file = Some(QueryFile::new(path, text));
+    }
+
+    /// Write the query to `path`, or to the open file when None; false if nothing was written
+    pub fn save_query_file(&mut self, path: Option<PathBuf>) -> bool {
+        let Some(path) = path.or_else(|| self.query_file.as_ref().map(|f| f.path.clone())) else {
+            self.open_file_browser(FileBrowserMode::SaveAs);
+            return false;
+        };
+
+        match std::fs::write(&path, self.editor.text()) {
+            Ok(()) => {
+                self.message = Some(format!(
+                    "✓ Wrote {} line(s) to {}",
+                    self.editor.line_count(),
+                    path.display()
+                ));
+                self.query_file = Some(QueryFile::new(path, self.editor.text().to_string()));
+                true
+            }
+            Err(e) => {
+                self.error = Some(format!("Save failed: {}", e));
+                false
+            }
+        }
+    }
+
+    /// Read the open file again, replacing the editor text (undoable)
+    pub fn reload_query_file(&mut self) {
+        if let Some(file) = self.query_file.take() {
+            self.open_query_file(file.path);
+        }
+    }
+
+    /// Keep the editor text and stop asking about the current disk version
+    pub fn dismiss_reload_prompt(&mut self) {
+        if let Some(file) = self.query_file.as_mut() {
+            file.changed_on_disk = false;
+            file.disk_modified = modified_time(&file.path);
+        }
+    }
+
+    /// Notice when another program changes the open file (checked about once a second)
+    pub fn check_query_file_changes(&mut self) {
+        if self.last_disk_check.elapsed().as_millis() < DISK_CHECK_INTERVAL_MS {
+            return;
+        }
+        self.last_disk_check = Instant::now();
+
+        let Some(file) = self.query_file.as_mut().filter(|f| !f.changed_on_disk) else {
+            return;
+        };
+        let modified = modified_time(&file.path);
+        if modified.is_some() && modified != file.disk_modified {
+            // Same content (e.g. touched or saved by us elsewhere) needs no prompt
+            match std::fs::read_to_string(&file.path) {
+                Ok(text) if text.replace("\r\n", "\n") == file.saved_text => file.disk_modified = modified,
+                _ => file.changed_on_disk = true,
+            }
+        }
+    }
+
+    /// Show the file browser, starting next to the open file
+    pub fn open_file_browser(&mut self, mode: FileBrowserMode) {
+        let (dir, name) = match self.query_file.as_ref() {
+            Some(file) => (
+                file.path.parent().map(Path::to_path_buf).filter(|p| !p.as_os_str().is_empty()),
+                file.name(),
+            ),
+            None => (None, String::new()),
+        };
+        let dir = dir
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("."));
+        let dir = dir.canonicalize().unwrap_or(dir);
+        let input = if mode == FileBrowserMode::SaveAs { name } else { String::new() };
+        self.file_browser = Some(FileBrowser::new(mode, dir, input));
+    }
+
+    /// Enter in the file browser: open a folder or file, or save under the typed name
+    pub fn confirm_file_browser(&mut self) {
+        let Some(browser) = self.file_browser.as_mut() else {
+            return;
+        };
+
+        match browser.mode {
+            FileBrowserMode::Open => {
+                let Some(entry) = browser.selected_entry().cloned() else {
+                    return;
+                };
+                if entry.is_dir {
+                    browser.enter_dir(&entry.name);
+                } else {
+                    let path = browser.dir.join(&entry.name);
+                    self.file_browser = None;
+                    self.open_query_file(path);
+                }
+            }
+            FileBrowserMode::SaveAs => {
+                // A folder picked in the list is entered rather than saved over
+                if let Some(entry) = browser.selected_entry().filter(|e| e.is_dir && browser.list_focus) {
+                    let name = entry.name.clone();
+                    browser.enter_dir(&name);
+                    return;
+                }
+                let Some(path) = browser.target_path() else {
+                    browser.error = Some("Type a file name".to_string());
+                    return;
+                };
+                let is_open_file = self.query_file.as_ref().is_some_and(|f| f.path == path);
+                if path.exists() && !is_open_file && browser.confirm_overwrite.as_ref() != Some(&path) {
+                    browser.error = Some(format!("{} exists, press Enter again to overwrite", path.display()));
+                    browser.confirm_overwrite = Some(path);
+                    return;
+                }
+                self.file_browser = None;
+                self.save_query_file(Some(path));
+            }
+        }
+    }
+}
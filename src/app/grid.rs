@@ -0,0 +1,568 @@
+//! Client-side sorting, filtering and quick-search for the results grid
+
+use crate::app::App;
+use crate::db::{CellValue, ColumnInfo};
+use crate::utils::display_width;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Sort direction for a result column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+/// Comparison used by a column filter
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterOp {
+    Eq,
+    NotEq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Contains,
+    NotContains,
+    IsNull,
+    IsNotNull,
+}
+
+/// Per-column filter expression such as `> 1000`, `~ 'abc'` or `is null`
+#[derive(Clone, Debug)]
+pub struct ColumnFilter {
+    pub expr: String,
+    pub op: FilterOp,
+    pub operand: String,
+}
+
+impl ColumnFilter {
+    /// Parse a filter expression; bare text means "contains"
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let trimmed = expr.trim();
+        let lower = trimmed.to_lowercase();
+
+        if lower == "is null" || lower == "null" {
+            return Ok(Self { expr: trimmed.to_string(), op: FilterOp::IsNull, operand: String::new() });
+        }
+        if lower == "is not null" || lower == "not null" {
+            return Ok(Self { expr: trimmed.to_string(), op: FilterOp::IsNotNull, operand: String::new() });
+        }
+
+        // Longest operators first so ">=" is not read as ">"
+        let ops = [
+            (">=", FilterOp::Gte),
+            ("<=", FilterOp::Lte),
+            ("<>", FilterOp::NotEq),
+            ("!=", FilterOp::NotEq),
+            ("!~", FilterOp::NotContains),
+            (">", FilterOp::Gt),
+            ("<", FilterOp::Lt),
+            ("=", FilterOp::Eq),
+            ("~", FilterOp::Contains),
+        ];
+
+        let (op, rest) = ops
+            .iter()
+            .find(|(symbol, _)| trimmed.starts_with(symbol))
+            .map(|(symbol, op)| (op.clone(), &trimmed[symbol.len()..]))
+            .unwrap_or((FilterOp::Contains, trimmed));
+
+        let operand = unquote(rest.trim());
+        if operand.is_empty() {
+            return Err(format!("Filter '{}' needs a value", trimmed));
+        }
+
+        Ok(Self { expr: trimmed.to_string(), op, operand })
+    }
+
+    /// Check whether a cell passes the filter
+    pub fn matches(&self, cell: &CellValue) -> bool {
+        let is_null = matches!(cell, CellValue::Null);
+        match self.op {
+            FilterOp::IsNull => return is_null,
+            FilterOp::IsNotNull => return !is_null,
+            _ if is_null => return false,
+            _ => {}
+        }
+
+        let text = cell.to_string();
+        match self.op {
+            FilterOp::Contains => text.to_lowercase().contains(&self.operand.to_lowercase()),
+            FilterOp::NotContains => !text.to_lowercase().contains(&self.operand.to_lowercase()),
+            _ => {
+                let ordering = match (cell_number(cell), self.operand.parse::<f64>().ok()) {
+                    (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+                    _ => text.to_lowercase().cmp(&self.operand.to_lowercase()),
+                };
+                match self.op {
+                    FilterOp::Eq => ordering == Ordering::Equal,
+                    FilterOp::NotEq => ordering != Ordering::Equal,
+                    FilterOp::Gt => ordering == Ordering::Greater,
+                    FilterOp::Gte => ordering != Ordering::Less,
+                    FilterOp::Lt => ordering == Ordering::Less,
+                    FilterOp::Lte => ordering != Ordering::Greater,
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+/// What a grid input prompt is collecting
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GridInputKind {
+    /// Filter expression for a column
+    Filter(usize),
+    /// Quick-search text
+    Search,
+}
+
+/// Text prompt shown under the Data tab
+#[derive(Clone, Debug)]
+pub struct GridInput {
+    pub kind: GridInputKind,
+    pub buffer: String,
+}
+
+/// Display order of result rows after sorting and filtering
+#[derive(Clone, Debug, Default)]
+pub struct ResultView {
+    /// Indices into `QueryResult::rows`, in display order
+    pub order: Vec<usize>,
+    pub sort: Option<(usize, SortDirection)>,
+    pub filters: BTreeMap<usize, ColumnFilter>,
+    pub search: Option<String>,
+    /// Cells of the visible rows matching `search`, counted when either changes
+    pub search_matches: usize,
+}
+
+impl ResultView {
+    /// Unsorted, unfiltered view over `row_count` rows
+    pub fn new(row_count: usize) -> Self {
+        Self {
+            order: (0..row_count).collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Result row index shown at a display position
+    pub fn row_at(&self, display_idx: usize) -> Option<usize> {
+        self.order.get(display_idx).copied()
+    }
+
+    /// Whether the view hides or reorders any rows
+    pub fn is_active(&self) -> bool {
+        self.sort.is_some() || !self.filters.is_empty()
+    }
+
+    /// Recompute the display order from the current sort and filters
+    pub fn rebuild(&mut self, rows: &[Vec<CellValue>]) {
+        let mut order: Vec<usize> = (0..rows.len())
+            .filter(|&i| {
+                self.filters
+                    .iter()
+                    .all(|(col, filter)| rows[i].get(*col).map(|c| filter.matches(c)).unwrap_or(false))
+            })
+            .collect();
+
+        if let Some((col, direction)) = self.sort {
+            // Stable sort keeps the server order for equal keys
+            order.sort_by(|&a, &b| {
+                let ordering = match (rows[a].get(col), rows[b].get(col)) {
+                    (Some(x), Some(y)) => compare_cells(x, y),
+                    _ => Ordering::Equal,
+                };
+                match direction {
+                    SortDirection::Ascending => ordering,
+                    SortDirection::Descending => ordering.reverse(),
+                }
+            });
+        }
+
+        self.order = order;
+        self.count_search_matches(rows);
+    }
+
+    /// Recount the cells of the visible rows that match the quick-search
+    pub fn count_search_matches(&mut self, rows: &[Vec<CellValue>]) {
+        self.search_matches = match self.search {
+            Some(_) => self
+                .order
+                .iter()
+                .filter_map(|&r| rows.get(r))
+                .flat_map(|row| row.iter())
+                .filter(|cell| self.is_search_match(cell))
+                .count(),
+            None => 0,
+        };
+    }
+
+    /// Whether a cell matches the quick-search text
+    pub fn is_search_match(&self, cell: &CellValue) -> bool {
+        match &self.search {
+            Some(term) if !matches!(cell, CellValue::Null) => {
+                cell.to_string().to_lowercase().contains(&term.to_lowercase())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Narrowest and widest automatic column widths
+pub(crate) const MIN_COLUMN_WIDTH: u16 = 6;
+pub(crate) const MAX_AUTO_COLUMN_WIDTH: u16 = 40;
+
+/// Column order, visibility, widths and pinning for the current result
+#[derive(Clone, Debug, Default)]
+pub struct ColumnLayout {
+    /// Visible result column indices in display order
+    pub order: Vec<usize>,
+    /// Hidden result column indices
+    pub hidden: Vec<usize>,
+    /// Width of each result column, including cell padding
+    pub widths: Vec<u16>,
+    /// Number of leading display columns frozen on the left
+    pub pinned: usize,
+}
+
+impl ColumnLayout {
+    /// Default layout with widths sized from `ColumnInfo::max_width`
+    pub fn new(columns: &[ColumnInfo]) -> Self {
+        Self {
+            order: (0..columns.len()).collect(),
+            hidden: Vec::new(),
+            widths: columns
+                .iter()
+                .map(|c| {
+                    // Header needs room for the type icon and sort/filter marks
+                    let content = c.max_width.max(display_width(&c.name) + 4) + 2;
+                    (content as u16).clamp(MIN_COLUMN_WIDTH, MAX_AUTO_COLUMN_WIDTH)
+                })
+                .collect(),
+            pinned: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Result column index shown at a display position
+    pub fn col_at(&self, display_idx: usize) -> Option<usize> {
+        self.order.get(display_idx).copied()
+    }
+
+    /// Display position of a result column, if visible
+    pub fn position_of(&self, col: usize) -> Option<usize> {
+        self.order.iter().position(|&c| c == col)
+    }
+
+    pub fn width(&self, col: usize) -> u16 {
+        self.widths.get(col).copied().unwrap_or(MIN_COLUMN_WIDTH)
+    }
+
+    /// Number of pinned columns, bounded by the visible count
+    pub fn pinned_count(&self) -> usize {
+        self.pinned.min(self.order.len())
+    }
+}
+
+/// Type-aware ordering of two cells; NULL sorts first
+pub fn compare_cells(a: &CellValue, b: &CellValue) -> Ordering {
+    match (a, b) {
+        (CellValue::Null, CellValue::Null) => Ordering::Equal,
+        (CellValue::Null, _) => Ordering::Less,
+        (_, CellValue::Null) => Ordering::Greater,
+        (CellValue::Bool(x), CellValue::Bool(y)) => x.cmp(y),
+        (CellValue::Int(x), CellValue::Int(y)) => x.cmp(y),
+        (CellValue::Binary(x), CellValue::Binary(y)) => x.cmp(y),
+        // ISO formatted, so lexical order is chronological
+        (CellValue::DateTime(x), CellValue::DateTime(y)) => x.cmp(y),
+        _ => match (cell_number(a), cell_number(b)) {
+            (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            _ => a.to_string().to_lowercase().cmp(&b.to_string().to_lowercase()),
+        },
+    }
+}
+
+/// Numeric value of a cell; DECIMAL/NUMERIC arrive as strings
+pub(crate) fn cell_number(cell: &CellValue) -> Option<f64> {
+    match cell {
+        CellValue::Int(v) => Some(*v as f64),
+        CellValue::Float(v) => Some(*v),
+        CellValue::Bool(v) => Some(if *v { 1.0 } else { 0.0 }),
+        CellValue::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+/// Strip matching single or double quotes
+fn unquote(s: &str) -> String {
+    let quoted = s.len() >= 2
+        && ((s.starts_with('\'') && s.ends_with('\'')) || (s.starts_with('"') && s.ends_with('"')));
+    if quoted {
+        s[1..s.len() - 1].to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+impl App {
+    /// Result row index under the grid cursor
+    pub fn selected_row(&self) -> Option<usize> {
+        self.result_view.row_at(self.results_selected)
+    }
+
+    /// Result column index under the grid cursor
+    pub fn selected_col(&self) -> Option<usize> {
+        self.column_layout.col_at(self.results_col_selected)
+    }
+
+    /// Start a fresh view and column layout after the result set changed
+    pub fn reset_result_view(&mut self) {
+        self.result_view = ResultView::new(self.result.rows.len());
+        self.column_layout = ColumnLayout::new(&self.result.columns);
+        self.grid_input = None;
+        self.grid_selection = None;
+    }
+
+    /// Rebuild the view, keeping the cursor on the same result row if still visible
+    pub fn refresh_result_view(&mut self) {
+        let current = self.selected_row();
+        self.result_view.rebuild(&self.result.rows);
+        self.grid_selection = None;
+        self.results_selected = current
+            .and_then(|row| self.result_view.order.iter().position(|&r| r == row))
+            .unwrap_or(0)
+            .min(self.result_view.len().saturating_sub(1));
+    }
+
+    /// Cycle ascending → descending → unsorted on the selected column
+    pub fn toggle_sort(&mut self) {
+        let Some(col) = self.selected_col() else {
+            return;
+        };
+
+        self.result_view.sort = match self.result_view.sort {
+            Some((c, SortDirection::Ascending)) if c == col => Some((col, SortDirection::Descending)),
+            Some((c, SortDirection::Descending)) if c == col => None,
+            _ => Some((col, SortDirection::Ascending)),
+        };
+        self.refresh_result_view();
+
+        let name = &self.result.columns[col].name;
+        self.message = Some(match self.result_view.sort {
+            Some((_, SortDirection::Ascending)) => format!("Sorted by {} ▲", name),
+            Some((_, SortDirection::Descending)) => format!("Sorted by {} ▼", name),
+            None => "Sort cleared".to_string(),
+        });
+    }
+
+    /// Open a prompt on the Data tab
+    pub fn open_grid_input(&mut self, kind: GridInputKind) {
+        let buffer = match kind {
+            GridInputKind::Filter(col) => self
+                .result_view
+                .filters
+                .get(&col)
+                .map(|f| f.expr.clone())
+                .unwrap_or_default(),
+            GridInputKind::Search => self.result_view.search.clone().unwrap_or_default(),
+        };
+        self.grid_input = Some(GridInput { kind, buffer });
+    }
+
+    /// Apply the text entered in the grid prompt
+    pub fn submit_grid_input(&mut self) {
+        let Some(input) = self.grid_input.take() else {
+            return;
+        };
+        let text = input.buffer.trim();
+
+        match input.kind {
+            GridInputKind::Filter(col) => {
+                if text.is_empty() {
+                    self.result_view.filters.remove(&col);
+                } else {
+                    match ColumnFilter::parse(text) {
+                        Ok(filter) => {
+                            self.result_view.filters.insert(col, filter);
+                        }
+                        Err(e) => {
+                            self.error = Some(e);
+                            return;
+                        }
+                    }
+                }
+                self.refresh_result_view();
+                self.message = Some(format!(
+                    "Showing {} of {} rows",
+                    self.result_view.len(),
+                    self.result.rows.len()
+                ));
+            }
+            GridInputKind::Search => {
+                self.result_view.search = (!text.is_empty()).then(|| text.to_string());
+                self.result_view.count_search_matches(&self.result.rows);
+                if self.result_view.search.is_some() {
+                    self.jump_to_search_match(true);
+                }
+            }
+        }
+    }
+
+    /// Drop sort, filters and search
+    pub fn clear_result_view(&mut self) {
+        let current = self.selected_row();
+        self.result_view = ResultView::new(self.result.rows.len());
+        self.results_selected = current.unwrap_or(0);
+        self.grid_selection = None;
+        self.message = Some("Sort, filters and search cleared".to_string());
+    }
+
+    /// Move the cursor to the next (or previous) cell matching the quick-search
+    pub fn jump_to_search_match(&mut self, forward: bool) {
+        if self.result_view.search.is_none() {
+            return;
+        }
+
+        let col_count = self.column_layout.len();
+        let total = self.result_view.len() * col_count;
+        if total == 0 {
+            return;
+        }
+
+        let start = self.results_selected * col_count + self.results_col_selected;
+        for step in 1..=total {
+            let pos = if forward {
+                (start + step) % total
+            } else {
+                (start + total - step % total) % total
+            };
+            let (display_row, display_col) = (pos / col_count, pos % col_count);
+            let matched = self
+                .result_view
+                .row_at(display_row)
+                .and_then(|r| self.result.rows.get(r))
+                .zip(self.column_layout.col_at(display_col))
+                .and_then(|(row, col)| row.get(col))
+                .map(|cell| self.result_view.is_search_match(cell))
+                .unwrap_or(false);
+
+            if matched {
+                self.results_selected = display_row;
+                self.results_col_selected = display_col;
+                return;
+            }
+        }
+
+        self.message = Some("No matches".to_string());
+    }
+
+    /// Cells matching the quick-search in the visible rows
+    pub fn search_match_count(&self) -> usize {
+        self.result_view.search_matches
+    }
+}
+
+impl App {
+    /// Grow or shrink the selected column
+    pub fn resize_selected_column(&mut self, delta: i16) {
+        if let Some(col) = self.selected_col() {
+            self.set_column_width(col, self.column_layout.width(col) as i32 + delta as i32);
+        }
+    }
+
+    /// Set a column width, keeping it within sensible bounds
+    pub fn set_column_width(&mut self, col: usize, width: i32) {
+        if let Some(w) = self.column_layout.widths.get_mut(col) {
+            *w = width.clamp(MIN_COLUMN_WIDTH as i32, 200) as u16;
+        }
+    }
+
+    /// Freeze columns up to and including the selected one (toggle)
+    pub fn toggle_pin_columns(&mut self) {
+        let pin_to = self.results_col_selected + 1;
+        if self.column_layout.pinned == pin_to {
+            self.column_layout.pinned = 0;
+            self.message = Some("Columns unpinned".to_string());
+        } else {
+            self.column_layout.pinned = pin_to;
+            self.message = Some(format!("Pinned {} column(s)", pin_to));
+        }
+    }
+
+    /// Hide the selected column from the grid
+    pub fn hide_selected_column(&mut self) {
+        if self.column_layout.len() <= 1 {
+            self.error = Some("Cannot hide the last visible column".to_string());
+            return;
+        }
+
+        let pos = self.results_col_selected.min(self.column_layout.len() - 1);
+        let col = self.column_layout.order.remove(pos);
+        self.column_layout.hidden.push(col);
+        if pos < self.column_layout.pinned {
+            self.column_layout.pinned -= 1;
+        }
+        self.results_col_selected = pos.min(self.column_layout.len() - 1);
+        self.message = Some(format!(
+            "Hid {} ({} hidden, U to show all)",
+            self.result.columns.get(col).map(|c| c.name.as_str()).unwrap_or("column"),
+            self.column_layout.hidden.len()
+        ));
+    }
+
+    /// Bring every hidden column back next to its original neighbours
+    pub fn show_all_columns(&mut self) {
+        let selected = self.selected_col();
+        let mut hidden = std::mem::take(&mut self.column_layout.hidden);
+        hidden.sort_unstable();
+
+        for col in hidden {
+            let pos = self
+                .column_layout
+                .order
+                .iter()
+                .position(|&c| c > col)
+                .unwrap_or(self.column_layout.order.len());
+            self.column_layout.order.insert(pos, col);
+        }
+
+        if let Some(pos) = selected.and_then(|c| self.column_layout.position_of(c)) {
+            self.results_col_selected = pos;
+        }
+        self.message = Some("All columns shown".to_string());
+    }
+
+    /// Move the selected column one place left or right
+    pub fn move_selected_column(&mut self, left: bool) {
+        let pos = self.results_col_selected;
+        let target = if left {
+            match pos.checked_sub(1) {
+                Some(t) => t,
+                None => return,
+            }
+        } else {
+            pos + 1
+        };
+
+        if target < self.column_layout.len() && pos < self.column_layout.len() {
+            self.column_layout.order.swap(pos, target);
+            self.results_col_selected = target;
+        }
+    }
+}
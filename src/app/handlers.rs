@@ -1,8 +1,9 @@
 //! Event handlers for the application - SIMPLIFIED VERSION
 
-use crate::app::{App, ActivePanel, ResultsTab, SPINNER_FRAMES};
+use crate::app::{step, App, ActivePanel, CopyFormat, ExportField, ExportSource, FileBrowserMode, GridInputKind, GridSelection, HistoryFilter, ImportField, ImportStep, ResultsTab, ScriptField, SelectionKind, INDENT, SPINNER_FRAMES};
+use crate::export::ExportFormat;
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::prelude::*;
 use std::time::Duration;
 
@@ -12,6 +13,11 @@ impl App {
         loop {
             // Check for query completion
             self.check_query_completion();
+            self.check_edit_completion();
+            self.check_db_switch_completion();
+            self.check_export_completion();
+            self.check_import_completion();
+            self.check_query_file_changes();
 
             // Advance spinner animation when loading
             if self.is_loading {
@@ -35,6 +41,9 @@ impl App {
                     Event::Mouse(mouse) => {
                         self.handle_mouse(mouse)?;
                     }
+                    Event::Paste(text) => {
+                        self.handle_paste(text);
+                    }
                     _ => {}
                 }
             }
@@ -49,13 +58,17 @@ impl App {
 
     /// Handle keyboard input - SIMPLIFIED!
     fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
-        // Don't process keys while loading (except quit)
+        // Don't process keys while loading (except quit, and Esc to cancel an export or import)
         if self.is_loading {
             match (key.code, key.modifiers) {
                 (KeyCode::Char('c'), KeyModifiers::CONTROL) |
                 (KeyCode::Char('q'), KeyModifiers::CONTROL) => {
                     self.should_quit = true;
                 }
+                (KeyCode::Esc, _) => {
+                    self.cancel_export();
+                    self.cancel_import();
+                }
                 _ => {}
             }
             return Ok(());
@@ -66,6 +79,18 @@ impl App {
             self.message = None;
         }
 
+        // Ctrl+C copies the editor selection; otherwise it quits
+        if key.code == KeyCode::Char('c')
+            && key.modifiers == KeyModifiers::CONTROL
+            && self.active_panel == ActivePanel::QueryEditor
+            && !self.show_help
+        {
+            if let Some(text) = self.editor.selected_text().map(|t| t.to_string()) {
+                self.copy_to_clipboard(&text);
+                return Ok(());
+            }
+        }
+
         // Quit shortcuts - always work
         match (key.code, key.modifiers) {
             (KeyCode::Char('c'), KeyModifiers::CONTROL) |
@@ -89,6 +114,78 @@ impl App {
             return Ok(());
         }
 
+        // Vim keymap toggle
+        if key.code == KeyCode::F(3) {
+            self.set_vim_enabled(!self.vim_enabled);
+            return Ok(());
+        }
+
+        if self.inspector.is_some() {
+            self.handle_inspector(key);
+            return Ok(());
+        }
+
+        if self.file_browser.is_some() {
+            self.handle_file_browser(key);
+            return Ok(());
+        }
+
+        if self.snippet_form.is_some() {
+            self.handle_snippet_form(key);
+            return Ok(());
+        }
+
+        if self.diff_setup.is_some() {
+            self.handle_diff_setup(key);
+            return Ok(());
+        }
+
+        if self.export_dialog.is_some() {
+            self.handle_export_dialog(key);
+            return Ok(());
+        }
+
+        if self.script_dialog.is_some() {
+            self.handle_script_dialog(key);
+            return Ok(());
+        }
+
+        if self.import_wizard.is_some() {
+            self.handle_import_wizard(key);
+            return Ok(());
+        }
+
+        if self.copy_menu.is_some() {
+            self.handle_copy_menu(key);
+            return Ok(());
+        }
+
+        // The open file changed on disk: y reloads it, n keeps the editor text
+        if self.query_file.as_ref().is_some_and(|f| f.changed_on_disk) {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('r') | KeyCode::Enter => self.reload_query_file(),
+                KeyCode::Char('n') | KeyCode::Esc => self.dismiss_reload_prompt(),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        // Ctrl+O opens a .sql file from any panel
+        if key.code == KeyCode::Char('o') && key.modifiers == KeyModifiers::CONTROL {
+            self.open_file_browser(FileBrowserMode::Open);
+            return Ok(());
+        }
+
+        // Shift+Tab outdents selected lines in the query editor
+        if key.code == KeyCode::BackTab
+            && self.active_panel == ActivePanel::QueryEditor
+            && self.editor.has_selection()
+            && !self.vim_enabled
+        {
+            self.editor.outdent_lines();
+            return Ok(());
+        }
+
         // Ctrl+Tab or Backtab to switch panels (Tab inserts indent in query editor)
         if key.code == KeyCode::Tab && key.modifiers.contains(KeyModifiers::CONTROL)
            || key.code == KeyCode::BackTab {
@@ -96,7 +193,8 @@ impl App {
                 ActivePanel::QueryEditor => ActivePanel::Results,
                 ActivePanel::Results => ActivePanel::SchemaExplorer,
                 ActivePanel::SchemaExplorer => ActivePanel::History,
-                ActivePanel::History => ActivePanel::QueryEditor,
+                ActivePanel::History => ActivePanel::Snippets,
+                ActivePanel::Snippets => ActivePanel::QueryEditor,
             };
             return Ok(());
         }
@@ -107,7 +205,8 @@ impl App {
                 ActivePanel::QueryEditor => ActivePanel::Results,
                 ActivePanel::Results => ActivePanel::SchemaExplorer,
                 ActivePanel::SchemaExplorer => ActivePanel::History,
-                ActivePanel::History => ActivePanel::QueryEditor,
+                ActivePanel::History => ActivePanel::Snippets,
+                ActivePanel::Snippets => ActivePanel::QueryEditor,
             };
             return Ok(());
         }
@@ -118,6 +217,7 @@ impl App {
             ActivePanel::Results => self.handle_results(key)?,
             ActivePanel::SchemaExplorer => self.handle_schema(key)?,
             ActivePanel::History => self.handle_history(key)?,
+            ActivePanel::Snippets => self.handle_snippets(key),
         }
 
         Ok(())
@@ -137,12 +237,116 @@ impl App {
             MouseEventKind::ScrollDown => {
                 self.scroll_down(3); // Scroll 3 lines at a time
             }
+            MouseEventKind::Down(MouseButton::Left) => {
+                let extend = mouse.modifiers.contains(KeyModifiers::SHIFT);
+                self.handle_grid_click(mouse.column, mouse.row, extend);
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let Some((col, start_x, start_width)) = self.column_drag {
+                    let delta = mouse.column as i32 - start_x as i32;
+                    self.set_column_width(col, start_width as i32 + delta);
+                } else {
+                    self.handle_grid_drag(mouse.column, mouse.row);
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                self.column_drag = None;
+            }
             _ => {}
         }
 
         Ok(())
     }
 
+    /// Whether mouse clicks land on the Data tab grid
+    fn grid_takes_mouse(&self) -> bool {
+        self.active_panel == ActivePanel::Results && self.results_tab == ResultsTab::Data && !self.record_view
+    }
+
+    /// Display row drawn at screen row `y` in the Data tab
+    fn grid_row_at(&self, y: u16) -> Option<usize> {
+        self.grid_rows_y
+            .contains(&y)
+            .then(|| self.results_scroll + (y - self.grid_rows_y.start) as usize)
+            .filter(|&row| row < self.result_view.len())
+    }
+
+    /// Column position drawn at screen column `x` in the Data tab
+    fn grid_col_at(&self, x: u16) -> Option<usize> {
+        self.grid_header_bounds
+            .iter()
+            .find(|(left, right, _)| x >= *left && x < *right)
+            .and_then(|&(_, _, col)| self.column_layout.position_of(col))
+    }
+
+    /// Grab a header border to resize; click a header, row number or cell to select
+    /// its column, row or cell (Shift extends the selection from the cursor)
+    fn handle_grid_click(&mut self, x: u16, y: u16, extend: bool) {
+        if !self.grid_takes_mouse() {
+            return;
+        }
+
+        let (row, col, kind) = if y == self.grid_header_y {
+            // Border between two headers, with one cell of slack either side
+            if let Some(&(_, _, col)) = self
+                .grid_header_bounds
+                .iter()
+                .find(|(_, right, _)| x + 1 >= *right && x <= *right)
+            {
+                self.column_drag = Some((col, x, self.column_layout.width(col)));
+                return;
+            }
+            let Some(pos) = self.grid_col_at(x) else {
+                return;
+            };
+            (self.results_selected, pos, SelectionKind::Columns)
+        } else {
+            let Some(row) = self.grid_row_at(y) else {
+                return;
+            };
+            if self.grid_row_number_x.contains(&x) {
+                (row, self.results_col_selected, SelectionKind::Rows)
+            } else {
+                let Some(pos) = self.grid_col_at(x) else {
+                    return;
+                };
+                (row, pos, SelectionKind::Cells)
+            }
+        };
+
+        if extend {
+            self.begin_selection(kind);
+        } else {
+            self.grid_selection = (kind != SelectionKind::Cells).then_some(GridSelection {
+                anchor_row: row,
+                anchor_col: col,
+                kind,
+            });
+        }
+        self.results_selected = row;
+        self.results_col_selected = col;
+    }
+
+    /// Dragging over the grid grows the selection from where the button went down
+    fn handle_grid_drag(&mut self, x: u16, y: u16) {
+        if !self.grid_takes_mouse() {
+            return;
+        }
+        let (row, col) = (self.grid_row_at(y), self.grid_col_at(x));
+        let kind = match self.grid_selection {
+            Some(selection) => selection.kind,
+            None if row.is_some() && col.is_some() => SelectionKind::Cells,
+            None => return,
+        };
+        self.begin_selection(kind);
+        if kind != SelectionKind::Columns {
+            self.results_selected = row.unwrap_or(self.results_selected);
+        }
+        if kind != SelectionKind::Rows {
+            self.results_col_selected = col.unwrap_or(self.results_col_selected);
+        }
+    }
+
     /// Scroll up in the current panel
     fn scroll_up(&mut self, amount: usize) {
         match self.active_panel {
@@ -158,6 +362,9 @@ impl App {
                     ResultsTab::Stats => {
                         // Stats view doesn't need scrolling (it's short)
                     }
+                    ResultsTab::Diff => {
+                        self.results_selected = self.results_selected.saturating_sub(amount);
+                    }
                 }
             }
             ActivePanel::SchemaExplorer => {
@@ -166,6 +373,9 @@ impl App {
             ActivePanel::History => {
                 self.history_selected = self.history_selected.saturating_sub(amount);
             }
+            ActivePanel::Snippets => {
+                self.move_snippet_selection(-(amount as isize));
+            }
             ActivePanel::QueryEditor => {
                 // Scroll query view
                 self.query_scroll_y = self.query_scroll_y.saturating_sub(amount);
@@ -179,7 +389,7 @@ impl App {
             ActivePanel::Results => {
                 match self.results_tab {
                     ResultsTab::Data => {
-                        let max_rows = self.result.rows.len().saturating_sub(1);
+                        let max_rows = self.result_view.len().saturating_sub(1);
                         self.results_selected = (self.results_selected + amount).min(max_rows);
                     }
                     ResultsTab::Columns => {
@@ -190,6 +400,10 @@ impl App {
                     ResultsTab::Stats => {
                         // Stats view doesn't need scrolling
                     }
+                    ResultsTab::Diff => {
+                        let max_rows = self.result_diff.as_ref().map_or(0, |d| d.view.len()).saturating_sub(1);
+                        self.results_selected = (self.results_selected + amount).min(max_rows);
+                    }
                 }
             }
             ActivePanel::SchemaExplorer => {
@@ -197,12 +411,14 @@ impl App {
                 self.schema_selected = (self.schema_selected + amount).min(max);
             }
             ActivePanel::History => {
-                let max = self.history.len().saturating_sub(1);
-                self.history_selected = (self.history_selected + amount).min(max);
+                self.move_history_selection(amount as isize);
+            }
+            ActivePanel::Snippets => {
+                self.move_snippet_selection(amount as isize);
             }
             ActivePanel::QueryEditor => {
                 // Scroll query view
-                let max_scroll = self.query.lines().count().saturating_sub(1);
+                let max_scroll = self.editor.text().lines().count().saturating_sub(1);
                 self.query_scroll_y = (self.query_scroll_y + amount).min(max_scroll);
             }
         }
@@ -210,12 +426,27 @@ impl App {
 
     /// Query Editor - Type and press Enter to run!
     fn handle_query_editor(&mut self, key: KeyEvent) -> Result<()> {
+        // Tab jumps through the placeholders of a just-inserted snippet; Esc stops that
+        if key.code == KeyCode::Tab && key.modifiers.is_empty() && self.next_snippet_stop() {
+            return Ok(());
+        }
+        if key.code == KeyCode::Esc {
+            self.snippet_stops = None;
+        }
+
+        if self.vim_enabled && self.handle_vim_key(key) {
+            return Ok(());
+        }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+
         match key.code {
             // ENTER = RUN QUERY!
-            KeyCode::Enter if key.modifiers.contains(KeyModifiers::SHIFT) => {
-                // Shift+Enter = new line
-                self.query.insert(self.cursor_pos, '\n');
-                self.cursor_pos += 1;
+            KeyCode::Enter if shift || alt => {
+                // Shift+Enter = new line, keeping indentation
+                self.editor.insert_newline();
             }
             KeyCode::Enter => {
                 // Plain Enter = RUN QUERY!
@@ -226,68 +457,116 @@ impl App {
                 self.start_query();
             }
             // Ctrl+F = Format SQL
-            KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            KeyCode::Char('f') if ctrl => {
                 self.format_sql();
             }
-            // Tab = insert 4 spaces for indentation
-            KeyCode::Tab => {
-                let indent = "    "; // 4 spaces
-                for c in indent.chars() {
-                    self.query.insert(self.cursor_pos, c);
-                    self.cursor_pos += 1;
+            // Ctrl+R = search the query history
+            KeyCode::Char('r') if ctrl => self.start_history_search(),
+            // Ctrl+E = export the query: re-run it and stream the rows to a file
+            KeyCode::Char('e') if ctrl => self.open_export_dialog(ExportSource::Query, None),
+            // Save to the open .sql file (asks for a name the first time); Alt+S saves as
+            KeyCode::Char('s') if ctrl => {
+                self.save_query_file(None);
+            }
+            KeyCode::Char('s') if alt => self.open_file_browser(FileBrowserMode::SaveAs),
+            // Undo / redo
+            KeyCode::Char('z') if ctrl && !shift => {
+                self.message = (!self.editor.undo()).then(|| "Nothing to undo".to_string());
+            }
+            KeyCode::Char('y') | KeyCode::Char('Z') | KeyCode::Char('z') if ctrl => {
+                self.message = (!self.editor.redo()).then(|| "Nothing to redo".to_string());
+            }
+            // Selection and clipboard
+            KeyCode::Char('a') if ctrl => self.editor.select_all(),
+            KeyCode::Char('x') if ctrl => {
+                if let Some(text) = self.editor.cut() {
+                    self.copy_to_clipboard(&text);
                 }
             }
-            // Typing
-            KeyCode::Char(c) => {
-                self.query.insert(self.cursor_pos, c);
-                self.cursor_pos += 1;
-            }
-            // Backspace
-            KeyCode::Backspace => {
-                if self.cursor_pos > 0 {
-                    self.cursor_pos -= 1;
-                    self.query.remove(self.cursor_pos);
+            KeyCode::Char('v') if ctrl => {
+                match arboard::Clipboard::new().and_then(|mut c| c.get_text()) {
+                    Ok(text) => self.editor.insert_str(&text),
+                    Err(e) => self.error = Some(format!("Clipboard: {}", e)),
                 }
             }
-            // Delete
-            KeyCode::Delete => {
-                if self.cursor_pos < self.query.len() {
-                    self.query.remove(self.cursor_pos);
-                }
-            }
-            // Arrow keys for cursor movement
-            KeyCode::Left => {
-                self.cursor_pos = self.cursor_pos.saturating_sub(1);
-            }
-            KeyCode::Right => {
-                self.cursor_pos = (self.cursor_pos + 1).min(self.query.len());
-            }
-            KeyCode::Up => {
-                // Move cursor up one line
-                self.move_cursor_up();
-            }
-            KeyCode::Down => {
-                // Move cursor down one line
-                self.move_cursor_down();
-            }
-            KeyCode::Home => {
-                self.cursor_pos = 0;
-            }
-            KeyCode::End => {
-                self.cursor_pos = self.query.len();
-            }
-            // Esc clears query
-            KeyCode::Esc => {
-                self.query.clear();
-                self.cursor_pos = 0;
-            }
+            // Line operations
+            KeyCode::Char('k') if ctrl => self.editor.delete_lines(),
+            KeyCode::Char('d') if ctrl => self.editor.duplicate_lines(),
+            KeyCode::Char('w') if ctrl => self.editor.delete_word_back(),
+            KeyCode::Backspace if ctrl || alt => self.editor.delete_word_back(),
+            KeyCode::Delete if ctrl => self.editor.delete_word_forward(),
+            // Tab indents the selected lines, or inserts 4 spaces
+            KeyCode::Tab if self.editor.has_selection() => self.editor.indent_lines(),
+            KeyCode::Tab => self.editor.insert_str(INDENT),
+            // Typing (Ctrl/Alt chords that are not bound above are ignored)
+            KeyCode::Char(c) if !(ctrl ^ alt) => self.editor.insert_char(c),
+            KeyCode::Backspace => self.editor.backspace(),
+            KeyCode::Delete => self.editor.delete(),
+            // Cursor movement; Shift extends the selection, Ctrl moves by word
+            KeyCode::Left if ctrl => self.editor.move_word_left(shift),
+            KeyCode::Right if ctrl => self.editor.move_word_right(shift),
+            KeyCode::Left => self.editor.move_left(shift),
+            KeyCode::Right => self.editor.move_right(shift),
+            KeyCode::Up => self.editor.move_up(shift),
+            KeyCode::Down => self.editor.move_down(shift),
+            KeyCode::Home if ctrl => self.editor.move_doc_start(shift),
+            KeyCode::End if ctrl => self.editor.move_doc_end(shift),
+            KeyCode::Home => self.editor.move_line_start(shift),
+            KeyCode::End => self.editor.move_line_end(shift),
+            // Esc drops the selection, then clears the query (undoable)
+            KeyCode::Esc if self.editor.has_selection() => self.editor.clear_selection(),
+            KeyCode::Esc => self.editor.clear(),
             _ => {}
         }
         Ok(())
     }
 
+    /// Bracketed paste: the whole text arrives as one event
+    fn handle_paste(&mut self, text: String) {
+        if self.is_loading || self.show_help {
+            return;
+        }
+
+        if let Some(input) = self.grid_input.as_mut() {
+            input.buffer.push_str(text.lines().next().unwrap_or(""));
+        } else if let Some(input) = self.edit_session.as_mut().and_then(|s| s.input.as_mut()) {
+            input.push_str(&text);
+        } else if self.active_panel == ActivePanel::QueryEditor {
+            self.editor.insert_str(&text);
+        }
+    }
+
+    /// Put text on the system clipboard
+    fn copy_to_clipboard(&mut self, text: &str) {
+        match arboard::Clipboard::new().and_then(|mut c| c.set_text(text)) {
+            Ok(()) => self.message = Some(format!("Copied {} char(s)", text.chars().count())),
+            Err(e) => self.error = Some(format!("Clipboard: {}", e)),
+        }
+    }
+
     /// Results panel navigation
     fn handle_results(&mut self, key: KeyEvent) -> Result<()> {
+        if self.grid_input.is_some() {
+            self.handle_grid_input(key);
+            return Ok(());
+        }
+
+        if self.edit_session.is_some() && self.results_tab == ResultsTab::Data && self.handle_edit_mode(key) {
+            return Ok(());
+        }
+
+        if self.record_view && self.results_tab == ResultsTab::Data && self.handle_record_view(key) {
+            return Ok(());
+        }
+
+        if self.results_tab == ResultsTab::Diff && self.handle_diff_view(key) {
+            return Ok(());
+        }
+
+        if self.results_tab == ResultsTab::Data && self.handle_grid_selection(key) {
+            return Ok(());
+        }
+
         match key.code {
             // Tab switching with number keys 1, 2, 3
             KeyCode::Char('1') => {
@@ -299,12 +578,16 @@ impl App {
             KeyCode::Char('3') => {
                 self.results_tab = ResultsTab::Stats;
             }
+            KeyCode::Char('4') => {
+                self.results_tab = ResultsTab::Diff;
+            }
             // Tab switching with Tab key
             KeyCode::Tab => {
                 self.results_tab = match self.results_tab {
                     ResultsTab::Data => ResultsTab::Columns,
                     ResultsTab::Columns => ResultsTab::Stats,
-                    ResultsTab::Stats => ResultsTab::Data,
+                    ResultsTab::Stats => ResultsTab::Diff,
+                    ResultsTab::Diff => ResultsTab::Data,
                 };
             }
             KeyCode::Up | KeyCode::Char('k') => {
@@ -312,19 +595,27 @@ impl App {
             }
             KeyCode::Down | KeyCode::Char('j') => {
                 let max_rows = match self.results_tab {
-                    ResultsTab::Data => self.result.rows.len(),
+                    ResultsTab::Data => self.result_view.len(),
                     ResultsTab::Columns => self.result.columns.len(),
                     ResultsTab::Stats => 10, // Fixed stats count
+                    ResultsTab::Diff => self.result_diff.as_ref().map_or(0, |d| d.view.len()),
                 };
                 if self.results_selected < max_rows.saturating_sub(1) {
                     self.results_selected += 1;
                 }
             }
+            // Reorder columns (Alt+←/→)
+            KeyCode::Left if key.modifiers.contains(KeyModifiers::ALT) && self.results_tab == ResultsTab::Data => {
+                self.move_selected_column(true);
+            }
+            KeyCode::Right if key.modifiers.contains(KeyModifiers::ALT) && self.results_tab == ResultsTab::Data => {
+                self.move_selected_column(false);
+            }
             KeyCode::Left | KeyCode::Char('h') => {
                 self.results_col_selected = self.results_col_selected.saturating_sub(1);
             }
             KeyCode::Right | KeyCode::Char('l') => {
-                let max_col = self.result.columns.len().saturating_sub(1);
+                let max_col = self.column_layout.len().saturating_sub(1);
                 if self.results_col_selected < max_col {
                     self.results_col_selected += 1;
                 }
@@ -334,9 +625,10 @@ impl App {
             }
             KeyCode::PageDown => {
                 let max_rows = match self.results_tab {
-                    ResultsTab::Data => self.result.rows.len(),
+                    ResultsTab::Data => self.result_view.len(),
                     ResultsTab::Columns => self.result.columns.len(),
                     ResultsTab::Stats => 10,
+                    ResultsTab::Diff => self.result_diff.as_ref().map_or(0, |d| d.view.len()),
                 };
                 self.results_selected = (self.results_selected + 20)
                     .min(max_rows.saturating_sub(1));
@@ -347,9 +639,10 @@ impl App {
             }
             KeyCode::End => {
                 let max_rows = match self.results_tab {
-                    ResultsTab::Data => self.result.rows.len(),
+                    ResultsTab::Data => self.result_view.len(),
                     ResultsTab::Columns => self.result.columns.len(),
                     ResultsTab::Stats => 10,
+                    ResultsTab::Diff => self.result_diff.as_ref().map_or(0, |d| d.view.len()),
                 };
                 self.results_selected = max_rows.saturating_sub(1);
             }
@@ -357,17 +650,82 @@ impl App {
             KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                 self.copy_current_cell();
             }
-            // Export CSV (Ctrl+E)
+            // Export dialog with the last-used settings (Ctrl+E), or preset to JSON / Excel
             KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.export_results_csv();
+                self.open_export_dialog(ExportSource::Grid, None);
             }
             KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.export_results_json();
+                self.open_export_dialog(ExportSource::Grid, Some(ExportFormat::Json));
+            }
+            KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.open_export_dialog(ExportSource::Grid, Some(ExportFormat::Xlsx));
             }
             // Copy row as INSERT statement
             KeyCode::Char('i') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                 self.copy_row_as_insert();
             }
+            // INSERT/MERGE script for the whole result or the filtered view
+            KeyCode::Char('I') if self.results_tab == ResultsTab::Data => {
+                self.open_script_dialog();
+            }
+            // Enter edit mode for single-table results
+            KeyCode::Char('e') if self.results_tab == ResultsTab::Data => {
+                self.start_edit_mode();
+            }
+            // Inspect the full cell value
+            KeyCode::Char('i') if self.results_tab == ResultsTab::Data => {
+                self.open_cell_inspector();
+            }
+            // Toggle record view for wide rows
+            KeyCode::Char('v') if self.results_tab == ResultsTab::Data => {
+                self.record_view = !self.record_view;
+                self.record_field_scroll = 0;
+            }
+            // Sort by selected column (asc → desc → off)
+            KeyCode::Char('s') if self.results_tab == ResultsTab::Data => {
+                self.toggle_sort();
+            }
+            // Filter selected column
+            KeyCode::Char('f') if self.results_tab == ResultsTab::Data => {
+                if let Some(col) = self.selected_col() {
+                    self.open_grid_input(GridInputKind::Filter(col));
+                }
+            }
+            // Quick search across all cells
+            KeyCode::Char('/') if self.results_tab == ResultsTab::Data => {
+                self.open_grid_input(GridInputKind::Search);
+            }
+            KeyCode::Char('n') if self.results_tab == ResultsTab::Data => {
+                self.jump_to_search_match(true);
+            }
+            KeyCode::Char('N') if self.results_tab == ResultsTab::Data => {
+                self.jump_to_search_match(false);
+            }
+            // Clear sort, filters and search
+            KeyCode::Char('x') if self.results_tab == ResultsTab::Data => {
+                self.clear_result_view();
+            }
+            // Column widths, pinning and visibility
+            KeyCode::Char('<') if self.results_tab == ResultsTab::Data => {
+                self.resize_selected_column(-2);
+            }
+            KeyCode::Char('>') if self.results_tab == ResultsTab::Data => {
+                self.resize_selected_column(2);
+            }
+            KeyCode::Char('P') if self.results_tab == ResultsTab::Data => {
+                self.toggle_pin_columns();
+            }
+            KeyCode::Char('H') if self.results_tab == ResultsTab::Data => {
+                self.hide_selected_column();
+            }
+            KeyCode::Char('U') if self.results_tab == ResultsTab::Data => {
+                self.show_all_columns();
+            }
+            // Earlier / later result sets, without re-running
+            KeyCode::Char('[') => self.step_result_history(-1),
+            KeyCode::Char(']') => self.step_result_history(1),
+            // Compare with an earlier result set
+            KeyCode::Char('D') => self.open_diff_setup(),
             // Enter/Esc goes back to query editor
             KeyCode::Enter | KeyCode::Esc => {
                 self.active_panel = ActivePanel::QueryEditor;
@@ -377,81 +735,430 @@ impl App {
         Ok(())
     }
 
-    /// Export results to CSV file
-    fn export_results_csv(&mut self) {
-        if self.result.rows.is_empty() {
-            self.error = Some("No results to export".to_string());
-            return;
+    /// Range selection on the Data tab: Shift+arrows extend it, V/C take whole rows/columns,
+    /// Ctrl+Y copies it as TSV and Y offers other formats; plain movement drops it
+    fn handle_grid_selection(&mut self, key: KeyEvent) -> bool {
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Up if shift => self.extend_selection(-1, 0),
+            KeyCode::Down if shift => self.extend_selection(1, 0),
+            KeyCode::Left if shift => self.extend_selection(0, -1),
+            KeyCode::Right if shift => self.extend_selection(0, 1),
+            KeyCode::PageUp if shift => self.extend_selection(-20, 0),
+            KeyCode::PageDown if shift => self.extend_selection(20, 0),
+            KeyCode::Char('V') => self.toggle_line_selection(SelectionKind::Rows),
+            KeyCode::Char('C') => self.toggle_line_selection(SelectionKind::Columns),
+            KeyCode::Char('a') if ctrl => self.select_all_cells(),
+            KeyCode::Char('y') if ctrl && self.grid_selection.is_some() => self.copy_selection(CopyFormat::Tsv),
+            KeyCode::Char('Y') => self.copy_menu = Some(CopyFormat::Tsv),
+            KeyCode::Esc if self.grid_selection.is_some() => self.grid_selection = None,
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Char('h' | 'j' | 'k' | 'l')
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End => {
+                self.grid_selection = None;
+                return false;
+            }
+            _ => return false,
         }
+        true
+    }
+
+    /// Copy-as menu: ↑/↓ and Enter, or a format's letter
+    fn handle_copy_menu(&mut self, key: KeyEvent) {
+        let Some(format) = self.copy_menu else {
+            return;
+        };
+        let chosen = match key.code {
+            KeyCode::Esc => {
+                self.copy_menu = None;
+                return;
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.copy_menu = Some(step(&CopyFormat::ALL, &format, -1));
+                return;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.copy_menu = Some(step(&CopyFormat::ALL, &format, 1));
+                return;
+            }
+            KeyCode::Enter => format,
+            KeyCode::Char(c) => match CopyFormat::ALL.into_iter().find(|f| f.key() == c) {
+                Some(format) => format,
+                None => return,
+            },
+            _ => return,
+        };
+        self.copy_menu = None;
+        self.copy_selection(chosen);
+    }
 
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let filename = format!("export_{}.csv", timestamp);
+    /// Record view: fields run vertically, records step left/right
+    fn handle_record_view(&mut self, key: KeyEvent) -> bool {
+        let field_count = self.column_layout.len();
+        let record_count = self.result_view.len();
+        let page = self.record_page_rows.saturating_sub(1).max(1);
+        let (field, record) = (self.results_col_selected, self.results_selected);
 
-        match self.export_csv(&filename) {
-            Ok(()) => {
-                self.message = Some(format!("✓ Exported {} rows to {}", self.result.rows.len(), filename));
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.results_col_selected = self.results_col_selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.results_col_selected + 1 < field_count {
+                    self.results_col_selected += 1;
+                }
             }
-            Err(e) => {
-                self.error = Some(format!("Export failed: {}", e));
+            // A field taller than the pane pages through its value before moving on
+            KeyCode::PageUp if self.record_field_scroll > 0 => {
+                self.record_field_scroll = self.record_field_scroll.saturating_sub(page);
+                return true;
+            }
+            KeyCode::PageDown if self.record_field_scroll < self.record_field_overflow => {
+                self.record_field_scroll = (self.record_field_scroll + page).min(self.record_field_overflow);
+                return true;
+            }
+            KeyCode::PageUp => {
+                self.results_col_selected = self.results_col_selected.saturating_sub(10);
+            }
+            KeyCode::PageDown => {
+                self.results_col_selected = (self.results_col_selected + 10).min(field_count.saturating_sub(1));
             }
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('[') => {
+                self.results_selected = self.results_selected.saturating_sub(1);
+            }
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(']') => {
+                if self.results_selected + 1 < record_count {
+                    self.results_selected += 1;
+                }
+            }
+            KeyCode::Home => self.results_col_selected = 0,
+            KeyCode::End => self.results_col_selected = field_count.saturating_sub(1),
+            KeyCode::Esc => self.record_view = false,
+            _ => return false,
         }
+        if (field, record) != (self.results_col_selected, self.results_selected) {
+            self.record_field_scroll = 0;
+        }
+        true
     }
 
-    /// Export results to JSON file
-    fn export_results_json(&mut self) {
-        if self.result.rows.is_empty() {
-            self.error = Some("No results to export".to_string());
+    /// Cell inspector popup: scroll, toggle raw/pretty, copy, save
+    fn handle_inspector(&mut self, key: KeyEvent) {
+        let Some(inspector) = self.inspector.as_mut() else {
             return;
+        };
+        let max_scroll = inspector.max_scroll();
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                inspector.scroll = inspector.scroll.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                inspector.scroll = (inspector.scroll + 1).min(max_scroll);
+            }
+            KeyCode::PageUp => {
+                inspector.scroll = inspector.scroll.saturating_sub(20);
+            }
+            KeyCode::PageDown => {
+                inspector.scroll = (inspector.scroll + 20).min(max_scroll);
+            }
+            KeyCode::Home => inspector.scroll = 0,
+            KeyCode::End => inspector.scroll = max_scroll,
+            KeyCode::Char('r') => inspector.toggle_pretty(),
+            KeyCode::Char('y') => {
+                let text = inspector.text();
+                if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                    let _ = clipboard.set_text(&text);
+                    self.message = Some(format!("Copied {} line(s)", text.lines().count()));
+                }
+            }
+            KeyCode::Char('w') => self.save_inspected_cell(),
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => self.save_inspected_cell(),
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('i') => self.inspector = None,
+            _ => {}
         }
+    }
 
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let filename = format!("export_{}.json", timestamp);
+    /// Open/save-as file browser popup
+    fn handle_file_browser(&mut self, key: KeyEvent) {
+        let Some(browser) = self.file_browser.as_mut() else {
+            return;
+        };
+        let typing = browser.mode == FileBrowserMode::SaveAs;
 
-        match self.export_json(&filename) {
-            Ok(()) => {
-                self.message = Some(format!("✓ Exported {} rows to {}", self.result.rows.len(), filename));
-            }
-            Err(e) => {
-                self.error = Some(format!("Export failed: {}", e));
+        match key.code {
+            KeyCode::Esc => self.file_browser = None,
+            KeyCode::Enter => self.confirm_file_browser(),
+            KeyCode::Up => browser.move_selection(-1),
+            KeyCode::Down => browser.move_selection(1),
+            KeyCode::PageUp => browser.move_selection(-20),
+            KeyCode::PageDown => browser.move_selection(20),
+            KeyCode::Home => browser.move_selection(-(browser.entries.len() as isize)),
+            KeyCode::End => browser.move_selection(browser.entries.len() as isize),
+            KeyCode::Tab => browser.toggle_show_all(),
+            KeyCode::Backspace if typing && !browser.input.is_empty() => browser.pop_input(),
+            KeyCode::Backspace | KeyCode::Left => browser.go_up(),
+            KeyCode::Char('h') if !typing => browser.go_up(),
+            KeyCode::Char('k') if !typing => browser.move_selection(-1),
+            KeyCode::Char('j') if !typing => browser.move_selection(1),
+            KeyCode::Right | KeyCode::Char('l') if !typing => {
+                if let Some(name) = browser.selected_entry().filter(|e| e.is_dir).map(|e| e.name.clone()) {
+                    browser.enter_dir(&name);
+                }
             }
+            KeyCode::Char(c) if typing && !key.modifiers.contains(KeyModifiers::CONTROL) => browser.push_input(c),
+            _ => {}
         }
     }
 
-    /// Copy current row as INSERT statement
-    fn copy_row_as_insert(&mut self) {
-        if self.result.rows.is_empty() || self.result.columns.is_empty() {
+    /// Filter/search prompt on the Data tab
+    fn handle_grid_input(&mut self, key: KeyEvent) {
+        let Some(input) = self.grid_input.as_mut() else {
             return;
+        };
+
+        match key.code {
+            KeyCode::Char(c) => input.buffer.push(c),
+            KeyCode::Backspace => {
+                input.buffer.pop();
+            }
+            KeyCode::Enter => self.submit_grid_input(),
+            KeyCode::Esc => self.grid_input = None,
+            _ => {}
         }
+    }
 
-        if let Some(row) = self.result.rows.get(self.results_selected) {
-            let columns: Vec<String> = self.result.columns.iter()
-                .map(|c| format!("[{}]", c.name))
-                .collect();
-
-            let values: Vec<String> = row.iter()
-                .map(|cell| {
-                    match cell {
-                        crate::db::CellValue::Null => "NULL".to_string(),
-                        crate::db::CellValue::String(s) => format!("'{}'", s.replace('\'', "''")),
-                        crate::db::CellValue::DateTime(s) => format!("'{}'", s),
-                        crate::db::CellValue::Int(n) => n.to_string(),
-                        crate::db::CellValue::Float(n) => n.to_string(),
-                        crate::db::CellValue::Bool(b) => if *b { "1" } else { "0" }.to_string(),
-                        crate::db::CellValue::Binary(b) => format!("0x{}", b.iter().map(|x| format!("{:02X}", x)).collect::<String>()),
+    /// Edit mode keys on the Data tab; returns false to fall through to navigation
+    fn handle_edit_mode(&mut self, key: KeyEvent) -> bool {
+        let col = self.selected_col().unwrap_or(usize::MAX);
+        let Some(session) = self.edit_session.as_mut() else {
+            return false;
+        };
+
+        // Typing into a cell
+        if let Some(input) = session.input.as_mut() {
+            match key.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter => {
+                    let text = input.clone();
+                    let row = self.result_view.row_at(self.results_selected).unwrap_or(usize::MAX);
+                    match session.stage_cell(&self.result.rows, row, col, &text) {
+                        Ok(()) => session.input = None,
+                        Err(e) => self.error = Some(e),
                     }
-                })
-                .collect();
+                }
+                KeyCode::Esc => {
+                    session.input = None;
+                    self.error = None;
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        // Reviewing the generated batch
+        if let Some(preview) = session.preview.as_ref() {
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => {
+                    session.preview_scroll = session.preview_scroll.saturating_sub(1);
+                }
+                KeyCode::Down | KeyCode::Char('j') if session.preview_scroll + 1 < preview.len() => {
+                    session.preview_scroll += 1;
+                }
+                KeyCode::Enter | KeyCode::Char('y') => self.apply_edits(),
+                KeyCode::Esc | KeyCode::Char('n') => session.preview = None,
+                _ => {}
+            }
+            return true;
+        }
+
+        if key.code != KeyCode::Esc {
+            session.confirm_discard = false;
+        }
+
+        let row = self.result_view.row_at(self.results_selected).unwrap_or(usize::MAX);
+        match key.code {
+            KeyCode::Enter | KeyCode::F(2) if row < self.result.rows.len() => {
+                match session.editable_column(col) {
+                    Ok(_) => {
+                        let current = self.result.rows.get(row)
+                            .and_then(|r| r.get(col))
+                            .map(|cell| session.display_value(row, col, cell))
+                            .map(|cell| match cell {
+                                crate::db::CellValue::Null => String::new(),
+                                other => other.to_string(),
+                            })
+                            .unwrap_or_default();
+                        session.input = Some(current);
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+            KeyCode::Char('o') | KeyCode::Insert => {
+                let width = self.result.columns.len();
+                self.result.rows.push(vec![crate::db::CellValue::Null; width]);
+                self.result.row_count = self.result.rows.len();
+                // Show the new row even if it does not pass the active filters
+                self.result_view.order.push(self.result.rows.len() - 1);
+                self.results_selected = self.result_view.len() - 1;
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if row < self.result.rows.len() {
+                    session.toggle_delete(row);
+                }
+            }
+            KeyCode::Char('u') => session.revert_row(row),
+            KeyCode::Char('p') => {
+                let statements = session.build_statements(&self.result.rows);
+                if statements.is_empty() {
+                    self.message = Some("No staged changes".to_string());
+                } else {
+                    session.preview = Some(statements);
+                    session.preview_scroll = 0;
+                }
+            }
+            KeyCode::Esc => {
+                let changes = session.change_count(self.result.rows.len());
+                if changes > 0 && !session.confirm_discard {
+                    session.confirm_discard = true;
+                    self.message = Some(format!("{} unsaved change(s) - press Esc again to discard", changes));
+                } else {
+                    self.discard_edits();
+                    self.message = Some("Left edit mode".to_string());
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Diff tab: scroll columns, filter by change, export, close
+    fn handle_diff_view(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Left | KeyCode::Char('h') => self.scroll_diff_columns(-1),
+            KeyCode::Right | KeyCode::Char('l') => self.scroll_diff_columns(1),
+            KeyCode::Char('f') => self.cycle_diff_filter(),
+            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => self.export_result_diff(),
+            KeyCode::Char('x') => self.close_result_diff(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Compare popup: pick a result set, then toggle key columns
+    fn handle_diff_setup(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_diff_setup_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_diff_setup_selection(1),
+            KeyCode::Char(' ') => self.toggle_diff_key(),
+            KeyCode::Enter => self.confirm_diff_setup(),
+            KeyCode::Esc | KeyCode::Char('q') => self.diff_setup = None,
+            _ => {}
+        }
+    }
 
-            let insert = format!(
-                "INSERT INTO [TableName] ({}) VALUES ({});",
-                columns.join(", "),
-                values.join(", ")
-            );
+    /// Script dialog: ↑/↓/Tab fields, ←/→/Space change, Enter generates
+    fn handle_script_dialog(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Enter {
+            self.confirm_script_dialog();
+            return;
+        }
+        let Some(dialog) = self.script_dialog.as_mut() else {
+            return;
+        };
+        let text_field = dialog.field.is_text();
+        match key.code {
+            KeyCode::Esc => self.script_dialog = None,
+            KeyCode::Tab | KeyCode::Down => dialog.next_field(1),
+            KeyCode::BackTab | KeyCode::Up => dialog.next_field(-1),
+            KeyCode::Left if !text_field => dialog.cycle(-1),
+            KeyCode::Right if !text_field => dialog.cycle(1),
+            KeyCode::Left | KeyCode::Right if dialog.field == ScriptField::Batch => {
+                dialog.cycle(if key.code == KeyCode::Left { -1 } else { 1 })
+            }
+            KeyCode::Backspace => dialog.pop(),
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => dialog.push(c),
+            _ => {}
+        }
+    }
+
+    /// Export dialog: ↑/↓ fields, ←/→/Space change, Tab completes the path
+    fn handle_export_dialog(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Enter {
+            self.confirm_export_dialog();
+            return;
+        }
+        let Some(dialog) = self.export_dialog.as_mut() else {
+            return;
+        };
+        let text_field = dialog.field.is_text();
+        match key.code {
+            KeyCode::Esc => self.export_dialog = None,
+            KeyCode::Tab if dialog.field == ExportField::Path => dialog.complete_path(),
+            KeyCode::Tab | KeyCode::Down => dialog.next_field(1),
+            KeyCode::BackTab | KeyCode::Up => dialog.next_field(-1),
+            KeyCode::Left if !text_field => dialog.cycle(-1),
+            KeyCode::Right if !text_field => dialog.cycle(1),
+            KeyCode::Backspace => dialog.pop(),
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => dialog.push(c),
+            _ => {}
+        }
+    }
 
-            if let Ok(mut clipboard) = arboard::Clipboard::new() {
-                let _ = clipboard.set_text(&insert);
-                self.message = Some("✓ Copied INSERT statement to clipboard".to_string());
+    /// Import wizard: fields on the File/Target pages, rows on the Mapping page, Esc goes back
+    fn handle_import_wizard(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => return self.confirm_import_step(),
+            KeyCode::Esc => return self.import_wizard_back(),
+            _ => {}
+        }
+        let Some(wizard) = self.import_wizard.as_mut() else {
+            return;
+        };
+        match wizard.step {
+            ImportStep::File | ImportStep::Target => {
+                let text_field = wizard.field.is_text();
+                match key.code {
+                    KeyCode::Tab if wizard.field == ImportField::Path => wizard.complete_path(),
+                    KeyCode::Tab | KeyCode::Down => wizard.next_field(1),
+                    KeyCode::BackTab | KeyCode::Up => wizard.next_field(-1),
+                    KeyCode::Left if !text_field => wizard.cycle(-1),
+                    KeyCode::Right if !text_field => wizard.cycle(1),
+                    KeyCode::Backspace => wizard.pop(),
+                    KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => wizard.push(c),
+                    _ => {}
+                }
             }
+            ImportStep::Mapping => match key.code {
+                KeyCode::Up | KeyCode::Char('k') => wizard.move_selection(-1),
+                KeyCode::Down | KeyCode::Char('j') => wizard.move_selection(1),
+                KeyCode::PageUp => wizard.move_selection(-10),
+                KeyCode::PageDown => wizard.move_selection(10),
+                KeyCode::Left | KeyCode::Char('h') => wizard.cycle_mapping(-1),
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') => wizard.cycle_mapping(1),
+                _ => {}
+            },
+            ImportStep::Report => match key.code {
+                KeyCode::Up | KeyCode::Char('k') => wizard.move_selection(-1),
+                KeyCode::Down | KeyCode::Char('j') => wizard.move_selection(1),
+                KeyCode::PageUp => wizard.move_selection(-10),
+                KeyCode::PageDown => wizard.move_selection(10),
+                KeyCode::Char('c') | KeyCode::Char('y') => {
+                    let text = wizard.report_text();
+                    self.copy_to_clipboard(&text);
+                }
+                _ => {}
+            },
         }
     }
 
@@ -477,6 +1184,16 @@ impl App {
                     }
                 }
             }
+            // Import a CSV file into the selected table
+            KeyCode::Char('i') => {
+                let visible = self.get_visible_schema_nodes();
+                let table = visible
+                    .get(self.schema_selected)
+                    .map(|(_, node)| node)
+                    .filter(|node| node.node_type == crate::app::SchemaNodeType::Table)
+                    .map(|node| node.name.clone());
+                self.open_import_wizard(None, table);
+            }
             KeyCode::Esc => {
                 self.active_panel = ActivePanel::QueryEditor;
             }
@@ -487,19 +1204,81 @@ impl App {
 
     /// History panel
     fn handle_history(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Up => {
-                self.history_selected = self.history_selected.saturating_sub(1);
-            }
-            KeyCode::Down => {
-                let max = self.history.len().saturating_sub(1);
-                if self.history_selected < max {
-                    self.history_selected += 1;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        // Incremental search: typing narrows the list, Ctrl+R steps to the next (older) match
+        if self.history_searching {
+            match key.code {
+                KeyCode::Esc => {
+                    self.history_searching = false;
+                    self.history_filter.text.clear();
+                    self.history_selected = 0;
+                }
+                KeyCode::Enter => {
+                    self.history_searching = false;
+                    self.load_history_entry();
+                }
+                KeyCode::Char('r') if ctrl => self.move_history_selection(1),
+                KeyCode::Up => self.move_history_selection(-1),
+                KeyCode::Down => self.move_history_selection(1),
+                KeyCode::Backspace => {
+                    self.history_filter.text.pop();
+                    self.history_selected = 0;
+                }
+                KeyCode::Char(c) if !ctrl => {
+                    self.history_filter.text.push(c);
+                    self.history_selected = 0;
                 }
+                _ => {}
             }
+            return Ok(());
+        }
+
+        if key.code != KeyCode::Char('d') {
+            self.history_confirm_delete = false;
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_history_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_history_selection(1),
+            KeyCode::PageUp => self.move_history_selection(-10),
+            KeyCode::PageDown => self.move_history_selection(10),
+            KeyCode::Home | KeyCode::Char('g') => self.history_selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.move_history_selection(isize::MAX / 2),
             KeyCode::Enter => {
                 self.load_history_entry();
             }
+            KeyCode::Char('/') => self.start_history_search(),
+            KeyCode::Char('r') if ctrl => self.start_history_search(),
+            // Filters: database, date range, status, minimum duration
+            KeyCode::Char('D') => self.cycle_history_database(),
+            KeyCode::Char('T') => {
+                self.history_filter.date_range = self.history_filter.date_range.next();
+                self.history_selected = 0;
+            }
+            KeyCode::Char('S') => {
+                self.history_filter.status = self.history_filter.status.next();
+                self.history_selected = 0;
+            }
+            KeyCode::Char('M') => {
+                self.history_filter.next_min_duration();
+                self.history_selected = 0;
+            }
+            KeyCode::Char('x') => {
+                self.history_filter = HistoryFilter::default();
+                self.history_selected = 0;
+            }
+            KeyCode::Char('r') => self.show_history_result(),
+            KeyCode::Char('p') => self.toggle_history_pin(),
+            KeyCode::Char('d') | KeyCode::Delete => self.delete_history_entry(),
+            KeyCode::Char('y') => {
+                if let Some(query) = self.selected_history_index().and_then(|i| self.history.get(i)).map(|e| e.query.clone()) {
+                    self.copy_to_clipboard(&query);
+                }
+            }
+            KeyCode::Esc if !self.history_filter.text.is_empty() || self.history_filter.has_filters() => {
+                self.history_filter = HistoryFilter::default();
+                self.history_selected = 0;
+            }
             KeyCode::Esc => {
                 self.active_panel = ActivePanel::QueryEditor;
             }
@@ -508,43 +1287,90 @@ impl App {
         Ok(())
     }
 
-    /// Move cursor up one line in query
-    fn move_cursor_up(&mut self) {
-        let text_before: String = self.query.chars().take(self.cursor_pos).collect();
-        if let Some(last_newline) = text_before.rfind('\n') {
-            let col = self.cursor_pos - last_newline - 1;
-            let before_that: String = text_before.chars().take(last_newline).collect();
-            if let Some(prev_newline) = before_that.rfind('\n') {
-                let prev_line_len = last_newline - prev_newline - 1;
-                self.cursor_pos = prev_newline + 1 + col.min(prev_line_len);
-            } else {
-                self.cursor_pos = col.min(last_newline);
+    /// Snippets panel: browse, search and insert saved snippets
+    fn handle_snippets(&mut self, key: KeyEvent) {
+        if self.snippet_panel.searching {
+            match key.code {
+                KeyCode::Esc => {
+                    self.snippet_panel.searching = false;
+                    self.snippet_panel.filter.clear();
+                }
+                KeyCode::Enter | KeyCode::Down => self.snippet_panel.searching = false,
+                KeyCode::Backspace => {
+                    self.snippet_panel.filter.pop();
+                    self.snippet_panel.selected = 0;
+                }
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.snippet_panel.filter.push(c);
+                    self.snippet_panel.selected = 0;
+                }
+                _ => {}
             }
+            return;
         }
-    }
 
-    /// Move cursor down one line in query
-    fn move_cursor_down(&mut self) {
-        let text_before: String = self.query.chars().take(self.cursor_pos).collect();
-        let text_after: String = self.query.chars().skip(self.cursor_pos).collect();
+        if key.code != KeyCode::Char('d') {
+            self.snippet_panel.confirm_delete = false;
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_snippet_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_snippet_selection(1),
+            KeyCode::PageUp => self.move_snippet_selection(-10),
+            KeyCode::PageDown => self.move_snippet_selection(10),
+            KeyCode::Home | KeyCode::Char('g') => self.snippet_panel.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.move_snippet_selection(isize::MAX / 2),
+            KeyCode::Enter => match self.selected_snippet() {
+                Some(index) => self.insert_snippet(index),
+                None => self.toggle_snippet_folder(),
+            },
+            KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right | KeyCode::Char('h') | KeyCode::Char('l') => {
+                self.toggle_snippet_folder();
+            }
+            KeyCode::Char('/') => {
+                self.snippet_panel.searching = true;
+                self.snippet_panel.filter.clear();
+                self.snippet_panel.selected = 0;
+            }
+            KeyCode::Char('a') => self.new_snippet_from_editor(),
+            KeyCode::Char('e') => self.edit_selected_snippet(),
+            KeyCode::Char('u') => self.update_selected_snippet_body(),
+            KeyCode::Char('d') | KeyCode::Delete => self.delete_selected_snippet(),
+            KeyCode::Char('y') => {
+                if let Some(body) = self.selected_snippet().and_then(|i| self.snippets.get(i)).map(|s| s.body.clone()) {
+                    self.copy_to_clipboard(&body);
+                }
+            }
+            KeyCode::Esc if !self.snippet_panel.filter.is_empty() => {
+                self.snippet_panel.filter.clear();
+                self.snippet_panel.selected = 0;
+            }
+            KeyCode::Esc => self.active_panel = ActivePanel::QueryEditor,
+            _ => {}
+        }
+    }
 
-        let col = if let Some(last_newline) = text_before.rfind('\n') {
-            self.cursor_pos - last_newline - 1
-        } else {
-            self.cursor_pos
+    /// New/edit snippet popup: Tab between fields, Enter saves, Esc cancels
+    fn handle_snippet_form(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Enter {
+            self.confirm_snippet_form();
+            return;
+        }
+        let Some(form) = self.snippet_form.as_mut() else {
+            return;
         };
-
-        if let Some(next_newline) = text_after.find('\n') {
-            let next_line_start = self.cursor_pos + next_newline + 1;
-            let remaining: String = self.query.chars().skip(next_line_start).collect();
-            let next_line_len = remaining.find('\n').unwrap_or(remaining.len());
-            self.cursor_pos = next_line_start + col.min(next_line_len);
+        match key.code {
+            KeyCode::Esc => self.snippet_form = None,
+            KeyCode::Tab | KeyCode::Down => form.next_field(1),
+            KeyCode::BackTab | KeyCode::Up => form.next_field(-1),
+            KeyCode::Backspace => form.pop(),
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => form.push(c),
+            _ => {}
         }
     }
 
     fn copy_current_cell(&mut self) {
-        if let Some(row) = self.result.rows.get(self.results_selected) {
-            if let Some(cell) = row.get(self.results_col_selected) {
+        if let Some(row) = self.selected_row().and_then(|r| self.result.rows.get(r)) {
+            if let Some(cell) = self.selected_col().and_then(|c| row.get(c)) {
                 let text = cell.to_string();
                 if let Ok(mut clipboard) = arboard::Clipboard::new() {
                     let _ = clipboard.set_text(&text);
@@ -553,32 +1379,4 @@ impl App {
             }
         }
     }
-
-    fn export_csv(&self, filename: &str) -> Result<()> {
-        let mut wtr = csv::Writer::from_path(filename)?;
-        let headers: Vec<String> = self.result.columns.iter().map(|c| c.name.clone()).collect();
-        wtr.write_record(&headers)?;
-        for row in &self.result.rows {
-            let record: Vec<String> = row.iter().map(|c| c.to_string()).collect();
-            wtr.write_record(&record)?;
-        }
-        wtr.flush()?;
-        Ok(())
-    }
-
-    fn export_json(&self, filename: &str) -> Result<()> {
-        let mut rows: Vec<serde_json::Map<String, serde_json::Value>> = Vec::new();
-        for row in &self.result.rows {
-            let mut obj = serde_json::Map::new();
-            for (i, col) in self.result.columns.iter().enumerate() {
-                if let Some(cell) = row.get(i) {
-                    obj.insert(col.name.clone(), serde_json::Value::String(cell.to_string()));
-                }
-            }
-            rows.push(obj);
-        }
-        let json = serde_json::to_string_pretty(&rows)?;
-        std::fs::write(filename, json)?;
-        Ok(())
-    }
 }
@@ -1,66 +1,397 @@
 //! Query history management
 
-use anyhow::Result;
-use chrono::{DateTime, Local};
+use crate::app::{ActivePanel, App};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Local, TimeZone};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::fs;
 use std::path::PathBuf;
 
+/// How a query run ended
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryStatus {
+    #[default]
+    Success,
+    Error,
+    Cancelled,
+}
+
+impl QueryStatus {
+    /// Value stored in the history database (matches the serde name)
+    fn label_key(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Error => "error",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Success => "ok",
+            Self::Error => "error",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
 /// A single history entry
+///
+/// Fields added after the first release default when missing, so older
+/// history files still load.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HistoryEntry {
+    /// Row id in the history database (0 until stored)
+    #[serde(default, skip_serializing)]
+    pub id: i64,
     pub query: String,
     pub timestamp: DateTime<Local>,
     pub execution_time_ms: u64,
     pub row_count: Option<usize>,
     pub database: String,
+    /// Kept at the top of the panel and never trimmed
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub status: QueryStatus,
+    /// Server error for a failed query
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Server the query ran on (host:port)
+    #[serde(default)]
+    pub server: String,
+    /// Connection profile name
+    #[serde(default)]
+    pub profile: String,
+    /// Rows changed by INSERT/UPDATE/DELETE statements
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub affected_rows: Option<u64>,
 }
 
-/// Query history manager
+impl HistoryEntry {
+    /// A successful run; use `failed`/`cancelled` to change the outcome
+    pub fn new(query: String, database: String, server: String, profile: String) -> Self {
+        Self {
+            id: 0,
+            query,
+            timestamp: Local::now(),
+            execution_time_ms: 0,
+            row_count: None,
+            database,
+            pinned: false,
+            status: QueryStatus::Success,
+            error: None,
+            server,
+            profile,
+            affected_rows: None,
+        }
+    }
+
+    pub fn failed(mut self, error: String) -> Self {
+        self.status = QueryStatus::Error;
+        self.error = Some(error);
+        self
+    }
+
+    pub fn cancelled(mut self) -> Self {
+        self.status = QueryStatus::Cancelled;
+        self
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status == QueryStatus::Success
+    }
+}
+
+/// Date filter of the history panel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HistoryDateRange {
+    #[default]
+    All,
+    Today,
+    Week,
+    Month,
+}
+
+impl HistoryDateRange {
+    pub fn next(self) -> Self {
+        match self {
+            Self::All => Self::Today,
+            Self::Today => Self::Week,
+            Self::Week => Self::Month,
+            Self::Month => Self::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::All => "any time",
+            Self::Today => "today",
+            Self::Week => "last 7 days",
+            Self::Month => "last 30 days",
+        }
+    }
+
+    /// Earliest timestamp inside the range
+    fn since(self) -> Option<DateTime<Local>> {
+        let now = Local::now();
+        match self {
+            Self::All => None,
+            Self::Today => now
+                .date_naive()
+                .and_hms_opt(0, 0, 0)
+                .and_then(|midnight| midnight.and_local_timezone(Local).earliest()),
+            Self::Week => Some(now - Duration::days(7)),
+            Self::Month => Some(now - Duration::days(30)),
+        }
+    }
+}
+
+/// Success/failure filter of the history panel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HistoryStatusFilter {
+    #[default]
+    All,
+    Success,
+    Failed,
+}
+
+impl HistoryStatusFilter {
+    pub fn next(self) -> Self {
+        match self {
+            Self::All => Self::Success,
+            Self::Success => Self::Failed,
+            Self::Failed => Self::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Success => "succeeded",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// Minimum durations the history filter cycles through
+pub const HISTORY_MIN_DURATIONS_MS: [u64; 4] = [0, 100, 1000, 10_000];
+
+/// Search text and filters of the history panel
 #[derive(Clone, Debug, Default)]
+pub struct HistoryFilter {
+    /// Every word must appear in the query
+    pub text: String,
+    pub database: Option<String>,
+    pub date_range: HistoryDateRange,
+    pub status: HistoryStatusFilter,
+    pub min_duration_ms: u64,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        let query = entry.query.to_lowercase();
+        self.text.split_whitespace().all(|term| query.contains(&term.to_lowercase()))
+            && self.database.as_ref().is_none_or(|db| entry.database.eq_ignore_ascii_case(db))
+            && self.date_range.since().is_none_or(|since| entry.timestamp >= since)
+            && match self.status {
+                HistoryStatusFilter::All => true,
+                HistoryStatusFilter::Success => entry.is_success(),
+                HistoryStatusFilter::Failed => !entry.is_success(),
+            }
+            && entry.execution_time_ms >= self.min_duration_ms
+    }
+
+    /// Filters other than the search text
+    pub fn has_filters(&self) -> bool {
+        self.database.is_some()
+            || self.date_range != HistoryDateRange::All
+            || self.status != HistoryStatusFilter::All
+            || self.min_duration_ms > 0
+    }
+
+    /// Short description of the active filters, e.g. "Staging · today · ≥1s"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(db) = &self.database {
+            parts.push(db.clone());
+        }
+        if self.date_range != HistoryDateRange::All {
+            parts.push(self.date_range.label().to_string());
+        }
+        if self.status != HistoryStatusFilter::All {
+            parts.push(self.status.label().to_string());
+        }
+        if self.min_duration_ms > 0 {
+            parts.push(format!("≥{}", format_duration_ms(self.min_duration_ms)));
+        }
+        parts.join(" · ")
+    }
+
+    /// Step to the next minimum duration
+    pub fn next_min_duration(&mut self) {
+        let index = HISTORY_MIN_DURATIONS_MS
+            .iter()
+            .position(|&d| d == self.min_duration_ms)
+            .map_or(0, |i| (i + 1) % HISTORY_MIN_DURATIONS_MS.len());
+        self.min_duration_ms = HISTORY_MIN_DURATIONS_MS[index];
+    }
+}
+
+/// Compact duration for the history columns: 850ms, 1.2s, 3m05s
+pub fn format_duration_ms(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if ms < 60_000 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else {
+        format!("{}m{:02}s", ms / 60_000, (ms % 60_000) / 1000)
+    }
+}
+
+/// Default cap on stored entries (pinned entries don't count)
+pub const DEFAULT_HISTORY_MAX_ENTRIES: usize = 10_000;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        query TEXT NOT NULL,
+        timestamp_ms INTEGER NOT NULL,
+        execution_time_ms INTEGER NOT NULL,
+        row_count INTEGER,
+        database TEXT NOT NULL,
+        pinned INTEGER NOT NULL DEFAULT 0,
+        status TEXT NOT NULL DEFAULT 'success',
+        error TEXT,
+        server TEXT NOT NULL DEFAULT '',
+        profile TEXT NOT NULL DEFAULT '',
+        affected_rows INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS history_timestamp ON history(timestamp_ms);
+    CREATE VIRTUAL TABLE IF NOT EXISTS history_fts
+        USING fts5(query, content='history', content_rowid='id', tokenize='trigram');
+    CREATE TRIGGER IF NOT EXISTS history_ai AFTER INSERT ON history BEGIN
+        INSERT INTO history_fts(rowid, query) VALUES (new.id, new.query);
+    END;
+    CREATE TRIGGER IF NOT EXISTS history_ad AFTER DELETE ON history BEGIN
+        INSERT INTO history_fts(history_fts, rowid, query) VALUES ('delete', old.id, old.query);
+    END;
+    CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+";
+
+const COLUMNS: &str = "id, query, timestamp_ms, execution_time_ms, row_count, database, pinned, \
+                       status, error, server, profile, affected_rows";
+
+/// Query history manager
+///
+/// Entries live in `history.db`, an SQLite database in WAL mode, so several
+/// terminals can append to it at once. Each run is a single INSERT; pins and
+/// deletes touch one row. The loaded entries are also kept in memory for the panel.
+#[derive(Debug, Default)]
 pub struct QueryHistory {
     entries: Vec<HistoryEntry>,
     max_entries: usize,
+    /// Drop unpinned entries older than this many days
+    retention_days: Option<u32>,
     current_index: Option<usize>,
+    /// None when the database can't be opened; history then lasts for the session only
+    conn: Option<Connection>,
+    /// Why the database couldn't be opened
+    open_error: Option<String>,
+    /// Why the old `history.json` could not be imported
+    import_error: Option<String>,
+    /// Last full-text search: (search text, matching ids)
+    search_cache: RefCell<Option<(String, Option<HashSet<i64>>)>>,
 }
 
 impl QueryHistory {
-    pub fn new(max_entries: usize) -> Self {
+    pub fn new(max_entries: usize, retention_days: Option<u32>) -> Self {
         let mut history = Self {
-            entries: Vec::new(),
             max_entries,
-            current_index: None,
+            retention_days,
+            ..Self::default()
         };
-        let _ = history.load();
+        if let Err(e) = history.open() {
+            history.open_error = Some(e.to_string());
+            history.conn = None;
+        }
         history
     }
 
-    /// Add a new entry to history
-    pub fn add(&mut self, query: String, execution_time_ms: u64, row_count: Option<usize>, database: String) {
+    /// History capped by HISTORY_MAX_ENTRIES and HISTORY_RETENTION_DAYS (unset keeps everything)
+    pub fn from_env() -> Self {
+        let max_entries = std::env::var("HISTORY_MAX_ENTRIES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_HISTORY_MAX_ENTRIES);
+        let retention_days = std::env::var("HISTORY_RETENTION_DAYS").ok().and_then(|v| v.parse().ok());
+        Self::new(max_entries, retention_days)
+    }
+
+    /// Why the history database couldn't be opened (history then lasts for the session only)
+    pub fn open_error(&self) -> Option<&str> {
+        self.open_error.as_deref()
+    }
+
+    /// Why the old `history.json` was not imported; the file is then moved aside
+    pub fn import_error(&self) -> Option<&str> {
+        self.import_error.as_deref()
+    }
+
+    /// Open the database, import the old JSON file once, apply retention and load
+    fn open(&mut self) -> Result<()> {
+        let path = Self::history_db();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut conn = Connection::open(&path)?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        if let Err(e) = Self::import_json(&mut conn) {
+            // A bad legacy file must not cost the database; keep it aside for a look later
+            let path = Self::history_json();
+            let aside = path.with_extension("json.unreadable");
+            self.import_error = Some(match fs::rename(&path, &aside) {
+                Ok(()) => format!("{:#}; the file was moved to {}", e, aside.display()),
+                Err(_) => format!("{:#}", e),
+            });
+        }
+        self.conn = Some(conn);
+        self.apply_retention()?;
+        self.load()
+    }
+
+    /// Add a new entry to history; returns its id (the last entry's when it repeats it)
+    pub fn add(&mut self, mut entry: HistoryEntry) -> i64 {
         // Don't add duplicates of the last entry
         if let Some(last) = self.entries.last() {
-            if last.query.trim() == query.trim() {
-                return;
+            if last.query.trim() == entry.query.trim() && last.status == entry.status {
+                return last.id;
             }
         }
 
-        let entry = HistoryEntry {
-            query,
-            timestamp: Local::now(),
-            execution_time_ms,
-            row_count,
-            database,
-        };
-
-        self.entries.push(entry);
+        // Reading back everything newer also picks up entries other terminals added meanwhile
+        let id = self.conn.as_ref().and_then(|conn| insert_entry(conn, &entry).ok());
+        if id.is_none() || self.load_new().is_err() {
+            // Session-only ids keep entries distinguishable without the database
+            entry.id = id.unwrap_or_else(|| self.entries.iter().map(|e| e.id).max().unwrap_or(0) + 1);
+            self.entries.push(entry);
+        }
+        self.search_cache.replace(None);
+        let id = id.unwrap_or_else(|| self.entries.last().map_or(0, |e| e.id));
 
-        // Limit history size
-        while self.entries.len() > self.max_entries {
-            self.entries.remove(0);
+        if self.entries.iter().filter(|e| !e.pinned).count() > self.max_entries {
+            let _ = self.apply_retention();
+            let _ = self.load();
         }
 
         self.current_index = None;
-        let _ = self.save();
+        id
     }
 
     /// Get previous entry (for up arrow)
@@ -80,6 +411,7 @@ impl QueryHistory {
     }
 
     /// Get next entry (for down arrow)
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<&HistoryEntry> {
         if self.entries.is_empty() {
             return None;
@@ -106,47 +438,214 @@ impl QueryHistory {
 
     /// Search history
     pub fn search(&self, term: &str) -> Vec<&HistoryEntry> {
-        let term_lower = term.to_lowercase();
-        self.entries
-            .iter()
-            .filter(|e| e.query.to_lowercase().contains(&term_lower))
-            .collect()
+        let filter = HistoryFilter { text: term.to_string(), ..HistoryFilter::default() };
+        self.filtered(&filter).into_iter().map(|i| &self.entries[i]).collect()
+    }
+
+    /// Indices of the entries matching a filter: pinned first, then newest first
+    pub fn filtered(&self, filter: &HistoryFilter) -> Vec<usize> {
+        let ids = self.full_text_ids(&filter.text);
+        let mut indices: Vec<usize> = (0..self.entries.len())
+            .rev()
+            .filter(|&i| ids.as_ref().is_none_or(|ids| ids.contains(&self.entries[i].id)))
+            .filter(|&i| filter.matches(&self.entries[i]))
+            .collect();
+        indices.sort_by_key(|&i| !self.entries[i].pinned);
+        indices
+    }
+
+    /// Ids whose query contains every search word of three or more characters,
+    /// from the trigram index; None when the index can't narrow the search
+    fn full_text_ids(&self, text: &str) -> Option<HashSet<i64>> {
+        if let Some((cached, ids)) = self.search_cache.borrow().as_ref() {
+            if cached == text {
+                return ids.clone();
+            }
+        }
+
+        let terms: Vec<String> = text
+            .split_whitespace()
+            .filter(|t| t.chars().count() >= 3)
+            .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+            .collect();
+        let ids = match (&self.conn, terms.is_empty()) {
+            (Some(conn), false) => conn
+                .prepare_cached("SELECT rowid FROM history_fts WHERE history_fts MATCH ?1")
+                .and_then(|mut stmt| {
+                    stmt.query_map([terms.join(" ")], |row| row.get::<_, i64>(0))?
+                        .collect::<rusqlite::Result<HashSet<i64>>>()
+                })
+                .ok(),
+            _ => None,
+        };
+        self.search_cache.replace(Some((text.to_string(), ids.clone())));
+        ids
+    }
+
+    /// Databases that appear in the history, sorted
+    pub fn databases(&self) -> Vec<String> {
+        let mut databases: Vec<String> = self.entries.iter().map(|e| e.database.clone()).collect();
+        databases.sort_by_key(|d| d.to_lowercase());
+        databases.dedup();
+        databases
+    }
+
+    pub fn get(&self, index: usize) -> Option<&HistoryEntry> {
+        self.entries.get(index)
     }
 
-    /// Get history file path
-    fn history_file() -> PathBuf {
+    /// Pin or unpin an entry; returns the new state
+    pub fn toggle_pin(&mut self, index: usize) -> Result<Option<bool>> {
+        let Some(entry) = self.entries.get_mut(index) else {
+            return Ok(None);
+        };
+        // Stored first, so the panel never shows a pin that was not saved
+        if let Some(conn) = &self.conn {
+            conn.execute("UPDATE history SET pinned = ?1 WHERE id = ?2", params![!entry.pinned, entry.id])?;
+        }
+        entry.pinned = !entry.pinned;
+        Ok(Some(entry.pinned))
+    }
+
+    /// Delete a single entry
+    pub fn remove(&mut self, index: usize) -> Result<Option<HistoryEntry>> {
+        let Some(entry) = self.entries.get(index) else {
+            return Ok(None);
+        };
+        if let Some(conn) = &self.conn {
+            conn.execute("DELETE FROM history WHERE id = ?1", [entry.id])?;
+        }
+        let entry = self.entries.remove(index);
+        self.current_index = None;
+        self.search_cache.replace(None);
+        Ok(Some(entry))
+    }
+
+    /// Get history database path
+    fn history_db() -> PathBuf {
+        Self::data_dir().join("history.db")
+    }
+
+    /// History file written by earlier versions
+    fn history_json() -> PathBuf {
+        Self::data_dir().join("history.json")
+    }
+
+    fn data_dir() -> PathBuf {
         dirs::data_local_dir()
             .unwrap_or_else(|| PathBuf::from("."))
             .join("alrajhi-sql-tui")
-            .join("history.json")
     }
 
-    /// Load history from disk
-    fn load(&mut self) -> Result<()> {
-        let path = Self::history_file();
-        if path.exists() {
-            let content = fs::read_to_string(&path)?;
-            self.entries = serde_json::from_str(&content)?;
+    /// Copy `history.json` into the database once, then rename it to `history.json.imported`
+    fn import_json(conn: &mut Connection) -> Result<()> {
+        let path = Self::history_json();
+        if !path.exists() {
+            return Ok(());
+        }
+
+        // The immediate transaction keeps two terminals from importing at the same time
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let done: Option<String> = tx
+            .query_row("SELECT value FROM meta WHERE key = 'json_imported'", [], |row| row.get(0))
+            .optional()?;
+        if done.is_none() {
+            let content = fs::read_to_string(&path).with_context(|| format!("cannot read {}", path.display()))?;
+            let entries: Vec<HistoryEntry> =
+                serde_json::from_str(&content).with_context(|| format!("cannot import {}", path.display()))?;
+            for entry in &entries {
+                insert_entry(&tx, entry)?;
+            }
+            tx.execute(
+                "INSERT INTO meta (key, value) VALUES ('json_imported', ?1)",
+                [format!("{} entries on {}", entries.len(), Local::now().to_rfc3339())],
+            )?;
         }
+        tx.commit()?;
+
+        let _ = fs::rename(&path, path.with_extension("json.imported"));
         Ok(())
     }
 
-    /// Save history to disk
-    fn save(&self) -> Result<()> {
-        let path = Self::history_file();
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
+    /// Drop unpinned entries past the retention period and beyond the size cap
+    fn apply_retention(&self) -> Result<()> {
+        let Some(conn) = &self.conn else {
+            return Ok(());
+        };
+        if let Some(days) = self.retention_days.filter(|d| *d > 0) {
+            let cutoff = (Local::now() - Duration::days(days as i64)).timestamp_millis();
+            conn.execute("DELETE FROM history WHERE pinned = 0 AND timestamp_ms < ?1", [cutoff])?;
         }
-        let content = serde_json::to_string_pretty(&self.entries)?;
-        fs::write(&path, content)?;
+        conn.execute(
+            "DELETE FROM history WHERE pinned = 0 AND id NOT IN \
+             (SELECT id FROM history WHERE pinned = 0 ORDER BY id DESC LIMIT ?1)",
+            [self.max_entries as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Load every stored entry, oldest first
+    fn load(&mut self) -> Result<()> {
+        self.entries = self.query_entries(0)?;
+        self.search_cache.replace(None);
         Ok(())
     }
 
+    /// Append entries with ids above the newest one already loaded
+    fn load_new(&mut self) -> Result<()> {
+        let newest = self.entries.iter().map(|e| e.id).max().unwrap_or(0);
+        let added = self.query_entries(newest)?;
+        self.entries.extend(added);
+        self.search_cache.replace(None);
+        Ok(())
+    }
+
+    fn query_entries(&self, after_id: i64) -> Result<Vec<HistoryEntry>> {
+        let Some(conn) = &self.conn else {
+            return Ok(Vec::new());
+        };
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM history WHERE id > ?1 ORDER BY id",
+            COLUMNS
+        ))?;
+        let entries = stmt
+            .query_map([after_id], |row| {
+                let status: String = row.get(7)?;
+                Ok(HistoryEntry {
+                    id: row.get(0)?,
+                    query: row.get(1)?,
+                    timestamp: Local
+                        .timestamp_millis_opt(row.get(2)?)
+                        .single()
+                        .unwrap_or_else(Local::now),
+                    execution_time_ms: row.get::<_, i64>(3)? as u64,
+                    row_count: row.get::<_, Option<i64>>(4)?.map(|r| r as usize),
+                    database: row.get(5)?,
+                    pinned: row.get(6)?,
+                    status: match status.as_str() {
+                        "error" => QueryStatus::Error,
+                        "cancelled" => QueryStatus::Cancelled,
+                        _ => QueryStatus::Success,
+                    },
+                    error: row.get(8)?,
+                    server: row.get(9)?,
+                    profile: row.get(10)?,
+                    affected_rows: row.get::<_, Option<i64>>(11)?.map(|r| r as u64),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
     /// Clear history
-    pub fn clear(&mut self) {
+    pub fn clear(&mut self) -> Result<()> {
+        if let Some(conn) = &self.conn {
+            conn.execute("DELETE FROM history", [])?;
+        }
         self.entries.clear();
         self.current_index = None;
-        let _ = self.save();
+        self.search_cache.replace(None);
+        Ok(())
     }
 
     /// Get entry count
@@ -158,3 +657,106 @@ impl QueryHistory {
         self.entries.is_empty()
     }
 }
+
+/// Append one entry; returns its id
+fn insert_entry(conn: &Connection, entry: &HistoryEntry) -> rusqlite::Result<i64> {
+    conn.prepare_cached(
+        "INSERT INTO history (query, timestamp_ms, execution_time_ms, row_count, database, pinned, \
+                              status, error, server, profile, affected_rows) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+    )?
+    .execute(params![
+        entry.query,
+        entry.timestamp.timestamp_millis(),
+        entry.execution_time_ms as i64,
+        entry.row_count.map(|r| r as i64),
+        entry.database,
+        entry.pinned,
+        entry.status.label_key(),
+        entry.error,
+        entry.server,
+        entry.profile,
+        entry.affected_rows.map(|r| r as i64),
+    ])?;
+    Ok(conn.last_insert_rowid())
+}
+
+impl App {
+    /// History entries shown in the panel, as indices into `history.entries()`
+    pub fn history_rows(&self) -> Vec<usize> {
+        self.history.filtered(&self.history_filter)
+    }
+
+    /// History entry under the panel selection
+    pub fn selected_history_index(&self) -> Option<usize> {
+        self.history_rows().get(self.history_selected).copied()
+    }
+
+    pub fn move_history_selection(&mut self, delta: isize) {
+        let last = self.history_rows().len().saturating_sub(1) as isize;
+        self.history_selected = (self.history_selected as isize + delta).clamp(0, last) as usize;
+        self.history_confirm_delete = false;
+    }
+
+    /// Start an incremental search from any panel (Ctrl+R)
+    pub fn start_history_search(&mut self) {
+        self.active_panel = ActivePanel::History;
+        self.history_searching = true;
+        self.history_filter.text.clear();
+        self.history_selected = 0;
+    }
+
+    /// Cycle the database filter through the databases seen in the history
+    pub fn cycle_history_database(&mut self) {
+        let databases = self.history.databases();
+        self.history_filter.database = match &self.history_filter.database {
+            None => databases.first().cloned(),
+            Some(current) => databases
+                .iter()
+                .position(|d| d == current)
+                .and_then(|i| databases.get(i + 1))
+                .cloned(),
+        };
+        self.history_selected = 0;
+    }
+
+    pub fn toggle_history_pin(&mut self) {
+        let Some(index) = self.selected_history_index() else {
+            return;
+        };
+        let pinned = match self.history.toggle_pin(index) {
+            Ok(Some(pinned)) => pinned,
+            Ok(None) => return,
+            Err(e) => {
+                self.error = Some(format!("History not updated: {}", e));
+                return;
+            }
+        };
+        self.message = Some(if pinned { "Pinned history entry" } else { "Unpinned history entry" }.to_string());
+        // Follow the entry as it moves to or from the pinned group
+        if let Some(row) = self.history_rows().iter().position(|&i| i == index) {
+            self.history_selected = row;
+        }
+    }
+
+    /// Delete the selected entry; the first press only asks for confirmation
+    pub fn delete_history_entry(&mut self) {
+        let Some(index) = self.selected_history_index() else {
+            return;
+        };
+        if !self.history_confirm_delete {
+            self.history_confirm_delete = true;
+            self.message = Some("Press d again to delete this history entry".to_string());
+            return;
+        }
+        self.history_confirm_delete = false;
+        match self.history.remove(index) {
+            Ok(Some(_)) => {
+                self.move_history_selection(0);
+                self.message = Some("Deleted history entry".to_string());
+            }
+            Ok(None) => {}
+            Err(e) => self.error = Some(format!("History entry not deleted: {}", e)),
+        }
+    }
+}
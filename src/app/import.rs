@@ -0,0 +1,585 @@
+//! CSV import wizard: pick a file, check the inferred types, map columns, load
+
+use crate::app::{error_chain, expand_tilde, step, App, PathCompleter};
+use crate::db::{ColumnDef, DbConnection, SchemaExplorer};
+use crate::import::{
+    column_def, create_table_sql, import_csv, is_loadable, qualified_table, read_sample, split_table_name, CsvSample,
+    CsvSettings, ImportColumn, ImportPlan, ImportProgress, ImportReport, ImportStatus, LoadMethod, TYPE_CHOICES,
+};
+use crate::utils::{format_duration, format_number};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::oneshot;
+
+/// Delimiters offered; any other ASCII character can be typed
+const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+/// Wizard pages, in order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportStep {
+    /// File, delimiter and header row
+    File,
+    /// Preview with inferred types; target table and whether to create it
+    Target,
+    /// Which CSV column feeds each table column (or each new column's type)
+    Mapping,
+    /// Rows that failed the checks
+    Report,
+}
+
+/// Fields of the File and Target pages
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportField {
+    Path,
+    Delimiter,
+    Headers,
+    Table,
+    Mode,
+}
+
+impl ImportField {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImportField::Path => "File",
+            ImportField::Delimiter => "Delimiter",
+            ImportField::Headers => "Header row",
+            ImportField::Table => "Table",
+            ImportField::Mode => "Load into",
+        }
+    }
+
+    /// Fields typed as text rather than picked from choices
+    pub fn is_text(&self) -> bool {
+        matches!(self, ImportField::Path | ImportField::Table)
+    }
+}
+
+/// Existing table, or one created from the file's columns
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportMode {
+    Existing,
+    Create,
+}
+
+/// Popup walking through a CSV import
+#[derive(Clone, Debug)]
+pub struct ImportWizard {
+    pub step: ImportStep,
+    pub field: ImportField,
+    pub path: String,
+    completer: PathCompleter,
+    pub settings: CsvSettings,
+    /// The file as read on leaving the File page
+    pub file: Option<(PathBuf, CsvSample)>,
+    pub table: String,
+    pub mode: ImportMode,
+    /// Every column of the existing table, which decides the load method
+    pub table_columns: Vec<ColumnDef>,
+    /// Existing table: its loadable columns and the CSV column feeding each
+    pub columns: Vec<ImportColumn>,
+    /// New table: type of each CSV column, `None` to leave it out
+    pub types: Vec<Option<String>>,
+    /// Selected mapping row, or first report line shown
+    pub selected: usize,
+    pub report: Option<ImportReport>,
+    pub error: Option<String>,
+}
+
+impl ImportWizard {
+    fn new(path: String, table: String) -> Self {
+        Self {
+            step: ImportStep::File,
+            field: ImportField::Path,
+            path,
+            completer: PathCompleter::default(),
+            settings: CsvSettings { delimiter: ',', has_headers: true },
+            file: None,
+            mode: if table.is_empty() { ImportMode::Create } else { ImportMode::Existing },
+            table,
+            table_columns: Vec::new(),
+            columns: Vec::new(),
+            types: Vec::new(),
+            selected: 0,
+            report: None,
+            error: None,
+        }
+    }
+
+    /// Fields of the current page
+    pub fn fields(&self) -> &'static [ImportField] {
+        match self.step {
+            ImportStep::File => &[ImportField::Path, ImportField::Delimiter, ImportField::Headers],
+            ImportStep::Target => &[ImportField::Table, ImportField::Mode],
+            ImportStep::Mapping | ImportStep::Report => &[],
+        }
+    }
+
+    pub fn sample(&self) -> Option<&CsvSample> {
+        self.file.as_ref().map(|(_, sample)| sample)
+    }
+
+    pub fn next_field(&mut self, delta: isize) {
+        let fields = self.fields();
+        if let Some(current) = fields.iter().position(|f| *f == self.field) {
+            self.field = fields[(current as isize + delta).rem_euclid(fields.len() as isize) as usize];
+        }
+    }
+
+    /// Step the chosen field through its values (booleans toggle)
+    pub fn cycle(&mut self, delta: isize) {
+        match self.field {
+            ImportField::Delimiter => self.settings.delimiter = step(&DELIMITERS, &self.settings.delimiter, delta),
+            ImportField::Headers => self.settings.has_headers = !self.settings.has_headers,
+            ImportField::Mode => {
+                self.mode = match self.mode {
+                    ImportMode::Existing => ImportMode::Create,
+                    ImportMode::Create => ImportMode::Existing,
+                }
+            }
+            ImportField::Path | ImportField::Table => {}
+        }
+        self.edited();
+    }
+
+    pub fn push(&mut self, c: char) {
+        match self.field {
+            ImportField::Path => self.path.push(c),
+            ImportField::Table => self.table.push(c),
+            ImportField::Delimiter if c.is_ascii() && c != ' ' => self.settings.delimiter = c,
+            _ => self.cycle(1),
+        }
+        self.edited();
+    }
+
+    pub fn pop(&mut self) {
+        match self.field {
+            ImportField::Path => {
+                self.path.pop();
+            }
+            ImportField::Table => {
+                self.table.pop();
+            }
+            _ => {}
+        }
+        self.edited();
+    }
+
+    fn edited(&mut self) {
+        self.completer.reset();
+        self.error = None;
+    }
+
+    pub fn complete_path(&mut self) {
+        if let Err(error) = self.completer.complete(&mut self.path) {
+            self.error = Some(error);
+        }
+    }
+
+    /// Text of a field, for drawing
+    pub fn value(&self, field: ImportField) -> String {
+        match field {
+            ImportField::Path => self.path.clone(),
+            ImportField::Delimiter => match self.settings.delimiter {
+                '\t' => "Tab".to_string(),
+                c => c.to_string(),
+            },
+            ImportField::Headers => if self.settings.has_headers { "Yes" } else { "No" }.to_string(),
+            ImportField::Table => self.table.clone(),
+            ImportField::Mode => match self.mode {
+                ImportMode::Existing => "Existing table".to_string(),
+                ImportMode::Create => "New table (CREATE TABLE)".to_string(),
+            },
+        }
+    }
+
+    /// Rows of the Mapping page: table column (or new column) and what feeds it
+    pub fn mapping_rows(&self) -> Vec<(String, String)> {
+        let Some(sample) = self.sample() else {
+            return Vec::new();
+        };
+        match self.mode {
+            ImportMode::Existing => self
+                .columns
+                .iter()
+                .map(|column| {
+                    let target = &column.target;
+                    let null = if target.is_nullable { "" } else { " NOT NULL" };
+                    let name = format!("{} {}{}", target.name, target.data_type.to_uppercase(), null);
+                    let source = match column.source {
+                        Some(i) => format!("← {}", sample.headers[i]),
+                        None => "(not loaded)".to_string(),
+                    };
+                    (name, source)
+                })
+                .collect(),
+            ImportMode::Create => sample
+                .headers
+                .iter()
+                .zip(&self.types)
+                .map(|(name, sql_type)| (name.clone(), sql_type.clone().unwrap_or_else(|| "(skipped)".to_string())))
+                .collect(),
+        }
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        let len = match self.step {
+            ImportStep::Report => self.report.as_ref().map_or(0, |r| r.errors.len()),
+            _ => self.mapping_rows().len(),
+        };
+        self.selected = (self.selected as isize + delta).clamp(0, len.saturating_sub(1) as isize) as usize;
+    }
+
+    /// ←/→ on a mapping row: the next CSV column, or the next type for a new column
+    pub fn cycle_mapping(&mut self, delta: isize) {
+        let Some(sample) = self.sample() else {
+            return;
+        };
+        let selected = self.selected;
+        match self.mode {
+            ImportMode::Existing => {
+                let sources: Vec<Option<usize>> = [None].into_iter().chain((0..sample.headers.len()).map(Some)).collect();
+                if let Some(column) = self.columns.get_mut(selected) {
+                    column.source = step(&sources, &column.source, delta);
+                }
+            }
+            ImportMode::Create => {
+                let inferred = sample.types.get(selected).cloned().unwrap_or_default();
+                let mut choices: Vec<Option<String>> = TYPE_CHOICES.iter().map(|t| Some(t.to_string())).collect();
+                if !TYPE_CHOICES.contains(&inferred.as_str()) {
+                    choices.insert(0, Some(inferred));
+                }
+                choices.push(None);
+                if let Some(current) = self.types.get_mut(selected) {
+                    let index = choices.iter().position(|c| c == current).unwrap_or(0) as isize;
+                    *current = choices[(index + delta).rem_euclid(choices.len() as isize) as usize].clone();
+                }
+            }
+        }
+        self.error = None;
+    }
+
+    /// Map each table column to the CSV column of the same name (ignoring case, spaces
+    /// and underscores), or by position when the file has no header row
+    fn auto_map(&mut self) {
+        let Some(sample) = self.sample() else {
+            return;
+        };
+        let normalize = |name: &str| name.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+        let headers: Vec<String> = sample.headers.iter().map(|h| normalize(h)).collect();
+        let count = headers.len();
+        let by_name = self.settings.has_headers;
+        for (i, column) in self.columns.iter_mut().enumerate() {
+            column.source = if by_name {
+                headers.iter().position(|h| *h == normalize(&column.target.name))
+            } else {
+                Some(i).filter(|&i| i < count)
+            };
+        }
+    }
+
+    /// The load for the current mapping, or why it cannot run
+    fn plan(&self) -> Result<ImportPlan, String> {
+        let (schema, name) = split_table_name(&self.table);
+        let table = qualified_table(&schema, &name);
+        match self.mode {
+            ImportMode::Existing => {
+                if self.columns.iter().all(|c| c.source.is_none()) {
+                    return Err("Map at least one CSV column (←/→)".to_string());
+                }
+                // Bulk copy sends NULL for unmapped columns; INSERTs let them take their defaults
+                let unmapped_required = self.columns.iter().any(|c| c.source.is_none() && !c.target.is_nullable);
+                let method = match LoadMethod::for_table(&self.table_columns) {
+                    LoadMethod::BulkCopy if !unmapped_required => LoadMethod::BulkCopy,
+                    _ => LoadMethod::Insert,
+                };
+                Ok(ImportPlan {
+                    table,
+                    create_sql: None,
+                    columns: self.columns.clone(),
+                    settings: self.settings,
+                    method,
+                })
+            }
+            ImportMode::Create => {
+                let sample = self.sample().ok_or("Pick a file first")?;
+                let columns: Vec<ImportColumn> = sample
+                    .headers
+                    .iter()
+                    .zip(&self.types)
+                    .enumerate()
+                    .filter_map(|(i, (header, sql_type))| {
+                        let target = column_def(header, sql_type.as_deref()?);
+                        Some(ImportColumn { target, source: Some(i) })
+                    })
+                    .collect();
+                if columns.is_empty() {
+                    return Err("Keep at least one column".to_string());
+                }
+                let defs: Vec<ColumnDef> = columns.iter().map(|c| c.target.clone()).collect();
+                Ok(ImportPlan {
+                    create_sql: Some(create_table_sql(&table, &defs)),
+                    table,
+                    columns,
+                    settings: self.settings,
+                    method: LoadMethod::for_table(&defs),
+                })
+            }
+        }
+    }
+
+    /// How the current mapping would load, for the Mapping page
+    pub fn plan_summary(&self) -> String {
+        match self.plan() {
+            Ok(plan) => {
+                let why = match (plan.method, self.mode) {
+                    (LoadMethod::BulkCopy, _) => "",
+                    (LoadMethod::Insert, ImportMode::Create) => " (DATE columns are not bulk copied)",
+                    (LoadMethod::Insert, ImportMode::Existing) => {
+                        if LoadMethod::for_table(&self.table_columns) == LoadMethod::BulkCopy {
+                            " (unmapped NOT NULL columns take their defaults)"
+                        } else {
+                            " (the table has types bulk copy cannot send)"
+                        }
+                    }
+                };
+                let action = if plan.create_sql.is_some() { "Create and load" } else { "Load" };
+                format!("{} {} with {}{}; one transaction", action, plan.table, plan.method.label(), why)
+            }
+            Err(error) => error,
+        }
+    }
+
+    /// The report as text, for the clipboard
+    pub fn report_text(&self) -> String {
+        let Some(report) = self.report.as_ref() else {
+            return String::new();
+        };
+        let mut text = format!("{} rows failed the checks for {}\n", report.error_count, self.table.trim());
+        for error in &report.errors {
+            text.push_str(&format!("line {}: {}\n", error.line, error.message));
+        }
+        if report.error_count > report.errors.len() as u64 {
+            text.push_str(&format!("... and {} more\n", report.error_count - report.errors.len() as u64));
+        }
+        text
+    }
+}
+
+/// What a background import task produced
+pub enum ImportOutcome {
+    /// Columns of the existing target table
+    Columns(Vec<ColumnDef>),
+    Loaded(ImportReport),
+}
+
+/// Background work for the import wizard: reading the target's columns, or the load
+pub struct ImportJob {
+    /// Counters of a running load; `None` while reading the table's columns
+    pub progress: Option<Arc<ImportProgress>>,
+    pub started: Instant,
+    /// What the job produced, and why the connection could not be replaced after a failed load
+    rx: oneshot::Receiver<(Result<ImportOutcome, String>, Option<String>)>,
+}
+
+impl App {
+    /// Open the import wizard, optionally loading into `table`
+    pub(crate) fn open_import_wizard(&mut self, path: Option<String>, table: Option<String>) {
+        let path = path.unwrap_or_else(|| {
+            self.export_options
+                .dir
+                .as_ref()
+                .filter(|dir| dir.is_dir())
+                .map(|dir| format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR))
+                .unwrap_or_default()
+        });
+        self.import_wizard = Some(ImportWizard::new(path, table.unwrap_or_default()));
+    }
+
+    /// Enter on a page: read the file, look up the table, or start the load
+    pub(crate) fn confirm_import_step(&mut self) {
+        let Some(wizard) = self.import_wizard.as_mut() else {
+            return;
+        };
+        match wizard.step {
+            ImportStep::File => {
+                let path = expand_tilde(wizard.path.trim());
+                if !path.is_file() {
+                    wizard.error = Some(format!("{} is not a file", path.display()));
+                    return;
+                }
+                match read_sample(&path, wizard.settings) {
+                    Ok(sample) if sample.rows.is_empty() => wizard.error = Some("The file has no rows".to_string()),
+                    Ok(sample) => {
+                        if wizard.table.is_empty() {
+                            wizard.table = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+                        }
+                        wizard.types = sample.types.iter().cloned().map(Some).collect();
+                        wizard.file = Some((path, sample));
+                        wizard.step = ImportStep::Target;
+                        wizard.field = ImportField::Table;
+                    }
+                    Err(e) => wizard.error = Some(error_chain(&e)),
+                }
+            }
+            ImportStep::Target => {
+                if split_table_name(&wizard.table).1.is_empty() {
+                    wizard.error = Some("Enter the target table".to_string());
+                    return;
+                }
+                wizard.selected = 0;
+                match wizard.mode {
+                    ImportMode::Create => wizard.step = ImportStep::Mapping,
+                    ImportMode::Existing => self.load_import_columns(),
+                }
+            }
+            ImportStep::Mapping => match wizard.plan() {
+                Ok(plan) => self.start_import(plan),
+                Err(error) => wizard.error = Some(error),
+            },
+            ImportStep::Report => self.import_wizard = None,
+        }
+    }
+
+    /// Esc: back one page, closing from the first
+    pub(crate) fn import_wizard_back(&mut self) {
+        let Some(wizard) = self.import_wizard.as_mut() else {
+            return;
+        };
+        wizard.error = None;
+        wizard.selected = 0;
+        match wizard.step {
+            ImportStep::File => self.import_wizard = None,
+            ImportStep::Target => {
+                wizard.step = ImportStep::File;
+                wizard.field = ImportField::Path;
+            }
+            ImportStep::Mapping => {
+                wizard.step = ImportStep::Target;
+                wizard.field = ImportField::Table;
+            }
+            ImportStep::Report => wizard.step = ImportStep::Mapping,
+        }
+    }
+
+    /// Read the target table's columns in the background
+    fn load_import_columns(&mut self) {
+        let Some(wizard) = self.import_wizard.as_ref() else {
+            return;
+        };
+        let (schema, table) = split_table_name(&wizard.table);
+
+        self.is_loading = true;
+        self.spinner_frame = 0;
+
+        let (tx, rx) = oneshot::channel();
+        let client_arc = self.db.client();
+        tokio::spawn(async move {
+            let mut client = client_arc.lock().await;
+            let columns = SchemaExplorer::get_columns(&mut client, &schema, &table).await;
+            let _ = tx.send((columns.map(ImportOutcome::Columns).map_err(|e| error_chain(&e)), None));
+        });
+        self.pending_import = Some(ImportJob { progress: None, started: Instant::now(), rx });
+    }
+
+    /// Check and load the file in the background
+    fn start_import(&mut self, plan: ImportPlan) {
+        let Some((path, _)) = self.import_wizard.as_ref().and_then(|w| w.file.clone()) else {
+            return;
+        };
+        self.is_loading = true;
+        self.error = None;
+        self.message = None;
+        self.spinner_frame = 0;
+
+        let progress = Arc::new(ImportProgress::default());
+        let (tx, rx) = oneshot::channel();
+        let client_arc = self.db.client();
+        let config = self.db.config.clone();
+        let task_progress = Arc::clone(&progress);
+
+        tokio::spawn(async move {
+            let mut client = client_arc.lock().await;
+            let outcome = import_csv(&mut client, &path, &plan, &task_progress).await;
+            // A failed bulk copy can leave the session mid-request; start a fresh one
+            let mut lost = None;
+            if outcome.is_err() {
+                match DbConnection::connect(&config).await {
+                    Ok(fresh) => *client = fresh,
+                    Err(e) => lost = Some(error_chain(&e)),
+                }
+            }
+            let _ = tx.send((outcome.map(ImportOutcome::Loaded).map_err(|e| error_chain(&e)), lost));
+        });
+
+        self.pending_import = Some(ImportJob { progress: Some(progress), started: Instant::now(), rx });
+    }
+
+    /// Ask the running import to stop and roll back
+    pub(crate) fn cancel_import(&mut self) {
+        if let Some(progress) = self.pending_import.as_ref().and_then(|job| job.progress.as_ref()) {
+            progress.cancel();
+        }
+    }
+
+    /// Check if the import wizard's background work has finished
+    pub fn check_import_completion(&mut self) {
+        let Some(job) = self.pending_import.as_mut() else {
+            return;
+        };
+        let (outcome, lost) = match job.rx.try_recv() {
+            Ok(done) => done,
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => (Err("Import was interrupted".to_string()), None),
+        };
+        let Some(job) = self.pending_import.take() else {
+            return;
+        };
+        self.is_loading = false;
+        // The old session may be stuck mid bulk copy, so it cannot run anything else
+        if let Some(e) = lost {
+            self.db.connected = false;
+            self.error = Some(format!("Connection lost, the next query reconnects: {}", e));
+        }
+        let Some(wizard) = self.import_wizard.as_mut() else {
+            return;
+        };
+
+        match outcome {
+            Ok(ImportOutcome::Columns(columns)) if columns.is_empty() => {
+                wizard.error = Some(format!("Table {} not found; choose New table to create it", wizard.table.trim()));
+            }
+            Ok(ImportOutcome::Columns(columns)) => {
+                wizard.columns = columns
+                    .iter()
+                    .filter(|c| is_loadable(c))
+                    .map(|c| ImportColumn { target: c.clone(), source: None })
+                    .collect();
+                wizard.table_columns = columns;
+                wizard.auto_map();
+                wizard.step = ImportStep::Mapping;
+            }
+            Ok(ImportOutcome::Loaded(report)) => match report.status {
+                ImportStatus::Committed => {
+                    let table = wizard.table.trim().to_string();
+                    self.import_wizard = None;
+                    self.message = Some(format!(
+                        "✓ Imported {} rows into {} in {}",
+                        format_number(report.rows as i64),
+                        table,
+                        format_duration(job.started.elapsed())
+                    ));
+                }
+                ImportStatus::Rejected => {
+                    wizard.step = ImportStep::Report;
+                    wizard.selected = 0;
+                    wizard.report = Some(report);
+                }
+                ImportStatus::Cancelled => {
+                    wizard.error = Some("Import cancelled; nothing was loaded".to_string());
+                }
+            },
+            Err(e) => wizard.error = Some(e),
+        }
+    }
+}
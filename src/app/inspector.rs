@@ -0,0 +1,453 @@
+//! Full-value inspector for a single result cell
+
+use crate::app::App;
+use crate::db::CellValue;
+use anyhow::Result;
+use std::path::Path;
+
+/// How the inspected value is rendered
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InspectorFormat {
+    Text,
+    Json,
+    Xml,
+    Hex,
+}
+
+impl InspectorFormat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            InspectorFormat::Text => "TEXT",
+            InspectorFormat::Json => "JSON",
+            InspectorFormat::Xml => "XML",
+            InspectorFormat::Hex => "HEX",
+        }
+    }
+
+    /// File extension used when saving the value
+    pub fn extension(&self) -> &'static str {
+        match self {
+            InspectorFormat::Text => "txt",
+            InspectorFormat::Json => "json",
+            InspectorFormat::Xml => "xml",
+            InspectorFormat::Hex => "bin",
+        }
+    }
+}
+
+/// Popup state for the cell inspector
+#[derive(Clone, Debug)]
+pub struct CellInspector {
+    pub column: String,
+    pub type_name: String,
+    pub value: CellValue,
+    pub format: InspectorFormat,
+    /// Show the pretty-printed form (false shows the raw value)
+    pub pretty: bool,
+    pub lines: Vec<String>,
+    /// `lines` wrapped to the popup width; scrolling counts these rows
+    pub rows: Vec<String>,
+    /// Width `rows` were wrapped to, 0 when they need wrapping again
+    wrap_width: usize,
+    pub scroll: usize,
+}
+
+impl CellInspector {
+    /// Inspect a value, detecting JSON/XML payloads and binary data
+    pub fn new(column: String, type_name: String, value: CellValue) -> Self {
+        let format = match &value {
+            CellValue::Binary(_) => InspectorFormat::Hex,
+            CellValue::String(text) if pretty_json(text).is_some() => InspectorFormat::Json,
+            CellValue::String(text) if pretty_xml(text).is_some() => InspectorFormat::Xml,
+            _ => InspectorFormat::Text,
+        };
+
+        let mut inspector = Self {
+            column,
+            type_name,
+            value,
+            format,
+            pretty: true,
+            lines: Vec::new(),
+            rows: Vec::new(),
+            wrap_width: 0,
+            scroll: 0,
+        };
+        inspector.render();
+        inspector
+    }
+
+    /// Switch between the formatted and raw value
+    pub fn toggle_pretty(&mut self) {
+        self.pretty = !self.pretty;
+        self.scroll = 0;
+        self.render();
+    }
+
+    /// Whether the displayed lines are formatted output
+    pub fn is_formatted(&self) -> bool {
+        self.pretty && self.format != InspectorFormat::Text
+    }
+
+    /// Text currently shown in the popup
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    fn render(&mut self) {
+        let raw = self.value.to_string();
+        let text = match (&self.value, self.format, self.pretty) {
+            (CellValue::Binary(bytes), InspectorFormat::Hex, true) => hex_dump(bytes).join("\n"),
+            (_, InspectorFormat::Json, true) => pretty_json(&raw).unwrap_or(raw),
+            (_, InspectorFormat::Xml, true) => pretty_xml(&raw).unwrap_or(raw),
+            _ => raw,
+        };
+        self.lines = text.lines().map(|l| l.to_string()).collect();
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.rows.clear();
+        self.wrap_width = 0;
+    }
+
+    /// Wrap the lines to the popup's inner width; hex dumps keep their columns
+    pub fn wrap(&mut self, width: usize) {
+        if width == self.wrap_width {
+            return;
+        }
+        self.wrap_width = width;
+        let hex = self.pretty && self.format == InspectorFormat::Hex;
+        self.rows = if hex || width == 0 {
+            self.lines.clone()
+        } else {
+            self.lines
+                .iter()
+                .flat_map(|line| match textwrap::wrap(line, width) {
+                    rows if rows.is_empty() => vec![String::new()],
+                    rows => rows.into_iter().map(|row| row.into_owned()).collect(),
+                })
+                .collect()
+        };
+        self.scroll = self.scroll.min(self.max_scroll());
+    }
+
+    /// Last scroll offset that still shows a row
+    pub fn max_scroll(&self) -> usize {
+        self.rows.len().saturating_sub(1)
+    }
+
+    /// Write the value to a file; binary values are written as raw bytes
+    pub fn save(&self, path: &Path) -> Result<()> {
+        match &self.value {
+            CellValue::Binary(bytes) => std::fs::write(path, bytes)?,
+            _ => std::fs::write(path, self.text())?,
+        }
+        Ok(())
+    }
+}
+
+/// Re-indent a JSON document, keeping key order and number formatting
+pub fn pretty_json(text: &str) -> Option<String> {
+    let trimmed = text.trim();
+    if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+        return None;
+    }
+    serde_json::from_str::<serde_json::Value>(trimmed).ok()?;
+
+    let chars: Vec<char> = trimmed.chars().collect();
+    let mut out = String::with_capacity(trimmed.len() * 2);
+    let mut indent = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut i = 0;
+
+    let newline = |out: &mut String, indent: usize| {
+        out.push('\n');
+        out.push_str(&"  ".repeat(indent));
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '{' | '[' => {
+                // Keep empty containers on one line
+                let next = chars[i + 1..].iter().position(|c| !c.is_whitespace()).map(|p| i + 1 + p);
+                if let Some(close) = next.filter(|&n| matches!(chars[n], '}' | ']')) {
+                    out.push(c);
+                    out.push(chars[close]);
+                    i = close;
+                } else {
+                    out.push(c);
+                    indent += 1;
+                    newline(&mut out, indent);
+                }
+            }
+            '}' | ']' => {
+                indent = indent.saturating_sub(1);
+                newline(&mut out, indent);
+                out.push(c);
+            }
+            ',' => {
+                out.push(c);
+                newline(&mut out, indent);
+            }
+            ':' => out.push_str(": "),
+            c if c.is_whitespace() => {}
+            c => out.push(c),
+        }
+        i += 1;
+    }
+
+    Some(out)
+}
+
+/// Piece of an XML document
+enum XmlToken {
+    Open(String, String),
+    Close(String, String),
+    SelfClosing(String),
+    Text(String),
+    Other(String),
+}
+
+fn tokenize_xml(text: &str) -> Option<Vec<XmlToken>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if let Some(body) = rest.strip_prefix("<!--") {
+            let end = body.find("-->")? + 4 + 3;
+            tokens.push(XmlToken::Other(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if rest.starts_with("<![CDATA[") {
+            let end = rest.find("]]>")? + 3;
+            tokens.push(XmlToken::Text(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if rest.starts_with("<?") {
+            let end = rest.find("?>")? + 2;
+            tokens.push(XmlToken::Other(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if rest.starts_with("<!") {
+            let end = rest.find('>')? + 1;
+            tokens.push(XmlToken::Other(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if rest.starts_with('<') {
+            // Find the closing '>' outside attribute quotes
+            let mut quote = None;
+            let end = rest.char_indices().find_map(|(i, c)| {
+                match (quote, c) {
+                    (None, '"' | '\'') => quote = Some(c),
+                    (Some(q), c) if c == q => quote = None,
+                    (None, '>') => return Some(i + 1),
+                    _ => {}
+                }
+                None
+            })?;
+            let tag = &rest[..end];
+            let name: String = tag
+                .trim_start_matches(['<', '/'])
+                .chars()
+                .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+                .collect();
+            if name.is_empty() {
+                return None;
+            }
+            if tag.starts_with("</") {
+                tokens.push(XmlToken::Close(name, tag.to_string()));
+            } else if tag.ends_with("/>") {
+                tokens.push(XmlToken::SelfClosing(tag.to_string()));
+            } else {
+                tokens.push(XmlToken::Open(name, tag.to_string()));
+            }
+            rest = &rest[end..];
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let content = rest[..end].trim();
+            if !content.is_empty() {
+                tokens.push(XmlToken::Text(content.to_string()));
+            }
+            rest = &rest[end..];
+        }
+    }
+
+    Some(tokens)
+}
+
+/// Indent an XML document or fragment; None if the tags do not balance
+pub fn pretty_xml(text: &str) -> Option<String> {
+    let trimmed = text.trim();
+    if !trimmed.starts_with('<') {
+        return None;
+    }
+
+    let tokens = tokenize_xml(trimmed)?;
+    if !tokens.iter().any(|t| matches!(t, XmlToken::Open(..) | XmlToken::SelfClosing(_))) {
+        return None;
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let pad = "  ".repeat(stack.len());
+        match &tokens[i] {
+            XmlToken::Open(name, tag) => {
+                // <a>text</a> stays on one line
+                if let (Some(XmlToken::Text(content)), Some(XmlToken::Close(close, end))) =
+                    (tokens.get(i + 1), tokens.get(i + 2))
+                {
+                    if close == name {
+                        lines.push(format!("{}{}{}{}", pad, tag, content, end));
+                        i += 3;
+                        continue;
+                    }
+                }
+                lines.push(format!("{}{}", pad, tag));
+                stack.push(name);
+            }
+            XmlToken::Close(name, tag) => {
+                if stack.pop()? != name {
+                    return None;
+                }
+                lines.push(format!("{}{}", "  ".repeat(stack.len()), tag));
+            }
+            XmlToken::SelfClosing(tag) | XmlToken::Text(tag) | XmlToken::Other(tag) => {
+                lines.push(format!("{}{}", pad, tag));
+            }
+        }
+        i += 1;
+    }
+
+    if !stack.is_empty() {
+        return None;
+    }
+    Some(lines.join("\n"))
+}
+
+/// Classic offset / hex / ASCII dump, 16 bytes per line
+pub fn hex_dump(bytes: &[u8]) -> Vec<String> {
+    if bytes.is_empty() {
+        return vec!["(0 bytes)".to_string()];
+    }
+
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut hex = String::with_capacity(50);
+            for (j, byte) in chunk.iter().enumerate() {
+                if j == 8 {
+                    hex.push(' ');
+                }
+                hex.push_str(&format!("{:02X} ", byte));
+            }
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+            format!("{:08X}  {:<49} |{}|", i * 16, hex, ascii)
+        })
+        .collect()
+}
+
+impl App {
+    /// Open the inspector on the selected Data tab cell
+    pub fn open_cell_inspector(&mut self) {
+        let Some((row, col)) = self.selected_row().zip(self.selected_col()) else {
+            self.error = Some("No cell selected".to_string());
+            return;
+        };
+        let Some(cell) = self.result.rows.get(row).and_then(|r| r.get(col)) else {
+            return;
+        };
+
+        let cell = self
+            .edit_session
+            .as_ref()
+            .map(|s| s.display_value(row, col, cell))
+            .unwrap_or(cell)
+            .clone();
+        let column = &self.result.columns[col];
+        self.inspector = Some(CellInspector::new(column.name.clone(), column.type_name.clone(), cell));
+    }
+
+    /// Save the inspected value next to the other exports
+    pub fn save_inspected_cell(&mut self) {
+        let Some(inspector) = self.inspector.as_ref() else {
+            return;
+        };
+
+        let column: String = inspector
+            .column
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let extension = if inspector.is_formatted() || matches!(inspector.value, CellValue::Binary(_)) {
+            inspector.format.extension()
+        } else {
+            "txt"
+        };
+        let path = self.export_target(&format!("cell_{}_{}.{}", column, timestamp, extension));
+
+        match inspector.save(&path) {
+            Ok(()) => self.message = Some(format!("✓ Saved cell to {}", path.display())),
+            Err(e) => self.error = Some(format!("Save to {} failed: {}", path.display(), e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::display_width;
+
+    fn inspect(value: &str) -> CellInspector {
+        CellInspector::new("c".to_string(), "NVARCHAR".to_string(), CellValue::String(value.to_string()))
+    }
+
+    #[test]
+    fn long_value_scrolls_by_wrapped_rows() {
+        let mut inspector = inspect(&"word ".repeat(100));
+        assert_eq!(inspector.lines.len(), 1);
+        inspector.wrap(20);
+        assert_eq!(inspector.rows.len(), 25);
+        assert_eq!(inspector.max_scroll(), 24);
+        assert!(inspector.rows.iter().all(|row| display_width(row) <= 20));
+    }
+
+    #[test]
+    fn wrapping_keeps_json_indentation_and_follows_the_width() {
+        let mut inspector = inspect(r#"{"name": "a long enough value to wrap", "n": 1}"#);
+        inspector.wrap(80);
+        assert_eq!(inspector.rows, inspector.lines);
+        assert_eq!(inspector.rows[1], r#"  "name": "a long enough value to wrap","#);
+        inspector.scroll = 3;
+        inspector.wrap(16);
+        assert!(inspector.rows.len() > inspector.lines.len());
+        assert!(inspector.rows[1].starts_with("  \"name\""));
+        // Raw mode drops the old rows until the next draw wraps them again
+        inspector.toggle_pretty();
+        assert!(inspector.rows.is_empty());
+        inspector.wrap(16);
+        assert!(inspector.rows.len() > 1);
+    }
+}
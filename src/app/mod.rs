@@ -1,9 +1,34 @@
 //! Application state and logic
 
 mod state;
+mod edit;
+mod editor;
+mod export;
+mod files;
+mod grid;
+mod inspector;
 mod handlers;
 mod history;
+mod import;
+mod result_diff;
+mod result_history;
+mod script;
+mod selection;
+mod snippets;
+mod vim;
 
 pub use state::*;
-pub use handlers::*;
+pub use edit::*;
+pub use editor::*;
+pub use export::*;
+pub use files::*;
+pub use grid::*;
+pub use inspector::*;
 pub use history::*;
+pub use import::*;
+pub use result_diff::*;
+pub use result_history::*;
+pub use script::*;
+pub use selection::*;
+pub use snippets::*;
+pub use vim::*;
@@ -0,0 +1,500 @@
+//! Side-by-side comparison of two result sets matched on key columns
+
+use crate::app::{cell_number, compare_cells, App, ResultsTab, MAX_AUTO_COLUMN_WIDTH, MIN_COLUMN_WIDTH};
+use crate::db::{CellValue, ColumnInfo, QueryResult};
+use crate::utils::display_width;
+use anyhow::Result;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+
+/// How a row differs between the two result sets
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffKind {
+    /// Only in the newer result
+    Added,
+    /// Only in the older result
+    Removed,
+    /// Same key, different values
+    Changed,
+}
+
+impl DiffKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DiffKind::Added => "added",
+            DiffKind::Removed => "removed",
+            DiffKind::Changed => "changed",
+        }
+    }
+
+    pub fn marker(&self) -> &'static str {
+        match self {
+            DiffKind::Added => "+",
+            DiffKind::Removed => "-",
+            DiffKind::Changed => "~",
+        }
+    }
+}
+
+/// A row that was added, removed or changed
+#[derive(Clone, Debug)]
+pub struct DiffRow {
+    pub kind: DiffKind,
+    /// Cells in `ResultDiff::columns` order: the newer values, or the older ones for a removed row
+    pub cells: Vec<CellValue>,
+    /// Older values of the changed columns
+    pub old: Vec<(usize, CellValue)>,
+}
+
+impl DiffRow {
+    /// Older value of a changed column
+    pub fn old_value(&self, col: usize) -> Option<&CellValue> {
+        self.old.iter().find(|(c, _)| *c == col).map(|(_, value)| value)
+    }
+}
+
+/// Result of comparing an older and a newer result set
+#[derive(Clone, Debug)]
+pub struct ResultDiff {
+    pub before_label: String,
+    pub after_label: String,
+    /// Columns present in both results
+    pub columns: Vec<ColumnInfo>,
+    /// Key columns (indexes into `columns`); empty means whole rows are compared
+    pub keys: Vec<usize>,
+    pub rows: Vec<DiffRow>,
+    pub unchanged: usize,
+    pub before_only_columns: Vec<String>,
+    pub after_only_columns: Vec<String>,
+    /// Keys that occur more than once in either result; their rows are matched in order
+    pub duplicate_keys: usize,
+    /// Display width of each column
+    pub widths: Vec<u16>,
+    /// Rows shown: all, or only one kind
+    pub filter: Option<DiffKind>,
+    /// Indexes into `rows` passing the filter
+    pub view: Vec<usize>,
+    /// First non-key column shown
+    pub col_offset: usize,
+}
+
+impl ResultDiff {
+    /// Match rows of `before` and `after` on the named key columns and collect the differences
+    pub fn compare(
+        before: &QueryResult,
+        after: &QueryResult,
+        key_names: &[String],
+        before_label: String,
+        after_label: String,
+    ) -> std::result::Result<Self, String> {
+        // Pair columns by name, case-insensitively
+        let mut used = vec![false; before.columns.len()];
+        let mut pairs: Vec<(usize, usize)> = Vec::new();
+        let mut after_only_columns = Vec::new();
+        for (a, column) in after.columns.iter().enumerate() {
+            let found = before
+                .columns
+                .iter()
+                .enumerate()
+                .position(|(b, c)| !used[b] && c.name.eq_ignore_ascii_case(&column.name));
+            match found {
+                Some(b) => {
+                    used[b] = true;
+                    pairs.push((b, a));
+                }
+                None => after_only_columns.push(column.name.clone()),
+            }
+        }
+        if pairs.is_empty() {
+            return Err("The result sets have no columns in common".to_string());
+        }
+        let before_only_columns = before
+            .columns
+            .iter()
+            .zip(&used)
+            .filter(|(_, used)| !**used)
+            .map(|(c, _)| c.name.clone())
+            .collect();
+
+        let columns: Vec<ColumnInfo> = pairs.iter().map(|&(_, a)| after.columns[a].clone()).collect();
+        let keys: Vec<usize> = columns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| key_names.iter().any(|k| k.eq_ignore_ascii_case(&c.name)))
+            .map(|(i, _)| i)
+            .collect();
+        let key_cols: Vec<usize> = if keys.is_empty() { (0..columns.len()).collect() } else { keys.clone() };
+
+        let project = |row: &[CellValue], side: fn(&(usize, usize)) -> usize| -> Vec<CellValue> {
+            pairs.iter().map(|p| row.get(side(p)).cloned().unwrap_or(CellValue::Null)).collect()
+        };
+        let key_of = |cells: &[CellValue]| -> Vec<Option<String>> { key_cols.iter().map(|&c| key_text(&cells[c])).collect() };
+
+        // Older rows by key, matched in order when a key repeats
+        let mut by_key: HashMap<Vec<Option<String>>, VecDeque<usize>> = HashMap::new();
+        let before_rows: Vec<Vec<CellValue>> = before.rows.iter().map(|r| project(r, |p| p.0)).collect();
+        for (i, cells) in before_rows.iter().enumerate() {
+            by_key.entry(key_of(cells)).or_default().push_back(i);
+        }
+        let after_rows: Vec<Vec<CellValue>> = after.rows.iter().map(|r| project(r, |p| p.1)).collect();
+        let mut after_counts: HashMap<Vec<Option<String>>, usize> = HashMap::new();
+        for cells in &after_rows {
+            *after_counts.entry(key_of(cells)).or_default() += 1;
+        }
+        let duplicate_keys = by_key
+            .keys()
+            .chain(after_counts.keys())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter(|key| by_key.get(*key).map_or(0, |rows| rows.len()) > 1 || after_counts.get(*key).copied().unwrap_or(0) > 1)
+            .count();
+
+        let mut rows = Vec::new();
+        let mut unchanged = 0;
+        for cells in after_rows {
+            let Some(b) = by_key.get_mut(&key_of(&cells)).and_then(|rows| rows.pop_front()) else {
+                rows.push(DiffRow { kind: DiffKind::Added, cells, old: Vec::new() });
+                continue;
+            };
+            let old: Vec<(usize, CellValue)> = (0..cells.len())
+                .filter(|c| !key_cols.contains(c) && !cells_equal(&before_rows[b][*c], &cells[*c]))
+                .map(|c| (c, before_rows[b][c].clone()))
+                .collect();
+            if old.is_empty() {
+                unchanged += 1;
+            } else {
+                rows.push(DiffRow { kind: DiffKind::Changed, cells, old });
+            }
+        }
+
+        let mut removed: Vec<usize> = by_key.into_values().flatten().collect();
+        removed.sort_unstable();
+        for b in removed {
+            rows.push(DiffRow { kind: DiffKind::Removed, cells: before_rows[b].clone(), old: Vec::new() });
+        }
+
+        // Interleave by key so a reconciliation reads top to bottom
+        rows.sort_by(|x, y| {
+            key_cols
+                .iter()
+                .map(|&c| compare_cells(&x.cells[c], &y.cells[c]))
+                .find(|o| *o != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let widths = columns
+            .iter()
+            .enumerate()
+            .map(|(c, column)| {
+                let content = rows
+                    .iter()
+                    .map(|row| match row.old_value(c) {
+                        Some(old) => display_width(&old.to_string()) + 3 + display_width(&row.cells[c].to_string()),
+                        None => display_width(&row.cells[c].to_string()),
+                    })
+                    .max()
+                    .unwrap_or(0)
+                    .max(display_width(&column.name) + 2)
+                    + 2;
+                (content.min(u16::MAX as usize) as u16).clamp(MIN_COLUMN_WIDTH, MAX_AUTO_COLUMN_WIDTH)
+            })
+            .collect();
+
+        let mut diff = Self {
+            before_label,
+            after_label,
+            columns,
+            keys,
+            rows,
+            unchanged,
+            before_only_columns,
+            after_only_columns,
+            duplicate_keys,
+            widths,
+            filter: None,
+            view: Vec::new(),
+            col_offset: 0,
+        };
+        diff.rebuild_view();
+        Ok(diff)
+    }
+
+    /// Number of rows of one kind
+    pub fn count(&self, kind: DiffKind) -> usize {
+        self.rows.iter().filter(|row| row.kind == kind).count()
+    }
+
+    /// Show all rows, then only added, removed and changed ones
+    pub fn cycle_filter(&mut self) {
+        self.filter = match self.filter {
+            None => Some(DiffKind::Added),
+            Some(DiffKind::Added) => Some(DiffKind::Removed),
+            Some(DiffKind::Removed) => Some(DiffKind::Changed),
+            Some(DiffKind::Changed) => None,
+        };
+        self.rebuild_view();
+    }
+
+    fn rebuild_view(&mut self) {
+        self.view = self
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| self.filter.is_none_or(|kind| row.kind == kind))
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    /// Key columns first, then the rest in result order
+    pub fn display_columns(&self) -> Vec<usize> {
+        let mut order = self.keys.clone();
+        order.extend((0..self.columns.len()).filter(|c| !self.keys.contains(c)));
+        order
+    }
+
+    /// Comma-separated key column names, or a note that whole rows are compared
+    pub fn key_summary(&self) -> String {
+        if self.keys.is_empty() {
+            "whole row".to_string()
+        } else {
+            self.keys.iter().map(|&k| self.columns[k].name.as_str()).collect::<Vec<_>>().join(", ")
+        }
+    }
+
+    /// Write the report as CSV: change, the column values, and `column: old → new` for changed rows
+    pub fn write_csv(&self, path: &Path) -> Result<()> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        let mut header = vec!["change".to_string()];
+        header.extend(self.columns.iter().map(|c| c.name.clone()));
+        header.push("changes".to_string());
+        wtr.write_record(&header)?;
+        for row in &self.rows {
+            let mut record = vec![row.kind.label().to_string()];
+            record.extend(row.cells.iter().map(|c| c.to_string()));
+            record.push(
+                row.old
+                    .iter()
+                    .map(|(c, old)| format!("{}: {} → {}", self.columns[*c].name, old, row.cells[*c]))
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            );
+            wtr.write_record(&record)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+/// Key text of a cell; integers and whole floats compare equal
+fn key_text(cell: &CellValue) -> Option<String> {
+    match cell {
+        CellValue::Null => None,
+        CellValue::Float(v) if v.fract() == 0.0 && v.abs() < 1e15 => Some((*v as i64).to_string()),
+        CellValue::Float(v) => Some(v.to_string()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Exact equality, except that numbers compare by value across types
+fn cells_equal(a: &CellValue, b: &CellValue) -> bool {
+    match (a, b) {
+        (CellValue::Null, CellValue::Null) => true,
+        (CellValue::Null, _) | (_, CellValue::Null) => false,
+        (CellValue::String(x), CellValue::String(y)) => x == y,
+        _ => match (cell_number(a), cell_number(b)) {
+            (Some(x), Some(y)) => x == y,
+            _ => a.to_string() == b.to_string(),
+        },
+    }
+}
+
+/// Picking the result to compare with and the key columns
+#[derive(Debug)]
+pub enum DiffSetupStage {
+    /// Choosing a kept result set
+    Result,
+    /// Choosing key columns of the chosen result
+    Keys {
+        other: usize,
+        result: Box<QueryResult>,
+        columns: Vec<String>,
+        keys: Vec<bool>,
+    },
+}
+
+/// Compare-results popup
+#[derive(Debug)]
+pub struct DiffSetup {
+    /// Result history indexes other than the shown one, newest first
+    pub candidates: Vec<usize>,
+    pub stage: DiffSetupStage,
+    pub selected: usize,
+    pub error: Option<String>,
+}
+
+impl App {
+    /// Open the compare popup to diff the shown result with a kept one
+    pub fn open_diff_setup(&mut self) {
+        let position = self.result_history.position();
+        let candidates: Vec<usize> = (0..self.result_history.len()).rev().filter(|i| Some(*i) != position).collect();
+        if position.is_none() || candidates.is_empty() {
+            self.error = Some("Run another query first: results are compared with an earlier result set".to_string());
+            return;
+        }
+        self.diff_setup = Some(DiffSetup {
+            candidates,
+            stage: DiffSetupStage::Result,
+            selected: 0,
+            error: None,
+        });
+    }
+
+    pub fn move_diff_setup_selection(&mut self, delta: isize) {
+        let Some(setup) = self.diff_setup.as_mut() else {
+            return;
+        };
+        let len = match &setup.stage {
+            DiffSetupStage::Result => setup.candidates.len(),
+            DiffSetupStage::Keys { columns, .. } => columns.len(),
+        };
+        setup.selected = (setup.selected as isize + delta).clamp(0, len.saturating_sub(1) as isize) as usize;
+    }
+
+    /// Toggle the selected key column
+    pub fn toggle_diff_key(&mut self) {
+        if let Some(DiffSetup { stage: DiffSetupStage::Keys { keys, .. }, selected, .. }) = self.diff_setup.as_mut() {
+            if let Some(key) = keys.get_mut(*selected) {
+                *key = !*key;
+            }
+        }
+    }
+
+    /// Enter: pick the result, then run the comparison
+    pub fn confirm_diff_setup(&mut self) {
+        let Some(setup) = self.diff_setup.as_mut() else {
+            return;
+        };
+        match &setup.stage {
+            DiffSetupStage::Result => {
+                let Some(&other) = setup.candidates.get(setup.selected) else {
+                    return;
+                };
+                let result = match self.result_history.result_at(other, &self.result) {
+                    Ok(result) => result,
+                    Err(e) => {
+                        setup.error = Some(format!("Cannot read kept result: {}", e));
+                        return;
+                    }
+                };
+                let columns: Vec<String> = self
+                    .result
+                    .columns
+                    .iter()
+                    .filter(|c| result.columns.iter().any(|o| o.name.eq_ignore_ascii_case(&c.name)))
+                    .map(|c| c.name.clone())
+                    .collect();
+                if columns.is_empty() {
+                    setup.error = Some("The result sets have no columns in common".to_string());
+                    return;
+                }
+                // Keep the keys of the last comparison when they still apply, else the first column
+                let previous: Vec<String> = self
+                    .result_diff
+                    .as_ref()
+                    .map(|d| d.keys.iter().map(|&k| d.columns[k].name.clone()).collect())
+                    .unwrap_or_default();
+                let mut keys: Vec<bool> = columns.iter().map(|c| previous.iter().any(|p| p.eq_ignore_ascii_case(c))).collect();
+                if !keys.contains(&true) {
+                    keys[0] = true;
+                }
+                setup.stage = DiffSetupStage::Keys { other, result: Box::new(result), columns, keys };
+                setup.selected = 0;
+                setup.error = None;
+            }
+            DiffSetupStage::Keys { .. } => self.run_result_diff(),
+        }
+    }
+
+    fn run_result_diff(&mut self) {
+        let Some(setup) = self.diff_setup.take() else {
+            return;
+        };
+        let DiffSetupStage::Keys { other, result, columns, keys } = setup.stage else {
+            return;
+        };
+        let Some(position) = self.result_history.position() else {
+            return;
+        };
+        let key_names: Vec<String> = columns.into_iter().zip(keys).filter(|(_, on)| *on).map(|(c, _)| c).collect();
+
+        // The older result set is always "before"
+        let label = |index: usize| {
+            let item = &self.result_history.items()[index];
+            format!("result {} ({})", index + 1, item.timestamp.format("%H:%M:%S"))
+        };
+        let compared = if other < position {
+            ResultDiff::compare(&result, &self.result, &key_names, label(other), label(position))
+        } else {
+            ResultDiff::compare(&self.result, &result, &key_names, label(position), label(other))
+        };
+        match compared {
+            Ok(diff) => {
+                let mut message = format!(
+                    "Compared on {}: +{} added, -{} removed, ~{} changed, {} unchanged",
+                    diff.key_summary(),
+                    diff.count(DiffKind::Added),
+                    diff.count(DiffKind::Removed),
+                    diff.count(DiffKind::Changed),
+                    diff.unchanged
+                );
+                if diff.duplicate_keys > 0 {
+                    message.push_str(&format!(" ({} duplicate key(s))", diff.duplicate_keys));
+                }
+                self.message = Some(message);
+                self.result_diff = Some(diff);
+                self.results_tab = ResultsTab::Diff;
+                self.results_selected = 0;
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Scroll the non-key diff columns left or right
+    pub fn scroll_diff_columns(&mut self, delta: isize) {
+        if let Some(diff) = self.result_diff.as_mut() {
+            let scrollable = diff.columns.len().saturating_sub(diff.keys.len());
+            diff.col_offset = (diff.col_offset as isize + delta).clamp(0, scrollable.saturating_sub(1) as isize) as usize;
+        }
+    }
+
+    pub fn cycle_diff_filter(&mut self) {
+        if let Some(diff) = self.result_diff.as_mut() {
+            diff.cycle_filter();
+            self.results_selected = 0;
+        }
+    }
+
+    /// Export the diff report to CSV in the last export folder
+    pub fn export_result_diff(&mut self) {
+        let Some(diff) = self.result_diff.as_ref() else {
+            self.error = Some("No comparison to export (D compares result sets)".to_string());
+            return;
+        };
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let path = self.export_target(&format!("diff_{}.csv", timestamp));
+        match diff.write_csv(&path) {
+            Ok(()) => {
+                self.message = Some(format!("✓ Exported {} difference(s) to {}", diff.rows.len(), path.display()))
+            }
+            Err(e) => self.error = Some(format!("Export to {} failed: {}", path.display(), e)),
+        }
+    }
+
+    /// Close the comparison and go back to the data
+    pub fn close_result_diff(&mut self) {
+        self.result_diff = None;
+        self.results_tab = ResultsTab::Data;
+        self.results_selected = 0;
+    }
+}
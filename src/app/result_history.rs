@@ -0,0 +1,301 @@
+//! Earlier result sets, kept to revisit without re-running the query
+
+use crate::app::{ActivePanel, App};
+use crate::db::QueryResult;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Default number of result sets kept
+pub const DEFAULT_RESULT_HISTORY_SIZE: usize = 20;
+
+/// Rows kept in memory across hidden result sets before older ones are spilled or dropped
+const MEMORY_ROW_BUDGET: usize = 200_000;
+
+/// Where a kept result set lives
+#[derive(Debug)]
+enum Slot {
+    /// Currently in `App::result`
+    Shown,
+    Memory(QueryResult),
+    /// Written to a spill file
+    Disk(PathBuf),
+}
+
+/// A past result set and the run it came from
+#[derive(Debug)]
+pub struct CachedResult {
+    /// History entry id of the run
+    pub history_id: i64,
+    pub query: String,
+    pub timestamp: DateTime<Local>,
+    pub row_count: usize,
+    slot: Slot,
+}
+
+impl CachedResult {
+    pub fn on_disk(&self) -> bool {
+        matches!(self.slot, Slot::Disk(_))
+    }
+}
+
+/// Bounded back/forward stack of result sets
+///
+/// The shown result stays in `App::result`; stepping swaps it with a kept one,
+/// so nothing is cloned. Past the row budget, older sets are written to a spill
+/// folder when one is configured and dropped otherwise.
+#[derive(Debug, Default)]
+pub struct ResultHistory {
+    items: Vec<CachedResult>,
+    /// Item shown in `App::result`
+    position: Option<usize>,
+    max_items: usize,
+    spill_dir: Option<PathBuf>,
+    next_file: usize,
+}
+
+impl ResultHistory {
+    pub fn new(max_items: usize, spill: bool) -> Self {
+        let spill_dir = spill.then(|| {
+            dirs::cache_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join("alrajhi-sql-tui")
+                .join(format!("results-{}", std::process::id()))
+        });
+        Self {
+            items: Vec::new(),
+            position: None,
+            max_items: max_items.max(1),
+            spill_dir,
+            next_file: 0,
+        }
+    }
+
+    pub fn items(&self) -> &[CachedResult] {
+        &self.items
+    }
+
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Newest kept result of a history entry
+    pub fn index_of(&self, history_id: i64) -> Option<usize> {
+        self.items.iter().rposition(|item| item.history_id == history_id)
+    }
+
+    /// Show a new result set: `current` (the shown one) is kept and replaced by `result`
+    pub fn push(&mut self, current: &mut QueryResult, result: QueryResult, history_id: i64, query: String) {
+        let row_count = result.row_count;
+        let previous = std::mem::replace(current, result);
+        self.stash(previous);
+
+        self.items.push(CachedResult {
+            history_id,
+            query,
+            timestamp: Local::now(),
+            row_count,
+            slot: Slot::Shown,
+        });
+        self.position = Some(self.items.len() - 1);
+
+        while self.items.len() > self.max_items {
+            self.remove(0);
+        }
+        self.enforce_budget();
+    }
+
+    /// Swap the kept result at `index` into `current`
+    pub fn show(&mut self, index: usize, current: &mut QueryResult) -> Result<()> {
+        if self.position == Some(index) {
+            return Ok(());
+        }
+        let Some(item) = self.items.get_mut(index) else {
+            return Ok(());
+        };
+        let result = match std::mem::replace(&mut item.slot, Slot::Shown) {
+            Slot::Memory(result) => result,
+            Slot::Disk(path) => match read_spill(&path) {
+                Ok(result) => {
+                    let _ = fs::remove_file(&path);
+                    result
+                }
+                Err(e) => {
+                    item.slot = Slot::Disk(path);
+                    return Err(e);
+                }
+            },
+            Slot::Shown => return Ok(()),
+        };
+
+        let previous = std::mem::replace(current, result);
+        self.stash(previous);
+        self.position = Some(index);
+        self.enforce_budget();
+        Ok(())
+    }
+
+    /// Copy of the kept result at `index`; `current` is the shown one
+    pub fn result_at(&self, index: usize, current: &QueryResult) -> Result<QueryResult> {
+        match self.items.get(index).map(|item| &item.slot) {
+            Some(Slot::Memory(result)) => Ok(result.clone()),
+            Some(Slot::Disk(path)) => read_spill(path),
+            Some(Slot::Shown) => Ok(current.clone()),
+            None => anyhow::bail!("no kept result {}", index + 1),
+        }
+    }
+
+    /// Put the result that was shown back into its slot
+    fn stash(&mut self, result: QueryResult) {
+        if let Some(item) = self.position.and_then(|p| self.items.get_mut(p)) {
+            item.slot = Slot::Memory(result);
+        }
+    }
+
+    fn remove(&mut self, index: usize) {
+        let item = self.items.remove(index);
+        if let Slot::Disk(path) = item.slot {
+            let _ = fs::remove_file(path);
+        }
+        self.position = match self.position {
+            Some(p) if p == index => None,
+            Some(p) if p > index => Some(p - 1),
+            other => other,
+        };
+    }
+
+    /// Spill or drop the oldest in-memory sets until the hidden ones fit the row budget
+    fn enforce_budget(&mut self) {
+        loop {
+            let in_memory: usize = self
+                .items
+                .iter()
+                .filter_map(|item| match &item.slot {
+                    Slot::Memory(result) => Some(result.rows.len()),
+                    _ => None,
+                })
+                .sum();
+            if in_memory <= MEMORY_ROW_BUDGET {
+                return;
+            }
+            let Some(oldest) = self.items.iter().position(|item| matches!(item.slot, Slot::Memory(_))) else {
+                return;
+            };
+            if !self.spill(oldest) {
+                self.remove(oldest);
+            }
+        }
+    }
+
+    /// Write an in-memory set to the spill folder; false if spilling is off or failed
+    fn spill(&mut self, index: usize) -> bool {
+        let Some(dir) = self.spill_dir.clone() else {
+            return false;
+        };
+        let Slot::Memory(result) = &self.items[index].slot else {
+            return false;
+        };
+        let path = dir.join(format!("{}.json", self.next_file));
+        let written = fs::create_dir_all(&dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(fs::write(&path, serde_json::to_vec(result)?)?));
+        if written.is_err() {
+            return false;
+        }
+        self.next_file += 1;
+        self.items[index].slot = Slot::Disk(path);
+        true
+    }
+}
+
+impl Drop for ResultHistory {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.spill_dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+fn read_spill(path: &Path) -> Result<QueryResult> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+impl App {
+    /// Show a freshly run result, keeping the previous one in the result history
+    pub(crate) fn show_new_result(&mut self, result: QueryResult, query: String, history_id: i64) {
+        // Staged insert rows are not part of the result that is kept
+        if let Some(session) = self.edit_session.take() {
+            self.result.rows.truncate(session.original_row_count);
+            self.result.row_count = self.result.rows.len();
+        }
+        self.result_history.push(&mut self.result, result, history_id, query.clone());
+        self.result_query = Some(query);
+        self.reset_result_view();
+        self.results_scroll = 0;
+        self.results_selected = 0;
+        self.results_col_selected = 0;
+        self.record_field_scroll = 0;
+    }
+
+    /// Step back (-1) or forward (+1) through earlier result sets
+    pub fn step_result_history(&mut self, delta: isize) {
+        let Some(position) = self.result_history.position() else {
+            return;
+        };
+        let target = position as isize + delta;
+        if target < 0 || target as usize >= self.result_history.len() {
+            self.message = Some(if delta < 0 { "No older result" } else { "No newer result" }.to_string());
+            return;
+        }
+        self.show_cached_result(target as usize);
+    }
+
+    /// Show the kept result of the selected history entry, without re-running it
+    pub fn show_history_result(&mut self) {
+        let Some(entry) = self.selected_history_index().and_then(|i| self.history.get(i)) else {
+            return;
+        };
+        match self.result_history.index_of(entry.id) {
+            Some(index) => {
+                self.show_cached_result(index);
+                self.active_panel = ActivePanel::Results;
+            }
+            None => self.message = Some("No kept result for this entry (Enter loads the query)".to_string()),
+        }
+    }
+
+    fn show_cached_result(&mut self, index: usize) {
+        if self.edit_session.as_ref().is_some_and(|s| s.change_count(self.result.rows.len()) > 0) {
+            self.error = Some("Commit or discard staged edits before switching results".to_string());
+            return;
+        }
+        if let Err(e) = self.result_history.show(index, &mut self.result) {
+            self.error = Some(format!("Cannot read kept result: {}", e));
+            return;
+        }
+        let item = &self.result_history.items()[index];
+        self.result_query = Some(item.query.clone());
+        self.message = Some(format!(
+            "Result {}/{} from {} ({} row(s))",
+            index + 1,
+            self.result_history.len(),
+            item.timestamp.format("%H:%M:%S"),
+            item.row_count
+        ));
+        self.edit_session = None;
+        self.reset_result_view();
+        self.results_scroll = 0;
+        self.results_selected = 0;
+        self.results_col_selected = 0;
+        self.record_field_scroll = 0;
+    }
+}
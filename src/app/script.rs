@@ -0,0 +1,337 @@
+//! INSERT/MERGE script generator for the result grid
+
+use crate::app::{step, ActivePanel, App};
+use crate::db::{CellValue, QueryExecutor};
+use crate::export::{generate_script, ScriptKind, ScriptOptions};
+
+/// Batch sizes offered; digits can also be typed
+const BATCH_SIZES: [usize; 5] = [1, 100, 250, 500, 1000];
+
+/// Table name used when the query's table is unknown
+const DEFAULT_TABLE: &str = "[TableName]";
+
+/// Rows the script covers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptRows {
+    /// Every row of the result set, in server order
+    All,
+    /// Rows left by the grid's filters, in display order
+    View,
+    /// The row under the cursor
+    Current,
+    /// Rows of the grid's range selection
+    Selection,
+}
+
+impl ScriptRows {
+    pub const ALL: [ScriptRows; 4] = [ScriptRows::All, ScriptRows::View, ScriptRows::Current, ScriptRows::Selection];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ScriptRows::All => "All rows",
+            ScriptRows::View => "Filtered/sorted view",
+            ScriptRows::Current => "Current row",
+            ScriptRows::Selection => "Selected rows",
+        }
+    }
+}
+
+/// Where the generated script goes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptOutput {
+    Clipboard,
+    /// Replace the editor text (undoable)
+    Editor,
+}
+
+/// Fields of the script dialog, in order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptField {
+    Rows,
+    Table,
+    Statement,
+    Keys,
+    Batch,
+    IdentityInsert,
+    Output,
+}
+
+impl ScriptField {
+    pub const ALL: [ScriptField; 7] = [
+        ScriptField::Rows,
+        ScriptField::Table,
+        ScriptField::Statement,
+        ScriptField::Keys,
+        ScriptField::Batch,
+        ScriptField::IdentityInsert,
+        ScriptField::Output,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ScriptField::Rows => "Rows",
+            ScriptField::Table => "Table",
+            ScriptField::Statement => "Statement",
+            ScriptField::Keys => "Key columns",
+            ScriptField::Batch => "Rows/batch",
+            ScriptField::IdentityInsert => "IDENTITY_INSERT",
+            ScriptField::Output => "Output",
+        }
+    }
+
+    /// Fields typed as text rather than picked from choices
+    pub fn is_text(&self) -> bool {
+        matches!(self, ScriptField::Table | ScriptField::Keys | ScriptField::Batch)
+    }
+}
+
+/// Popup generating an INSERT or MERGE script from result rows
+#[derive(Clone, Debug)]
+pub struct ScriptDialog {
+    pub rows: ScriptRows,
+    pub table: String,
+    pub kind: ScriptKind,
+    /// Comma-separated key column names for MERGE
+    pub keys: String,
+    pub batch: String,
+    pub identity_insert: bool,
+    pub output: ScriptOutput,
+    pub field: ScriptField,
+    pub error: Option<String>,
+}
+
+impl ScriptDialog {
+    /// Fields shown for the chosen statement
+    pub fn fields(&self) -> Vec<ScriptField> {
+        ScriptField::ALL
+            .into_iter()
+            .filter(|f| *f != ScriptField::Keys || self.kind.needs_keys())
+            .collect()
+    }
+
+    pub fn next_field(&mut self, delta: isize) {
+        let fields = self.fields();
+        let current = fields.iter().position(|f| *f == self.field).unwrap_or(0) as isize;
+        self.field = fields[(current + delta).rem_euclid(fields.len() as isize) as usize];
+    }
+
+    /// Step the chosen field through its values (booleans toggle)
+    pub fn cycle(&mut self, delta: isize) {
+        match self.field {
+            ScriptField::Rows => self.rows = step(&ScriptRows::ALL, &self.rows, delta),
+            ScriptField::Statement => self.kind = step(&ScriptKind::ALL, &self.kind, delta),
+            ScriptField::Batch => {
+                let current = self.batch.trim().parse().unwrap_or(1);
+                let index = BATCH_SIZES.iter().position(|&b| b >= current).unwrap_or(0) as isize;
+                let next = BATCH_SIZES[(index + delta).rem_euclid(BATCH_SIZES.len() as isize) as usize];
+                self.batch = next.to_string();
+            }
+            ScriptField::IdentityInsert => self.identity_insert = !self.identity_insert,
+            ScriptField::Output => {
+                self.output = match self.output {
+                    ScriptOutput::Clipboard => ScriptOutput::Editor,
+                    ScriptOutput::Editor => ScriptOutput::Clipboard,
+                }
+            }
+            ScriptField::Table | ScriptField::Keys => {}
+        }
+        self.error = None;
+    }
+
+    pub fn push(&mut self, c: char) {
+        match self.field {
+            ScriptField::Table => self.table.push(c),
+            ScriptField::Keys => self.keys.push(c),
+            ScriptField::Batch if c.is_ascii_digit() => self.batch.push(c),
+            ScriptField::Batch => {}
+            _ => self.cycle(1),
+        }
+        self.error = None;
+    }
+
+    pub fn pop(&mut self) {
+        match self.field {
+            ScriptField::Table => {
+                self.table.pop();
+            }
+            ScriptField::Keys => {
+                self.keys.pop();
+            }
+            ScriptField::Batch => {
+                self.batch.pop();
+            }
+            _ => {}
+        }
+        self.error = None;
+    }
+
+    /// Text of a field, for drawing
+    pub fn value(&self, field: ScriptField) -> String {
+        match field {
+            ScriptField::Rows => self.rows.label().to_string(),
+            ScriptField::Table => self.table.clone(),
+            ScriptField::Statement => self.kind.label().to_string(),
+            ScriptField::Keys => self.keys.clone(),
+            ScriptField::Batch => self.batch.clone(),
+            ScriptField::IdentityInsert => if self.identity_insert { "ON" } else { "OFF" }.to_string(),
+            ScriptField::Output => match self.output {
+                ScriptOutput::Clipboard => "Clipboard".to_string(),
+                ScriptOutput::Editor => "Query editor (replaces the text)".to_string(),
+            },
+        }
+    }
+}
+
+impl App {
+    /// Target table for generated statements: the current result's table, if known
+    fn script_table(&self) -> String {
+        self.result_query
+            .as_deref()
+            .and_then(QueryExecutor::extract_table_name)
+            .unwrap_or_else(|| DEFAULT_TABLE.to_string())
+    }
+
+    /// Open the script dialog, keyed on the selected column
+    pub(crate) fn open_script_dialog(&mut self) {
+        if self.result.rows.is_empty() || self.result.columns.is_empty() {
+            self.error = Some("No results to script".to_string());
+            return;
+        }
+        let row = self.selected_row().unwrap_or(0);
+        let (_, columns) = self.result.result_set_of(row);
+        let keys = self
+            .selected_col()
+            .and_then(|c| columns.get(c))
+            .map(|c| c.name.clone())
+            .unwrap_or_default();
+        self.script_dialog = Some(ScriptDialog {
+            rows: if self.grid_selection.is_some() {
+                ScriptRows::Selection
+            } else if self.result_view.is_active() {
+                ScriptRows::View
+            } else {
+                ScriptRows::All
+            },
+            table: self.script_table(),
+            kind: ScriptKind::Insert,
+            keys,
+            batch: "100".to_string(),
+            identity_insert: false,
+            output: ScriptOutput::Clipboard,
+            field: ScriptField::Rows,
+            error: None,
+        });
+    }
+
+    /// Generate the script and send it to the clipboard or the editor
+    pub(crate) fn confirm_script_dialog(&mut self) {
+        let Some(dialog) = self.script_dialog.as_ref() else {
+            return;
+        };
+        let (kind, output) = (dialog.kind, dialog.output);
+        let (script, count) = match self.build_script(dialog) {
+            Ok(script) => script,
+            Err(error) => {
+                if let Some(dialog) = self.script_dialog.as_mut() {
+                    dialog.error = Some(error);
+                }
+                return;
+            }
+        };
+        self.script_dialog = None;
+
+        match output {
+            ScriptOutput::Clipboard => match arboard::Clipboard::new().and_then(|mut c| c.set_text(&script)) {
+                Ok(()) => self.message = Some(format!("✓ Copied {} script for {} row(s)", kind.label(), count)),
+                Err(e) => self.error = Some(format!("Clipboard: {}", e)),
+            },
+            ScriptOutput::Editor => {
+                self.editor.set_text(script);
+                self.active_panel = ActivePanel::QueryEditor;
+                self.message = Some(format!("{} script for {} row(s) in the editor (Ctrl+Z restores the query)", kind.label(), count));
+            }
+        }
+    }
+
+    /// Script for the dialog's settings and the number of rows it covers
+    fn build_script(&self, dialog: &ScriptDialog) -> Result<(String, usize), String> {
+        let row = self.selected_row().unwrap_or(0);
+        let (set, columns) = self.result.result_set_of(row);
+        let table = dialog.table.trim();
+        if table.is_empty() {
+            return Err("Enter the target table".to_string());
+        }
+        let batch_size = dialog.batch.trim().parse::<usize>().unwrap_or(0);
+        if batch_size == 0 {
+            return Err("Rows per batch must be at least 1".to_string());
+        }
+
+        let mut keys = Vec::new();
+        if dialog.kind.needs_keys() {
+            for name in dialog.keys.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                match columns.iter().position(|c| c.name.eq_ignore_ascii_case(name)) {
+                    Some(i) => keys.push(i),
+                    None => return Err(format!("No column named {}", name)),
+                }
+            }
+            if keys.is_empty() {
+                return Err("MERGE needs at least one key column".to_string());
+            }
+        }
+
+        let rows: Vec<&[CellValue]> = match dialog.rows {
+            ScriptRows::All => self.result.rows[set].iter().map(Vec::as_slice).collect(),
+            ScriptRows::View => self
+                .result_view
+                .order
+                .iter()
+                .filter(|r| set.contains(r))
+                .map(|&r| self.result.rows[r].as_slice())
+                .collect(),
+            ScriptRows::Current => self.result.rows.get(row).map(Vec::as_slice).into_iter().collect(),
+            ScriptRows::Selection => self
+                .selection_bounds()
+                .0
+                .filter_map(|d| self.result_view.row_at(d))
+                .filter(|r| set.contains(r))
+                .map(|r| self.result.rows[r].as_slice())
+                .collect(),
+        };
+        if rows.is_empty() {
+            return Err("No rows to script".to_string());
+        }
+
+        let options = ScriptOptions {
+            table: table.to_string(),
+            kind: dialog.kind,
+            batch_size,
+            identity_insert: dialog.identity_insert,
+            keys,
+        };
+        Ok((generate_script(columns, &rows, &options), rows.len()))
+    }
+
+    /// Copy the selected row as one typed INSERT statement
+    pub(crate) fn copy_row_as_insert(&mut self) {
+        let Some(row) = self.selected_row().filter(|&r| r < self.result.rows.len()) else {
+            return;
+        };
+        let (_, columns) = self.result.result_set_of(row);
+        if columns.is_empty() {
+            return;
+        }
+        let options = ScriptOptions {
+            table: self.script_table(),
+            kind: ScriptKind::Insert,
+            batch_size: 1,
+            identity_insert: false,
+            keys: Vec::new(),
+        };
+        let insert = generate_script(columns, &[self.result.rows[row].as_slice()], &options);
+
+        if let Ok(mut clipboard) = arboard::Clipboard::new() {
+            let _ = clipboard.set_text(insert.trim_end());
+            self.message = Some("✓ Copied INSERT statement to clipboard".to_string());
+        }
+    }
+}
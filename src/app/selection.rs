@@ -0,0 +1,221 @@
+//! Range selection in the results grid, copied as TSV, CSV, Markdown or an IN list
+
+use crate::app::App;
+use crate::db::{quote_ident, CellValue};
+use crate::export::{markdown_escape, plain_text, sql_literal};
+use crate::utils::truncate_with_ellipsis;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+/// What a grid selection spans between its anchor and the cursor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionKind {
+    /// The rectangle of cells
+    Cells,
+    /// Every column of the rows
+    Rows,
+    /// Every row of the columns
+    Columns,
+}
+
+/// Selected range in display coordinates; the cursor is the other corner
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridSelection {
+    pub anchor_row: usize,
+    pub anchor_col: usize,
+    pub kind: SelectionKind,
+}
+
+/// Text the selection is copied as
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyFormat {
+    /// Tab-separated values; pastes into Excel as cells
+    Tsv,
+    TsvHeaders,
+    Csv,
+    Markdown,
+    /// `[Column] IN (...)` of the distinct values of one column
+    InList,
+}
+
+impl CopyFormat {
+    pub const ALL: [CopyFormat; 5] = [
+        CopyFormat::Tsv,
+        CopyFormat::TsvHeaders,
+        CopyFormat::Csv,
+        CopyFormat::Markdown,
+        CopyFormat::InList,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CopyFormat::Tsv => "TSV (paste into Excel)",
+            CopyFormat::TsvHeaders => "TSV with column names",
+            CopyFormat::Csv => "CSV with column names",
+            CopyFormat::Markdown => "Markdown table",
+            CopyFormat::InList => "SQL IN (...) list of the column",
+        }
+    }
+
+    /// Key choosing the format in the copy menu
+    pub fn key(&self) -> char {
+        match self {
+            CopyFormat::Tsv => 't',
+            CopyFormat::TsvHeaders => 'h',
+            CopyFormat::Csv => 'c',
+            CopyFormat::Markdown => 'm',
+            CopyFormat::InList => 'q',
+        }
+    }
+}
+
+impl App {
+    /// Display rows and column positions of the selection, or of the cursor cell
+    pub fn selection_bounds(&self) -> (RangeInclusive<usize>, RangeInclusive<usize>) {
+        let last_row = self.result_view.len().saturating_sub(1);
+        let last_col = self.column_layout.len().saturating_sub(1);
+        let (row, col) = (self.results_selected.min(last_row), self.results_col_selected.min(last_col));
+        let Some(selection) = self.grid_selection else {
+            return (row..=row, col..=col);
+        };
+        let (anchor_row, anchor_col) = (selection.anchor_row.min(last_row), selection.anchor_col.min(last_col));
+        let rows = anchor_row.min(row)..=anchor_row.max(row);
+        let cols = anchor_col.min(col)..=anchor_col.max(col);
+        match selection.kind {
+            SelectionKind::Cells => (rows, cols),
+            SelectionKind::Rows => (rows, 0..=last_col),
+            SelectionKind::Columns => (0..=last_row, cols),
+        }
+    }
+
+    /// Start a selection at the cursor unless one is already open
+    pub fn begin_selection(&mut self, kind: SelectionKind) {
+        if self.grid_selection.is_none() {
+            self.grid_selection = Some(GridSelection {
+                anchor_row: self.results_selected,
+                anchor_col: self.results_col_selected,
+                kind,
+            });
+        }
+    }
+
+    /// Move the cursor, growing the selection from where it started
+    pub fn extend_selection(&mut self, rows: isize, cols: isize) {
+        self.begin_selection(SelectionKind::Cells);
+        let last_row = self.result_view.len().saturating_sub(1) as isize;
+        let last_col = self.column_layout.len().saturating_sub(1) as isize;
+        self.results_selected = (self.results_selected as isize + rows).clamp(0, last_row) as usize;
+        self.results_col_selected = (self.results_col_selected as isize + cols).clamp(0, last_col) as usize;
+    }
+
+    /// Select whole rows or whole columns from the cursor; pressing again clears it
+    pub fn toggle_line_selection(&mut self, kind: SelectionKind) {
+        match self.grid_selection.as_mut() {
+            Some(selection) if selection.kind == kind => self.grid_selection = None,
+            Some(selection) => selection.kind = kind,
+            None => self.begin_selection(kind),
+        }
+    }
+
+    /// Select every cell of the view
+    pub fn select_all_cells(&mut self) {
+        self.grid_selection = Some(GridSelection { anchor_row: 0, anchor_col: 0, kind: SelectionKind::Cells });
+        self.results_selected = self.result_view.len().saturating_sub(1);
+        self.results_col_selected = self.column_layout.len().saturating_sub(1);
+    }
+
+    /// Copy the selection (or the cursor cell) to the clipboard
+    pub(crate) fn copy_selection(&mut self, format: CopyFormat) {
+        let (rows, cols) = self.selection_bounds();
+        let text = match self.selection_text(format) {
+            Ok(text) => text,
+            Err(error) => {
+                self.error = Some(error);
+                return;
+            }
+        };
+        match arboard::Clipboard::new().and_then(|mut c| c.set_text(&text)) {
+            Ok(()) => {
+                self.message = Some(match format {
+                    CopyFormat::InList => format!("Copied {}", truncate_with_ellipsis(&text, 60)),
+                    _ => format!("Copied {} row(s) × {} column(s) as {}", rows.count(), cols.count(), format.label()),
+                })
+            }
+            Err(e) => self.error = Some(format!("Clipboard: {}", e)),
+        }
+    }
+
+    /// Text of the selection in the chosen format
+    fn selection_text(&self, format: CopyFormat) -> Result<String, String> {
+        let (rows, cols) = self.selection_bounds();
+        let columns: Vec<usize> = cols.filter_map(|p| self.column_layout.col_at(p)).collect();
+        let rows: Vec<&[CellValue]> = rows
+            .filter_map(|d| self.result_view.row_at(d))
+            .filter_map(|r| self.result.rows.get(r))
+            .map(Vec::as_slice)
+            .collect();
+        if rows.is_empty() || columns.is_empty() {
+            return Err("Nothing selected to copy".to_string());
+        }
+        let names: Vec<&str> = columns
+            .iter()
+            .map(|&c| self.result.columns.get(c).map_or("", |c| c.name.as_str()))
+            .collect();
+        let fields = |row: &[CellValue]| -> Vec<String> {
+            columns.iter().map(|&c| row.get(c).map(plain_text).unwrap_or_default()).collect()
+        };
+
+        let mut text = String::new();
+        match format {
+            CopyFormat::Tsv | CopyFormat::TsvHeaders | CopyFormat::Csv => {
+                let delimiter = if format == CopyFormat::Csv { ',' } else { '\t' };
+                let line = |values: Vec<String>| -> String {
+                    let quoted: Vec<String> = values.iter().map(|v| delimited(v, delimiter)).collect();
+                    quoted.join(&delimiter.to_string())
+                };
+                if format != CopyFormat::Tsv {
+                    text.push_str(&line(names.iter().map(|n| n.to_string()).collect()));
+                    text.push('\n');
+                }
+                let lines: Vec<String> = rows.iter().map(|row| line(fields(row))).collect();
+                text.push_str(&lines.join("\n"));
+            }
+            CopyFormat::Markdown => {
+                let escaped: Vec<String> = names.iter().map(|n| markdown_escape(n)).collect();
+                text.push_str(&format!("| {} |\n", escaped.join(" | ")));
+                text.push_str(&format!("|{}\n", " --- |".repeat(columns.len())));
+                for row in &rows {
+                    let escaped: Vec<String> = fields(row).iter().map(|v| markdown_escape(v)).collect();
+                    text.push_str(&format!("| {} |\n", escaped.join(" | ")));
+                }
+            }
+            CopyFormat::InList => {
+                // The cursor's column when it is in the selection, else the first selected one
+                let col = self.selected_col().filter(|c| columns.contains(c)).unwrap_or(columns[0]);
+                let column = &self.result.columns[col];
+                let mut seen = HashSet::new();
+                let values: Vec<String> = rows
+                    .iter()
+                    .filter_map(|row| row.get(col))
+                    .filter(|cell| !matches!(cell, CellValue::Null))
+                    .map(|cell| sql_literal(cell, &column.type_name))
+                    .filter(|literal| seen.insert(literal.clone()))
+                    .collect();
+                if values.is_empty() {
+                    return Err(format!("{} has only NULLs in the selection", column.name));
+                }
+                text = format!("{} IN ({})", quote_ident(&column.name), values.join(", "));
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// Field quoted when it holds the delimiter, a quote or a line break, as Excel does
+fn delimited(text: &str, delimiter: char) -> String {
+    if text.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,622 @@
+//! Saved query snippets with folders, tags and placeholders
+
+use crate::app::{ActivePanel, App, InputMode, SelectionMode};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// A named, reusable piece of SQL
+///
+/// The body may contain placeholders: `${1:default}`, `${1}` or `$1` are visited
+/// in number order, `${name}` / `${name:default}` after them, and `$0` marks
+/// where the cursor ends up. `\$` inserts a literal dollar sign.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Snippet {
+    pub name: String,
+    #[serde(default)]
+    pub folder: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+    pub body: String,
+}
+
+impl Snippet {
+    /// Every search term must appear in the name, folder, description, tags or body;
+    /// `#term` only matches tags
+    fn matches(&self, filter: &str) -> bool {
+        filter.split_whitespace().all(|term| {
+            let term = term.to_lowercase();
+            match term.strip_prefix('#') {
+                Some(tag) => self.tags.iter().any(|t| t.to_lowercase().contains(tag)),
+                None => {
+                    self.name.to_lowercase().contains(&term)
+                        || self.folder.to_lowercase().contains(&term)
+                        || self.description.to_lowercase().contains(&term)
+                        || self.tags.iter().any(|t| t.to_lowercase().contains(&term))
+                        || self.body.to_lowercase().contains(&term)
+                }
+            }
+        })
+    }
+}
+
+/// One row of the snippets panel
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnippetRow {
+    Folder { name: String, count: usize, collapsed: bool },
+    Snippet(usize),
+}
+
+/// Snippet store, kept in `snippets.json` next to the query history
+#[derive(Clone, Debug, Default)]
+pub struct SnippetLibrary {
+    snippets: Vec<Snippet>,
+    /// Why `snippets.json` could not be read at startup
+    load_error: Option<String>,
+    /// The unreadable file is still in place, so saving would overwrite it
+    read_only: bool,
+}
+
+impl SnippetLibrary {
+    /// Load the library; a first run starts with a few examples
+    pub fn new() -> Self {
+        let mut library = Self::default();
+        let path = Self::snippets_file();
+        if path.exists() {
+            if let Err(e) = library.load() {
+                // Move the file aside so the next save cannot overwrite the user's snippets
+                let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                let backup = path.with_file_name(format!("snippets.unreadable-{}.json", stamp));
+                library.load_error = Some(match fs::rename(&path, &backup) {
+                    Ok(()) => format!("Snippets could not be read ({:#}); the file was moved to {}", e, backup.display()),
+                    Err(_) => {
+                        library.read_only = true;
+                        format!("Snippets could not be read ({:#}); changes will not be saved until {} is fixed", e, path.display())
+                    }
+                });
+            }
+        } else {
+            library.snippets = default_snippets();
+            library.sort();
+        }
+        library
+    }
+
+    /// Error from reading `snippets.json` at startup, if any
+    pub fn load_error(&self) -> Option<&str> {
+        self.load_error.as_deref()
+    }
+
+    pub fn snippets(&self) -> &[Snippet] {
+        &self.snippets
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Snippet> {
+        self.snippets.get(index)
+    }
+
+    /// Add a snippet and return its index
+    pub fn add(&mut self, snippet: Snippet) -> Result<usize> {
+        self.snippets.push(snippet);
+        let index = self.sort_tracking(self.snippets.len() - 1);
+        self.save()?;
+        Ok(index)
+    }
+
+    /// Replace a snippet and return its (possibly moved) index
+    pub fn update(&mut self, index: usize, snippet: Snippet) -> Result<usize> {
+        if let Some(slot) = self.snippets.get_mut(index) {
+            *slot = snippet;
+        }
+        let index = self.sort_tracking(index);
+        self.save()?;
+        Ok(index)
+    }
+
+    pub fn remove(&mut self, index: usize) -> Result<Option<Snippet>> {
+        if index >= self.snippets.len() {
+            return Ok(None);
+        }
+        let removed = self.snippets.remove(index);
+        self.save()?;
+        Ok(Some(removed))
+    }
+
+    /// Rows to show for a search filter: loose snippets first, then each folder
+    /// followed by its snippets unless collapsed (a search shows every match)
+    pub fn rows(&self, filter: &str, collapsed: &HashSet<String>) -> Vec<SnippetRow> {
+        let searching = !filter.trim().is_empty();
+        let matching: Vec<usize> = (0..self.snippets.len())
+            .filter(|&i| !searching || self.snippets[i].matches(filter))
+            .collect();
+
+        let mut rows: Vec<SnippetRow> = matching
+            .iter()
+            .filter(|&&i| self.snippets[i].folder.is_empty())
+            .map(|&i| SnippetRow::Snippet(i))
+            .collect();
+
+        let mut folder: Option<&str> = None;
+        for &i in matching.iter().filter(|&&i| !self.snippets[i].folder.is_empty()) {
+            let name = self.snippets[i].folder.as_str();
+            let is_collapsed = !searching && collapsed.contains(name);
+            if folder != Some(name) {
+                folder = Some(name);
+                let count = matching.iter().filter(|&&j| self.snippets[j].folder == name).count();
+                rows.push(SnippetRow::Folder { name: name.to_string(), count, collapsed: is_collapsed });
+            }
+            if !is_collapsed {
+                rows.push(SnippetRow::Snippet(i));
+            }
+        }
+        rows
+    }
+
+    /// Folders sorted by name, snippets by name within a folder
+    fn sort(&mut self) {
+        self.snippets.sort_by_key(|s| (s.folder.to_lowercase(), s.name.to_lowercase()));
+    }
+
+    /// Sort and return where the snippet at `index` ended up
+    fn sort_tracking(&mut self, index: usize) -> usize {
+        let mut order: Vec<usize> = (0..self.snippets.len()).collect();
+        order.sort_by_key(|&i| (self.snippets[i].folder.to_lowercase(), self.snippets[i].name.to_lowercase()));
+        self.sort();
+        order.iter().position(|&i| i == index).unwrap_or(0)
+    }
+
+    /// Get snippets file path
+    fn snippets_file() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("alrajhi-sql-tui")
+            .join("snippets.json")
+    }
+
+    /// Load snippets from disk
+    fn load(&mut self) -> Result<()> {
+        let content = fs::read_to_string(Self::snippets_file())?;
+        self.snippets = serde_json::from_str(&content)?;
+        self.sort();
+        Ok(())
+    }
+
+    /// Save snippets to disk
+    fn save(&self) -> Result<()> {
+        let path = Self::snippets_file();
+        if self.read_only {
+            bail!("{} could not be read at startup and is left untouched; fix it and restart", path.display());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(&self.snippets)?;
+        fs::write(&path, content)?;
+        Ok(())
+    }
+}
+
+fn default_snippets() -> Vec<Snippet> {
+    let snippet = |folder: &str, name: &str, tags: &[&str], description: &str, body: &str| Snippet {
+        name: name.to_string(),
+        folder: folder.to_string(),
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        description: description.to_string(),
+        body: body.to_string(),
+    };
+    vec![
+        snippet(
+            "Basics",
+            "Top rows",
+            &["select"],
+            "First rows of a table with a filter",
+            "SELECT TOP ${1:100} *\nFROM ${2:dbo.TableName}\nWHERE ${3:1 = 1};$0",
+        ),
+        snippet(
+            "Basics",
+            "Duplicate keys",
+            &["select", "quality"],
+            "Rows whose key appears more than once",
+            "SELECT *\n\
+             FROM (\n\
+             \x20   SELECT *, COUNT(*) OVER (PARTITION BY ${2:KeyColumn}) AS cnt\n\
+             \x20   FROM ${1:dbo.TableName}\n\
+             ) d\n\
+             WHERE cnt > 1;$0",
+        ),
+        snippet(
+            "Admin",
+            "Row counts by table",
+            &["sys", "size"],
+            "Approximate row count of every table",
+            "SELECT s.name AS schema_name, t.name AS table_name, SUM(p.rows) AS row_count\n\
+             FROM sys.tables t\n\
+             JOIN sys.schemas s ON s.schema_id = t.schema_id\n\
+             JOIN sys.partitions p ON p.object_id = t.object_id AND p.index_id IN (0, 1)\n\
+             GROUP BY s.name, t.name\n\
+             ORDER BY row_count DESC;",
+        ),
+        snippet(
+            "Admin",
+            "Find column",
+            &["sys", "search"],
+            "Tables that have a column matching a name",
+            "SELECT t.name AS table_name, c.name AS column_name\n\
+             FROM sys.columns c\n\
+             JOIN sys.tables t ON t.object_id = c.object_id\n\
+             WHERE c.name LIKE '%${1:column}%'\n\
+             ORDER BY t.name, c.name;$0",
+        ),
+        snippet(
+            "Admin",
+            "Running requests",
+            &["sys", "sessions"],
+            "What the server is executing right now",
+            "SELECT r.session_id, r.status, r.wait_type, r.total_elapsed_time, t.text\n\
+             FROM sys.dm_exec_requests r\n\
+             CROSS APPLY sys.dm_exec_sql_text(r.sql_handle) t\n\
+             WHERE r.session_id <> @@SPID;",
+        ),
+    ]
+}
+
+/// Replace placeholders with their default text; returns the text and the
+/// placeholder ranges in the order the cursor visits them
+pub fn expand_placeholders(body: &str) -> (String, Vec<Range<usize>>) {
+    // (group, number, range): numbered stops, then named ones, then $0
+    let mut stops: Vec<(u8, usize, Range<usize>)> = Vec::new();
+    let mut defaults: Vec<(String, String)> = Vec::new();
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+
+    while let Some(pos) = rest.find(['$', '\\']) {
+        out.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+
+        if let Some(after) = tail.strip_prefix("\\$") {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+        if let Some(after) = tail.strip_prefix('\\') {
+            out.push('\\');
+            rest = after;
+            continue;
+        }
+
+        let (key, default, len) = match parse_placeholder(tail) {
+            Some(parsed) => parsed,
+            None => {
+                out.push('$');
+                rest = &tail[1..];
+                continue;
+            }
+        };
+        rest = &tail[len..];
+
+        // A repeated placeholder reuses the first default and is only visited once
+        let default = match defaults.iter().find(|(k, _)| *k == key) {
+            Some((_, first)) if default.is_none() => first.clone(),
+            _ => default.unwrap_or_else(|| if key.parse::<usize>().is_ok() { String::new() } else { key.clone() }),
+        };
+        let start = out.len();
+        out.push_str(&default);
+        if defaults.iter().any(|(k, _)| *k == key) {
+            continue;
+        }
+        defaults.push((key.clone(), default));
+
+        let (group, number) = match key.parse::<usize>() {
+            Ok(0) => (2, 0),
+            Ok(n) => (0, n),
+            Err(_) => (1, 0),
+        };
+        stops.push((group, number, start..out.len()));
+    }
+    out.push_str(rest);
+
+    stops.sort_by_key(|(group, number, _)| (*group, *number));
+    (out, stops.into_iter().map(|(_, _, range)| range).collect())
+}
+
+/// `$N`, `${N}`, `${N:default}`, `${name}` or `${name:default}` at the start of `text`:
+/// (key, default, length)
+fn parse_placeholder(text: &str) -> Option<(String, Option<String>, usize)> {
+    let after = &text[1..];
+    let digits = after.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        return Some((after[..digits].to_string(), None, 1 + digits));
+    }
+
+    let inner = after.strip_prefix('{')?;
+    let end = inner.find('}')?;
+    let content = &inner[..end];
+    let (key, default) = match content.split_once(':') {
+        Some((key, default)) => (key, Some(default.to_string())),
+        None => (content, None),
+    };
+    let valid = !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+    valid.then(|| (key.to_string(), default, 2 + end + 1))
+}
+
+/// Placeholders of the last inserted snippet that Tab still has to visit
+#[derive(Clone, Debug)]
+pub struct SnippetStops {
+    stops: Vec<Range<usize>>,
+    current: usize,
+    /// Editor text length when the current placeholder was selected
+    text_len: usize,
+}
+
+/// Browse/search state of the snippets panel
+#[derive(Clone, Debug, Default)]
+pub struct SnippetPanel {
+    pub selected: usize,
+    pub filter: String,
+    /// Typing goes into the search filter
+    pub searching: bool,
+    pub collapsed: HashSet<String>,
+    /// `d` was pressed once; a second press deletes
+    pub confirm_delete: bool,
+}
+
+/// Labels of the snippet form fields, in order
+pub const SNIPPET_FORM_FIELDS: [&str; 4] = ["Name", "Folder", "Tags", "Description"];
+
+/// Popup for naming a new snippet or editing a snippet's details
+#[derive(Clone, Debug, Default)]
+pub struct SnippetForm {
+    /// Snippet being edited; None saves a new one
+    pub editing: Option<usize>,
+    /// Name, folder, comma-separated tags and description
+    pub fields: [String; 4],
+    pub field: usize,
+    pub body: String,
+    pub error: Option<String>,
+}
+
+impl SnippetForm {
+    fn new(editing: Option<usize>, snippet: &Snippet) -> Self {
+        Self {
+            editing,
+            fields: [
+                snippet.name.clone(),
+                snippet.folder.clone(),
+                snippet.tags.join(", "),
+                snippet.description.clone(),
+            ],
+            field: 0,
+            body: snippet.body.clone(),
+            error: None,
+        }
+    }
+
+    pub fn next_field(&mut self, delta: isize) {
+        let count = SNIPPET_FORM_FIELDS.len() as isize;
+        self.field = (self.field as isize + delta).rem_euclid(count) as usize;
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.fields[self.field].push(c);
+        self.error = None;
+    }
+
+    pub fn pop(&mut self) {
+        self.fields[self.field].pop();
+    }
+
+    fn snippet(&self) -> Snippet {
+        Snippet {
+            name: self.fields[0].trim().to_string(),
+            folder: self.fields[1].trim().trim_matches('/').to_string(),
+            tags: self.fields[2]
+                .split([',', ' '])
+                .map(|t| t.trim().trim_start_matches('#'))
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect(),
+            description: self.fields[3].trim().to_string(),
+            body: self.body.clone(),
+        }
+    }
+}
+
+impl App {
+    /// Rows currently shown in the snippets panel
+    pub fn snippet_rows(&self) -> Vec<SnippetRow> {
+        self.snippets.rows(&self.snippet_panel.filter, &self.snippet_panel.collapsed)
+    }
+
+    /// Snippet under the panel selection
+    pub fn selected_snippet(&self) -> Option<usize> {
+        match self.snippet_rows().get(self.snippet_panel.selected) {
+            Some(SnippetRow::Snippet(index)) => Some(*index),
+            _ => None,
+        }
+    }
+
+    pub fn move_snippet_selection(&mut self, delta: isize) {
+        let last = self.snippet_rows().len().saturating_sub(1) as isize;
+        self.snippet_panel.selected = (self.snippet_panel.selected as isize + delta).clamp(0, last) as usize;
+        self.snippet_panel.confirm_delete = false;
+    }
+
+    /// Collapse or expand the selected folder
+    pub fn toggle_snippet_folder(&mut self) {
+        if let Some(SnippetRow::Folder { name, .. }) = self.snippet_rows().get(self.snippet_panel.selected) {
+            if !self.snippet_panel.collapsed.remove(name) {
+                self.snippet_panel.collapsed.insert(name.clone());
+            }
+        }
+    }
+
+    /// Insert a snippet at the cursor (replacing any selection) and select its first placeholder
+    pub fn insert_snippet(&mut self, index: usize) {
+        let Some(snippet) = self.snippets.get(index) else {
+            return;
+        };
+        let (text, stops) = expand_placeholders(&snippet.body.replace("\r\n", "\n"));
+        let name = snippet.name.clone();
+
+        let start = self.editor.selection().map(|r| r.start).unwrap_or(self.editor.cursor());
+        self.editor.insert_str(&text);
+        self.active_panel = ActivePanel::QueryEditor;
+        self.snippet_stops = None;
+        self.message = Some(format!("Inserted snippet '{}'", name));
+
+        if stops.is_empty() {
+            return;
+        }
+        if self.vim_enabled && self.input_mode != InputMode::Insert {
+            self.vim_start_insert();
+        }
+        let stops = stops.into_iter().map(|r| r.start + start..r.end + start).collect();
+        let session = SnippetStops { stops, current: 0, text_len: self.editor.text().len() };
+        self.select_snippet_stop(&session.stops[0]);
+        if session.stops.len() > 1 {
+            self.snippet_stops = Some(session);
+        }
+    }
+
+    /// Tab after inserting a snippet: select the next placeholder; false when there is none
+    pub fn next_snippet_stop(&mut self) -> bool {
+        let Some(mut session) = self.snippet_stops.take() else {
+            return false;
+        };
+
+        // Whatever was typed into the current placeholder shifts the ones after it in the text
+        let delta = self.editor.text().len() as isize - session.text_len as isize;
+        let current = session.stops[session.current].clone();
+        let end = current.end as isize + delta;
+        let cursor = self.editor.cursor();
+        if end < current.start as isize || cursor < current.start || cursor as isize > end {
+            // The cursor left the placeholder, so the remaining positions can't be trusted
+            return false;
+        }
+        for stop in session.stops.iter_mut().filter(|s| s.start >= current.end && **s != current) {
+            stop.start = (stop.start as isize + delta) as usize;
+            stop.end = (stop.end as isize + delta) as usize;
+        }
+
+        session.current += 1;
+        let next = session.stops[session.current].clone();
+        self.select_snippet_stop(&next);
+        if session.current + 1 < session.stops.len() {
+            session.text_len = self.editor.text().len();
+            self.snippet_stops = Some(session);
+        }
+        true
+    }
+
+    fn select_snippet_stop(&mut self, stop: &Range<usize>) {
+        self.editor.set_selection_mode(SelectionMode::Exclusive);
+        self.editor.set_anchor((!stop.is_empty()).then_some(stop.start));
+        self.editor.set_cursor(stop.end);
+    }
+
+    /// Open the form to save the editor selection (or the whole query) as a snippet
+    pub fn new_snippet_from_editor(&mut self) {
+        let body = self.editor.selected_text().unwrap_or(self.editor.text()).trim_end().to_string();
+        if body.trim().is_empty() {
+            self.error = Some("Nothing to save: the query is empty".to_string());
+            return;
+        }
+        // Default to the folder of the selected snippet
+        let folder = self
+            .selected_snippet()
+            .and_then(|i| self.snippets.get(i))
+            .map(|s| s.folder.clone())
+            .unwrap_or_default();
+        self.snippet_form = Some(SnippetForm::new(None, &Snippet { folder, body, ..Snippet::default() }));
+    }
+
+    /// Open the form to edit the selected snippet's name, folder, tags and description
+    pub fn edit_selected_snippet(&mut self) {
+        if let Some(index) = self.selected_snippet() {
+            let form = SnippetForm::new(Some(index), &self.snippets.snippets()[index]);
+            self.snippet_form = Some(form);
+        }
+    }
+
+    /// Save the snippet form; it stays open with an error when the name is missing
+    pub fn confirm_snippet_form(&mut self) {
+        let Some(form) = self.snippet_form.as_mut() else {
+            return;
+        };
+        let snippet = form.snippet();
+        if snippet.name.is_empty() {
+            form.error = Some("Name is required".to_string());
+            form.field = 0;
+            return;
+        }
+        let editing = form.editing;
+        self.snippet_form = None;
+
+        let name = snippet.name.clone();
+        let saved = match editing {
+            Some(index) => self.snippets.update(index, snippet),
+            None => self.snippets.add(snippet),
+        };
+        match saved {
+            Ok(index) => {
+                self.select_snippet_index(index);
+                self.message = Some(format!("✓ Saved snippet '{}'", name));
+            }
+            Err(e) => self.error = Some(format!("Snippet save failed: {}", e)),
+        }
+    }
+
+    /// Replace the selected snippet's body with the editor selection (or the whole query)
+    pub fn update_selected_snippet_body(&mut self) {
+        let Some(index) = self.selected_snippet() else {
+            return;
+        };
+        let body = self.editor.selected_text().unwrap_or(self.editor.text()).trim_end().to_string();
+        let mut snippet = self.snippets.snippets()[index].clone();
+        snippet.body = body;
+        let name = snippet.name.clone();
+        match self.snippets.update(index, snippet) {
+            Ok(index) => {
+                self.select_snippet_index(index);
+                self.message = Some(format!("✓ Updated '{}' from the editor", name));
+            }
+            Err(e) => self.error = Some(format!("Snippet save failed: {}", e)),
+        }
+    }
+
+    /// Delete the selected snippet; the first press only asks for confirmation
+    pub fn delete_selected_snippet(&mut self) {
+        let Some(index) = self.selected_snippet() else {
+            return;
+        };
+        if !self.snippet_panel.confirm_delete {
+            self.snippet_panel.confirm_delete = true;
+            self.message = Some(format!("Press d again to delete '{}'", self.snippets.snippets()[index].name));
+            return;
+        }
+        self.snippet_panel.confirm_delete = false;
+        match self.snippets.remove(index) {
+            Ok(Some(snippet)) => {
+                self.move_snippet_selection(0);
+                self.message = Some(format!("Deleted snippet '{}'", snippet.name));
+            }
+            Ok(None) => {}
+            Err(e) => self.error = Some(format!("Snippet save failed: {}", e)),
+        }
+    }
+
+    /// Move the panel selection to a snippet, expanding its folder
+    fn select_snippet_index(&mut self, index: usize) {
+        if let Some(snippet) = self.snippets.get(index) {
+            self.snippet_panel.collapsed.remove(&snippet.folder);
+        }
+        if let Some(row) = self.snippet_rows().iter().position(|r| *r == SnippetRow::Snippet(index)) {
+            self.snippet_panel.selected = row;
+        }
+    }
+}
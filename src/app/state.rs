@@ -1,10 +1,16 @@
 //! Application state
 
 use crate::db::{DbConfig, DbConnection, QueryResult};
-use crate::app::QueryHistory;
+use crate::app::{CellInspector, ColumnLayout, CopyFormat, DiffSetup, EditOutcome, EditSession, EditorBuffer, ExportDialog, ExportJob, FileBrowser, GridInput, GridSelection, HistoryEntry, HistoryFilter, ImportJob, ImportWizard, QueryFile, DEFAULT_RESULT_HISTORY_SIZE, ResultHistory, QueryHistory, ResultDiff, ResultView, ScriptDialog, SnippetForm, SnippetLibrary, SnippetPanel, SnippetStops, VimState};
+use crate::export::ExportOptions;
 use anyhow::Result;
-use std::error::Error;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::Instant;
+use tiberius::Client;
+use tokio::net::TcpStream;
 use tokio::sync::oneshot;
+use tokio_util::compat::Compat;
 
 /// Active panel in the UI
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -13,6 +19,7 @@ pub enum ActivePanel {
     Results,
     SchemaExplorer,
     History,
+    Snippets,
 }
 
 /// Results tab view
@@ -21,6 +28,7 @@ pub enum ResultsTab {
     Data,       // Table data
     Columns,    // Column names and types
     Stats,      // Query statistics
+    Diff,       // Comparison with an earlier result set
 }
 
 /// Input mode for the query editor
@@ -28,9 +36,24 @@ pub enum ResultsTab {
 pub enum InputMode {
     Normal,
     Insert,
+    Visual,
+    VisualLine,
     Command,
 }
 
+impl InputMode {
+    /// Status bar label
+    pub fn label(&self) -> &'static str {
+        match self {
+            InputMode::Normal => "NORMAL",
+            InputMode::Insert => "INSERT",
+            InputMode::Visual => "VISUAL",
+            InputMode::VisualLine => "V-LINE",
+            InputMode::Command => "COMMAND",
+        }
+    }
+}
+
 /// Schema tree node
 #[derive(Clone, Debug)]
 pub struct SchemaNode {
@@ -81,15 +104,15 @@ pub struct App {
     /// Database connection
     pub db: DbConnection,
 
-    /// Current query text
-    pub query: String,
-
-    /// Cursor position in query
-    pub cursor_pos: usize,
+    /// Query editor text, cursor, selection and undo history
+    pub editor: EditorBuffer,
 
     /// Current query result
     pub result: QueryResult,
 
+    /// Earlier result sets to step back and forward through
+    pub result_history: ResultHistory,
+
     /// Is query running?
     pub is_loading: bool,
 
@@ -105,16 +128,43 @@ pub struct App {
     /// Input mode (vim-style)
     pub input_mode: InputMode,
 
+    /// Vim keymap in the query editor (F3 or VIM_MODE=1)
+    pub vim_enabled: bool,
+
+    /// Unfinished vim command and yank register
+    pub vim: VimState,
+
+    /// .sql file the query was opened from or saved to
+    pub query_file: Option<QueryFile>,
+
+    /// Open/save-as file browser popup
+    pub file_browser: Option<FileBrowser>,
+
+    /// Last time the open file was checked for changes on disk
+    pub last_disk_check: Instant,
+
     /// Query history
     pub history: QueryHistory,
 
+    /// Saved snippets library
+    pub snippets: SnippetLibrary,
+
+    /// Snippets panel selection, search and collapsed folders
+    pub snippet_panel: SnippetPanel,
+
+    /// New/edit snippet popup
+    pub snippet_form: Option<SnippetForm>,
+
+    /// Placeholders of the last inserted snippet, visited with Tab
+    pub snippet_stops: Option<SnippetStops>,
+
     /// Schema tree
     pub schema_tree: Vec<SchemaNode>,
 
     /// Selected index in schema tree
     pub schema_selected: usize,
 
-    /// Results scroll position
+    /// First display row drawn in the Data tab, from the last draw
     pub results_scroll: usize,
 
     /// Selected row in results
@@ -126,9 +176,79 @@ pub struct App {
     /// Current results tab
     pub results_tab: ResultsTab,
 
-    /// History scroll position
+    /// Sort/filter view over the result rows
+    pub result_view: ResultView,
+
+    /// Filter or search prompt on the Data tab
+    pub grid_input: Option<GridInput>,
+
+    /// Show the selected row as a vertical name/type/value list
+    pub record_view: bool,
+
+    /// Rows scrolled inside the selected record field when it is taller than the pane
+    pub record_field_scroll: usize,
+
+    /// From the last draw: rows of the selected field below a full pane, and the pane height
+    pub record_field_overflow: usize,
+    pub record_page_rows: usize,
+
+    /// Column order, widths, visibility and pinning in the Data tab
+    pub column_layout: ColumnLayout,
+
+    /// Header cells from the last draw: (left x, right edge x, result column)
+    pub grid_header_bounds: Vec<(u16, u16, usize)>,
+
+    /// Header row y position from the last draw
+    pub grid_header_y: u16,
+
+    /// Column being resized with the mouse: (result column, start x, start width)
+    pub column_drag: Option<(usize, u16, u16)>,
+
+    /// Data row y positions from the last draw
+    pub grid_rows_y: Range<u16>,
+
+    /// Row number column x positions from the last draw
+    pub grid_row_number_x: Range<u16>,
+
+    /// Range selected in the Data tab, from its anchor to the cursor
+    pub grid_selection: Option<GridSelection>,
+
+    /// Copy-as menu for the selection, with the highlighted format
+    pub copy_menu: Option<CopyFormat>,
+
+    /// Full-value popup for the selected cell
+    pub inspector: Option<CellInspector>,
+
+    /// Compare-results popup (pick a result set and key columns)
+    pub diff_setup: Option<DiffSetup>,
+
+    /// Last comparison, shown on the Diff tab
+    pub result_diff: Option<ResultDiff>,
+
+    /// Export dialog (path, format, encoding...)
+    pub export_dialog: Option<ExportDialog>,
+
+    /// Last-used export settings, saved in export.json
+    pub export_options: ExportOptions,
+
+    /// INSERT/MERGE script generator popup
+    pub script_dialog: Option<ScriptDialog>,
+
+    /// CSV import wizard
+    pub import_wizard: Option<ImportWizard>,
+
+    /// Selected row in the (filtered) history panel
     pub history_selected: usize,
 
+    /// History search text and filters
+    pub history_filter: HistoryFilter,
+
+    /// Typing goes into the history search (Ctrl+R)
+    pub history_searching: bool,
+
+    /// `d` was pressed once in the history panel; a second press deletes
+    pub history_confirm_delete: bool,
+
     /// Command buffer (for : commands)
     pub command_buffer: String,
 
@@ -153,19 +273,43 @@ pub struct App {
     /// Query being executed (for history)
     pub pending_query_text: Option<String>,
 
+    /// When the pending query started, to time failed runs
+    pub pending_query_started: Option<Instant>,
+
     /// Query editor horizontal scroll offset
     pub query_scroll_x: usize,
 
     /// Query editor vertical scroll offset
     pub query_scroll_y: usize,
+
+    /// Query that produced the current result
+    pub result_query: Option<String>,
+
+    /// Staged edits when the Data tab is in edit mode
+    pub edit_session: Option<EditSession>,
+
+    /// Pending edit-mode operation (loading table metadata or applying changes)
+    pub pending_edit: Option<oneshot::Receiver<Result<EditOutcome, String>>>,
+
+    /// Pending database switch: new database name and its schema tree
+    pub pending_db_switch: Option<oneshot::Receiver<DbSwitchResult>>,
+
+    /// Query export streaming to disk
+    pub pending_export: Option<ExportJob>,
+
+    /// Import wizard work: reading the target's columns or loading the file
+    pub pending_import: Option<ImportJob>,
 }
 
+/// New database name and its schema tree, or the error from `USE`
+pub type DbSwitchResult = Result<(String, Vec<SchemaNode>), String>;
+
 /// Spinner animation frames
 pub const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
 impl App {
-    /// Create new app with database connection
-    pub async fn new() -> Result<Self> {
+    /// Create new app with database connection, optionally opening a .sql file
+    pub async fn new(query_file: Option<PathBuf>) -> Result<Self> {
         let config = DbConfig::default();
         let db = DbConnection::new(config).await?;
 
@@ -174,26 +318,67 @@ impl App {
 
         // Default query for quick testing
         let default_query = "SELECT TOP 2 * FROM Staging.[dbo].RBS_rbsdw98d_trx_ISS_SORT".to_string();
-        let cursor_pos = default_query.len();
+
+        let vim_enabled = std::env::var("VIM_MODE").is_ok_and(|v| matches!(v.as_str(), "1" | "true" | "on"));
+
+        // Earlier result sets kept for back/forward, optionally spilled to disk
+        let result_history_size = std::env::var("RESULT_HISTORY_SIZE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_RESULT_HISTORY_SIZE);
+        let result_spill = std::env::var("RESULT_HISTORY_SPILL").is_ok_and(|v| matches!(v.as_str(), "1" | "true" | "on"));
 
         let mut app = Self {
             db,
-            query: default_query,
-            cursor_pos,
+            editor: EditorBuffer::new(default_query),
             result: QueryResult::empty(),
+            result_history: ResultHistory::new(result_history_size, result_spill),
             is_loading: false,
             error: None,
             message: Some("Connected to SQL Server".to_string()),
             active_panel: ActivePanel::QueryEditor,
-            input_mode: InputMode::Insert,
-            history: QueryHistory::new(1000),
+            input_mode: if vim_enabled { InputMode::Normal } else { InputMode::Insert },
+            vim_enabled,
+            vim: VimState::default(),
+            query_file: None,
+            file_browser: None,
+            last_disk_check: Instant::now(),
+            history: QueryHistory::from_env(),
+            snippets: SnippetLibrary::new(),
+            snippet_panel: SnippetPanel::default(),
+            snippet_form: None,
+            snippet_stops: None,
             schema_tree: Vec::new(),
             schema_selected: 0,
             results_scroll: 0,
             results_selected: 0,
             results_col_selected: 0,
             results_tab: ResultsTab::Data,
+            result_view: ResultView::default(),
+            grid_input: None,
+            record_view: false,
+            record_field_scroll: 0,
+            record_field_overflow: 0,
+            record_page_rows: 0,
+            column_layout: ColumnLayout::default(),
+            grid_header_bounds: Vec::new(),
+            grid_header_y: 0,
+            column_drag: None,
+            grid_rows_y: 0..0,
+            grid_row_number_x: 0..0,
+            grid_selection: None,
+            copy_menu: None,
+            inspector: None,
+            diff_setup: None,
+            result_diff: None,
+            export_dialog: None,
+            export_options: ExportOptions::load(),
+            script_dialog: None,
+            import_wizard: None,
             history_selected: 0,
+            history_filter: HistoryFilter::default(),
+            history_searching: false,
+            history_confirm_delete: false,
             command_buffer: String::new(),
             should_quit: false,
             show_help: false,
@@ -202,116 +387,181 @@ impl App {
             spinner_frame: 0,
             pending_query: None,
             pending_query_text: None,
+            pending_query_started: None,
             query_scroll_x: 0,
             query_scroll_y: 0,
+            result_query: None,
+            edit_session: None,
+            pending_edit: None,
+            pending_db_switch: None,
+            pending_export: None,
+            pending_import: None,
         };
 
+        if let Some(e) = app.history.open_error() {
+            app.error = Some(format!("History database unavailable, keeping history for this session only: {}", e));
+        }
+        if let Some(e) = app.history.import_error() {
+            app.error = Some(format!("Old history not imported: {}", e));
+        }
+        if let Some(e) = app.snippets.load_error() {
+            app.error = Some(e.to_string());
+        }
+
         // Load initial schema
         app.load_schema().await?;
 
-        // Auto-execute default query to show results on startup
-        app.execute_default_query().await;
+        // Open the requested file, or auto-execute the default query to show results on startup
+        match query_file {
+            Some(path) => app.open_query_file(path),
+            None => app.execute_default_query().await,
+        }
 
         Ok(app)
     }
 
     /// Execute the default query on startup
     async fn execute_default_query(&mut self) {
-        if self.query.is_empty() {
+        let query = self.editor.text().to_string();
+        if query.is_empty() {
             return;
         }
 
         let client_arc = self.db.client();
         let mut client = client_arc.lock().await;
 
-        match crate::db::QueryExecutor::execute(&mut client, &self.query).await {
+        match crate::db::QueryExecutor::execute(&mut client, &query).await {
             Ok(result) => {
-                let row_count = result.row_count;
-                let exec_time = result.execution_time.as_millis() as u64;
-
-                self.history.add(
-                    self.query.clone(),
-                    exec_time,
-                    Some(row_count),
-                    self.db.config.database.clone(),
-                );
+                let history_id = self.history.add(self.history_entry(query.clone(), Some(&result), 0));
 
                 self.message = Some(format!(
                     "{} row(s) returned in {:.2}ms",
-                    row_count,
+                    result.row_count,
                     result.execution_time.as_secs_f64() * 1000.0
                 ));
 
-                self.result = result;
-                self.results_selected = 0;
-                self.results_col_selected = 0;
+                self.show_new_result(result, query, history_id);
             }
             Err(e) => {
+                self.history.add(self.history_entry(query, None, 0).failed(e.to_string()));
                 self.error = Some(e.to_string());
             }
         }
     }
 
+    /// History entry for a run, tagged with the current database, server and profile;
+    /// without a result it is timed by `elapsed_ms` and marked failed/cancelled by the caller
+    fn history_entry(&self, query: String, result: Option<&QueryResult>, elapsed_ms: u64) -> HistoryEntry {
+        let config = &self.db.config;
+        let entry = HistoryEntry::new(
+            query,
+            config.database.clone(),
+            format!("{}:{}", config.host, config.port),
+            config.profile.clone(),
+        );
+        match result {
+            Some(result) => HistoryEntry {
+                execution_time_ms: result.execution_time.as_millis() as u64,
+                row_count: Some(result.row_count),
+                affected_rows: result.affected_rows,
+                ..entry
+            },
+            None => HistoryEntry { execution_time_ms: elapsed_ms, ..entry },
+        }
+    }
+
+    /// Milliseconds since the pending query started
+    fn pending_elapsed_ms(&self) -> u64 {
+        self.pending_query_started.map_or(0, |t| t.elapsed().as_millis() as u64)
+    }
+
     /// Load schema tree
     pub async fn load_schema(&mut self) -> Result<()> {
         let client_arc = self.db.client();
         let mut client = client_arc.lock().await;
+        self.schema_tree = build_schema_tree(&mut client).await;
 
-        // Create root folders
-        let mut tables_folder = SchemaNode::new_folder("Tables");
-        let mut views_folder = SchemaNode::new_folder("Views");
-        let mut procs_folder = SchemaNode::new_folder("Stored Procedures");
-
-        // Load tables
-        if let Ok(tables) = crate::db::SchemaExplorer::get_tables(&mut client, None).await {
-            for table in tables {
-                tables_folder.children.push(SchemaNode {
-                    name: format!("{}.{}", table.schema, table.name),
-                    node_type: SchemaNodeType::Table,
-                    expanded: false,
-                    children: Vec::new(),
-                    schema: Some(table.schema),
-                });
-            }
-        }
+        Ok(())
+    }
 
-        // Load views
-        if let Ok(views) = crate::db::SchemaExplorer::get_views(&mut client, None).await {
-            for view in views {
-                views_folder.children.push(SchemaNode {
-                    name: format!("{}.{}", view.schema, view.name),
-                    node_type: SchemaNodeType::View,
-                    expanded: false,
-                    children: Vec::new(),
-                    schema: Some(view.schema),
-                });
-            }
+    /// Switch the connection to another database and reload the schema tree (non-blocking)
+    pub fn switch_database(&mut self, database: String) {
+        if self.is_loading {
+            return;
         }
+        self.is_loading = true;
+        self.error = None;
+        self.message = None;
+        self.spinner_frame = 0;
 
-        // Load procedures
-        if let Ok(procs) = crate::db::SchemaExplorer::get_procedures(&mut client, None).await {
-            for proc in procs {
-                procs_folder.children.push(SchemaNode {
-                    name: format!("{}.{}", proc.schema, proc.name),
-                    node_type: SchemaNodeType::Procedure,
-                    expanded: false,
-                    children: Vec::new(),
-                    schema: Some(proc.schema),
-                });
+        let (tx, rx) = oneshot::channel();
+        let client_arc = self.db.client();
+        self.pending_db_switch = Some(rx);
+
+        tokio::spawn(async move {
+            let mut client = client_arc.lock().await;
+            let sql = format!("USE {}", crate::db::quote_ident(&database));
+            let switched = match client.simple_query(sql).await {
+                Ok(stream) => stream.into_results().await.map(|_| ()),
+                Err(e) => Err(e),
+            };
+            let outcome = match switched {
+                Ok(()) => Ok((database, build_schema_tree(&mut client).await)),
+                Err(e) => Err(error_chain(&e.into())),
+            };
+            let _ = tx.send(outcome);
+        });
+    }
+
+    /// Check if a database switch has finished
+    pub fn check_db_switch_completion(&mut self) {
+        let Some(rx) = self.pending_db_switch.as_mut() else {
+            return;
+        };
+
+        let outcome = match rx.try_recv() {
+            Ok(outcome) => outcome,
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => {
+                Err("Database switch was interrupted".to_string())
             }
-        }
+        };
 
-        self.schema_tree = vec![tables_folder, views_folder, procs_folder];
+        self.is_loading = false;
+        self.pending_db_switch = None;
 
-        Ok(())
+        match outcome {
+            Ok((database, tree)) => {
+                self.message = Some(format!("Switched to database {}", database));
+                self.db.config.database = database;
+                self.schema_tree = tree;
+                self.schema_selected = 0;
+            }
+            Err(error_msg) => self.error = Some(error_msg),
+        }
     }
 
     /// Start query execution (non-blocking)
     pub fn start_query(&mut self) {
-        if self.query.trim().is_empty() || self.is_loading {
+        if self.editor.text().trim().is_empty() || self.is_loading {
             return;
         }
 
+        self.run_query(self.editor.text().to_string());
+    }
+
+    /// Run the given query text in the background
+    pub fn run_query(&mut self, query: String) {
+        if let Some(session) = self.edit_session.as_ref() {
+            let changes = session.change_count(self.result.rows.len());
+            if changes > 0 {
+                self.error = Some(format!(
+                    "{} staged change(s) in edit mode; commit them (p) or discard them (Esc) before running a query",
+                    changes
+                ));
+                return;
+            }
+        }
         self.is_loading = true;
         self.error = None;
         self.message = None;
@@ -319,28 +569,27 @@ impl App {
 
         let (tx, rx) = oneshot::channel();
         let client_arc = self.db.client();
-        let query = self.query.clone();
+        let reconnect = (!self.db.connected).then(|| self.db.config.clone());
 
         self.pending_query = Some(rx);
         self.pending_query_text = Some(query.clone());
+        self.pending_query_started = Some(Instant::now());
 
         // Spawn query execution in background
         tokio::spawn(async move {
             let mut client = client_arc.lock().await;
-            let result = crate::db::QueryExecutor::execute(&mut client, &query).await;
-
-            let _ = tx.send(match result {
-                Ok(r) => Ok(r),
-                Err(e) => {
-                    let mut error_msg = e.to_string();
-                    let mut source = e.source();
-                    while let Some(s) = source {
-                        error_msg.push_str(&format!(" | Caused by: {}", s));
-                        source = std::error::Error::source(s);
+            if let Some(config) = reconnect {
+                match DbConnection::connect(&config).await {
+                    Ok(fresh) => *client = fresh,
+                    Err(e) => {
+                        let _ = tx.send(Err(error_chain(&e)));
+                        return;
                     }
-                    Err(error_msg)
                 }
-            });
+            }
+            let result = crate::db::QueryExecutor::execute(&mut client, &query).await;
+
+            let _ = tx.send(result.map_err(|e| error_chain(&e)));
         });
     }
 
@@ -351,30 +600,25 @@ impl App {
                 Ok(result) => {
                     match result {
                         Ok(query_result) => {
-                            let row_count = query_result.row_count;
-                            let exec_time = query_result.execution_time.as_millis() as u64;
-
-                            if let Some(ref query_text) = self.pending_query_text {
-                                self.history.add(
-                                    query_text.clone(),
-                                    exec_time,
-                                    Some(row_count),
-                                    self.db.config.database.clone(),
-                                );
-                            }
+                            self.db.connected = true;
+                            let query_text = self.pending_query_text.clone().unwrap_or_default();
+                            let history_id = self.history.add(self.history_entry(query_text.clone(), Some(&query_result), 0));
 
                             self.message = Some(format!(
                                 "{} row(s) returned in {:.2}ms",
-                                row_count,
+                                query_result.row_count,
                                 query_result.execution_time.as_secs_f64() * 1000.0
                             ));
 
-                            self.result = query_result;
-                            self.results_scroll = 0;
-                            self.results_selected = 0;
+                            self.show_new_result(query_result, query_text, history_id);
                             self.active_panel = ActivePanel::Results;
                         }
                         Err(error_msg) => {
+                            // Failed queries are kept too, they are often the ones worth revisiting
+                            if let Some(query_text) = self.pending_query_text.clone() {
+                                let elapsed = self.pending_elapsed_ms();
+                                self.history.add(self.history_entry(query_text, None, elapsed).failed(error_msg.clone()));
+                            }
                             self.error = Some(error_msg);
                         }
                     }
@@ -382,16 +626,23 @@ impl App {
                     self.is_loading = false;
                     self.pending_query = None;
                     self.pending_query_text = None;
+                    self.pending_query_started = None;
                 }
                 Err(oneshot::error::TryRecvError::Empty) => {
                     // Still waiting
                 }
                 Err(oneshot::error::TryRecvError::Closed) => {
-                    // Channel closed unexpectedly
+                    // The query task ended without answering (it panicked or was dropped);
+                    // that is a failure, not a cancel the user asked for
                     self.error = Some("Query execution was interrupted".to_string());
+                    if let Some(query_text) = self.pending_query_text.clone() {
+                        let elapsed = self.pending_elapsed_ms();
+                        self.history.add(self.history_entry(query_text, None, elapsed).failed("interrupted".to_string()));
+                    }
                     self.is_loading = false;
                     self.pending_query = None;
                     self.pending_query_text = None;
+                    self.pending_query_started = None;
                 }
             }
         }
@@ -444,8 +695,7 @@ impl App {
         if let Some((_, node)) = visible.get(self.schema_selected) {
             if node.node_type == SchemaNodeType::Table || node.node_type == SchemaNodeType::View {
                 let insert_text = format!("[{}]", node.name);
-                self.query.insert_str(self.cursor_pos, &insert_text);
-                self.cursor_pos += insert_text.len();
+                self.editor.insert_str(&insert_text);
                 self.active_panel = ActivePanel::QueryEditor;
             }
         }
@@ -453,18 +703,16 @@ impl App {
 
     /// Load history entry into query
     pub fn load_history_entry(&mut self) {
-        let entries = self.history.entries();
-        if let Some(entry) = entries.get(entries.len().saturating_sub(1).saturating_sub(self.history_selected)) {
-            self.query = entry.query.clone();
-            self.cursor_pos = self.query.len();
+        if let Some(entry) = self.selected_history_index().and_then(|i| self.history.get(i)) {
+            self.editor.set_text(entry.query.clone());
             self.active_panel = ActivePanel::QueryEditor;
         }
     }
 
     /// Update scroll position to keep cursor visible
     pub fn update_scroll(&mut self, visible_width: usize, visible_height: usize) {
-        // Calculate current line and column
-        let (line, col) = self.get_cursor_line_col();
+        // Calculate current line and on-screen column
+        let (line, col) = self.editor.visual_line_col();
 
         // Horizontal scroll - keep cursor visible with some margin
         let margin = 5;
@@ -484,34 +732,78 @@ impl App {
 
     /// Get cursor line and column
     pub fn get_cursor_line_col(&self) -> (usize, usize) {
-        let mut line = 0;
-        let mut col = 0;
-
-        for (i, ch) in self.query.chars().enumerate() {
-            if i >= self.cursor_pos {
-                break;
-            }
-            if ch == '\n' {
-                line += 1;
-                col = 0;
-            } else {
-                col += 1;
-            }
-        }
-
-        (line, col)
+        self.editor.line_col()
     }
 
     /// Format SQL query with proper indentation and line breaks
     pub fn format_sql(&mut self) {
-        let formatted = format_sql_query(&self.query);
-        self.query = formatted;
-        self.cursor_pos = self.query.len();
+        let formatted = format_sql_query(self.editor.text());
+        self.editor.set_text(formatted);
         self.query_scroll_x = 0;
         self.query_scroll_y = 0;
     }
 }
 
+/// Tables, views and stored procedures of the current database as folders
+pub(crate) async fn build_schema_tree(client: &mut Client<Compat<TcpStream>>) -> Vec<SchemaNode> {
+    // Create root folders
+    let mut tables_folder = SchemaNode::new_folder("Tables");
+    let mut views_folder = SchemaNode::new_folder("Views");
+    let mut procs_folder = SchemaNode::new_folder("Stored Procedures");
+
+    // Load tables
+    if let Ok(tables) = crate::db::SchemaExplorer::get_tables(client, None).await {
+        for table in tables {
+            tables_folder.children.push(SchemaNode {
+                name: format!("{}.{}", table.schema, table.name),
+                node_type: SchemaNodeType::Table,
+                expanded: false,
+                children: Vec::new(),
+                schema: Some(table.schema),
+            });
+        }
+    }
+
+    // Load views
+    if let Ok(views) = crate::db::SchemaExplorer::get_views(client, None).await {
+        for view in views {
+            views_folder.children.push(SchemaNode {
+                name: format!("{}.{}", view.schema, view.name),
+                node_type: SchemaNodeType::View,
+                expanded: false,
+                children: Vec::new(),
+                schema: Some(view.schema),
+            });
+        }
+    }
+
+    // Load procedures
+    if let Ok(procs) = crate::db::SchemaExplorer::get_procedures(client, None).await {
+        for proc in procs {
+            procs_folder.children.push(SchemaNode {
+                name: format!("{}.{}", proc.schema, proc.name),
+                node_type: SchemaNodeType::Procedure,
+                expanded: false,
+                children: Vec::new(),
+                schema: Some(proc.schema),
+            });
+        }
+    }
+
+    vec![tables_folder, views_folder, procs_folder]
+}
+
+/// Flatten an error and its causes into a single message
+pub(crate) fn error_chain(e: &anyhow::Error) -> String {
+    let mut error_msg = e.to_string();
+    let mut source = e.source();
+    while let Some(s) = source {
+        error_msg.push_str(&format!(" | Caused by: {}", s));
+        source = std::error::Error::source(s);
+    }
+    error_msg
+}
+
 /// SQL formatter - formats SQL with proper indentation and line breaks
 fn format_sql_query(sql: &str) -> String {
     let keywords_newline_before = [
@@ -531,21 +823,19 @@ fn format_sql_query(sql: &str) -> String {
     let mut indent_level = 0;
     let mut i = 0;
     let chars: Vec<char> = sql.chars().collect();
-    let sql_upper = sql.to_uppercase();
+    // Keywords are ASCII, so one keyword byte is one char; `i` always counts chars
+    let is_word_char = |c: &char| c.is_alphanumeric() || *c == '_';
+    let keyword_at = |i: usize, keyword: &str| {
+        let end = i + keyword.len();
+        end <= chars.len()
+            && keyword.chars().zip(&chars[i..end]).all(|(k, c)| c.to_ascii_uppercase() == k)
+            && (i == 0 || !is_word_char(&chars[i - 1]))
+            && !chars.get(end).is_some_and(is_word_char)
+    };
 
     while i < chars.len() {
         // Check for keywords that need newline before
-        let mut matched_keyword = None;
-        for keyword in &keywords_newline_before {
-            if sql_upper[i..].starts_with(keyword) {
-                // Make sure it's a word boundary
-                let end = i + keyword.len();
-                if end >= sql_upper.len() || !sql_upper.chars().nth(end).unwrap().is_alphanumeric() {
-                    matched_keyword = Some(*keyword);
-                    break;
-                }
-            }
-        }
+        let matched_keyword = keywords_newline_before.iter().copied().find(|keyword| keyword_at(i, keyword));
 
         if let Some(keyword) = matched_keyword {
             // Add newline before keyword (except at start)
@@ -615,3 +905,23 @@ fn format_sql_query(sql: &str) -> String {
         .trim()
         .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_keeps_arabic_text_intact() {
+        let formatted = format_sql_query("select name from العملاء where الاسم = N'محمد' and city = 'الرياض'");
+        assert_eq!(
+            formatted,
+            "SELECT\n    name\nFROM\n    العملاء\nWHERE الاسم = N'محمد'\n    AND city = 'الرياض'"
+        );
+    }
+
+    #[test]
+    fn format_matches_whole_words_only() {
+        let formatted = format_sql_query("select color, selected_flag from orders order by color");
+        assert_eq!(formatted, "SELECT\n    color,\n    selected_flag\nFROM\n    orders\nORDER BY color");
+    }
+}
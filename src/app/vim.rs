@@ -0,0 +1,1162 @@
+//! Optional vim keymap for the query editor
+
+use crate::app::{grapheme_class, App, EditorBuffer, ExportSource, FileBrowserMode, InputMode, SelectionMode};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::ops::Range;
+use std::path::PathBuf;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Operator waiting for a motion or text object
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+    Indent,
+    Outdent,
+}
+
+impl Operator {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'd' => Some(Operator::Delete),
+            'c' => Some(Operator::Change),
+            'y' => Some(Operator::Yank),
+            '>' => Some(Operator::Indent),
+            '<' => Some(Operator::Outdent),
+            _ => None,
+        }
+    }
+
+    fn key(&self) -> char {
+        match self {
+            Operator::Delete => 'd',
+            Operator::Change => 'c',
+            Operator::Yank => 'y',
+            Operator::Indent => '>',
+            Operator::Outdent => '<',
+        }
+    }
+}
+
+/// Prefix key that needs one more key to complete
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Pending {
+    /// `g`, as in `gg`
+    G,
+    /// `f`, `F`, `t` or `T`
+    Find(char),
+    /// `r`
+    Replace,
+    /// `i` (inner) or `a` (around) text object
+    Object(bool),
+}
+
+/// How a motion's target maps to the range an operator acts on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MotionKind {
+    Exclusive,
+    Inclusive,
+    Linewise,
+}
+
+/// Keys typed so far in a normal or visual mode command, plus the yank register
+#[derive(Clone, Debug, Default)]
+pub struct VimState {
+    count: Option<usize>,
+    operator: Option<Operator>,
+    /// Count typed before the operator (the 2 in `2d3w`)
+    op_count: Option<usize>,
+    pending: Option<Pending>,
+    register: String,
+    /// The register holds whole lines
+    linewise: bool,
+    /// Last `f`/`F`/`t`/`T` search, repeated by `;` and `,`
+    last_find: Option<(char, char)>,
+}
+
+impl VimState {
+    /// Keys of the unfinished command, for the status bar
+    pub fn pending_keys(&self) -> String {
+        let mut keys = String::new();
+        if let Some(n) = self.op_count {
+            keys.push_str(&n.to_string());
+        }
+        if let Some(op) = self.operator {
+            keys.push(op.key());
+        }
+        if let Some(n) = self.count {
+            keys.push_str(&n.to_string());
+        }
+        match self.pending {
+            Some(Pending::G) => keys.push('g'),
+            Some(Pending::Find(c)) => keys.push(c),
+            Some(Pending::Replace) => keys.push('r'),
+            Some(Pending::Object(inner)) => keys.push(if inner { 'i' } else { 'a' }),
+            None => {}
+        }
+        keys
+    }
+
+    /// Drop the unfinished command, keeping the register and last search
+    fn reset(&mut self) {
+        self.count = None;
+        self.operator = None;
+        self.op_count = None;
+        self.pending = None;
+    }
+
+    /// Effective count: `2d3w` deletes six words
+    fn take_count(&mut self) -> usize {
+        let count = self.count.take().unwrap_or(1) * self.op_count.take().unwrap_or(1);
+        count.max(1)
+    }
+}
+
+impl App {
+    /// Turn the vim keymap on or off
+    pub fn set_vim_enabled(&mut self, enabled: bool) {
+        self.vim_enabled = enabled;
+        self.vim.reset();
+        self.editor.clear_selection();
+        self.editor.set_selection_mode(SelectionMode::Exclusive);
+        self.editor.end_undo_group();
+        self.input_mode = if enabled { InputMode::Normal } else { InputMode::Insert };
+        self.message = Some(format!("Vim keys {}", if enabled { "on" } else { "off" }));
+    }
+
+    /// Query editor keys when the vim keymap is on; false falls through to the default keys
+    pub(crate) fn handle_vim_key(&mut self, key: KeyEvent) -> bool {
+        // Ctrl/Alt chords keep their usual meaning, except Ctrl+R (redo) in normal mode
+        let ctrl = key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let redo = key.code == KeyCode::Char('r') && key.modifiers == KeyModifiers::CONTROL;
+        match self.input_mode {
+            InputMode::Insert if ctrl => return false,
+            InputMode::Normal if ctrl && !redo => return false,
+            InputMode::Visual | InputMode::VisualLine if ctrl => return true,
+            _ => {}
+        }
+
+        match self.input_mode {
+            InputMode::Insert => self.vim_insert(key),
+            InputMode::Normal => {
+                self.vim_normal(key);
+                true
+            }
+            InputMode::Visual | InputMode::VisualLine => {
+                self.vim_visual(key);
+                true
+            }
+            InputMode::Command => {
+                self.vim_command_line(key);
+                true
+            }
+        }
+    }
+
+    fn vim_insert(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => {
+                self.editor.end_undo_group();
+                let line_start = self.editor.line_start(self.editor.cursor());
+                if self.editor.cursor() > line_start {
+                    self.editor.set_cursor(self.editor.prev_boundary(self.editor.cursor()));
+                }
+                self.input_mode = InputMode::Normal;
+                true
+            }
+            // Enter only runs the query from normal mode
+            KeyCode::Enter if key.modifiers.is_empty() => {
+                self.editor.insert_newline();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Enter insert mode; everything typed until Esc undoes as one step
+    pub(crate) fn vim_start_insert(&mut self) {
+        self.editor.begin_undo_group();
+        self.input_mode = InputMode::Insert;
+    }
+
+    fn vim_normal(&mut self, key: KeyEvent) {
+        // The only Ctrl chord that reaches here is Ctrl+R
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let Some(c) = vim_key_char(key) else {
+            match key.code {
+                KeyCode::Esc => self.vim.reset(),
+                KeyCode::Enter if self.vim.operator.is_none() => {
+                    self.vim.reset();
+                    self.start_query();
+                }
+                KeyCode::F(5) => self.start_query(),
+                _ => {}
+            }
+            return;
+        };
+
+        if ctrl {
+            let count = self.vim.take_count();
+            self.vim.reset();
+            if !(0..count).fold(false, |done, _| self.editor.redo() || done) {
+                self.message = Some("Nothing to redo".to_string());
+            }
+            self.vim_clamp_cursor();
+            return;
+        }
+
+        if let Some(pending) = self.vim.pending.take() {
+            self.vim_pending(pending, c);
+            self.vim_clamp_cursor();
+            return;
+        }
+
+        // Counts; a leading 0 is the line-start motion
+        if let Some(digit) = c.to_digit(10).filter(|&d| d > 0 || self.vim.count.is_some()) {
+            let count = self.vim.count.unwrap_or(0).saturating_mul(10).saturating_add(digit as usize);
+            self.vim.count = Some(count.min(99_999));
+            return;
+        }
+
+        if let Some(op) = self.vim.operator {
+            match c {
+                // dd, cc, yy, >>, <<
+                c if c == op.key() => {
+                    let count = self.vim.take_count();
+                    let line = self.editor.line_col().0;
+                    let last = (line + count - 1).min(self.editor.line_count() - 1);
+                    let start = self.editor.position_at(line, 0);
+                    let end = self.editor.line_end(self.editor.position_at(last, 0));
+                    self.vim.reset();
+                    self.vim_apply(op, start..end, true);
+                }
+                'i' | 'a' => self.vim.pending = Some(Pending::Object(c == 'i')),
+                'g' | 'f' | 'F' | 't' | 'T' => self.vim_prefix(c),
+                _ => {
+                    let counted = self.vim.count.is_some() || self.vim.op_count.is_some();
+                    let count = self.vim.take_count();
+                    // `G` reads the count itself: `d3G` deletes through line 3
+                    if counted {
+                        self.vim.count = Some(count);
+                    }
+                    self.vim.operator = None;
+                    if let Some((range, linewise)) = self.vim_operator_range(op, c, count) {
+                        self.vim_apply(op, range, linewise);
+                    } else {
+                        self.vim.reset();
+                    }
+                }
+            }
+            self.vim_clamp_cursor();
+            return;
+        }
+
+        if let Some(op) = Operator::from_char(c) {
+            self.vim.operator = Some(op);
+            self.vim.op_count = self.vim.count.take();
+            return;
+        }
+
+        let count = self.vim.count.unwrap_or(1);
+        match c {
+            'g' | 'f' | 'F' | 't' | 'T' | 'r' => {
+                self.vim_prefix(c);
+                return;
+            }
+            'i' => self.vim_start_insert(),
+            'a' => {
+                let pos = self.editor.cursor();
+                if pos < self.editor.line_end(pos) {
+                    self.editor.set_cursor(self.editor.next_boundary(pos));
+                }
+                self.vim_start_insert();
+            }
+            'I' => {
+                self.editor.set_cursor(first_non_blank(&self.editor, self.editor.cursor()));
+                self.vim_start_insert();
+            }
+            'A' => {
+                self.editor.set_cursor(self.editor.line_end(self.editor.cursor()));
+                self.vim_start_insert();
+            }
+            'o' => {
+                self.editor.set_cursor(self.editor.line_end(self.editor.cursor()));
+                self.vim_start_insert();
+                self.editor.insert_newline();
+            }
+            'O' => {
+                let start = self.editor.line_start(self.editor.cursor());
+                let indent = leading_blanks(&self.editor.text()[start..]).to_string();
+                self.vim_start_insert();
+                self.editor.replace_range(start..start, &format!("{}\n", indent));
+                self.editor.set_cursor(start + indent.len());
+            }
+            'x' | 'X' | 's' | 'D' | 'C' | 'Y' | 'S' => {
+                let (op, motion) = match c {
+                    'x' => (Operator::Delete, 'l'),
+                    'X' => (Operator::Delete, 'h'),
+                    's' => (Operator::Change, 'l'),
+                    'D' => (Operator::Delete, '$'),
+                    'C' => (Operator::Change, '$'),
+                    'Y' => (Operator::Yank, 'y'),
+                    _ => (Operator::Change, 'c'),
+                };
+                self.vim.operator = Some(op);
+                self.vim_normal(KeyEvent::new(KeyCode::Char(motion), KeyModifiers::NONE));
+                return;
+            }
+            'p' | 'P' => self.vim_put(c == 'p', count),
+            'u' => {
+                if !(0..count).fold(false, |done, _| self.editor.undo() || done) {
+                    self.message = Some("Nothing to undo".to_string());
+                }
+            }
+            'J' => self.vim_join(self.editor.cursor(), count.max(2) - 1),
+            'v' | 'V' => {
+                let mode = if c == 'v' { InputMode::Visual } else { InputMode::VisualLine };
+                self.editor.set_anchor(Some(self.editor.cursor()));
+                self.vim_set_visual(mode);
+            }
+            ':' => {
+                self.command_buffer.clear();
+                self.input_mode = InputMode::Command;
+            }
+            _ => {
+                if let Some((target, _)) = self.vim_motion(c, count) {
+                    self.vim_move(c, target, count, false);
+                }
+            }
+        }
+        self.vim.reset();
+        self.vim_clamp_cursor();
+    }
+
+    fn vim_visual(&mut self, key: KeyEvent) {
+        let Some(c) = vim_key_char(key) else {
+            match key.code {
+                KeyCode::Esc => self.vim_leave_visual(),
+                KeyCode::F(5) => self.start_query(),
+                _ => {}
+            }
+            return;
+        };
+        if let Some(pending) = self.vim.pending.take() {
+            match pending {
+                Pending::Object(inner) => {
+                    if let Some(range) = self.vim_text_object(inner, c) {
+                        self.editor.set_anchor(Some(range.start));
+                        self.editor.set_cursor(self.editor.prev_boundary(range.end).max(range.start));
+                    }
+                    self.vim.reset();
+                }
+                other => self.vim_pending(other, c),
+            }
+            return;
+        }
+
+        if let Some(digit) = c.to_digit(10).filter(|&d| d > 0 || self.vim.count.is_some()) {
+            let count = self.vim.count.unwrap_or(0).saturating_mul(10).saturating_add(digit as usize);
+            self.vim.count = Some(count.min(99_999));
+            return;
+        }
+
+        let linewise = self.input_mode == InputMode::VisualLine;
+        let count = self.vim.count.unwrap_or(1);
+        match c {
+            'g' | 'f' | 'F' | 't' | 'T' => {
+                self.vim_prefix(c);
+                return;
+            }
+            'i' | 'a' => {
+                self.vim.pending = Some(Pending::Object(c == 'i'));
+                return;
+            }
+            'v' | 'V' => {
+                let mode = if c == 'v' { InputMode::Visual } else { InputMode::VisualLine };
+                if mode == self.input_mode {
+                    self.vim_leave_visual();
+                } else {
+                    self.vim_set_visual(mode);
+                }
+            }
+            'o' => {
+                if let Some(anchor) = self.editor.anchor() {
+                    let cursor = self.editor.cursor();
+                    self.editor.set_cursor(anchor);
+                    self.editor.set_anchor(Some(cursor));
+                }
+            }
+            'd' | 'x' | 'c' | 's' | 'y' | '>' | '<' | 'D' | 'X' | 'Y' | 'C' | 'S' | 'R' => {
+                let op = match c {
+                    'c' | 's' | 'C' | 'S' | 'R' => Operator::Change,
+                    'y' | 'Y' => Operator::Yank,
+                    '>' => Operator::Indent,
+                    '<' => Operator::Outdent,
+                    _ => Operator::Delete,
+                };
+                // Upper-case forms act on whole lines
+                let whole_lines = linewise || c.is_ascii_uppercase();
+                if let Some(range) = self.editor.selection() {
+                    let range = if whole_lines {
+                        self.editor.line_start(range.start)..self.editor.line_end(range.end)
+                    } else {
+                        range
+                    };
+                    self.vim_end_visual();
+                    self.vim_apply(op, range, whole_lines);
+                }
+            }
+            'p' | 'P' => {
+                if let Some(range) = self.editor.selection() {
+                    let text = self.vim.register.repeat(count);
+                    self.vim_end_visual();
+                    self.editor.replace_range(range.clone(), &text);
+                    self.editor.set_cursor(self.editor.prev_boundary(range.start + text.len()).max(range.start));
+                }
+            }
+            'u' | 'U' | '~' => {
+                if let Some(range) = self.editor.selection() {
+                    let selected = &self.editor.text()[range.clone()];
+                    let changed: String = match c {
+                        'u' => selected.to_lowercase(),
+                        'U' => selected.to_uppercase(),
+                        _ => selected
+                            .chars()
+                            .map(|ch| {
+                                if ch.is_uppercase() {
+                                    ch.to_lowercase().to_string()
+                                } else {
+                                    ch.to_uppercase().to_string()
+                                }
+                            })
+                            .collect(),
+                    };
+                    self.vim_end_visual();
+                    self.editor.replace_range(range.clone(), &changed);
+                    self.editor.set_cursor(range.start);
+                }
+            }
+            'J' => {
+                if let Some(range) = self.editor.selection() {
+                    let lines = self.editor.text()[range.clone()].trim_end_matches('\n').matches('\n').count();
+                    self.vim_end_visual();
+                    self.vim_join(range.start, lines.max(1));
+                }
+            }
+            ':' => {
+                self.vim_leave_visual();
+                self.command_buffer.clear();
+                self.input_mode = InputMode::Command;
+            }
+            _ => {
+                if let Some((target, _)) = self.vim_motion(c, count) {
+                    self.vim_move(c, target, count, true);
+                }
+            }
+        }
+        self.vim.reset();
+        if self.input_mode == InputMode::Normal {
+            self.vim_clamp_cursor();
+        }
+    }
+
+    fn vim_set_visual(&mut self, mode: InputMode) {
+        self.input_mode = mode;
+        self.editor.set_selection_mode(if mode == InputMode::VisualLine {
+            SelectionMode::Line
+        } else {
+            SelectionMode::Inclusive
+        });
+    }
+
+    /// Drop the visual selection before an operator runs on it
+    fn vim_end_visual(&mut self) {
+        self.editor.clear_selection();
+        self.editor.set_selection_mode(SelectionMode::Exclusive);
+        self.input_mode = InputMode::Normal;
+    }
+
+    fn vim_leave_visual(&mut self) {
+        self.vim_end_visual();
+        self.vim.reset();
+        self.vim_clamp_cursor();
+    }
+
+    /// Remember a prefix key that needs another key
+    fn vim_prefix(&mut self, c: char) {
+        self.vim.pending = Some(match c {
+            'g' => Pending::G,
+            'r' => Pending::Replace,
+            other => Pending::Find(other),
+        });
+    }
+
+    /// Second key of `gg`, `f{char}`, `r{char}` or a text object
+    fn vim_pending(&mut self, pending: Pending, c: char) {
+        let visual = matches!(self.input_mode, InputMode::Visual | InputMode::VisualLine);
+        match (pending, self.vim.operator) {
+            (Pending::Replace, _) => {
+                let count = self.vim.take_count();
+                self.vim_replace_chars(c, count);
+                self.vim.reset();
+            }
+            (Pending::Object(inner), Some(op)) => {
+                let range = self.vim_text_object(inner, c);
+                self.vim.reset();
+                if let Some(range) = range {
+                    self.vim_apply(op, range, false);
+                }
+            }
+            (Pending::Object(_), None) => self.vim.reset(),
+            (Pending::G, _) if c != 'g' => self.vim.reset(),
+            (Pending::G, _) | (Pending::Find(_), _) => {
+                // `{count}gg` goes to that line; `f{char}` is replayed through `;`
+                let count = self.vim.take_count();
+                let target = match pending {
+                    Pending::Find(kind) => {
+                        self.vim.last_find = Some((kind, c));
+                        self.vim_motion(';', count)
+                    }
+                    _ => self.vim_goto_line(count),
+                };
+                match (target, self.vim.operator.take()) {
+                    (Some((target, kind)), Some(op)) => {
+                        let (range, linewise) = self.vim_motion_range(target, kind);
+                        self.vim_apply(op, range, linewise);
+                    }
+                    (Some((target, _)), None) => self.vim_move('g', target, count, visual),
+                    (None, _) => {}
+                }
+                self.vim.reset();
+            }
+        }
+    }
+
+    /// Move the cursor to a motion target; j/k keep the preferred column
+    fn vim_move(&mut self, motion: char, target: usize, count: usize, visual: bool) {
+        match motion {
+            'j' | 'k' => {
+                for _ in 0..count {
+                    let before = self.editor.cursor();
+                    if motion == 'j' {
+                        self.editor.move_down(visual);
+                    } else {
+                        self.editor.move_up(visual);
+                    }
+                    if self.editor.cursor() == before {
+                        break;
+                    }
+                }
+            }
+            _ => self.editor.set_cursor(target),
+        }
+    }
+
+    /// Where a motion key goes from the cursor
+    fn vim_motion(&mut self, c: char, count: usize) -> Option<(usize, MotionKind)> {
+        let ed = &self.editor;
+        let pos = ed.cursor();
+        let line_start = ed.line_start(pos);
+        let line_end = ed.line_end(pos);
+
+        let target = match c {
+            'h' => {
+                let mut target = pos;
+                for _ in 0..count {
+                    if target <= line_start {
+                        break;
+                    }
+                    target = ed.prev_boundary(target);
+                }
+                (target, MotionKind::Exclusive)
+            }
+            'l' | ' ' => {
+                let mut target = pos;
+                for _ in 0..count {
+                    if target >= line_end {
+                        break;
+                    }
+                    target = ed.next_boundary(target);
+                }
+                (target, MotionKind::Exclusive)
+            }
+            'j' | 'k' => {
+                let (line, col) = ed.line_col();
+                let line = if c == 'j' {
+                    (line + count).min(ed.line_count() - 1)
+                } else {
+                    line.saturating_sub(count)
+                };
+                (ed.position_at(line, col), MotionKind::Linewise)
+            }
+            'w' | 'W' => {
+                let big = c == 'W';
+                let target = (0..count).fold(pos, |p, _| ed.word_forward(p, big));
+                (target, MotionKind::Exclusive)
+            }
+            'b' | 'B' => {
+                let big = c == 'B';
+                let target = (0..count).fold(pos, |p, _| ed.word_backward(p, big));
+                (target, MotionKind::Exclusive)
+            }
+            'e' | 'E' => {
+                let big = c == 'E';
+                let target = (0..count).fold(pos, |p, _| word_end(ed, p, big));
+                (target, MotionKind::Inclusive)
+            }
+            '0' => (line_start, MotionKind::Exclusive),
+            '^' => (first_non_blank(ed, pos), MotionKind::Exclusive),
+            '$' => {
+                let line = (ed.line_col().0 + count - 1).min(ed.line_count() - 1);
+                (ed.line_end(ed.position_at(line, 0)), MotionKind::Exclusive)
+            }
+            'G' => {
+                let line = self.vim.count.map(|n| n.max(1)).unwrap_or(ed.line_count());
+                return self.vim_goto_line(line);
+            }
+            '%' => (matching_bracket(ed, pos)?, MotionKind::Inclusive),
+            ';' | ',' => {
+                let (kind, target) = self.vim.last_find?;
+                let kind = if c == ',' { reverse_find(kind) } else { kind };
+                let found = find_in_line(ed, pos, kind, target, count)?;
+                let motion = if matches!(kind, 'f' | 't') { MotionKind::Inclusive } else { MotionKind::Exclusive };
+                (found, motion)
+            }
+            _ => return None,
+        };
+        Some(target)
+    }
+
+    /// First non-blank of a 1-based line (`gg`, `G`)
+    fn vim_goto_line(&self, line: usize) -> Option<(usize, MotionKind)> {
+        let line = line.clamp(1, self.editor.line_count()) - 1;
+        let start = self.editor.position_at(line, 0);
+        Some((first_non_blank(&self.editor, start), MotionKind::Linewise))
+    }
+
+    /// Range for an operator followed by a motion key
+    fn vim_operator_range(&mut self, op: Operator, c: char, count: usize) -> Option<(Range<usize>, bool)> {
+        let pos = self.editor.cursor();
+        // `cw` changes to the end of the word, like `ce`
+        let on_word = self
+            .editor
+            .text()[pos..]
+            .graphemes(true)
+            .next()
+            .is_some_and(|g| grapheme_class(g, false) != 0);
+        let motion = match c {
+            'w' if op == Operator::Change && on_word => 'e',
+            'W' if op == Operator::Change && on_word => 'E',
+            other => other,
+        };
+        let (mut target, kind) = self.vim_motion(motion, count)?;
+
+        // A word motion does not carry an operator past the end of the line
+        if matches!(motion, 'w' | 'W') && target > self.editor.line_end(pos) {
+            target = self.editor.line_end(pos);
+        }
+        Some(self.vim_motion_range(target, kind))
+    }
+
+    /// Byte range between the cursor and a motion target
+    fn vim_motion_range(&self, target: usize, kind: MotionKind) -> (Range<usize>, bool) {
+        let ed = &self.editor;
+        let pos = ed.cursor();
+        let (lo, hi) = (pos.min(target), pos.max(target));
+        match kind {
+            MotionKind::Exclusive => (lo..hi, false),
+            MotionKind::Inclusive => (lo..ed.next_boundary(hi).min(ed.line_end(hi)).max(hi), false),
+            MotionKind::Linewise => (ed.line_start(lo)..ed.line_end(hi), true),
+        }
+    }
+
+    /// Run an operator over a range; linewise ranges cover whole lines without the final newline
+    fn vim_apply(&mut self, op: Operator, range: Range<usize>, linewise: bool) {
+        let text = self.editor.text()[range.clone()].to_string();
+        match op {
+            Operator::Yank => {
+                self.vim_store(text, linewise);
+                self.editor.set_cursor(range.start);
+            }
+            Operator::Delete => {
+                self.vim_store(text, linewise);
+                let len = self.editor.text().len();
+                let delete = match linewise {
+                    true if range.end < len => range.start..range.end + 1,
+                    true if range.start > 0 => range.start - 1..range.end,
+                    _ => range.clone(),
+                };
+                self.editor.replace_range(delete.clone(), "");
+                let pos = delete.start.min(self.editor.text().len());
+                let pos = if linewise { first_non_blank(&self.editor, pos) } else { pos };
+                self.editor.set_cursor(pos);
+            }
+            Operator::Change => {
+                self.vim_store(text, linewise);
+                self.vim_start_insert();
+                // Changing lines keeps the indent of the first one
+                let (range, indent) = if linewise {
+                    let indent = leading_blanks(&self.editor.text()[range.clone()]).to_string();
+                    (range, indent)
+                } else {
+                    (range, String::new())
+                };
+                self.editor.replace_range(range.clone(), &indent);
+                self.editor.set_cursor(range.start + indent.len());
+            }
+            Operator::Indent | Operator::Outdent => {
+                self.editor.set_anchor(Some(range.start));
+                self.editor.set_cursor(range.end);
+                if op == Operator::Indent {
+                    self.editor.indent_lines();
+                } else {
+                    self.editor.outdent_lines();
+                }
+                self.editor.clear_selection();
+                self.editor.set_cursor(first_non_blank(&self.editor, range.start));
+            }
+        }
+    }
+
+    fn vim_store(&mut self, text: String, linewise: bool) {
+        if text.is_empty() && !linewise {
+            return;
+        }
+        self.vim.register = text;
+        self.vim.linewise = linewise;
+    }
+
+    /// `p` / `P`: put the register after or before the cursor
+    fn vim_put(&mut self, after: bool, count: usize) {
+        if self.vim.register.is_empty() {
+            return;
+        }
+        let pos = self.editor.cursor();
+
+        if self.vim.linewise {
+            let block = vec![self.vim.register.as_str(); count].join("\n");
+            let start = if after {
+                let end = self.editor.line_end(pos);
+                self.editor.replace_range(end..end, &format!("\n{}", block));
+                end + 1
+            } else {
+                let start = self.editor.line_start(pos);
+                self.editor.replace_range(start..start, &format!("{}\n", block));
+                start
+            };
+            self.editor.set_cursor(first_non_blank(&self.editor, start));
+        } else {
+            let text = self.vim.register.repeat(count);
+            let at = if after && pos < self.editor.line_end(pos) {
+                self.editor.next_boundary(pos)
+            } else {
+                pos
+            };
+            self.editor.replace_range(at..at, &text);
+            self.editor.set_cursor(self.editor.prev_boundary(at + text.len()).max(at));
+        }
+    }
+
+    /// `r{char}`: replace characters under the cursor
+    fn vim_replace_chars(&mut self, c: char, count: usize) {
+        let pos = self.editor.cursor();
+        let line_end = self.editor.line_end(pos);
+        let graphemes = self.editor.text()[pos..line_end].graphemes(true).count();
+        if graphemes < count {
+            return;
+        }
+        let end = (0..count).fold(pos, |p, _| self.editor.next_boundary(p));
+        let replacement = c.to_string().repeat(count);
+        self.editor.replace_range(pos..end, &replacement);
+        self.editor.set_cursor(self.editor.prev_boundary(pos + replacement.len()));
+    }
+
+    /// `J`: join lines, putting one space between them
+    fn vim_join(&mut self, pos: usize, joins: usize) {
+        self.editor.set_cursor(pos);
+        self.editor.begin_undo_group();
+        for _ in 0..joins {
+            let end = self.editor.line_end(self.editor.cursor());
+            if end >= self.editor.text().len() {
+                break;
+            }
+            let next = &self.editor.text()[end + 1..];
+            let blanks = next.len() - next.trim_start_matches([' ', '\t']).len();
+            let rest = &next[blanks..];
+            let before = &self.editor.text()[..end];
+            let separator = if rest.is_empty() || rest.starts_with([')', '\n']) || before.ends_with([' ', '\t']) {
+                ""
+            } else {
+                " "
+            };
+            self.editor.replace_range(end..end + 1 + blanks, separator);
+            self.editor.set_cursor(end);
+        }
+        self.editor.end_undo_group();
+    }
+
+    /// Range of an `iw`/`aw`, quote or bracket text object around the cursor
+    fn vim_text_object(&self, inner: bool, c: char) -> Option<Range<usize>> {
+        let ed = &self.editor;
+        match c {
+            'w' | 'W' => Some(word_object(ed, ed.cursor(), inner, c == 'W')),
+            '"' | '\'' | '`' => quote_object(ed, ed.cursor(), c, inner),
+            '(' | ')' | 'b' => bracket_object(ed, ed.cursor(), '(', ')', inner),
+            '[' | ']' => bracket_object(ed, ed.cursor(), '[', ']', inner),
+            '{' | '}' | 'B' => bracket_object(ed, ed.cursor(), '{', '}', inner),
+            '<' | '>' => bracket_object(ed, ed.cursor(), '<', '>', inner),
+            _ => None,
+        }
+    }
+
+    /// Normal mode keeps the cursor on a character, not past the end of the line
+    fn vim_clamp_cursor(&mut self) {
+        if self.input_mode != InputMode::Normal {
+            return;
+        }
+        let pos = self.editor.cursor();
+        let line_start = self.editor.line_start(pos);
+        if pos > line_start && pos == self.editor.line_end(pos) {
+            self.editor.set_cursor(self.editor.prev_boundary(pos));
+        }
+    }
+
+    // ----- Command line -----
+
+    fn vim_command_line(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.command_buffer.clear();
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Enter => {
+                let command = std::mem::take(&mut self.command_buffer);
+                self.input_mode = InputMode::Normal;
+                self.run_vim_command(&command);
+                self.vim_clamp_cursor();
+            }
+            KeyCode::Backspace if self.command_buffer.is_empty() => self.input_mode = InputMode::Normal,
+            KeyCode::Backspace => {
+                self.command_buffer.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.command_buffer.push(c);
+            }
+            _ => {}
+        }
+    }
+
+    /// Run a `:` command
+    pub fn run_vim_command(&mut self, command: &str) {
+        let command = command.trim().trim_start_matches(':').trim();
+        let (name, arg) = command
+            .split_once(char::is_whitespace)
+            .map(|(name, arg)| (name, arg.trim()))
+            .unwrap_or((command, ""));
+
+        match name {
+            "" => {}
+            line if line.parse::<usize>().is_ok() => {
+                if let Some((target, _)) = self.vim_goto_line(line.parse().unwrap_or(1)) {
+                    self.editor.set_cursor(target);
+                }
+            }
+            "w" | "write" => {
+                self.save_query_file(path_arg(arg));
+            }
+            "wq" | "x" => {
+                if self.save_query_file(path_arg(arg)) {
+                    self.should_quit = true;
+                }
+            }
+            "sav" | "saveas" => match path_arg(arg) {
+                Some(path) => {
+                    self.save_query_file(Some(path));
+                }
+                None => self.open_file_browser(FileBrowserMode::SaveAs),
+            },
+            "q" | "quit" if self.is_query_modified() => {
+                self.error = Some("No write since last change (add ! to override)".to_string());
+            }
+            "q" | "q!" | "quit" => self.should_quit = true,
+            "e" | "edit" | "e!" => match path_arg(arg) {
+                Some(path) => self.open_query_file(path),
+                None if self.query_file.is_some() => self.reload_query_file(),
+                None => self.open_file_browser(FileBrowserMode::Open),
+            },
+            "Ex" | "Explore" | "browse" => self.open_file_browser(FileBrowserMode::Open),
+            "run" => self.start_query(),
+            "export" => {
+                // `:export query [format]` re-runs the query and streams it to the file
+                let (source, format) = match arg.split_once(' ').unwrap_or((arg, "")) {
+                    ("query", format) => (ExportSource::Query, format.trim()),
+                    _ => (ExportSource::Grid, arg),
+                };
+                match crate::export::ExportFormat::parse(format) {
+                    _ if format.is_empty() => self.open_export_dialog(source, None),
+                    Some(format) => self.open_export_dialog(source, Some(format)),
+                    None => {
+                        self.error = Some(format!(
+                            "Unknown export format: {} (csv, tsv, json, jsonl, md, html, sql, parquet, xlsx)",
+                            format
+                        ))
+                    }
+                }
+            }
+            "import" => self.open_import_wizard(Some(arg.to_string()).filter(|a| !a.is_empty()), None),
+            "db" | "use" => {
+                if arg.is_empty() {
+                    self.message = Some(format!("Database: {}", self.db.config.database));
+                } else {
+                    self.switch_database(arg.trim_matches(['[', ']']).to_string());
+                }
+            }
+            "format" => self.format_sql(),
+            "set" => match arg {
+                "novim" => self.set_vim_enabled(false),
+                "vim" => self.set_vim_enabled(true),
+                other => self.error = Some(format!("Unknown option: {}", other)),
+            },
+            _ => self.error = Some(format!("Not an editor command: {}", command)),
+        }
+    }
+}
+
+/// Path argument of `:w`, `:e` and `:saveas`
+fn path_arg(arg: &str) -> Option<PathBuf> {
+    (!arg.is_empty()).then(|| PathBuf::from(arg))
+}
+
+/// The character a key stands for in normal and visual mode (arrows map to hjkl)
+fn vim_key_char(key: KeyEvent) -> Option<char> {
+    match key.code {
+        KeyCode::Char(c) => Some(c),
+        KeyCode::Left | KeyCode::Backspace => Some('h'),
+        KeyCode::Right => Some('l'),
+        KeyCode::Down => Some('j'),
+        KeyCode::Up => Some('k'),
+        KeyCode::Home => Some('0'),
+        KeyCode::End => Some('$'),
+        KeyCode::Delete => Some('x'),
+        _ => None,
+    }
+}
+
+fn leading_blanks(text: &str) -> &str {
+    let line = text.split('\n').next().unwrap_or("");
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// First non-blank character of the line containing `pos`
+fn first_non_blank(ed: &EditorBuffer, pos: usize) -> usize {
+    let start = ed.line_start(pos);
+    start + leading_blanks(&ed.text()[start..]).len()
+}
+
+/// Last grapheme of the next word (`e`)
+fn word_end(ed: &EditorBuffer, pos: usize, big: bool) -> usize {
+    let graphemes: Vec<(usize, &str)> = ed.text()[pos..]
+        .grapheme_indices(true)
+        .map(|(i, g)| (pos + i, g))
+        .collect();
+    let mut i = 1;
+    while i < graphemes.len() && grapheme_class(graphemes[i].1, big) == 0 {
+        i += 1;
+    }
+    let Some(&(_, first)) = graphemes.get(i) else {
+        return pos;
+    };
+    let class = grapheme_class(first, big);
+    while i + 1 < graphemes.len() && grapheme_class(graphemes[i + 1].1, big) == class {
+        i += 1;
+    }
+    graphemes[i].0
+}
+
+fn reverse_find(kind: char) -> char {
+    match kind {
+        'f' => 'F',
+        'F' => 'f',
+        't' => 'T',
+        _ => 't',
+    }
+}
+
+/// Target of `f`/`F`/`t`/`T` on the cursor line
+fn find_in_line(ed: &EditorBuffer, pos: usize, kind: char, target: char, count: usize) -> Option<usize> {
+    let start = ed.line_start(pos);
+    let end = ed.line_end(pos);
+    let text = ed.text();
+    let matches = |g: &str| g.starts_with(target);
+
+    match kind {
+        'f' | 't' => {
+            // `t` skips an adjacent match so `;` can move on
+            let from = if kind == 't' { ed.next_boundary(pos).min(end) } else { pos };
+            let found = text[from..end]
+                .grapheme_indices(true)
+                .map(|(i, g)| (from + i, g))
+                .filter(|&(i, g)| i > pos && matches(g))
+                .nth(count - 1)?
+                .0;
+            Some(if kind == 't' { ed.prev_boundary(found) } else { found })
+        }
+        _ => {
+            let to = if kind == 'T' { ed.prev_boundary(pos).max(start) } else { pos };
+            let found = text[start..to]
+                .grapheme_indices(true)
+                .rev()
+                .map(|(i, g)| (start + i, g))
+                .filter(|&(_, g)| matches(g))
+                .nth(count - 1)?;
+            Some(if kind == 'T' { found.0 + found.1.len() } else { found.0 })
+        }
+    }
+}
+
+/// `%`: the bracket matching the one at or after the cursor on its line
+fn matching_bracket(ed: &EditorBuffer, pos: usize) -> Option<usize> {
+    let text = ed.text();
+    let end = ed.line_end(pos);
+    let (at, bracket) = text[pos..end].char_indices().find(|(_, c)| "()[]{}".contains(*c))?;
+    let at = pos + at;
+    let (open, close, forward) = match bracket {
+        '(' => ('(', ')', true),
+        '[' => ('[', ']', true),
+        '{' => ('{', '}', true),
+        ')' => ('(', ')', false),
+        ']' => ('[', ']', false),
+        _ => ('{', '}', false),
+    };
+
+    let mut depth = 0usize;
+    if forward {
+        for (i, c) in text[at..].char_indices() {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(at + i);
+                }
+            }
+        }
+    } else {
+        for (i, c) in text[..=at].char_indices().rev() {
+            if c == close {
+                depth += 1;
+            } else if c == open {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// `iw` / `aw`: the run of same-class characters under the cursor
+fn word_object(ed: &EditorBuffer, pos: usize, inner: bool, big: bool) -> Range<usize> {
+    let start = ed.line_start(pos);
+    let end = ed.line_end(pos);
+    let graphemes: Vec<(usize, &str)> = ed.text()[start..end]
+        .grapheme_indices(true)
+        .map(|(i, g)| (start + i, g))
+        .collect();
+    let Some(index) = graphemes.iter().position(|&(i, _)| i >= pos) else {
+        return pos..pos;
+    };
+
+    let class = |i: usize| grapheme_class(graphemes[i].1, big);
+    let run = |from: usize| {
+        let (mut lo, mut hi) = (from, from);
+        while lo > 0 && class(lo - 1) == class(from) {
+            lo -= 1;
+        }
+        while hi + 1 < graphemes.len() && class(hi + 1) == class(from) {
+            hi += 1;
+        }
+        (lo, hi)
+    };
+    let byte_end = |i: usize| graphemes[i].0 + graphemes[i].1.len();
+
+    let (lo, hi) = run(index);
+    if inner {
+        return graphemes[lo].0..byte_end(hi);
+    }
+
+    if class(index) == 0 {
+        // On blanks: the blanks plus the following word
+        if hi + 1 < graphemes.len() {
+            let (_, word_hi) = run(hi + 1);
+            return graphemes[lo].0..byte_end(word_hi);
+        }
+        return graphemes[lo].0..byte_end(hi);
+    }
+
+    // On a word: the word plus trailing blanks, or leading blanks at the end of a line
+    if hi + 1 < graphemes.len() && class(hi + 1) == 0 {
+        let (_, blank_hi) = run(hi + 1);
+        graphemes[lo].0..byte_end(blank_hi)
+    } else if lo > 0 && class(lo - 1) == 0 {
+        let (blank_lo, _) = run(lo - 1);
+        graphemes[blank_lo].0..byte_end(hi)
+    } else {
+        graphemes[lo].0..byte_end(hi)
+    }
+}
+
+/// `i"` / `a"`: the quoted string around or after the cursor on its line
+fn quote_object(ed: &EditorBuffer, pos: usize, quote: char, inner: bool) -> Option<Range<usize>> {
+    let start = ed.line_start(pos);
+    let end = ed.line_end(pos);
+    let quotes: Vec<usize> = ed.text()[start..end]
+        .char_indices()
+        .filter(|&(_, c)| c == quote)
+        .map(|(i, _)| start + i)
+        .collect();
+
+    let (open, close) = quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|&(_, close)| pos <= close)?;
+    Some(if inner { open + 1..close } else { open..close + 1 })
+}
+
+/// `i(` / `a(`: the innermost bracket pair around the cursor
+fn bracket_object(ed: &EditorBuffer, pos: usize, open: char, close: char, inner: bool) -> Option<Range<usize>> {
+    let text = ed.text();
+    // On an opening bracket, its own pair; otherwise the nearest unmatched one before the cursor
+    let start = if text[pos..].starts_with(open) {
+        pos
+    } else {
+        let mut depth = 0usize;
+        text[..pos].char_indices().rev().find_map(|(i, c)| {
+            if c == close {
+                depth += 1;
+            } else if c == open {
+                if depth == 0 {
+                    return Some(i);
+                }
+                depth -= 1;
+            }
+            None
+        })?
+    };
+
+    let mut depth = 0usize;
+    let end = text[start..].char_indices().find_map(|(i, c)| {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(start + i);
+            }
+        }
+        None
+    })?;
+    Some(if inner { start + open.len_utf8()..end } else { start..end + close.len_utf8() })
+}
@@ -0,0 +1,196 @@
+//! Command-line arguments: the TUI by default, or a subcommand
+
+use crate::cli::{DiagnoseArgs, ExecArgs, OutputFormat, ReplArgs, ReportFormat, SqlSource};
+use crate::export::ExportFormat;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Exit code of a failed query or an unwritable output
+pub const EXIT_FAILURE: u8 = 1;
+/// Exit code of bad arguments or an unreadable script
+pub const EXIT_USAGE: u8 = 2;
+/// Exit code when the server cannot be reached or the login fails
+pub const EXIT_CONNECTION: u8 = 3;
+
+pub const USAGE: &str = "\
+Usage:
+  alrajhi_sql_tui [FILE.sql]              Open the TUI, optionally with a .sql file
+  alrajhi_sql_tui exec -q SQL | -f FILE   Run SQL without the TUI and print the results
+  alrajhi_sql_tui repl [-d NAME]          Line-mode SQL prompt with text table results
+  alrajhi_sql_tui diagnose [OPTIONS]      Check each step of connecting and print a report
+
+exec options:
+  -q, --query SQL         SQL text to run
+  -f, --file FILE         Script to run, split into batches on GO lines (- reads stdin)
+      --format FORMAT     table (default), csv, tsv, json, jsonl, md, html, sql or parquet
+  -o, --out FILE          Write the results to FILE instead of standard output
+  -d, --database NAME     Database to use instead of DB_DATABASE
+
+repl options:
+  -d, --database NAME     Database to use instead of DB_DATABASE
+  Type \\? at the prompt for its commands.
+
+diagnose options:
+  -d, --database NAME     Database to use instead of DB_DATABASE
+  -n, --samples N         Round trips timed for latency percentiles (default 20)
+  -t, --timeout SECS      Limit for each step (default 10)
+      --format FORMAT     text (default) or json
+  -o, --out FILE          Also save the report to FILE
+
+The connection comes from DB_HOST, DB_PORT, DB_USER, DB_PASSWORD, DB_DATABASE and DB_PROFILE.
+Exit codes: 0 success, 1 query failed, 2 bad arguments, 3 cannot connect (or a diagnose check failed).";
+
+/// What the command line asks for
+#[derive(Clone, Debug)]
+pub enum Command {
+    /// The interactive UI, with an optional .sql file to open
+    Tui(Option<PathBuf>),
+    Exec(ExecArgs),
+    Repl(ReplArgs),
+    Diagnose(DiagnoseArgs),
+    Help,
+}
+
+/// Parse the arguments after the program name
+pub fn parse_args(args: impl IntoIterator<Item = OsString>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let Some(first) = args.next() else {
+        return Ok(Command::Tui(None));
+    };
+    match first.to_str() {
+        Some("exec") => {
+            let rest: Vec<OsString> = args.collect();
+            if rest.iter().any(|a| a == "-h" || a == "--help") {
+                return Ok(Command::Help);
+            }
+            parse_exec(rest.into_iter()).map(Command::Exec)
+        }
+        Some("repl") => parse_repl(args),
+        Some("diagnose") => parse_diagnose(args),
+        Some("-h" | "--help" | "help") => Ok(Command::Help),
+        Some(flag) if flag.starts_with('-') => Err(format!("unknown option {}", flag)),
+        _ => match args.next() {
+            Some(extra) => Err(format!("unexpected argument {}", extra.to_string_lossy())),
+            None => Ok(Command::Tui(Some(PathBuf::from(first)))),
+        },
+    }
+}
+
+fn parse_repl(mut args: impl Iterator<Item = OsString>) -> Result<Command, String> {
+    let mut database = None;
+    while let Some(arg) = args.next() {
+        let arg = arg.to_string_lossy().to_string();
+        match arg.split_once('=') {
+            Some(("--database", value)) => database = Some(value.to_string()),
+            _ => match arg.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                "-d" | "--database" => {
+                    let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+                    database = Some(value.to_string_lossy().to_string());
+                }
+                other => return Err(format!("unknown repl option {}", other)),
+            },
+        }
+    }
+    Ok(Command::Repl(ReplArgs { database }))
+}
+
+fn parse_diagnose(mut args: impl Iterator<Item = OsString>) -> Result<Command, String> {
+    let mut options = DiagnoseArgs::default();
+    while let Some(arg) = args.next() {
+        let arg = arg.to_string_lossy().to_string();
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (arg, None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next().map(|a| a.to_string_lossy().to_string()))
+                .ok_or_else(|| format!("{} needs a value", name))
+        };
+        match name.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-d" | "--database" => options.database = Some(value(&name)?),
+            "-n" | "--samples" => {
+                let text = value(&name)?;
+                options.samples = text
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| format!("{} needs a positive number, not {}", name, text))?;
+            }
+            "-t" | "--timeout" => {
+                let text = value(&name)?;
+                let secs: u64 = text
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| format!("{} needs a positive number of seconds, not {}", name, text))?;
+                options.timeout = Duration::from_secs(secs);
+            }
+            "--format" => {
+                options.format = match value(&name)?.to_lowercase().as_str() {
+                    "text" => ReportFormat::Text,
+                    "json" => ReportFormat::Json,
+                    other => return Err(format!("unknown report format {}; use text or json", other)),
+                };
+            }
+            "-o" | "--out" => options.out = Some(PathBuf::from(value(&name)?)),
+            other => return Err(format!("unknown diagnose option {}", other)),
+        }
+    }
+    Ok(Command::Diagnose(options))
+}
+
+fn parse_exec(mut args: impl Iterator<Item = OsString>) -> Result<ExecArgs, String> {
+    let mut source = None;
+    let mut format = OutputFormat::Table;
+    let mut out = None;
+    let mut database = None;
+
+    while let Some(arg) = args.next() {
+        let arg = arg.into_string().map_err(|a| format!("invalid argument {}", a.to_string_lossy()))?;
+        // --name=value is the same as --name value
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(OsString::from(value))),
+            _ => (arg, None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} needs a value", name))
+        };
+        match name.as_str() {
+            "-q" | "--query" | "-f" | "--file" if source.is_some() => {
+                return Err("give either -q or -f, once".to_string());
+            }
+            "-q" | "--query" => {
+                let text = value(&name)?.into_string().map_err(|_| "the query is not valid UTF-8".to_string())?;
+                source = Some(SqlSource::Text(text));
+            }
+            "-f" | "--file" => source = Some(SqlSource::File(PathBuf::from(value(&name)?))),
+            "--format" => {
+                let text = value(&name)?.to_string_lossy().to_string();
+                format = OutputFormat::parse(&text).ok_or_else(|| format!("unknown format {}", text))?;
+            }
+            "-o" | "--out" => out = Some(PathBuf::from(value(&name)?)),
+            "-d" | "--database" => database = Some(value(&name)?.to_string_lossy().to_string()),
+            other => return Err(format!("unknown exec option {}", other)),
+        }
+    }
+
+    let source = source.ok_or("exec needs -q SQL or -f FILE")?;
+    match format {
+        OutputFormat::Export(ExportFormat::Xlsx) => {
+            return Err("exec cannot write xlsx; use csv or parquet".to_string());
+        }
+        OutputFormat::Export(ExportFormat::Parquet) if out.is_none() => {
+            return Err("parquet output needs --out FILE".to_string());
+        }
+        _ => {}
+    }
+    Ok(ExecArgs { source, format, out, database })
+}
@@ -0,0 +1,456 @@
+//! `diagnose`: step-by-step connection checks with a report to attach to a support ticket
+
+use crate::cli::{EXIT_CONNECTION, EXIT_FAILURE};
+use crate::db::{prelogin, DbConfig, DbConnection, PreloginEncryption};
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+
+/// Checks in the order they run; a failure skips the rest
+const STEPS: [&str; 7] = ["DNS lookup", "TCP connect", "Prelogin", "Login", "Database", "Server version", "Latency"];
+
+/// SQL Server error raised when the login works but its database cannot be opened
+const CANNOT_OPEN_DATABASE: u32 = 4060;
+
+/// SQL Server error for a refused user name or password
+const LOGIN_FAILED: u32 = 18456;
+
+/// How the report is written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+/// Options of the `diagnose` subcommand
+#[derive(Clone, Debug)]
+pub struct DiagnoseArgs {
+    /// Database used instead of DB_DATABASE
+    pub database: Option<String>,
+    /// Round trips timed for the latency percentiles
+    pub samples: usize,
+    /// Limit for each network step
+    pub timeout: Duration,
+    pub format: ReportFormat,
+    /// File the report is also saved to
+    pub out: Option<PathBuf>,
+}
+
+impl Default for DiagnoseArgs {
+    fn default() -> Self {
+        Self { database: None, samples: 20, timeout: Duration::from_secs(10), format: ReportFormat::Text, out: None }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+impl CheckStatus {
+    fn label(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+            CheckStatus::Skip => "SKIP",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    status: CheckStatus,
+    elapsed_ms: Option<f64>,
+    detail: String,
+    /// What to look at when the check failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<&'static str>,
+}
+
+/// Round-trip times of `SELECT 1`, in milliseconds
+#[derive(Clone, Debug, Serialize)]
+struct Latency {
+    samples: usize,
+    failures: usize,
+    min_ms: f64,
+    p50_ms: f64,
+    p90_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+}
+
+/// Everything `diagnose` found; holds no password so it can be shared as is
+#[derive(Clone, Debug, Serialize)]
+struct Report {
+    generated: String,
+    tool_version: &'static str,
+    host: String,
+    port: u16,
+    database: String,
+    user: String,
+    profile: String,
+    encrypt: bool,
+    checks: Vec<Check>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency: Option<Latency>,
+}
+
+impl Report {
+    fn new(config: &DbConfig) -> Self {
+        Self {
+            generated: chrono::Local::now().format("%Y-%m-%d %H:%M:%S %:z").to_string(),
+            tool_version: env!("CARGO_PKG_VERSION"),
+            host: config.host.clone(),
+            port: config.port,
+            database: config.database.clone(),
+            user: config.user.clone(),
+            profile: config.profile.clone(),
+            encrypt: config.encrypt,
+            checks: Vec::new(),
+            latency: None,
+        }
+    }
+
+    fn add(&mut self, name: &'static str, status: CheckStatus, elapsed_ms: Option<f64>, detail: String) {
+        self.checks.push(Check { name, status, elapsed_ms, detail, hint: None });
+    }
+
+    fn pass(&mut self, name: &'static str, elapsed_ms: f64, detail: String) {
+        self.add(name, CheckStatus::Pass, Some(elapsed_ms), detail);
+    }
+
+    /// Record a failed check and skip the ones after it
+    fn fail(&mut self, name: &'static str, elapsed_ms: f64, error: &anyhow::Error, hint: &'static str) {
+        self.checks.push(Check {
+            name,
+            status: CheckStatus::Fail,
+            elapsed_ms: Some(elapsed_ms),
+            detail: format!("{:#}", error),
+            hint: Some(hint),
+        });
+        for step in STEPS.iter().skip_while(|&&step| step != name).skip(1) {
+            self.add(step, CheckStatus::Skip, None, format!("skipped after {} failed", name));
+        }
+    }
+
+    fn failed(&self) -> usize {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Fail).count()
+    }
+
+    fn render_text(&self) -> String {
+        let mut out = String::new();
+        out.push_str("SQL Server connection diagnostics\n");
+        out.push_str(&format!("Generated   {} by alrajhi_sql_tui {}\n", self.generated, self.tool_version));
+        out.push_str(&format!("Server      {}:{}\n", self.host, self.port));
+        out.push_str(&format!("Database    {}\n", self.database));
+        out.push_str(&format!("User        {}\n", self.user));
+        out.push_str(&format!("Profile     {}\n", self.profile));
+        out.push_str(&format!("Encryption  {}\n\n", if self.encrypt { "required" } else { "off" }));
+
+        let name_width = self.checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+        for check in &self.checks {
+            let elapsed = check.elapsed_ms.map(|ms| format!("{:.1} ms", ms)).unwrap_or_default();
+            out.push_str(&format!(
+                "{}  {:<name_width$}  {:>10}  {}\n",
+                check.status.label(),
+                check.name,
+                elapsed,
+                check.detail
+            ));
+            if let Some(hint) = check.hint {
+                out.push_str(&format!("{:>width$}hint: {}\n", "", hint, width = name_width + 20));
+            }
+        }
+
+        let failed = self.failed();
+        out.push_str(&match failed {
+            0 => "\nResult: all checks passed\n".to_string(),
+            1 => "\nResult: 1 check failed\n".to_string(),
+            n => format!("\nResult: {} checks failed\n", n),
+        });
+        out
+    }
+}
+
+/// Run the `diagnose` subcommand: print the report, and save it with `--out`
+pub async fn run_diagnose(args: DiagnoseArgs) -> ExitCode {
+    let mut config = DbConfig::default();
+    if let Some(database) = &args.database {
+        config.database = database.clone();
+    }
+    let report = diagnose(&config, &args).await;
+
+    let text = match args.format {
+        ReportFormat::Text => report.render_text(),
+        ReportFormat::Json => match serde_json::to_string_pretty(&report) {
+            Ok(json) => json + "\n",
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+        },
+    };
+    print!("{}", text);
+    if let Some(path) = &args.out {
+        if let Err(e) = std::fs::write(path, &text) {
+            eprintln!("Error: cannot write {}: {}", path.display(), e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+        eprintln!("Report saved to {}", path.display());
+    }
+
+    if report.failed() > 0 {
+        ExitCode::from(EXIT_CONNECTION)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+async fn diagnose(config: &DbConfig, args: &DiagnoseArgs) -> Report {
+    let mut report = Report::new(config);
+
+    let (addresses, ms) = timed(args.timeout, async {
+        let addresses: Vec<SocketAddr> = tokio::net::lookup_host((config.host.as_str(), config.port)).await?.collect();
+        if addresses.is_empty() {
+            return Err(anyhow!("{} has no addresses", config.host));
+        }
+        Ok(addresses)
+    })
+    .await;
+    let addresses = match addresses {
+        Ok(addresses) => {
+            let list: Vec<String> = addresses.iter().map(|a| a.ip().to_string()).collect();
+            report.pass("DNS lookup", ms, list.join(", "));
+            addresses
+        }
+        Err(e) => {
+            report.fail("DNS lookup", ms, &e, "Check DB_HOST, and that the VPN is up if the name is internal");
+            return report;
+        }
+    };
+
+    let (tcp, ms) = timed(args.timeout, connect_any(&addresses)).await;
+    let mut tcp = match tcp {
+        Ok(tcp) => {
+            let peer = tcp.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+            report.pass("TCP connect", ms, peer);
+            tcp
+        }
+        Err(e) => {
+            report.fail(
+                "TCP connect",
+                ms,
+                &e,
+                "Check DB_PORT; a firewall may need to allow this machine's IP to reach the server",
+            );
+            return report;
+        }
+    };
+
+    let client_encryption =
+        if config.encrypt { PreloginEncryption::Required } else { PreloginEncryption::NotSupported };
+    let (reply, ms) = timed(args.timeout, prelogin(&mut tcp, client_encryption)).await;
+    drop(tcp);
+    match reply {
+        Ok(reply) => {
+            let (major, minor, build) = reply.version;
+            let server = format!("{} ({}.{}.{})", reply.product_name(), major, minor, build);
+            match reply.negotiate(client_encryption) {
+                Some(encrypted) => {
+                    let tls = if encrypted { "TLS negotiated" } else { "no TLS" };
+                    let detail = format!("{}, server encryption {}, {}", server, reply.encryption.label(), tls);
+                    report.pass("Prelogin", ms, detail);
+                }
+                None => {
+                    let e = anyhow!(
+                        "{}: server encryption is {} but this client asks for {}",
+                        server,
+                        reply.encryption.label(),
+                        client_encryption.label()
+                    );
+                    report.fail("Prelogin", ms, &e, "The server and client encryption settings cannot agree");
+                    return report;
+                }
+            }
+        }
+        Err(e) => {
+            report.fail("Prelogin", ms, &e, "Something answered on the port that is not SQL Server; check DB_PORT");
+            return report;
+        }
+    }
+
+    // Logging in also opens the database, so a refused database still proves the credentials
+    let (db, ms) = timed(args.timeout, DbConnection::new(config.clone())).await;
+    let db = match db {
+        Ok(db) => {
+            report.pass("Login", ms, format!("signed in as {}", config.user));
+            report.add("Database", CheckStatus::Pass, None, format!("{} opened at login", config.database));
+            db
+        }
+        Err(e) if server_error_code(&e) == Some(CANNOT_OPEN_DATABASE) => {
+            report.pass("Login", ms, format!("signed in as {}", config.user));
+            report.fail(
+                "Database",
+                ms,
+                &e,
+                "The login has no access to this database, or it is offline; check DB_DATABASE",
+            );
+            return report;
+        }
+        Err(e) => {
+            report.fail("Login", ms, &e, login_hint(&e));
+            return report;
+        }
+    };
+    let (version, ms) = timed(args.timeout, server_version(&db)).await;
+    match version {
+        Ok(version) => report.pass("Server version", ms, version),
+        Err(e) => {
+            report.fail("Server version", ms, &e, "The session dropped right after login; see the error");
+            return report;
+        }
+    }
+
+    let latency = measure_latency(&db, args.samples, args.timeout).await;
+    let detail = format!(
+        "SELECT 1 x{}: min {:.1}, p50 {:.1}, p90 {:.1}, p99 {:.1}, max {:.1} ms",
+        latency.samples, latency.min_ms, latency.p50_ms, latency.p90_ms, latency.p99_ms, latency.max_ms
+    );
+    if latency.failures > 0 {
+        let detail = format!("{}; {} of {} failed", detail, latency.failures, latency.samples + latency.failures);
+        report.add("Latency", CheckStatus::Warn, None, detail);
+    } else {
+        report.add("Latency", CheckStatus::Pass, None, detail);
+    }
+    report.latency = Some(latency);
+    report
+}
+
+/// Run a step under the timeout; returns its result and the milliseconds it took
+async fn timed<T>(timeout: Duration, step: impl Future<Output = Result<T>>) -> (Result<T>, f64) {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(timeout, step).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow!("no answer within {} s", timeout.as_secs())),
+    };
+    (result, elapsed_ms(started))
+}
+
+/// Milliseconds since `started`, to a tenth
+fn elapsed_ms(started: Instant) -> f64 {
+    (started.elapsed().as_secs_f64() * 10_000.0).round() / 10.0
+}
+
+/// Connect to the first address that accepts
+async fn connect_any(addresses: &[SocketAddr]) -> Result<TcpStream> {
+    let mut last_error = None;
+    for address in addresses {
+        match TcpStream::connect(address).await {
+            Ok(tcp) => return Ok(tcp),
+            Err(e) => last_error = Some(anyhow::Error::new(e).context(format!("cannot connect to {}", address))),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| anyhow!("no address to connect to")))
+}
+
+/// Number of the SQL Server error behind a failed connect
+fn server_error_code(error: &anyhow::Error) -> Option<u32> {
+    error.chain().find_map(|e| e.downcast_ref::<tiberius::error::Error>()).and_then(|e| e.code())
+}
+
+/// Hint for a failed login; the TLS handshake happens in this step too, so its errors land here
+fn login_hint(error: &anyhow::Error) -> &'static str {
+    use tiberius::error::Error;
+    match error.chain().find_map(|e| e.downcast_ref::<Error>()) {
+        Some(Error::Tls(_)) => {
+            "The TLS handshake failed: the server certificate is not trusted or has expired, or no TLS version is shared"
+        }
+        Some(Error::Io { .. }) => {
+            "The connection dropped during the TLS handshake or login; a proxy or firewall may be intercepting TLS"
+        }
+        Some(Error::Routing { .. }) => "The server redirects logins to another host; connect to that host and port instead",
+        Some(e) if e.code() == Some(LOGIN_FAILED) => "Check DB_USER and DB_PASSWORD, and that SQL logins are enabled",
+        Some(Error::Server(_)) => "The server refused the login; its message says why",
+        _ => "No answer to the TLS handshake or login; the server may be overloaded, or something between cuts TLS",
+    }
+}
+
+/// `@@VERSION` headline with the edition and server name
+async fn server_version(db: &DbConnection) -> Result<String> {
+    let version = db.get_server_version().await?;
+    let headline = version.lines().next().unwrap_or_default().trim().to_string();
+    let client = db.client();
+    let mut client = client.lock().await;
+    let row = client
+        .simple_query(
+            "SELECT CAST(SERVERPROPERTY('Edition') AS nvarchar(128)), CAST(@@SERVERNAME AS nvarchar(128))",
+        )
+        .await?
+        .into_row()
+        .await?
+        .context("no server properties")?;
+    let edition = row.get::<&str, _>(0).unwrap_or("unknown edition");
+    let name = row.get::<&str, _>(1).unwrap_or("unnamed");
+    Ok(format!("{}; {}; server {}", headline, edition, name))
+}
+
+/// Time `samples` round trips of `DbConnection::test_connection`
+async fn measure_latency(db: &DbConnection, samples: usize, timeout: Duration) -> Latency {
+    let mut times = Vec::with_capacity(samples);
+    let mut failures = 0;
+    for _ in 0..samples {
+        let started = Instant::now();
+        match tokio::time::timeout(timeout, db.test_connection()).await {
+            Ok(Ok(true)) => times.push(elapsed_ms(started)),
+            _ => failures += 1,
+        }
+    }
+    times.sort_by(f64::total_cmp);
+    let percentile = |p: f64| -> f64 {
+        if times.is_empty() {
+            return 0.0;
+        }
+        // Nearest rank
+        let rank = ((p / 100.0) * times.len() as f64).ceil() as usize;
+        times[rank.clamp(1, times.len()) - 1]
+    };
+    Latency {
+        samples: times.len(),
+        failures,
+        min_ms: times.first().copied().unwrap_or(0.0),
+        p50_ms: percentile(50.0),
+        p90_ms: percentile(90.0),
+        p99_ms: percentile(99.0),
+        max_ms: times.last().copied().unwrap_or(0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiberius::error::{Error, IoErrorKind};
+
+    fn hint(error: Error) -> &'static str {
+        login_hint(&anyhow::Error::new(error).context("Failed to authenticate with SQL Server"))
+    }
+
+    #[test]
+    fn login_hint_tells_tls_from_credentials() {
+        assert!(hint(Error::Tls("invalid peer certificate: UnknownIssuer".to_string())).starts_with("The TLS handshake failed"));
+        let reset = Error::Io { kind: IoErrorKind::ConnectionReset, message: "reset".to_string() };
+        assert!(hint(reset).contains("TLS handshake or login"));
+        assert!(hint(Error::Routing { host: "node".to_string(), port: 11000 }).contains("another host"));
+        assert!(login_hint(&anyhow!("no answer within 10 s")).starts_with("No answer"));
+    }
+}
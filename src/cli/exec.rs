@@ -0,0 +1,195 @@
+//! `exec`: run SQL without the TUI and write the results to stdout or a file
+
+use crate::cli::{TableWriter, EXIT_CONNECTION, EXIT_FAILURE, EXIT_USAGE};
+use crate::db::{DbConfig, DbConnection, QueryExecutor};
+use crate::export::{create_text_writer, create_writer, ExportFormat, ExportOptions, RowWriter};
+use anyhow::{Context, Result};
+use futures::TryStreamExt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tiberius::{Client, QueryItem};
+use tokio::net::TcpStream;
+use tokio_util::compat::Compat;
+
+/// Where the SQL comes from
+#[derive(Clone, Debug)]
+pub enum SqlSource {
+    Text(String),
+    /// A script file; `-` reads standard input
+    File(PathBuf),
+}
+
+impl SqlSource {
+    pub fn read(&self) -> Result<String> {
+        match self {
+            SqlSource::Text(text) => Ok(text.clone()),
+            SqlSource::File(path) if path.as_os_str() == "-" => {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text).context("cannot read standard input")?;
+                Ok(text)
+            }
+            SqlSource::File(path) => {
+                std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))
+            }
+        }
+    }
+}
+
+/// How `exec` writes result rows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Aligned columns for reading in a terminal
+    Table,
+    Export(ExportFormat),
+}
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "table" | "text" => Some(OutputFormat::Table),
+            name => ExportFormat::parse(name).map(OutputFormat::Export),
+        }
+    }
+}
+
+/// Options of the `exec` subcommand
+#[derive(Clone, Debug)]
+pub struct ExecArgs {
+    pub source: SqlSource,
+    pub format: OutputFormat,
+    /// File to write; standard output when unset
+    pub out: Option<PathBuf>,
+    /// Database used instead of DB_DATABASE
+    pub database: Option<String>,
+}
+
+/// Split a script into batches on lines holding only `GO`, as sqlcmd does
+pub fn split_batches(script: &str) -> Vec<String> {
+    let mut batches = Vec::new();
+    let mut current = String::new();
+    for line in script.lines() {
+        if line.trim().eq_ignore_ascii_case("go") {
+            batches.push(std::mem::take(&mut current));
+        } else {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    batches.push(current);
+    batches.into_iter().filter(|b| !b.trim().is_empty()).collect()
+}
+
+/// Run the `exec` subcommand; the exit code says whether it worked
+pub async fn run_exec(args: ExecArgs) -> ExitCode {
+    let batches = match args.source.read() {
+        Ok(sql) => split_batches(&sql),
+        Err(e) => return fail(EXIT_USAGE, &e),
+    };
+    if batches.is_empty() {
+        eprintln!("Error: no SQL to run");
+        return ExitCode::from(EXIT_USAGE);
+    }
+
+    let mut config = DbConfig::default();
+    if let Some(database) = &args.database {
+        config.database = database.clone();
+    }
+    let db = match DbConnection::new(config).await {
+        Ok(db) => db,
+        Err(e) => return fail(EXIT_CONNECTION, &e),
+    };
+
+    let table = QueryExecutor::extract_table_name(&batches[0]);
+    let mut writer = match open_writer(&args, table.as_deref()) {
+        Ok(writer) => writer,
+        Err(e) => return fail(EXIT_FAILURE, &e),
+    };
+    let client = db.client();
+    let mut client = client.lock().await;
+    let mut rows = 0;
+    for (i, batch) in batches.iter().enumerate() {
+        match run_batch(&mut client, batch, writer.as_mut()).await {
+            Ok(count) => rows += count,
+            Err(e) => {
+                drop(writer);
+                let e = if batches.len() > 1 { e.context(format!("batch {} of {}", i + 1, batches.len())) } else { e };
+                return fail(EXIT_FAILURE, &discard_output(&args, e));
+            }
+        }
+    }
+    if let Err(e) = writer.finish() {
+        drop(writer);
+        return fail(EXIT_FAILURE, &discard_output(&args, e));
+    }
+
+    if let Some(path) = &args.out {
+        eprintln!("{} row(s) written to {}", rows, path.display());
+    }
+    ExitCode::SUCCESS
+}
+
+/// Remove a partly written output file, so a consumer that only looks for the file
+/// never reads truncated data
+fn discard_output(args: &ExecArgs, error: anyhow::Error) -> anyhow::Error {
+    let Some(path) = &args.out else {
+        return error;
+    };
+    match std::fs::remove_file(path) {
+        Ok(()) => error.context(format!("partial {} removed", path.display())),
+        Err(e) => error.context(format!("partial {} left behind: {}", path.display(), e)),
+    }
+}
+
+fn fail(code: u8, error: &anyhow::Error) -> ExitCode {
+    eprintln!("Error: {:#}", error);
+    ExitCode::from(code)
+}
+
+/// Writer for the chosen format, into the output file or standard output; `table` names
+/// the target of `--format sql` INSERTs
+fn open_writer(args: &ExecArgs, table: Option<&str>) -> Result<Box<dyn RowWriter>> {
+    let mut options = ExportOptions::default();
+    if let OutputFormat::Export(format) = args.format {
+        options.set_format(format);
+    }
+    if let (Some(path), ExportFormat::Parquet) = (&args.out, options.format) {
+        return create_writer(path, &options, table);
+    }
+
+    let out: Box<dyn io::Write + Send> = match &args.out {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).with_context(|| format!("cannot create {}", path.display()))?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    match args.format {
+        OutputFormat::Table => Ok(Box::new(TableWriter::new(out))),
+        OutputFormat::Export(_) => create_text_writer(out, &options, table),
+    }
+}
+
+/// Stream one batch's result sets into the writer; returns the rows written
+async fn run_batch(client: &mut Client<Compat<TcpStream>>, sql: &str, writer: &mut dyn RowWriter) -> Result<u64> {
+    let mut stream = QueryExecutor::open_stream(client, sql).await?;
+    let mut begun = false;
+    let mut rows = 0;
+    while let Some(item) = stream.try_next().await? {
+        match item {
+            QueryItem::Metadata(meta) => {
+                writer.begin(&QueryExecutor::column_info(meta.columns()))?;
+                begun = true;
+            }
+            QueryItem::Row(row) => {
+                if !begun {
+                    writer.begin(&QueryExecutor::column_info(row.columns()))?;
+                    begun = true;
+                }
+                writer.write_row(&QueryExecutor::row_values(&row))?;
+                rows += 1;
+            }
+        }
+    }
+    Ok(rows)
+}
@@ -0,0 +1,201 @@
+//! Single-line input with editing keys and query history, for the REPL
+
+use crate::app::QueryHistory;
+use crossterm::cursor::MoveToColumn;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{execute, queue};
+use std::io::{self, BufRead, IsTerminal, Write};
+use unicode_width::UnicodeWidthChar;
+
+/// What a prompt read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReadLine {
+    Line(String),
+    /// Ctrl+C: drop what was typed
+    Interrupted,
+    /// Ctrl+D on an empty line, or the end of piped input
+    Eof,
+}
+
+/// Reads lines with cursor keys and history in a terminal, or plain lines from a pipe
+pub struct LineEditor {
+    interactive: bool,
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self { interactive: io::stdin().is_terminal() && io::stdout().is_terminal() }
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+
+    /// Show `prompt` and read one line; Up/Down step through `history`
+    pub fn read_line(&mut self, prompt: &str, history: &mut QueryHistory) -> io::Result<ReadLine> {
+        if !self.interactive {
+            let mut line = String::new();
+            return Ok(match io::stdin().lock().read_line(&mut line)? {
+                0 => ReadLine::Eof,
+                _ => ReadLine::Line(line.trim_end_matches(['\r', '\n']).to_string()),
+            });
+        }
+
+        terminal::enable_raw_mode()?;
+        let result = LineState::new(prompt).run(history);
+        terminal::disable_raw_mode()?;
+        history.reset_navigation();
+        result
+    }
+}
+
+/// Text being edited; `cursor` counts chars
+struct LineState<'a> {
+    prompt: &'a str,
+    buffer: Vec<char>,
+    cursor: usize,
+    /// What was typed before stepping into the history
+    draft: Option<Vec<char>>,
+}
+
+impl<'a> LineState<'a> {
+    fn new(prompt: &'a str) -> Self {
+        Self { prompt, buffer: Vec::new(), cursor: 0, draft: None }
+    }
+
+    fn run(&mut self, history: &mut QueryHistory) -> io::Result<ReadLine> {
+        let mut out = io::stdout();
+        self.redraw(&mut out)?;
+        loop {
+            let Event::Key(key) = event::read()? else {
+                // Resizes and the like: draw again at the new width
+                self.redraw(&mut out)?;
+                continue;
+            };
+            if key.kind == KeyEventKind::Release {
+                continue;
+            }
+            if let Some(done) = self.handle_key(key, history) {
+                let echo = if done == ReadLine::Interrupted { "^C\r\n" } else { "\r\n" };
+                execute!(out, Print(echo))?;
+                return Ok(done);
+            }
+            self.redraw(&mut out)?;
+        }
+    }
+
+    /// Apply a key; returns the result once the line is finished
+    fn handle_key(&mut self, key: KeyEvent, history: &mut QueryHistory) -> Option<ReadLine> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => return Some(ReadLine::Line(self.buffer.iter().collect())),
+            KeyCode::Char('c') if ctrl => return Some(ReadLine::Interrupted),
+            KeyCode::Char('d') if ctrl && self.buffer.is_empty() => return Some(ReadLine::Eof),
+            KeyCode::Char('d') if ctrl => self.delete_forward(),
+            KeyCode::Delete => self.delete_forward(),
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.buffer.len(),
+            KeyCode::End => self.cursor = self.buffer.len(),
+            KeyCode::Char('u') if ctrl => {
+                self.buffer.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyCode::Char('k') if ctrl => self.buffer.truncate(self.cursor),
+            KeyCode::Char('l') if ctrl => {
+                let _ = execute!(io::stdout(), Clear(ClearType::All), crossterm::cursor::MoveTo(0, 0));
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.buffer.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.buffer.remove(self.cursor);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.buffer.len()),
+            KeyCode::Up => {
+                if let Some(entry) = history.previous() {
+                    let query = recalled(&entry.query);
+                    self.draft.get_or_insert_with(|| self.buffer.clone());
+                    self.set(query.chars().collect());
+                }
+            }
+            KeyCode::Down => match history.next() {
+                Some(entry) => {
+                    let query = recalled(&entry.query);
+                    self.set(query.chars().collect());
+                }
+                None => {
+                    history.reset_navigation();
+                    if let Some(draft) = self.draft.take() {
+                        self.set(draft);
+                    }
+                }
+            },
+            _ => {}
+        }
+        None
+    }
+
+    fn delete_forward(&mut self) {
+        if self.cursor < self.buffer.len() {
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    fn set(&mut self, buffer: Vec<char>) {
+        self.cursor = buffer.len();
+        self.buffer = buffer;
+    }
+
+    /// Draw the prompt and the part of the line around the cursor that fits the terminal
+    fn redraw(&self, out: &mut impl Write) -> io::Result<()> {
+        let columns = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+        let prompt_width = self.prompt.chars().filter_map(UnicodeWidthChar::width).sum::<usize>();
+        let available = columns.saturating_sub(prompt_width + 1).max(1);
+        // Line breaks of a recalled query show as ↵
+        let shown: Vec<char> = self.buffer.iter().map(|&c| if c == '\n' { '↵' } else if c == '\t' { ' ' } else { c }).collect();
+        let width = |chars: &[char]| chars.iter().filter_map(|c| c.width()).sum::<usize>();
+
+        let mut start = 0;
+        while width(&shown[start..self.cursor]) > available {
+            start += 1;
+        }
+        let mut end = self.cursor;
+        while end < shown.len() && width(&shown[start..=end]) <= available {
+            end += 1;
+        }
+        let visible: String = shown[start..end].iter().collect();
+        let cursor_x = prompt_width + width(&shown[start..self.cursor]);
+
+        queue!(
+            out,
+            MoveToColumn(0),
+            Clear(ClearType::UntilNewLine),
+            Print(self.prompt),
+            Print(visible),
+            MoveToColumn(cursor_x as u16)
+        )?;
+        out.flush()
+    }
+}
+
+/// History text ready to run again: a recalled query ends with `;` so Enter runs it
+fn recalled(query: &str) -> String {
+    let query = query.trim_end();
+    if query.ends_with(';') {
+        query.to_string()
+    } else {
+        format!("{};", query)
+    }
+}
@@ -0,0 +1,15 @@
+//! Command-line entry points besides the TUI
+
+mod args;
+mod diagnose;
+mod exec;
+mod line;
+mod repl;
+mod table;
+
+pub use args::*;
+pub use diagnose::*;
+pub use exec::*;
+pub use line::*;
+pub use repl::*;
+pub use table::*;
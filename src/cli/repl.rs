@@ -0,0 +1,474 @@
+//! `repl`: a line-mode prompt that runs SQL and prints results as text tables
+
+use crate::app::{error_chain, HistoryEntry, QueryHistory};
+use crate::cli::{format_table, LineEditor, ReadLine, EXIT_CONNECTION, EXIT_FAILURE};
+use crate::db::{
+    format_data_type, quote_ident, CellValue, ColumnInfo, DbConfig, DbConnection, QueryExecutor,
+    QueryResult, SchemaExplorer,
+};
+use crate::import::split_table_name;
+use anyhow::{Context, Result};
+use std::process::ExitCode;
+use std::time::Instant;
+
+const REPL_HELP: &str = "\
+End a statement with ; or put GO on a line of its own to run it. A ; inside quotes,
+comments or a BEGIN...END block does not end it; GO ends a CREATE PROCEDURE without one.
+  \\dt [PATTERN]   List tables, optionally those whose name contains PATTERN
+  \\d TABLE        Describe the columns of TABLE
+  \\l              List databases
+  \\c DATABASE     Switch to DATABASE
+  \\?              Show this help
+  \\q              Quit (or Ctrl+D)
+Up/Down recall queries from the history shared with the TUI; Ctrl+C cancels a running query.";
+
+/// Options of the `repl` subcommand
+#[derive(Clone, Debug, Default)]
+pub struct ReplArgs {
+    /// Database used instead of DB_DATABASE
+    pub database: Option<String>,
+}
+
+/// Run the `repl` subcommand until `\q` or end of input
+pub async fn run_repl(args: ReplArgs) -> ExitCode {
+    let mut config = DbConfig::default();
+    if let Some(database) = args.database {
+        config.database = database;
+    }
+    let db = match DbConnection::new(config).await {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            return ExitCode::from(EXIT_CONNECTION);
+        }
+    };
+
+    let mut editor = LineEditor::new();
+    let mut repl = Repl {
+        db,
+        history: QueryHistory::from_env(),
+        interactive: editor.is_interactive(),
+        failed: false,
+    };
+    if repl.interactive {
+        let config = &repl.db.config;
+        println!("Connected to {}:{} as {}. Type \\? for help, \\q to quit.", config.host, config.port, config.user);
+    }
+
+    let mut buffer = String::new();
+    loop {
+        let prompt = format!("{}> ", repl.db.config.database);
+        let prompt = if buffer.is_empty() { prompt } else { format!("{:>width$}", "-> ", width = prompt.chars().count()) };
+        let line = match editor.read_line(&prompt, &mut repl.history) {
+            Ok(ReadLine::Line(line)) => line,
+            Ok(ReadLine::Interrupted) => {
+                buffer.clear();
+                continue;
+            }
+            Ok(ReadLine::Eof) => break,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+        };
+
+        let trimmed = line.trim();
+        if buffer.is_empty() && trimmed.starts_with('\\') {
+            if !repl.run_command(trimmed).await {
+                break;
+            }
+        } else if trimmed.eq_ignore_ascii_case("go") {
+            repl.run_query(&std::mem::take(&mut buffer)).await;
+        } else if !trimmed.is_empty() || !buffer.is_empty() {
+            buffer.push_str(&line);
+            buffer.push('\n');
+            if ends_statement(&buffer) {
+                repl.run_query(&std::mem::take(&mut buffer)).await;
+            }
+        }
+    }
+
+    // Piped scripts may leave off the last terminator
+    if !repl.interactive {
+        repl.run_query(&buffer).await;
+    }
+    if repl.failed && !repl.interactive {
+        return ExitCode::from(EXIT_FAILURE);
+    }
+    ExitCode::SUCCESS
+}
+
+struct Repl {
+    db: DbConnection,
+    history: QueryHistory,
+    interactive: bool,
+    /// Whether any statement failed, for the exit code of piped input
+    failed: bool,
+}
+
+impl Repl {
+    /// Run a statement, print its results and record it in the history
+    async fn run_query(&mut self, sql: &str) {
+        let sql = sql.trim();
+        if sql.is_empty() {
+            return;
+        }
+        let started = Instant::now();
+        let client = self.db.client();
+        let outcome = {
+            let mut client = client.lock().await;
+            if self.interactive {
+                tokio::select! {
+                    result = QueryExecutor::execute(&mut client, sql) => Some(result),
+                    _ = tokio::signal::ctrl_c() => None,
+                }
+            } else {
+                Some(QueryExecutor::execute(&mut client, sql).await)
+            }
+        };
+
+        match outcome {
+            Some(Ok(result)) => {
+                print_result(&result);
+                self.history.add(self.history_entry(sql, Some(&result), 0));
+                if changes_database(sql) {
+                    self.refresh_database().await;
+                }
+            }
+            Some(Err(e)) => {
+                let error = error_chain(&e);
+                eprintln!("Error: {}", error);
+                self.failed = true;
+                let elapsed = started.elapsed().as_millis() as u64;
+                self.history.add(self.history_entry(sql, None, elapsed).failed(error));
+            }
+            None => {
+                eprintln!("Cancelled");
+                let elapsed = started.elapsed().as_millis() as u64;
+                self.history.add(self.history_entry(sql, None, elapsed).cancelled());
+                // The half-read reply leaves the session unusable
+                if let Err(e) = self.db.reconnect().await {
+                    eprintln!("Error: reconnect failed: {:#}", e);
+                }
+            }
+        }
+    }
+
+    /// Run a backslash command; false means quit
+    async fn run_command(&mut self, line: &str) -> bool {
+        let (command, argument) = match line.split_once(char::is_whitespace) {
+            Some((command, argument)) => (command, argument.trim()),
+            None => (line, ""),
+        };
+        let outcome = match command {
+            "\\q" => return false,
+            "\\?" | "\\h" => {
+                println!("{}", REPL_HELP);
+                Ok(())
+            }
+            "\\dt" => self.list_tables(argument).await,
+            "\\d" if argument.is_empty() => Err(anyhow::anyhow!("\\d needs a table name")),
+            "\\d" => self.describe_table(argument).await,
+            "\\l" => self.list_databases().await,
+            "\\c" if argument.is_empty() => Err(anyhow::anyhow!("\\c needs a database name")),
+            "\\c" => self.switch_database(argument).await,
+            other => Err(anyhow::anyhow!("unknown command {}; \\? lists them", other)),
+        };
+        if let Err(e) = outcome {
+            eprintln!("Error: {:#}", e);
+            self.failed = true;
+        }
+        true
+    }
+
+    async fn list_tables(&mut self, pattern: &str) -> Result<()> {
+        let tables = {
+            let client = self.db.client();
+            let mut client = client.lock().await;
+            SchemaExplorer::get_tables(&mut client, None).await?
+        };
+        let pattern = pattern.to_lowercase();
+        let rows: Vec<Vec<String>> = tables
+            .into_iter()
+            .filter(|t| format!("{}.{}", t.schema, t.name).to_lowercase().contains(&pattern))
+            .map(|t| vec![t.schema, t.name])
+            .collect();
+        print!("{}", text_table(&["Schema", "Table"], rows));
+        Ok(())
+    }
+
+    async fn describe_table(&mut self, name: &str) -> Result<()> {
+        let (schema, table) = split_table_name(name);
+        let columns = {
+            let client = self.db.client();
+            let mut client = client.lock().await;
+            SchemaExplorer::get_columns(&mut client, &schema, &table).await?
+        };
+        if columns.is_empty() {
+            anyhow::bail!("no table {}.{} in {}", schema, table, self.db.config.database);
+        }
+        let rows: Vec<Vec<String>> = columns
+            .into_iter()
+            .map(|c| {
+                let data_type = format_data_type(
+                    &c.data_type,
+                    c.max_length.unwrap_or(0),
+                    c.precision.unwrap_or(0),
+                    c.scale.unwrap_or(0),
+                );
+                let key = match (c.is_primary_key, c.is_identity, c.is_computed) {
+                    (true, true, _) => "PK, identity",
+                    (true, false, _) => "PK",
+                    (false, true, _) => "identity",
+                    (false, false, true) => "computed",
+                    (false, false, false) => "",
+                };
+                let null = if c.is_nullable { "YES" } else { "NO" };
+                vec![c.name, data_type, null.to_string(), key.to_string()]
+            })
+            .collect();
+        print!("{}", text_table(&["Column", "Type", "Null", "Key"], rows));
+        Ok(())
+    }
+
+    async fn list_databases(&mut self) -> Result<()> {
+        let databases = {
+            let client = self.db.client();
+            let mut client = client.lock().await;
+            SchemaExplorer::get_databases(&mut client).await?
+        };
+        let current = &self.db.config.database;
+        let rows: Vec<Vec<String>> = databases
+            .into_iter()
+            .map(|name| {
+                let marker = if name.eq_ignore_ascii_case(current) { "*" } else { "" };
+                vec![name, marker.to_string()]
+            })
+            .collect();
+        print!("{}", text_table(&["Database", "Current"], rows));
+        Ok(())
+    }
+
+    async fn switch_database(&mut self, name: &str) -> Result<()> {
+        let name = name.trim_end_matches(';').trim_start_matches('[').trim_end_matches(']');
+        {
+            let client = self.db.client();
+            let mut client = client.lock().await;
+            let stream = client.simple_query(format!("USE {}", quote_ident(name))).await?;
+            stream.into_results().await?;
+        }
+        self.refresh_database().await;
+        println!("Now using {}", self.db.config.database);
+        Ok(())
+    }
+
+    /// Read back the session's database after a `USE`, for the prompt and reconnects
+    async fn refresh_database(&mut self) {
+        let current = {
+            let client = self.db.client();
+            let mut client = client.lock().await;
+            current_database(&mut client).await
+        };
+        match current {
+            Ok(name) => self.db.config.database = name,
+            Err(e) => eprintln!("Error: {:#}", e),
+        }
+    }
+
+    /// History entry tagged with the current database, server and profile, as the TUI records them
+    fn history_entry(&self, sql: &str, result: Option<&QueryResult>, elapsed_ms: u64) -> HistoryEntry {
+        let config = &self.db.config;
+        let entry = HistoryEntry::new(
+            sql.to_string(),
+            config.database.clone(),
+            format!("{}:{}", config.host, config.port),
+            config.profile.clone(),
+        );
+        match result {
+            Some(result) => HistoryEntry {
+                execution_time_ms: result.execution_time.as_millis() as u64,
+                row_count: Some(result.row_count),
+                affected_rows: result.affected_rows,
+                ..entry
+            },
+            None => HistoryEntry { execution_time_ms: elapsed_ms, ..entry },
+        }
+    }
+}
+
+async fn current_database(
+    client: &mut tiberius::Client<tokio_util::compat::Compat<tokio::net::TcpStream>>,
+) -> Result<String> {
+    let row = client.simple_query("SELECT DB_NAME()").await?.into_row().await?.context("no database name")?;
+    Ok(row.get::<&str, _>(0).context("no database name")?.to_string())
+}
+
+/// Whether the batch has a `USE` statement, which moves the session to another database
+fn changes_database(sql: &str) -> bool {
+    sql.split([';', '\n'])
+        .map(|statement| statement.trim_start().to_lowercase())
+        .any(|statement| statement.starts_with("use ") || statement.starts_with("use["))
+}
+
+/// Whether `sql` ends with a `;` outside strings, quoted names, comments and
+/// BEGIN...END or CASE...END blocks; comments after the `;` still count as the end
+fn ends_statement(sql: &str) -> bool {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut depth = 0usize;
+    // BEGIN opens a block unless TRAN and the like follow it
+    let mut begin_pending = false;
+    let mut ended = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '-' && next == Some('-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        if c == '/' && next == Some('*') {
+            // Block comments nest in T-SQL
+            let mut nested = 0;
+            loop {
+                match (chars.get(i), chars.get(i + 1)) {
+                    (None, _) => return false,
+                    (Some('/'), Some('*')) => {
+                        nested += 1;
+                        i += 2;
+                    }
+                    (Some('*'), Some('/')) => {
+                        nested -= 1;
+                        i += 2;
+                        if nested == 0 {
+                            break;
+                        }
+                    }
+                    _ => i += 1,
+                }
+            }
+            continue;
+        }
+
+        let word_start = i;
+        if matches!(c, '\'' | '"' | '[') {
+            // A doubled closing quote is part of the text
+            let close = if c == '[' { ']' } else { c };
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return false,
+                    Some(&q) if q == close && chars.get(i + 1) == Some(&close) => i += 2,
+                    Some(&q) if q == close => break,
+                    Some(_) => i += 1,
+                }
+            }
+            i += 1;
+        } else if c.is_alphanumeric() || matches!(c, '_' | '@' | '#') {
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '@' | '#' | '$')) {
+                i += 1;
+            }
+        } else {
+            i += 1;
+        }
+        let token: String = chars[word_start..i].iter().collect::<String>().to_uppercase();
+
+        if begin_pending {
+            begin_pending = false;
+            if !matches!(token.as_str(), "TRAN" | "TRANSACTION" | "DISTRIBUTED" | "DIALOG" | "CONVERSATION") {
+                depth += 1;
+            }
+        }
+        match token.as_str() {
+            "BEGIN" => begin_pending = true,
+            "CASE" => depth += 1,
+            "END" => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        ended = token == ";" && depth == 0;
+    }
+    ended
+}
+
+/// Each result set as a table, then messages and timing
+fn print_result(result: &QueryResult) {
+    let mut printed = false;
+    for (columns, rows) in result.result_sets() {
+        if columns.is_empty() {
+            continue;
+        }
+        if printed {
+            println!();
+        }
+        print!("{}", format_table(columns, rows));
+        printed = true;
+    }
+    for message in &result.messages {
+        println!("{}", message);
+    }
+    match result.affected_rows {
+        Some(affected) if !printed => println!("{} row(s) affected", affected),
+        _ if !printed => println!("Command completed"),
+        _ => {}
+    }
+    println!("Time: {:.2} ms", result.execution_time.as_secs_f64() * 1000.0);
+}
+
+/// Aligned table of text columns, for the backslash command listings
+fn text_table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let columns: Vec<ColumnInfo> = headers
+        .iter()
+        .map(|name| ColumnInfo { name: name.to_string(), type_name: "NVARCHAR".to_string(), max_width: 0 })
+        .collect();
+    let rows: Vec<Vec<CellValue>> = rows.into_iter().map(|row| row.into_iter().map(CellValue::String).collect()).collect();
+    format_table(&columns, &rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn semicolon_ends_a_plain_statement() {
+        assert!(ends_statement("SELECT 1;\n"));
+        assert!(ends_statement("SELECT 1; -- first\n"));
+        assert!(ends_statement("SELECT 1; /* done */\n"));
+        assert!(!ends_statement("SELECT 1\n"));
+        assert!(!ends_statement("SELECT 1; SELECT 2\n"));
+    }
+
+    #[test]
+    fn semicolon_in_strings_names_and_comments_does_not_end() {
+        assert!(!ends_statement("SELECT 'a;\n"));
+        assert!(ends_statement("SELECT 'a;\nb';\n"));
+        assert!(!ends_statement("SELECT 'it''s;\n"));
+        assert!(ends_statement("SELECT 'it''s';\n"));
+        assert!(!ends_statement("SELECT [odd;\n"));
+        assert!(!ends_statement("SELECT \"odd;\n"));
+        assert!(!ends_statement("SELECT 1 -- keep going;\n"));
+        assert!(!ends_statement("SELECT 1 /* keep going;\n"));
+        assert!(!ends_statement("SELECT 1 /* outer /* inner */ still;\n"));
+    }
+
+    #[test]
+    fn semicolon_inside_a_block_does_not_end() {
+        let body = "CREATE PROCEDURE p AS\nBEGIN\n  SELECT 1;\n";
+        assert!(!ends_statement(body));
+        assert!(!ends_statement(&format!("{}END\n", body)));
+        assert!(ends_statement(&format!("{}END;\n", body)));
+        assert!(!ends_statement("BEGIN TRY\n  SELECT 1/0;\nEND TRY\nBEGIN CATCH\n  SELECT 2;\n"));
+        assert!(ends_statement("SELECT CASE WHEN x = 1 THEN 'a;' ELSE 'b' END FROM t;\n"));
+    }
+
+    #[test]
+    fn transactions_are_not_blocks() {
+        assert!(ends_statement("BEGIN TRAN;\n"));
+        assert!(ends_statement("begin transaction;\n"));
+        assert!(ends_statement("BEGIN DISTRIBUTED TRANSACTION;\n"));
+    }
+}
@@ -0,0 +1,131 @@
+//! Result sets as aligned plain-text tables
+
+use crate::db::{CellValue, ColumnInfo};
+use crate::export::{is_decimal_text, plain_text, RowWriter};
+use crate::utils::{display_width, truncate_with_ellipsis};
+use anyhow::Result;
+use std::io::Write;
+
+/// Longer values are cut with an ellipsis so rows stay on one line
+pub const MAX_TABLE_COLUMN_WIDTH: usize = 60;
+
+/// Aligned table of one result set, with a row count footer
+pub fn format_table(columns: &[ColumnInfo], rows: &[Vec<CellValue>]) -> String {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| row.iter().map(table_text).collect())
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            cells
+                .iter()
+                .filter_map(|row| row.get(i))
+                .map(|text| display_width(text))
+                .chain([display_width(&column.name)])
+                .max()
+                .unwrap_or(0)
+                .min(MAX_TABLE_COLUMN_WIDTH)
+        })
+        .collect();
+    // Numbers line up on the right
+    let numeric: Vec<bool> = (0..columns.len())
+        .map(|i| {
+            let type_name = columns[i].type_name.as_str();
+            rows.iter().filter_map(|row| row.get(i)).all(|cell| match cell {
+                CellValue::Null | CellValue::Int(_) | CellValue::Float(_) => true,
+                CellValue::String(v) => matches!(type_name, "DECIMAL" | "NUMERIC" | "MONEY" | "SMALLMONEY") && is_decimal_text(v),
+                _ => false,
+            })
+        })
+        .collect();
+
+    let line = |texts: Vec<&str>, align: &dyn Fn(usize) -> bool| -> String {
+        let fields: Vec<String> = texts
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (text, &width))| {
+                let text = truncate_with_ellipsis(text, width);
+                let pad = " ".repeat(width.saturating_sub(display_width(&text)));
+                if align(i) { format!("{}{}", pad, text) } else { format!("{}{}", text, pad) }
+            })
+            .collect();
+        fields.join(" | ").trim_end().to_string()
+    };
+
+    let mut out = String::new();
+    out.push_str(&line(columns.iter().map(|c| c.name.as_str()).collect(), &|_| false));
+    out.push('\n');
+    let rule: Vec<String> = widths.iter().map(|&w| "-".repeat(w)).collect();
+    out.push_str(&rule.join("-+-"));
+    out.push('\n');
+    for row in &cells {
+        out.push_str(&line(row.iter().map(String::as_str).collect(), &|i| numeric[i]));
+        out.push('\n');
+    }
+    out.push_str(&format!("({} row{})\n", rows.len(), if rows.len() == 1 { "" } else { "s" }));
+    out
+}
+
+/// One-line cell text: NULL spelled out, line breaks and tabs as spaces
+fn table_text(cell: &CellValue) -> String {
+    match cell {
+        CellValue::Null => "NULL".to_string(),
+        cell => plain_text(cell).replace(['\r', '\n', '\t'], " "),
+    }
+}
+
+/// Table output for `exec`: rows are held until their result set ends, to size the columns
+pub struct TableWriter {
+    out: Box<dyn Write + Send>,
+    columns: Vec<ColumnInfo>,
+    rows: Vec<Vec<CellValue>>,
+    sets: usize,
+    written: u64,
+}
+
+impl TableWriter {
+    pub fn new(out: Box<dyn Write + Send>) -> Self {
+        Self { out, columns: Vec::new(), rows: Vec::new(), sets: 0, written: 0 }
+    }
+
+    /// Write out the result set collected so far
+    fn flush_set(&mut self) -> Result<()> {
+        if self.sets == 0 {
+            return Ok(());
+        }
+        let mut text = if self.sets > 1 { "\n".to_string() } else { String::new() };
+        text.push_str(&format_table(&self.columns, &self.rows));
+        self.out.write_all(text.as_bytes())?;
+        self.written += text.len() as u64;
+        self.rows.clear();
+        Ok(())
+    }
+}
+
+impl RowWriter for TableWriter {
+    fn begin(&mut self, columns: &[ColumnInfo]) -> Result<()> {
+        self.flush_set()?;
+        self.columns = columns.to_vec();
+        self.sets += 1;
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &[CellValue]) -> Result<()> {
+        self.rows.push(row.to_vec());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.flush_set()?;
+        self.sets = 0;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.written
+    }
+}
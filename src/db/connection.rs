@@ -17,18 +17,23 @@ pub struct DbConfig {
     pub database: String,
     pub encrypt: bool,
     pub trust_cert: bool,
+    /// Name recorded with history entries (DB_PROFILE, or user@host)
+    pub profile: String,
 }
 
 impl Default for DbConfig {
     fn default() -> Self {
+        let host = std::env::var("DB_HOST").unwrap_or_else(|_| "localhost".to_string());
+        let user = std::env::var("DB_USER").unwrap_or_else(|_| "sa".to_string());
         Self {
-            host: std::env::var("DB_HOST").unwrap_or_else(|_| "localhost".to_string()),
             port: std::env::var("DB_PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(1433),
-            user: std::env::var("DB_USER").unwrap_or_else(|_| "sa".to_string()),
             password: std::env::var("DB_PASSWORD").unwrap_or_else(|_| "".to_string()),
             database: std::env::var("DB_DATABASE").unwrap_or_else(|_| "master".to_string()),
             encrypt: false,
             trust_cert: true,
+            profile: std::env::var("DB_PROFILE").unwrap_or_else(|_| format!("{}@{}", user, host)),
+            host,
+            user,
         }
     }
 }
@@ -53,7 +58,7 @@ impl DbConnection {
     }
 
     /// Connect to SQL Server
-    async fn connect(db_config: &DbConfig) -> Result<Client<Compat<TcpStream>>> {
+    pub async fn connect(db_config: &DbConfig) -> Result<Client<Compat<TcpStream>>> {
         let mut config = Config::new();
 
         config.host(&db_config.host);
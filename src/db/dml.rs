@@ -0,0 +1,150 @@
+//! Parameterized DML batches
+
+use crate::db::CellValue;
+use anyhow::{Context, Result};
+use tiberius::{Client, Query};
+use tokio::net::TcpStream;
+use tokio_util::compat::Compat;
+
+/// A single parameterized DML statement
+#[derive(Clone, Debug)]
+pub struct DmlStatement {
+    /// SQL text using `@P1`, `@P2`, ... placeholders
+    pub sql: String,
+    /// Parameter values in placeholder order
+    pub params: Vec<CellValue>,
+    /// Fail the batch unless exactly one row is affected
+    pub expect_single_row: bool,
+}
+
+impl DmlStatement {
+    /// Render the statement followed by its parameter values, for preview
+    pub fn preview(&self) -> String {
+        if self.params.is_empty() {
+            return self.sql.clone();
+        }
+
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, p)| format!("@P{} = {}", i + 1, sql_literal(p)))
+            .collect();
+
+        format!("{}\n    -- {}", self.sql, params.join(", "))
+    }
+}
+
+/// Executes DML batches inside a single transaction
+pub struct DmlExecutor;
+
+impl DmlExecutor {
+    /// Run every statement in one transaction, rolling back on the first failure
+    pub async fn execute_batch(
+        client: &mut Client<Compat<TcpStream>>,
+        statements: &[DmlStatement],
+    ) -> Result<u64> {
+        let xact_abort_was_on = begin_transaction(client).await?;
+
+        match Self::execute_all(client, statements).await {
+            Ok(total) => match end_transaction(client, true, xact_abort_was_on).await {
+                Ok(()) => Ok(total),
+                Err(e) => {
+                    let _ = end_transaction(client, false, xact_abort_was_on).await;
+                    Err(e.context("Commit failed"))
+                }
+            },
+            Err(e) => {
+                let _ = end_transaction(client, false, xact_abort_was_on).await;
+                Err(e.context("Changes rolled back"))
+            }
+        }
+    }
+
+    async fn execute_all(
+        client: &mut Client<Compat<TcpStream>>,
+        statements: &[DmlStatement],
+    ) -> Result<u64> {
+        let mut total = 0;
+
+        for (i, statement) in statements.iter().enumerate() {
+            let mut query = Query::new(statement.sql.as_str());
+            for param in &statement.params {
+                bind_cell(&mut query, param);
+            }
+
+            let result = query
+                .execute(client)
+                .await
+                .with_context(|| format!("Statement {} failed", i + 1))?;
+            let affected = result.total();
+
+            if statement.expect_single_row && affected != 1 {
+                anyhow::bail!(
+                    "Statement {} affected {} rows (expected 1): {}",
+                    i + 1,
+                    affected,
+                    statement.sql
+                );
+            }
+
+            total += affected;
+        }
+
+        Ok(total)
+    }
+}
+
+/// XACT_ABORT bit of `@@OPTIONS`
+const XACT_ABORT_OPTION: i32 = 16384;
+
+/// Start a transaction with XACT_ABORT on, so any error rolls all of it back; returns
+/// whether the session already had XACT_ABORT on, for `end_transaction`
+pub async fn begin_transaction(client: &mut Client<Compat<TcpStream>>) -> Result<bool> {
+    let row = client
+        .simple_query(format!("SELECT @@OPTIONS & {}", XACT_ABORT_OPTION))
+        .await?
+        .into_row()
+        .await?;
+    let was_on = row.and_then(|r| r.get::<i32, _>(0)).unwrap_or(0) != 0;
+    client.simple_query("SET XACT_ABORT ON; BEGIN TRANSACTION").await?.into_results().await?;
+    Ok(was_on)
+}
+
+/// Commit or roll back, then put XACT_ABORT back as it was so the user's later queries
+/// in this session do not abort their batch on the first error
+pub async fn end_transaction(
+    client: &mut Client<Compat<TcpStream>>,
+    commit: bool,
+    xact_abort_was_on: bool,
+) -> Result<()> {
+    let end = if commit { "COMMIT TRANSACTION" } else { "IF @@TRANCOUNT > 0 ROLLBACK TRANSACTION" };
+    let restore = if xact_abort_was_on { "" } else { "; SET XACT_ABORT OFF" };
+    client.simple_query(format!("{}{}", end, restore)).await?.into_results().await?;
+    Ok(())
+}
+
+/// Bind a cell value as a typed query parameter
+fn bind_cell(query: &mut Query<'_>, cell: &CellValue) {
+    match cell {
+        CellValue::Null => query.bind(Option::<String>::None),
+        CellValue::Bool(v) => query.bind(*v),
+        CellValue::Int(v) => query.bind(*v),
+        CellValue::Float(v) => query.bind(*v),
+        CellValue::String(v) | CellValue::DateTime(v) => query.bind(v.clone()),
+        CellValue::Binary(v) => query.bind(v.clone()),
+    }
+}
+
+/// Format a cell value as a T-SQL literal
+pub fn sql_literal(cell: &CellValue) -> String {
+    match cell {
+        CellValue::Null => "NULL".to_string(),
+        CellValue::Bool(v) => if *v { "1" } else { "0" }.to_string(),
+        CellValue::Int(v) => v.to_string(),
+        CellValue::Float(v) => v.to_string(),
+        CellValue::String(v) => format!("N'{}'", v.replace('\'', "''")),
+        CellValue::DateTime(v) => format!("'{}'", v),
+        CellValue::Binary(_) => cell.to_string(),
+    }
+}
@@ -1,9 +1,13 @@
 //! Database module for SQL Server connectivity
 
 mod connection;
+mod dml;
+mod prelogin;
 mod query;
 mod schema;
 
 pub use connection::*;
+pub use dml::*;
+pub use prelogin::*;
 pub use query::*;
 pub use schema::*;
@@ -0,0 +1,174 @@
+//! TDS prelogin probe: the first exchange with SQL Server, before TLS and login
+
+use anyhow::{bail, Context, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const PACKET_PRELOGIN: u8 = 0x12;
+const PACKET_REPLY: u8 = 0x04;
+const STATUS_END_OF_MESSAGE: u8 = 0x01;
+const HEADER_LEN: usize = 8;
+
+const OPTION_VERSION: u8 = 0x00;
+const OPTION_ENCRYPTION: u8 = 0x01;
+const OPTION_INSTANCE: u8 = 0x02;
+const OPTION_THREAD_ID: u8 = 0x03;
+const OPTION_MARS: u8 = 0x04;
+const OPTION_END: u8 = 0xFF;
+
+/// Encryption setting exchanged in prelogin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreloginEncryption {
+    /// Only the login packet is encrypted
+    Off,
+    On,
+    NotSupported,
+    Required,
+}
+
+impl PreloginEncryption {
+    fn from_byte(byte: u8) -> Result<Self> {
+        Ok(match byte {
+            0 => PreloginEncryption::Off,
+            1 => PreloginEncryption::On,
+            2 => PreloginEncryption::NotSupported,
+            3 => PreloginEncryption::Required,
+            other => bail!("unknown encryption setting {:#04x}", other),
+        })
+    }
+
+    fn byte(self) -> u8 {
+        match self {
+            PreloginEncryption::Off => 0,
+            PreloginEncryption::On => 1,
+            PreloginEncryption::NotSupported => 2,
+            PreloginEncryption::Required => 3,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PreloginEncryption::Off => "login only",
+            PreloginEncryption::On => "on",
+            PreloginEncryption::NotSupported => "not supported",
+            PreloginEncryption::Required => "required",
+        }
+    }
+}
+
+/// What the server answered to prelogin
+#[derive(Clone, Debug)]
+pub struct PreloginReply {
+    /// Major, minor and build number of the server
+    pub version: (u8, u8, u16),
+    pub encryption: PreloginEncryption,
+}
+
+impl PreloginReply {
+    /// Marketing name of the major version, e.g. `SQL Server 2019`
+    pub fn product_name(&self) -> String {
+        let year = match self.version.0 {
+            8 => "2000",
+            9 => "2005",
+            10 => "2008",
+            11 => "2012",
+            12 => "2014",
+            13 => "2016",
+            14 => "2017",
+            15 => "2019",
+            16 => "2022",
+            17 => "2025",
+            _ => return format!("SQL Server {}.{}", self.version.0, self.version.1),
+        };
+        format!("SQL Server {}", year)
+    }
+
+    /// Whether a session comes out encrypted, or `None` when the two sides cannot agree
+    pub fn negotiate(&self, client: PreloginEncryption) -> Option<bool> {
+        use PreloginEncryption::*;
+        match (client, self.encryption) {
+            (NotSupported, Required) | (On | Required, NotSupported) => None,
+            (NotSupported, _) | (_, NotSupported) => Some(false),
+            (Off, Off) => Some(false),
+            _ => Some(true),
+        }
+    }
+}
+
+/// Send a prelogin packet on a fresh connection and read the server's reply
+pub async fn prelogin(tcp: &mut TcpStream, encryption: PreloginEncryption) -> Result<PreloginReply> {
+    tcp.write_all(&prelogin_packet(encryption)).await.context("cannot send prelogin")?;
+
+    let mut payload = Vec::new();
+    loop {
+        let mut header = [0u8; HEADER_LEN];
+        tcp.read_exact(&mut header).await.context("connection closed during prelogin")?;
+        if header[0] != PACKET_REPLY {
+            bail!("reply is not TDS (packet type {:#04x}); is this a SQL Server port?", header[0]);
+        }
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let mut body = vec![0u8; length.saturating_sub(HEADER_LEN)];
+        tcp.read_exact(&mut body).await.context("connection closed during prelogin")?;
+        payload.extend_from_slice(&body);
+        if header[1] & STATUS_END_OF_MESSAGE != 0 {
+            break;
+        }
+    }
+    parse_reply(&payload)
+}
+
+fn prelogin_packet(encryption: PreloginEncryption) -> Vec<u8> {
+    let options: [(u8, Vec<u8>); 5] = [
+        (OPTION_VERSION, vec![0; 6]),
+        (OPTION_ENCRYPTION, vec![encryption.byte()]),
+        (OPTION_INSTANCE, vec![0]),
+        (OPTION_THREAD_ID, vec![0; 4]),
+        (OPTION_MARS, vec![0]),
+    ];
+    let mut offset = options.len() * 5 + 1;
+    let mut table = Vec::new();
+    let mut data = Vec::new();
+    for (token, value) in &options {
+        table.push(*token);
+        table.extend_from_slice(&(offset as u16).to_be_bytes());
+        table.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        data.extend_from_slice(value);
+        offset += value.len();
+    }
+    table.push(OPTION_END);
+
+    let length = (HEADER_LEN + table.len() + data.len()) as u16;
+    let mut packet = vec![PACKET_PRELOGIN, STATUS_END_OF_MESSAGE];
+    packet.extend_from_slice(&length.to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 1, 0]);
+    packet.extend(table);
+    packet.extend(data);
+    packet
+}
+
+fn parse_reply(payload: &[u8]) -> Result<PreloginReply> {
+    let mut version = None;
+    let mut encryption = None;
+    let mut i = 0;
+    while let Some(&token) = payload.get(i) {
+        if token == OPTION_END {
+            break;
+        }
+        let entry = payload.get(i + 1..i + 5).context("truncated prelogin reply")?;
+        let offset = u16::from_be_bytes([entry[0], entry[1]]) as usize;
+        let length = u16::from_be_bytes([entry[2], entry[3]]) as usize;
+        let value = payload.get(offset..offset + length).context("truncated prelogin reply")?;
+        match token {
+            OPTION_VERSION if value.len() >= 4 => {
+                version = Some((value[0], value[1], u16::from_be_bytes([value[2], value[3]])));
+            }
+            OPTION_ENCRYPTION if !value.is_empty() => encryption = Some(PreloginEncryption::from_byte(value[0])?),
+            _ => {}
+        }
+        i += 5;
+    }
+    Ok(PreloginReply {
+        version: version.context("prelogin reply has no version")?,
+        encryption: encryption.context("prelogin reply has no encryption setting")?,
+    })
+}
@@ -2,13 +2,14 @@
 
 use anyhow::Result;
 use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
-use tiberius::{Client, Column, ColumnType, Row, numeric::Numeric};
+use tiberius::{Client, Column, ColumnType, QueryStream, Row, numeric::Numeric};
 use tokio::net::TcpStream;
 use tokio_util::compat::Compat;
 
 /// Represents a cell value in the result set
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum CellValue {
     Null,
     Bool(bool),
@@ -34,15 +35,22 @@ impl std::fmt::Display for CellValue {
 }
 
 /// Column metadata
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ColumnInfo {
     pub name: String,
     pub type_name: String,
     pub max_width: usize,
 }
 
+/// First row and columns of a further result set in a multi-statement batch
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ResultSetStart {
+    pub row: usize,
+    pub columns: Vec<ColumnInfo>,
+}
+
 /// Query result
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct QueryResult {
     pub columns: Vec<ColumnInfo>,
     pub rows: Vec<Vec<CellValue>>,
@@ -50,6 +58,9 @@ pub struct QueryResult {
     pub execution_time: Duration,
     pub affected_rows: Option<u64>,
     pub messages: Vec<String>,
+    /// Result sets after the first; the grid shows all rows together
+    #[serde(default)]
+    pub more_sets: Vec<ResultSetStart>,
 }
 
 impl QueryResult {
@@ -61,7 +72,38 @@ impl QueryResult {
             execution_time: Duration::ZERO,
             affected_rows: None,
             messages: Vec::new(),
+            more_sets: Vec::new(),
+        }
+    }
+
+    /// Columns and rows of each result set the batch returned
+    pub fn result_sets(&self) -> Vec<(&[ColumnInfo], &[Vec<CellValue>])> {
+        let mut sets = Vec::with_capacity(self.more_sets.len() + 1);
+        let mut columns = self.columns.as_slice();
+        let mut start = 0;
+        for next in &self.more_sets {
+            let end = next.row.clamp(start, self.rows.len());
+            sets.push((columns, &self.rows[start..end]));
+            columns = next.columns.as_slice();
+            start = end;
+        }
+        sets.push((columns, &self.rows[start..]));
+        sets
+    }
+
+    /// Row range and columns of the result set holding `row`
+    pub fn result_set_of(&self, row: usize) -> (std::ops::Range<usize>, &[ColumnInfo]) {
+        let mut columns = self.columns.as_slice();
+        let mut start = 0;
+        for next in &self.more_sets {
+            let end = next.row.clamp(start, self.rows.len());
+            if row < end {
+                return (start..end, columns);
+            }
+            columns = next.columns.as_slice();
+            start = end;
         }
+        (start..self.rows.len(), columns)
     }
 }
 
@@ -75,7 +117,16 @@ impl QueryExecutor {
         query: &str,
     ) -> Result<QueryResult> {
         let start = Instant::now();
+        let stream = Self::open_stream(client, query).await?;
+        Self::process_results(stream, start).await
+    }
 
+    /// Run a query and hand back the unread result stream, for callers that consume rows
+    /// as they arrive instead of collecting them (see `crate::export::stream_query`)
+    pub async fn open_stream<'a>(
+        client: &'a mut Client<Compat<TcpStream>>,
+        query: &str,
+    ) -> Result<QueryStream<'a>> {
         // For SELECT * queries, proactively check for DATE columns and rewrite
         let query_to_execute = if Self::is_select_star_query(query) {
             if let Some(fixed_query) = Self::try_fix_date_columns(client, query).await {
@@ -88,10 +139,8 @@ impl QueryExecutor {
         };
 
         // Execute the query
-        let result = client.simple_query(&query_to_execute).await;
-
-        match result {
-            Ok(stream) => Self::process_results(stream, start).await,
+        match client.simple_query(&query_to_execute).await {
+            Ok(stream) => Ok(stream),
             Err(e) => {
                 let err_str = e.to_string();
                 if err_str.contains("unsupported column type: 40") || err_str.contains("column type: 40") {
@@ -107,6 +156,27 @@ impl QueryExecutor {
         }
     }
 
+    /// Column metadata of a result set
+    pub fn column_info(columns: &[Column]) -> Vec<ColumnInfo> {
+        columns
+            .iter()
+            .map(|c| ColumnInfo {
+                name: c.name().to_string(),
+                type_name: format_column_type(c),
+                max_width: crate::utils::display_width(c.name()).max(4),
+            })
+            .collect()
+    }
+
+    /// Cell values of a row
+    pub fn row_values(row: &Row) -> Vec<CellValue> {
+        row.columns()
+            .iter()
+            .enumerate()
+            .map(|(i, col)| extract_cell_value(row, i, col))
+            .collect()
+    }
+
     /// Check if query is a SELECT * query
     fn is_select_star_query(query: &str) -> bool {
         let query_upper = query.to_uppercase();
@@ -118,34 +188,34 @@ impl QueryExecutor {
 
     /// Process query results from a stream
     async fn process_results(
-        stream: tiberius::QueryStream<'_>,
+        stream: QueryStream<'_>,
         start: Instant,
     ) -> Result<QueryResult> {
         let mut columns: Vec<ColumnInfo> = Vec::new();
         let mut rows: Vec<Vec<CellValue>> = Vec::new();
+        let mut more_sets: Vec<ResultSetStart> = Vec::new();
 
         // Process results
         let results = stream.into_results().await?;
 
         for result in results {
+            // Remember where each later result set starts, with its own columns
+            if let Some(first) = result.first().filter(|_| !rows.is_empty()) {
+                more_sets.push(ResultSetStart {
+                    row: rows.len(),
+                    columns: Self::column_info(first.columns()),
+                });
+            }
             for row in result {
                 if columns.is_empty() {
-                    columns = row
-                        .columns()
-                        .iter()
-                        .map(|c| ColumnInfo {
-                            name: c.name().to_string(),
-                            type_name: format_column_type(c),
-                            max_width: c.name().len().max(4),
-                        })
-                        .collect();
+                    columns = Self::column_info(row.columns());
                 }
 
                 let mut row_data: Vec<CellValue> = Vec::new();
 
                 for (i, col) in row.columns().iter().enumerate() {
                     let value = extract_cell_value(&row, i, col);
-                    let value_len = value.to_string().len();
+                    let value_len = crate::utils::display_width(&value.to_string());
 
                     if i < columns.len() {
                         columns[i].max_width = columns[i].max_width.max(value_len);
@@ -167,6 +237,7 @@ impl QueryExecutor {
             execution_time,
             affected_rows: None,
             messages: Vec::new(),
+            more_sets,
         })
     }
 
@@ -223,7 +294,7 @@ impl QueryExecutor {
     }
 
     /// Extract table name from a SELECT query
-    fn extract_table_name(query: &str) -> Option<String> {
+    pub(crate) fn extract_table_name(query: &str) -> Option<String> {
         let query_upper = query.to_uppercase();
 
         // Find FROM clause
@@ -279,7 +350,7 @@ impl QueryExecutor {
     }
 
     /// Parse table name into schema and table parts
-    fn parse_table_name(table_name: &str) -> (Option<String>, String) {
+    pub(crate) fn parse_table_name(table_name: &str) -> (Option<String>, String) {
         // Remove brackets and parse
         let clean = table_name.replace(['[', ']'], "");
         let parts: Vec<&str> = clean.split('.').collect();
@@ -320,7 +391,7 @@ impl QueryExecutor {
         let mut column_defs = Vec::new();
         for result in results {
             for row in result {
-                if let (Some(col_name), Some(data_type)) = (
+                if let (Some(col_name), Some(_data_type)) = (
                     row.get::<&str, _>(0),
                     row.get::<&str, _>(1),
                 ) {
@@ -51,6 +51,8 @@ pub struct ColumnDef {
     pub max_length: Option<i32>,
     pub precision: Option<i32>,
     pub scale: Option<i32>,
+    pub is_identity: bool,
+    pub is_computed: bool,
 }
 
 /// Table definition
@@ -62,6 +64,34 @@ pub struct TableDef {
     pub row_count: Option<i64>,
 }
 
+/// PRIMARY KEY / UNIQUE constraint collected while scripting DDL
+struct KeyConstraintScript {
+    name: String,
+    is_primary_key: bool,
+    index_type: String,
+    columns: Vec<String>,
+}
+
+/// FOREIGN KEY constraint collected while scripting DDL
+struct ForeignKeyScript {
+    name: String,
+    columns: Vec<String>,
+    ref_table: String,
+    ref_columns: Vec<String>,
+    on_delete: String,
+    on_update: String,
+}
+
+/// Index collected while scripting DDL
+struct IndexScript {
+    name: String,
+    is_unique: bool,
+    index_type: String,
+    filter: Option<String>,
+    key_columns: Vec<String>,
+    included_columns: Vec<String>,
+}
+
 /// Schema explorer
 pub struct SchemaExplorer;
 
@@ -198,7 +228,9 @@ impl SchemaExplorer {
                 ISNULL(pk.is_primary_key, 0) as is_primary_key,
                 c.max_length,
                 c.precision,
-                c.scale
+                c.scale,
+                c.is_identity,
+                c.is_computed
              FROM sys.columns c
              INNER JOIN sys.types t ON c.user_type_id = t.user_type_id
              INNER JOIN sys.tables tbl ON c.object_id = tbl.object_id
@@ -228,6 +260,8 @@ impl SchemaExplorer {
                     max_length: row.get::<i16, _>(4).map(|v| v as i32),
                     precision: row.get::<u8, _>(5).map(|v| v as i32),
                     scale: row.get::<u8, _>(6).map(|v| v as i32),
+                    is_identity: row.get::<bool, _>(7).unwrap_or(false),
+                    is_computed: row.get::<bool, _>(8).unwrap_or(false),
                 });
             }
         }
@@ -297,43 +331,443 @@ impl SchemaExplorer {
     }
 
     /// Get table DDL
+    ///
+    /// Scripts the table the way SSMS does: columns with full type precision,
+    /// identity, defaults and computed expressions, followed by key, check and
+    /// foreign key constraints, indexes and extended properties.
     pub async fn get_table_ddl(
         client: &mut Client<Compat<TcpStream>>,
         schema: &str,
         table: &str,
     ) -> Result<String> {
-        let columns = Self::get_columns(client, schema, table).await?;
+        let table_name = format!("{}.{}", quote_ident(schema), quote_ident(table));
+        let filter = format!(
+            "s.name = N'{}' AND t.name = N'{}'",
+            escape_literal(schema),
+            escape_literal(table)
+        );
+
+        let mut body = Self::script_columns(client, &filter).await?;
+        if body.is_empty() {
+            anyhow::bail!("Table {} not found", table_name);
+        }
+        body.extend(Self::script_key_constraints(client, &filter).await?);
+        body.extend(Self::script_check_constraints(client, &filter).await?);
+
+        let mut ddl = format!("CREATE TABLE {} (\n", table_name);
+        ddl.push_str(&body.join(",\n"));
+        ddl.push_str("\n);\nGO\n");
 
-        let mut ddl = format!("CREATE TABLE [{}].[{}] (\n", schema, table);
+        let foreign_keys = Self::script_foreign_keys(client, &filter, &table_name).await?;
+        let indexes = Self::script_indexes(client, &filter, &table_name).await?;
+        let properties = Self::script_extended_properties(client, &filter, schema, table).await?;
 
-        for (i, col) in columns.iter().enumerate() {
-            let type_str = if col.data_type == "varchar" || col.data_type == "nvarchar" {
-                if col.max_length == Some(-1) {
-                    format!("{}(MAX)", col.data_type.to_uppercase())
+        for section in [foreign_keys, indexes, properties] {
+            if section.is_empty() {
+                continue;
+            }
+            ddl.push('\n');
+            for statement in section {
+                ddl.push_str(&statement);
+                ddl.push_str("\nGO\n");
+            }
+        }
+
+        Ok(ddl.trim_end().to_string())
+    }
+
+    /// Script column definitions for CREATE TABLE
+    async fn script_columns(
+        client: &mut Client<Compat<TcpStream>>,
+        filter: &str,
+    ) -> Result<Vec<String>> {
+        let query = format!(
+            "SELECT
+                c.name,
+                ty.name,
+                CAST(c.max_length AS INT),
+                CAST(c.precision AS INT),
+                CAST(c.scale AS INT),
+                c.is_nullable,
+                c.is_identity,
+                CAST(ic.seed_value AS NVARCHAR(40)),
+                CAST(ic.increment_value AS NVARCHAR(40)),
+                dc.name,
+                dc.definition,
+                cc.definition,
+                ISNULL(cc.is_persisted, 0),
+                ty.is_user_defined
+             FROM sys.columns c
+             INNER JOIN sys.types ty ON c.user_type_id = ty.user_type_id
+             INNER JOIN sys.tables t ON c.object_id = t.object_id
+             INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+             LEFT JOIN sys.identity_columns ic ON c.object_id = ic.object_id AND c.column_id = ic.column_id
+             LEFT JOIN sys.default_constraints dc ON c.default_object_id = dc.object_id
+             LEFT JOIN sys.computed_columns cc ON c.object_id = cc.object_id AND c.column_id = cc.column_id
+             WHERE {}
+             ORDER BY c.column_id",
+            filter
+        );
+
+        let stream = client.simple_query(&query).await?;
+        let results = stream.into_results().await?;
+
+        let mut lines = Vec::new();
+        for result in results {
+            for row in result {
+                let name = quote_ident(row.get::<&str, _>(0).unwrap_or(""));
+
+                // Computed columns carry no type, only their expression
+                if let Some(definition) = row.get::<&str, _>(11) {
+                    let persisted = if row.get::<bool, _>(12).unwrap_or(false) { " PERSISTED" } else { "" };
+                    lines.push(format!("    {} AS {}{}", name, definition, persisted));
+                    continue;
+                }
+
+                let type_name = row.get::<&str, _>(1).unwrap_or("");
+                let mut line = if row.get::<bool, _>(13).unwrap_or(false) {
+                    format!("    {} {}", name, quote_ident(type_name))
                 } else {
-                    format!("{}({})", col.data_type.to_uppercase(), col.max_length.unwrap_or(0))
+                    format!(
+                        "    {} {}",
+                        name,
+                        format_data_type(
+                            type_name,
+                            row.get::<i32, _>(2).unwrap_or(0),
+                            row.get::<i32, _>(3).unwrap_or(0),
+                            row.get::<i32, _>(4).unwrap_or(0),
+                        )
+                    )
+                };
+
+                if row.get::<bool, _>(6).unwrap_or(false) {
+                    line.push_str(&format!(
+                        " IDENTITY({}, {})",
+                        row.get::<&str, _>(7).unwrap_or("1"),
+                        row.get::<&str, _>(8).unwrap_or("1")
+                    ));
                 }
-            } else if col.data_type == "decimal" || col.data_type == "numeric" {
+
+                if let Some(default) = row.get::<&str, _>(10) {
+                    match row.get::<&str, _>(9) {
+                        Some(constraint) => line.push_str(&format!(
+                            " CONSTRAINT {} DEFAULT {}",
+                            quote_ident(constraint),
+                            default
+                        )),
+                        None => line.push_str(&format!(" DEFAULT {}", default)),
+                    }
+                }
+
+                line.push_str(if row.get::<bool, _>(5).unwrap_or(true) { " NULL" } else { " NOT NULL" });
+                lines.push(line);
+            }
+        }
+
+        Ok(lines)
+    }
+
+    /// Script PRIMARY KEY and UNIQUE constraints (composite keys included)
+    async fn script_key_constraints(
+        client: &mut Client<Compat<TcpStream>>,
+        filter: &str,
+    ) -> Result<Vec<String>> {
+        let query = format!(
+            "SELECT
+                kc.name,
+                CAST(kc.type AS NVARCHAR(2)),
+                i.type_desc,
+                c.name,
+                ic.is_descending_key
+             FROM sys.key_constraints kc
+             INNER JOIN sys.tables t ON kc.parent_object_id = t.object_id
+             INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+             INNER JOIN sys.indexes i ON i.object_id = t.object_id AND i.index_id = kc.unique_index_id
+             INNER JOIN sys.index_columns ic ON ic.object_id = i.object_id AND ic.index_id = i.index_id
+             INNER JOIN sys.columns c ON c.object_id = ic.object_id AND c.column_id = ic.column_id
+             WHERE {}
+             ORDER BY kc.type, kc.name, ic.key_ordinal",
+            filter
+        );
+
+        let stream = client.simple_query(&query).await?;
+        let results = stream.into_results().await?;
+
+        let mut constraints: Vec<KeyConstraintScript> = Vec::new();
+        for result in results {
+            for row in result {
+                let name = row.get::<&str, _>(0).unwrap_or("").to_string();
+                let column = format!(
+                    "{} {}",
+                    quote_ident(row.get::<&str, _>(3).unwrap_or("")),
+                    if row.get::<bool, _>(4).unwrap_or(false) { "DESC" } else { "ASC" }
+                );
+
+                match constraints.last_mut() {
+                    Some(last) if last.name == name => last.columns.push(column),
+                    _ => constraints.push(KeyConstraintScript {
+                        name,
+                        is_primary_key: row.get::<&str, _>(1).unwrap_or("") == "PK",
+                        index_type: row.get::<&str, _>(2).unwrap_or("NONCLUSTERED").to_string(),
+                        columns: vec![column],
+                    }),
+                }
+            }
+        }
+
+        Ok(constraints
+            .into_iter()
+            .map(|key| {
                 format!(
-                    "{}({}, {})",
-                    col.data_type.to_uppercase(),
-                    col.precision.unwrap_or(18),
-                    col.scale.unwrap_or(0)
+                    "    CONSTRAINT {} {} {} ({})",
+                    quote_ident(&key.name),
+                    if key.is_primary_key { "PRIMARY KEY" } else { "UNIQUE" },
+                    key.index_type,
+                    key.columns.join(", ")
                 )
-            } else {
-                col.data_type.to_uppercase()
-            };
+            })
+            .collect())
+    }
+
+    /// Script CHECK constraints
+    async fn script_check_constraints(
+        client: &mut Client<Compat<TcpStream>>,
+        filter: &str,
+    ) -> Result<Vec<String>> {
+        let query = format!(
+            "SELECT cc.name, cc.definition
+             FROM sys.check_constraints cc
+             INNER JOIN sys.tables t ON cc.parent_object_id = t.object_id
+             INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+             WHERE {}
+             ORDER BY cc.name",
+            filter
+        );
+
+        let stream = client.simple_query(&query).await?;
+        let results = stream.into_results().await?;
+
+        let mut checks = Vec::new();
+        for result in results {
+            for row in result {
+                checks.push(format!(
+                    "    CONSTRAINT {} CHECK {}",
+                    quote_ident(row.get::<&str, _>(0).unwrap_or("")),
+                    row.get::<&str, _>(1).unwrap_or("")
+                ));
+            }
+        }
 
-            let nullable = if col.is_nullable { "NULL" } else { "NOT NULL" };
-            let pk = if col.is_primary_key { " PRIMARY KEY" } else { "" };
-            let comma = if i < columns.len() - 1 { "," } else { "" };
+        Ok(checks)
+    }
 
-            ddl.push_str(&format!("    [{}] {} {}{}{}\n", col.name, type_str, nullable, pk, comma));
+    /// Script FOREIGN KEY constraints as ALTER TABLE statements
+    async fn script_foreign_keys(
+        client: &mut Client<Compat<TcpStream>>,
+        filter: &str,
+        table_name: &str,
+    ) -> Result<Vec<String>> {
+        let query = format!(
+            "SELECT
+                fk.name,
+                pc.name,
+                rs.name,
+                rt.name,
+                rc.name,
+                fk.delete_referential_action_desc,
+                fk.update_referential_action_desc
+             FROM sys.foreign_keys fk
+             INNER JOIN sys.foreign_key_columns fkc ON fkc.constraint_object_id = fk.object_id
+             INNER JOIN sys.tables t ON fk.parent_object_id = t.object_id
+             INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+             INNER JOIN sys.columns pc ON pc.object_id = fkc.parent_object_id AND pc.column_id = fkc.parent_column_id
+             INNER JOIN sys.tables rt ON rt.object_id = fkc.referenced_object_id
+             INNER JOIN sys.schemas rs ON rs.schema_id = rt.schema_id
+             INNER JOIN sys.columns rc ON rc.object_id = fkc.referenced_object_id AND rc.column_id = fkc.referenced_column_id
+             WHERE {}
+             ORDER BY fk.name, fkc.constraint_column_id",
+            filter
+        );
+
+        let stream = client.simple_query(&query).await?;
+        let results = stream.into_results().await?;
+
+        let mut keys: Vec<ForeignKeyScript> = Vec::new();
+        for result in results {
+            for row in result {
+                let name = row.get::<&str, _>(0).unwrap_or("").to_string();
+                let column = quote_ident(row.get::<&str, _>(1).unwrap_or(""));
+                let ref_column = quote_ident(row.get::<&str, _>(4).unwrap_or(""));
+
+                match keys.last_mut() {
+                    Some(last) if last.name == name => {
+                        last.columns.push(column);
+                        last.ref_columns.push(ref_column);
+                    }
+                    _ => keys.push(ForeignKeyScript {
+                        name,
+                        columns: vec![column],
+                        ref_table: format!(
+                            "{}.{}",
+                            quote_ident(row.get::<&str, _>(2).unwrap_or("dbo")),
+                            quote_ident(row.get::<&str, _>(3).unwrap_or(""))
+                        ),
+                        ref_columns: vec![ref_column],
+                        on_delete: row.get::<&str, _>(5).unwrap_or("NO_ACTION").to_string(),
+                        on_update: row.get::<&str, _>(6).unwrap_or("NO_ACTION").to_string(),
+                    }),
+                }
+            }
         }
 
-        ddl.push_str(");");
+        Ok(keys
+            .into_iter()
+            .map(|key| {
+                let mut statement = format!(
+                    "ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({})\n    REFERENCES {} ({})",
+                    table_name,
+                    quote_ident(&key.name),
+                    key.columns.join(", "),
+                    key.ref_table,
+                    key.ref_columns.join(", ")
+                );
+                if key.on_delete != "NO_ACTION" {
+                    statement.push_str(&format!(" ON DELETE {}", key.on_delete.replace('_', " ")));
+                }
+                if key.on_update != "NO_ACTION" {
+                    statement.push_str(&format!(" ON UPDATE {}", key.on_update.replace('_', " ")));
+                }
+                statement.push(';');
+                statement
+            })
+            .collect())
+    }
+
+    /// Script indexes that are not backing a PRIMARY KEY or UNIQUE constraint
+    async fn script_indexes(
+        client: &mut Client<Compat<TcpStream>>,
+        filter: &str,
+        table_name: &str,
+    ) -> Result<Vec<String>> {
+        let query = format!(
+            "SELECT
+                i.name,
+                i.is_unique,
+                i.type_desc,
+                i.filter_definition,
+                c.name,
+                ic.is_descending_key,
+                ic.is_included_column
+             FROM sys.indexes i
+             INNER JOIN sys.tables t ON i.object_id = t.object_id
+             INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+             INNER JOIN sys.index_columns ic ON ic.object_id = i.object_id AND ic.index_id = i.index_id
+             INNER JOIN sys.columns c ON c.object_id = ic.object_id AND c.column_id = ic.column_id
+             WHERE {} AND i.type IN (1, 2) AND i.is_primary_key = 0 AND i.is_unique_constraint = 0
+             ORDER BY i.index_id, ic.is_included_column, ic.key_ordinal, ic.index_column_id",
+            filter
+        );
 
-        Ok(ddl)
+        let stream = client.simple_query(&query).await?;
+        let results = stream.into_results().await?;
+
+        let mut indexes: Vec<IndexScript> = Vec::new();
+        for result in results {
+            for row in result {
+                let name = row.get::<&str, _>(0).unwrap_or("").to_string();
+                let column_name = quote_ident(row.get::<&str, _>(4).unwrap_or(""));
+                let included = row.get::<bool, _>(6).unwrap_or(false);
+
+                if indexes.last().map(|last| last.name != name).unwrap_or(true) {
+                    indexes.push(IndexScript {
+                        name,
+                        is_unique: row.get::<bool, _>(1).unwrap_or(false),
+                        index_type: row.get::<&str, _>(2).unwrap_or("NONCLUSTERED").to_string(),
+                        filter: row.get::<&str, _>(3).map(|f| f.to_string()),
+                        key_columns: Vec::new(),
+                        included_columns: Vec::new(),
+                    });
+                }
+
+                if let Some(last) = indexes.last_mut() {
+                    if included {
+                        last.included_columns.push(column_name);
+                    } else {
+                        let direction = if row.get::<bool, _>(5).unwrap_or(false) { "DESC" } else { "ASC" };
+                        last.key_columns.push(format!("{} {}", column_name, direction));
+                    }
+                }
+            }
+        }
+
+        Ok(indexes
+            .into_iter()
+            .map(|index| {
+                let mut statement = format!(
+                    "CREATE {}{} INDEX {} ON {} ({})",
+                    if index.is_unique { "UNIQUE " } else { "" },
+                    index.index_type,
+                    quote_ident(&index.name),
+                    table_name,
+                    index.key_columns.join(", ")
+                );
+                if !index.included_columns.is_empty() {
+                    statement.push_str(&format!("\n    INCLUDE ({})", index.included_columns.join(", ")));
+                }
+                if let Some(filter) = index.filter {
+                    statement.push_str(&format!("\n    WHERE {}", filter));
+                }
+                statement.push(';');
+                statement
+            })
+            .collect())
+    }
+
+    /// Script extended properties on the table and its columns
+    async fn script_extended_properties(
+        client: &mut Client<Compat<TcpStream>>,
+        filter: &str,
+        schema: &str,
+        table: &str,
+    ) -> Result<Vec<String>> {
+        let query = format!(
+            "SELECT ep.name, CAST(ep.value AS NVARCHAR(MAX)), c.name
+             FROM sys.extended_properties ep
+             INNER JOIN sys.tables t ON ep.major_id = t.object_id
+             INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+             LEFT JOIN sys.columns c ON c.object_id = ep.major_id AND c.column_id = ep.minor_id
+             WHERE ep.class = 1 AND {}
+             ORDER BY ep.minor_id, ep.name",
+            filter
+        );
+
+        let stream = client.simple_query(&query).await?;
+        let results = stream.into_results().await?;
+
+        let mut properties = Vec::new();
+        for result in results {
+            for row in result {
+                let mut statement = format!(
+                    "EXEC sys.sp_addextendedproperty @name = N'{}', @value = N'{}',\n    \
+                     @level0type = N'SCHEMA', @level0name = N'{}',\n    \
+                     @level1type = N'TABLE', @level1name = N'{}'",
+                    escape_literal(row.get::<&str, _>(0).unwrap_or("")),
+                    escape_literal(row.get::<&str, _>(1).unwrap_or("")),
+                    escape_literal(schema),
+                    escape_literal(table)
+                );
+                if let Some(column) = row.get::<&str, _>(2) {
+                    statement.push_str(&format!(
+                        ",\n    @level2type = N'COLUMN', @level2name = N'{}'",
+                        escape_literal(column)
+                    ));
+                }
+                statement.push(';');
+                properties.push(statement);
+            }
+        }
+
+        Ok(properties)
     }
 
     /// Search for objects by name
@@ -378,3 +812,41 @@ impl SchemaExplorer {
         Ok(objects)
     }
 }
+
+/// Quote an identifier with brackets, escaping any closing bracket
+pub fn quote_ident(name: &str) -> String {
+    format!("[{}]", name.replace(']', "]]"))
+}
+
+/// Escape a value for use inside a single-quoted SQL literal
+fn escape_literal(value: &str) -> String {
+    value.replace('\'', "''")
+}
+
+/// Format a system data type with its length, precision or scale
+///
+/// `max_length` is the byte length reported by `sys.columns`, so Unicode
+/// types are halved and `-1` becomes `MAX`.
+pub fn format_data_type(type_name: &str, max_length: i32, precision: i32, scale: i32) -> String {
+    let upper = type_name.to_uppercase();
+    match upper.as_str() {
+        "VARCHAR" | "CHAR" | "VARBINARY" | "BINARY" => {
+            if max_length == -1 {
+                format!("{}(MAX)", upper)
+            } else {
+                format!("{}({})", upper, max_length)
+            }
+        }
+        "NVARCHAR" | "NCHAR" => {
+            if max_length == -1 {
+                format!("{}(MAX)", upper)
+            } else {
+                format!("{}({})", upper, max_length / 2)
+            }
+        }
+        "DECIMAL" | "NUMERIC" => format!("{}({}, {})", upper, precision, scale),
+        "DATETIME2" | "DATETIMEOFFSET" | "TIME" => format!("{}({})", upper, scale),
+        "FLOAT" if precision != 53 => format!("FLOAT({})", precision),
+        _ => upper,
+    }
+}
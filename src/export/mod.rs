@@ -0,0 +1,38 @@
+//! Writing result sets to files
+
+mod options;
+mod parquet;
+mod script;
+mod stream;
+mod writer;
+mod xlsx;
+mod zip;
+
+pub use options::*;
+pub use parquet::*;
+pub use script::*;
+pub use stream::*;
+pub use writer::*;
+pub use xlsx::*;
+
+use crate::db::QueryResult;
+use anyhow::Result;
+use std::path::Path;
+
+/// Write every result set of `result` to `path` in the chosen format; returns bytes written
+pub fn export_result(result: &QueryResult, path: &Path, options: &ExportOptions, default_table: Option<&str>) -> Result<u64> {
+    if options.format == ExportFormat::Xlsx {
+        write_xlsx(path, &result_sheets(result))?;
+        return Ok(std::fs::metadata(path).map(|m| m.len()).unwrap_or(0));
+    }
+    let mut writer = create_writer(path, options, default_table)?;
+    for (columns, rows) in result.result_sets() {
+        writer.begin(columns)?;
+        for row in rows {
+            writer.write_row(row)?;
+        }
+    }
+    writer.finish()?;
+    Ok(writer.bytes_written())
+}
+
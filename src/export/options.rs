@@ -0,0 +1,206 @@
+//! Export format and options, remembered between sessions
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// File format of an export
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Tsv,
+    Json,
+    JsonLines,
+    Markdown,
+    Html,
+    Sql,
+    Parquet,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 9] = [
+        ExportFormat::Csv,
+        ExportFormat::Tsv,
+        ExportFormat::Json,
+        ExportFormat::JsonLines,
+        ExportFormat::Markdown,
+        ExportFormat::Html,
+        ExportFormat::Sql,
+        ExportFormat::Parquet,
+        ExportFormat::Xlsx,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Tsv => "TSV",
+            ExportFormat::Json => "JSON",
+            ExportFormat::JsonLines => "JSON Lines",
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Html => "HTML",
+            ExportFormat::Sql => "SQL INSERT",
+            ExportFormat::Parquet => "Parquet",
+            ExportFormat::Xlsx => "Excel (.xlsx)",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Json => "json",
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Sql => "sql",
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+
+    /// Format named by a file extension or a `:export` argument
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "tsv" | "tab" => Some(ExportFormat::Tsv),
+            "json" => Some(ExportFormat::Json),
+            "jsonl" | "ndjson" | "jsonlines" => Some(ExportFormat::JsonLines),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            "html" | "htm" => Some(ExportFormat::Html),
+            "sql" | "insert" => Some(ExportFormat::Sql),
+            "parquet" => Some(ExportFormat::Parquet),
+            "xlsx" | "excel" => Some(ExportFormat::Xlsx),
+            _ => None,
+        }
+    }
+
+    /// Plain-text formats; the others are binary and ignore encoding and BOM
+    pub fn is_text(&self) -> bool {
+        !matches!(self, ExportFormat::Parquet | ExportFormat::Xlsx)
+    }
+
+    /// Delimited formats, where delimiter and quote apply
+    pub fn is_delimited(&self) -> bool {
+        matches!(self, ExportFormat::Csv | ExportFormat::Tsv)
+    }
+
+    /// Formats that write rows as they arrive, without holding the result in memory
+    pub fn is_streaming(&self) -> bool {
+        !matches!(self, ExportFormat::Xlsx)
+    }
+}
+
+/// Character encoding of text exports
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    /// Arabic Windows code page
+    Windows1256,
+    /// Western European Windows code page
+    Windows1252,
+}
+
+impl TextEncoding {
+    pub const ALL: [TextEncoding; 4] = [
+        TextEncoding::Utf8,
+        TextEncoding::Utf16Le,
+        TextEncoding::Windows1256,
+        TextEncoding::Windows1252,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Utf16Le => "UTF-16 LE",
+            TextEncoding::Windows1256 => "Windows-1256 (Arabic)",
+            TextEncoding::Windows1252 => "Windows-1252",
+        }
+    }
+
+    /// Byte order mark, for encodings that have one
+    pub fn bom(&self) -> &'static [u8] {
+        match self {
+            TextEncoding::Utf8 => &[0xEF, 0xBB, 0xBF],
+            TextEncoding::Utf16Le => &[0xFF, 0xFE],
+            _ => &[],
+        }
+    }
+}
+
+/// Everything the export dialog asks for
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Folder of the last export; new file names start here
+    pub dir: Option<PathBuf>,
+    pub delimiter: char,
+    /// Quote character for delimited formats; `None` never quotes
+    pub quote: Option<char>,
+    pub encoding: TextEncoding,
+    /// Write a byte order mark (Excel needs one to read UTF-8 CSV)
+    pub bom: bool,
+    /// Text written for NULL in CSV, TSV, Markdown and HTML
+    pub null_text: String,
+    pub headers: bool,
+    /// Target table of SQL INSERT exports; empty uses the query's table
+    pub table: String,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Csv,
+            dir: None,
+            delimiter: ',',
+            quote: Some('"'),
+            encoding: TextEncoding::Utf8,
+            bom: false,
+            null_text: String::new(),
+            headers: true,
+            table: String::new(),
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Last-used options, or the defaults
+    pub fn load() -> Self {
+        fs::read_to_string(Self::options_file())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Remember these options for the next export
+    pub fn save(&self) -> Result<()> {
+        let path = Self::options_file();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn options_file() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("alrajhi-sql-tui")
+            .join("export.json")
+    }
+
+    /// Switch format, moving between the comma and tab delimiters with CSV and TSV
+    pub fn set_format(&mut self, format: ExportFormat) {
+        match format {
+            ExportFormat::Tsv if self.delimiter == ',' => self.delimiter = '\t',
+            ExportFormat::Csv if self.delimiter == '\t' => self.delimiter = ',',
+            _ => {}
+        }
+        self.format = format;
+    }
+}
@@ -0,0 +1,549 @@
+//! Apache Parquet writer: nullable columns, PLAIN encoding, no compression
+
+use crate::db::{CellValue, ColumnInfo};
+use crate::export::{plain_text, RowWriter};
+use anyhow::{bail, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Rows buffered before a row group is written
+const ROW_GROUP_ROWS: usize = 100_000;
+
+/// Buffered value bytes before a row group is written early
+const ROW_GROUP_BYTES: usize = 64 * 1024 * 1024;
+
+const MAGIC: &[u8] = b"PAR1";
+
+/// Parquet physical type and converted type of a column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ColumnKind {
+    Boolean,
+    Int64,
+    Double,
+    /// INT32 days since 1970-01-01
+    Date,
+    /// INT64 milliseconds since 1970-01-01
+    Timestamp,
+    Utf8,
+    Binary,
+}
+
+impl ColumnKind {
+    /// Kind for a SQL Server type name; DECIMAL stays text so no digits are lost
+    fn of(type_name: &str) -> Self {
+        match type_name {
+            "BIT" => ColumnKind::Boolean,
+            "TINYINT" | "SMALLINT" | "INT" | "BIGINT" => ColumnKind::Int64,
+            "REAL" | "FLOAT" | "MONEY" | "SMALLMONEY" => ColumnKind::Double,
+            "DATE" => ColumnKind::Date,
+            "DATETIME" | "DATETIME2" | "SMALLDATETIME" => ColumnKind::Timestamp,
+            "BINARY" | "VARBINARY(MAX)" | "IMAGE" => ColumnKind::Binary,
+            _ => ColumnKind::Utf8,
+        }
+    }
+
+    /// Physical type: BOOLEAN 0, INT32 1, INT64 2, DOUBLE 5, BYTE_ARRAY 6
+    fn physical(&self) -> i32 {
+        match self {
+            ColumnKind::Boolean => 0,
+            ColumnKind::Date => 1,
+            ColumnKind::Int64 | ColumnKind::Timestamp => 2,
+            ColumnKind::Double => 5,
+            ColumnKind::Utf8 | ColumnKind::Binary => 6,
+        }
+    }
+
+    /// Converted type: UTF8 0, DATE 6, TIMESTAMP_MILLIS 9
+    fn converted(&self) -> Option<i32> {
+        match self {
+            ColumnKind::Utf8 => Some(0),
+            ColumnKind::Date => Some(6),
+            ColumnKind::Timestamp => Some(9),
+            _ => None,
+        }
+    }
+}
+
+/// Values of one column in the current row group
+struct ColumnBuffer {
+    name: String,
+    kind: ColumnKind,
+    /// Definition level of each row: false for NULL
+    defined: Vec<bool>,
+    /// PLAIN-encoded non-null values (booleans are bit-packed at write time)
+    values: Vec<u8>,
+    bools: Vec<bool>,
+}
+
+impl ColumnBuffer {
+    fn push(&mut self, cell: &CellValue) {
+        let written = match self.kind {
+            ColumnKind::Boolean => match cell {
+                CellValue::Bool(v) => Some(*v),
+                CellValue::Int(v) => Some(*v != 0),
+                _ => None,
+            }
+            .map(|v| self.bools.push(v)),
+            ColumnKind::Int64 => match cell {
+                CellValue::Int(v) => Some(*v),
+                CellValue::Bool(v) => Some(*v as i64),
+                CellValue::Float(v) if v.fract() == 0.0 => Some(*v as i64),
+                CellValue::String(v) => v.trim().parse().ok(),
+                _ => None,
+            }
+            .map(|v| self.values.extend_from_slice(&v.to_le_bytes())),
+            ColumnKind::Double => match cell {
+                CellValue::Float(v) => Some(*v),
+                CellValue::Int(v) => Some(*v as f64),
+                CellValue::String(v) => v.trim().parse().ok(),
+                _ => None,
+            }
+            .map(|v| self.values.extend_from_slice(&v.to_le_bytes())),
+            ColumnKind::Date => match cell {
+                CellValue::DateTime(v) => parse_date(v),
+                _ => None,
+            }
+            .map(|v| self.values.extend_from_slice(&v.to_le_bytes())),
+            ColumnKind::Timestamp => match cell {
+                CellValue::DateTime(v) => NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S%.f")
+                    .ok()
+                    .map(|dt| dt.and_utc().timestamp_millis()),
+                _ => None,
+            }
+            .map(|v| self.values.extend_from_slice(&v.to_le_bytes())),
+            ColumnKind::Utf8 | ColumnKind::Binary => match cell {
+                CellValue::Null => None,
+                CellValue::Binary(v) if self.kind == ColumnKind::Binary => Some(v.clone()),
+                cell => Some(plain_text(cell).into_bytes()),
+            }
+            .map(|bytes| {
+                self.values.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                self.values.extend_from_slice(&bytes);
+            }),
+        };
+        self.defined.push(written.is_some());
+    }
+
+    fn clear(&mut self) {
+        self.defined.clear();
+        self.values.clear();
+        self.bools.clear();
+    }
+
+    /// Data page body: length-prefixed definition levels, then the values
+    fn page(&self) -> Vec<u8> {
+        let levels = rle_levels(&self.defined);
+        let mut page = Vec::with_capacity(4 + levels.len() + self.values.len() + self.bools.len() / 8 + 1);
+        page.extend_from_slice(&(levels.len() as u32).to_le_bytes());
+        page.extend_from_slice(&levels);
+        if self.kind == ColumnKind::Boolean {
+            let mut packed = vec![0u8; self.bools.len().div_ceil(8)];
+            for (i, _) in self.bools.iter().enumerate().filter(|(_, v)| **v) {
+                packed[i / 8] |= 1 << (i % 8);
+            }
+            page.extend_from_slice(&packed);
+        } else {
+            page.extend_from_slice(&self.values);
+        }
+        page
+    }
+}
+
+/// Days since 1970-01-01 of a `YYYY-MM-DD` date
+fn parse_date(text: &str) -> Option<i32> {
+    let date = NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()?;
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+    i32::try_from((date - epoch).num_days()).ok()
+}
+
+/// Bit width 1 definition levels as RLE runs of the RLE/bit-packing hybrid
+fn rle_levels(defined: &[bool]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < defined.len() {
+        let value = defined[i];
+        let run = defined[i..].iter().take_while(|v| **v == value).count();
+        put_varint(&mut out, (run as u64) << 1);
+        out.push(value as u8);
+        i += run;
+    }
+    out
+}
+
+/// Metadata of a written column chunk
+struct ChunkMeta {
+    offset: u64,
+    size: u64,
+    values: usize,
+}
+
+/// Writes row groups as rows arrive and the file footer at the end
+pub struct ParquetWriter {
+    out: BufWriter<File>,
+    written: u64,
+    columns: Vec<ColumnBuffer>,
+    begun: bool,
+    /// Rows in the buffered row group
+    rows: usize,
+    total_rows: usize,
+    /// Written row groups: row count and column chunks
+    row_groups: Vec<(usize, Vec<ChunkMeta>)>,
+}
+
+impl ParquetWriter {
+    pub fn new(mut out: BufWriter<File>) -> Result<Self> {
+        out.write_all(MAGIC)?;
+        Ok(Self {
+            out,
+            written: MAGIC.len() as u64,
+            columns: Vec::new(),
+            begun: false,
+            rows: 0,
+            total_rows: 0,
+            row_groups: Vec::new(),
+        })
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.out.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Write the buffered rows as a row group with one data page per column
+    fn flush_row_group(&mut self) -> Result<()> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+        let mut chunks = Vec::with_capacity(self.columns.len());
+        for i in 0..self.columns.len() {
+            let page = self.columns[i].page();
+            let Ok(page_size) = i32::try_from(page.len()) else {
+                bail!("column {} is too large for one Parquet page", self.columns[i].name);
+            };
+            let mut header = Thrift::new();
+            header.i32(1, 0); // DATA_PAGE
+            header.i32(2, page_size);
+            header.i32(3, page_size);
+            header.begin_struct(5);
+            header.i32(1, self.rows as i32);
+            header.i32(2, 0); // PLAIN
+            header.i32(3, 3); // RLE definition levels
+            header.i32(4, 3); // RLE repetition levels
+            header.end_struct();
+            let header = header.finish();
+
+            let offset = self.written;
+            self.write(&header)?;
+            self.write(&page)?;
+            chunks.push(ChunkMeta { offset, size: (header.len() + page.len()) as u64, values: self.rows });
+            self.columns[i].clear();
+        }
+        self.row_groups.push((self.rows, chunks));
+        self.total_rows += self.rows;
+        self.rows = 0;
+        Ok(())
+    }
+
+    fn footer(&self) -> Vec<u8> {
+        let mut meta = Thrift::new();
+        meta.i32(1, 1);
+        meta.list(2, 12, self.columns.len() + 1);
+        meta.begin_element();
+        meta.string(4, "schema");
+        meta.i32(5, self.columns.len() as i32);
+        meta.end_struct();
+        for column in &self.columns {
+            meta.begin_element();
+            meta.i32(1, column.kind.physical());
+            meta.i32(3, 1); // OPTIONAL
+            meta.string(4, &column.name);
+            if let Some(converted) = column.kind.converted() {
+                meta.i32(6, converted);
+            }
+            meta.end_struct();
+        }
+        meta.i64(3, self.total_rows as i64);
+        meta.list(4, 12, self.row_groups.len());
+        for (rows, chunks) in &self.row_groups {
+            meta.begin_element();
+            meta.list(1, 12, chunks.len());
+            for (column, chunk) in self.columns.iter().zip(chunks) {
+                meta.begin_element();
+                meta.i64(2, chunk.offset as i64);
+                meta.begin_struct(3);
+                meta.i32(1, column.kind.physical());
+                meta.list(2, 5, 2);
+                meta.list_i32(0); // PLAIN
+                meta.list_i32(3); // RLE
+                meta.list(3, 8, 1);
+                meta.list_string(&column.name);
+                meta.i32(4, 0); // UNCOMPRESSED
+                meta.i64(5, chunk.values as i64);
+                meta.i64(6, chunk.size as i64);
+                meta.i64(7, chunk.size as i64);
+                meta.i64(9, chunk.offset as i64);
+                meta.end_struct();
+                meta.end_struct();
+            }
+            meta.i64(2, chunks.iter().map(|c| c.size).sum::<u64>() as i64);
+            meta.i64(3, *rows as i64);
+            meta.end_struct();
+        }
+        meta.string(6, concat!("alrajhi-sql-tui ", env!("CARGO_PKG_VERSION")));
+        meta.finish()
+    }
+}
+
+impl RowWriter for ParquetWriter {
+    fn begin(&mut self, columns: &[ColumnInfo]) -> Result<()> {
+        if self.begun {
+            bail!("a Parquet file holds one result set; export the others separately");
+        }
+        if columns.is_empty() {
+            bail!("nothing to write: the result has no columns");
+        }
+        self.begun = true;
+        self.columns = columns
+            .iter()
+            .map(|c| ColumnBuffer {
+                name: c.name.clone(),
+                kind: ColumnKind::of(&c.type_name),
+                defined: Vec::new(),
+                values: Vec::new(),
+                bools: Vec::new(),
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &[CellValue]) -> Result<()> {
+        for (i, column) in self.columns.iter_mut().enumerate() {
+            column.push(row.get(i).unwrap_or(&CellValue::Null));
+        }
+        self.rows += 1;
+        let buffered: usize = self.columns.iter().map(|c| c.values.len()).sum();
+        if self.rows >= ROW_GROUP_ROWS || buffered >= ROW_GROUP_BYTES {
+            self.flush_row_group()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.flush_row_group()?;
+        let footer = self.footer();
+        self.write(&footer)?;
+        self.write(&(footer.len() as u32).to_le_bytes())?;
+        self.write(MAGIC)?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.written
+    }
+}
+
+/// Thrift compact protocol encoder for the page headers and file metadata
+struct Thrift {
+    buf: Vec<u8>,
+    /// Last field id of each open struct
+    last: Vec<i16>,
+}
+
+impl Thrift {
+    fn new() -> Self {
+        Self { buf: Vec::new(), last: vec![0] }
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self.last.last_mut().expect("open struct");
+        let delta = id - *last;
+        if (1..=15).contains(&delta) {
+            self.buf.push(((delta as u8) << 4) | kind);
+        } else {
+            self.buf.push(kind);
+            put_varint(&mut self.buf, zigzag(id as i64));
+        }
+        *last = id;
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, 5);
+        put_varint(&mut self.buf, zigzag(value as i64));
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, 6);
+        put_varint(&mut self.buf, zigzag(value));
+    }
+
+    fn string(&mut self, id: i16, value: &str) {
+        self.field(id, 8);
+        self.list_string(value);
+    }
+
+    fn begin_struct(&mut self, id: i16) {
+        self.field(id, 12);
+        self.last.push(0);
+    }
+
+    fn end_struct(&mut self) {
+        self.buf.push(0);
+        self.last.pop();
+    }
+
+    /// List field header; the elements follow
+    fn list(&mut self, id: i16, element: u8, len: usize) {
+        self.field(id, 9);
+        if len < 15 {
+            self.buf.push(((len as u8) << 4) | element);
+        } else {
+            self.buf.push(0xF0 | element);
+            put_varint(&mut self.buf, len as u64);
+        }
+    }
+
+    /// Struct element of a list, closed with `end_struct`
+    fn begin_element(&mut self) {
+        self.last.push(0);
+    }
+
+    fn list_i32(&mut self, value: i32) {
+        put_varint(&mut self.buf, zigzag(value as i64));
+    }
+
+    fn list_string(&mut self, value: &str) {
+        put_varint(&mut self.buf, value.len() as u64);
+        self.buf.extend_from_slice(value.as_bytes());
+    }
+
+    /// Close the outer struct
+    fn finish(mut self) -> Vec<u8> {
+        self.buf.push(0);
+        self.buf
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+
+    #[test]
+    fn zigzag_interleaves_signs() {
+        assert_eq!(zigzag(0), 0);
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+        assert_eq!(zigzag(-2), 3);
+        assert_eq!(zigzag(i64::MAX), u64::MAX - 1);
+        assert_eq!(zigzag(i64::MIN), u64::MAX);
+    }
+
+    #[test]
+    fn varints_use_seven_bits_per_byte() {
+        let varint = |value| {
+            let mut out = Vec::new();
+            put_varint(&mut out, value);
+            out
+        };
+        assert_eq!(varint(0), [0x00]);
+        assert_eq!(varint(127), [0x7F]);
+        assert_eq!(varint(128), [0x80, 0x01]);
+        assert_eq!(varint(300), [0xAC, 0x02]);
+        assert_eq!(varint(u64::MAX).len(), 10);
+    }
+
+    #[test]
+    fn levels_are_runs_of_defined_and_null() {
+        assert_eq!(rle_levels(&[]), Vec::<u8>::new());
+        assert_eq!(rle_levels(&[true, true, false]), [4, 1, 2, 0]);
+        assert_eq!(rle_levels(&[false, true, false]), [2, 0, 2, 1, 2, 0]);
+        // A run of 200 needs a two-byte header
+        assert_eq!(rle_levels(&[true; 200]), [0x90, 0x03, 1]);
+    }
+
+    #[test]
+    fn file_reads_back_with_nulls_bools_and_dates() {
+        let path = std::env::temp_dir().join(format!("parquet-test-{}.parquet", std::process::id()));
+        let column = |name: &str, type_name: &str| ColumnInfo {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            max_width: 0,
+        };
+        let columns = [
+            column("id", "INT"),
+            column("active", "BIT"),
+            column("opened", "DATE"),
+            column("updated", "DATETIME"),
+            column("name", "NVARCHAR"),
+            column("rate", "FLOAT"),
+        ];
+        let rows = [
+            vec![
+                CellValue::Int(1),
+                CellValue::Bool(true),
+                CellValue::DateTime("2024-01-15".to_string()),
+                CellValue::DateTime("1970-01-02 00:00:01.500".to_string()),
+                CellValue::String("الراجحي".to_string()),
+                CellValue::Float(0.25),
+            ],
+            vec![CellValue::Null, CellValue::Null, CellValue::Null, CellValue::Null, CellValue::Null, CellValue::Null],
+            vec![
+                CellValue::Int(-3),
+                CellValue::Bool(false),
+                CellValue::DateTime("1969-12-31".to_string()),
+                CellValue::DateTime("not a date".to_string()),
+                CellValue::String(String::new()),
+                CellValue::Int(2),
+            ],
+        ];
+
+        let mut writer = ParquetWriter::new(BufWriter::new(File::create(&path).unwrap())).unwrap();
+        writer.begin(&columns).unwrap();
+        for row in &rows {
+            writer.write_row(row).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 3);
+        let read: Vec<Vec<Field>> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap().get_column_iter().map(|(_, field)| field.clone()).collect())
+            .collect();
+        assert_eq!(
+            read[0],
+            [
+                Field::Long(1),
+                Field::Bool(true),
+                Field::Date(19737),
+                Field::TimestampMillis(86_401_500),
+                Field::Str("الراجحي".to_string()),
+                Field::Double(0.25),
+            ]
+        );
+        assert!(read[1].iter().all(|field| *field == Field::Null));
+        assert_eq!(
+            read[2],
+            [Field::Long(-3), Field::Bool(false), Field::Date(-1), Field::Null, Field::Str(String::new()), Field::Double(2.0)]
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,165 @@
+//! INSERT and MERGE scripts with typed T-SQL literals
+
+use crate::db::{quote_ident, CellValue, ColumnInfo};
+
+/// Most rows SQL Server accepts in one `VALUES` list
+pub const MAX_VALUES_ROWS: usize = 1000;
+
+/// Statement a generated script uses for each batch
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptKind {
+    Insert,
+    /// MERGE on the key columns: update matching rows, insert the others
+    Upsert,
+    /// MERGE on the key columns: insert only rows whose key is missing
+    InsertMissing,
+}
+
+impl ScriptKind {
+    pub const ALL: [ScriptKind; 3] = [ScriptKind::Insert, ScriptKind::Upsert, ScriptKind::InsertMissing];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ScriptKind::Insert => "INSERT",
+            ScriptKind::Upsert => "MERGE (update + insert)",
+            ScriptKind::InsertMissing => "MERGE (insert missing)",
+        }
+    }
+
+    /// MERGE variants match rows on key columns
+    pub fn needs_keys(&self) -> bool {
+        *self != ScriptKind::Insert
+    }
+}
+
+/// What to generate and for which table
+#[derive(Clone, Debug)]
+pub struct ScriptOptions {
+    /// Target table as written in the script, e.g. `dbo.Branches` or `[dbo].[Branches]`
+    pub table: String,
+    pub kind: ScriptKind,
+    /// Rows per statement, 1 to `MAX_VALUES_ROWS`
+    pub batch_size: usize,
+    /// Wrap the script in `SET IDENTITY_INSERT ... ON/OFF`
+    pub identity_insert: bool,
+    /// Key columns (indexes into the columns) for the MERGE variants
+    pub keys: Vec<usize>,
+}
+
+/// Script inserting or merging `rows` into the target table, one statement per batch
+pub fn generate_script(columns: &[ColumnInfo], rows: &[&[CellValue]], options: &ScriptOptions) -> String {
+    let names: Vec<String> = columns.iter().map(|c| quote_ident(&c.name)).collect();
+    let batch_size = options.batch_size.clamp(1, MAX_VALUES_ROWS);
+    let mut statements = Vec::new();
+    for batch in rows.chunks(batch_size) {
+        let values: Vec<String> = batch.iter().map(|row| values_row(columns, row)).collect();
+        statements.push(match options.kind {
+            ScriptKind::Insert if values.len() == 1 => format!(
+                "INSERT INTO {} ({}) VALUES {};\n",
+                options.table,
+                names.join(", "),
+                values[0]
+            ),
+            ScriptKind::Insert => format!(
+                "INSERT INTO {} ({}) VALUES\n    {};\n",
+                options.table,
+                names.join(", "),
+                values.join(",\n    ")
+            ),
+            kind => merge_statement(&options.table, &names, &values, &options.keys, kind),
+        });
+    }
+    // Single-line INSERTs run together; multi-line statements get a blank line between them
+    let separator = if options.kind == ScriptKind::Insert && batch_size == 1 { "" } else { "\n" };
+    let mut script = statements.join(separator);
+
+    if options.identity_insert {
+        script = format!(
+            "SET IDENTITY_INSERT {table} ON;\n\n{script}\nSET IDENTITY_INSERT {table} OFF;\n",
+            table = options.table
+        );
+    }
+    script
+}
+
+/// `(v1, v2, ...)` with each value typed by its column
+fn values_row(columns: &[ColumnInfo], row: &[CellValue]) -> String {
+    let values: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| sql_literal(row.get(i).unwrap_or(&CellValue::Null), &column.type_name))
+        .collect();
+    format!("({})", values.join(", "))
+}
+
+fn merge_statement(table: &str, names: &[String], values: &[String], keys: &[usize], kind: ScriptKind) -> String {
+    let on: Vec<String> = keys
+        .iter()
+        .filter_map(|&k| names.get(k))
+        .map(|name| format!("target.{} = source.{}", name, name))
+        .collect();
+    let updates: Vec<String> = names
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !keys.contains(i))
+        .map(|(_, name)| format!("target.{} = source.{}", name, name))
+        .collect();
+    let source_names: Vec<String> = names.iter().map(|name| format!("source.{}", name)).collect();
+
+    let mut sql = format!(
+        "MERGE INTO {} WITH (HOLDLOCK) AS target\nUSING (VALUES\n    {}\n) AS source ({})\nON {}\n",
+        table,
+        values.join(",\n    "),
+        names.join(", "),
+        on.join(" AND ")
+    );
+    if kind == ScriptKind::Upsert && !updates.is_empty() {
+        sql.push_str(&format!("WHEN MATCHED THEN\n    UPDATE SET {}\n", updates.join(", ")));
+    }
+    sql.push_str(&format!(
+        "WHEN NOT MATCHED BY TARGET THEN\n    INSERT ({}) VALUES ({});\n",
+        names.join(", "),
+        source_names.join(", ")
+    ));
+    sql
+}
+
+/// T-SQL literal of a cell for a column of `type_name`: numbers unquoted, `N''` for Unicode
+/// text, dates in a form every DATEFORMAT/LANGUAGE setting reads the same way, binary as hex
+pub fn sql_literal(cell: &CellValue, type_name: &str) -> String {
+    match cell {
+        CellValue::Null => "NULL".to_string(),
+        CellValue::Bool(v) => if *v { "1" } else { "0" }.to_string(),
+        CellValue::Int(v) => v.to_string(),
+        CellValue::Float(v) if v.is_finite() => v.to_string(),
+        CellValue::Float(_) => "NULL".to_string(),
+        CellValue::String(v) if matches!(type_name, "DECIMAL" | "NUMERIC") && is_decimal_text(v) => {
+            v.trim().to_string()
+        }
+        CellValue::String(v) => {
+            let unicode = matches!(type_name, "NVARCHAR" | "NCHAR" | "NTEXT" | "XML") || !v.is_ascii();
+            format!("{}'{}'", if unicode { "N" } else { "" }, v.replace('\'', "''"))
+        }
+        CellValue::DateTime(v) => format!("'{}'", date_literal(v, type_name)),
+        CellValue::Binary(v) => format!("0x{}", v.iter().map(|b| format!("{:02X}", b)).collect::<String>()),
+    }
+}
+
+/// `2024-03-01 10:00:00` as ISO 8601 `2024-03-01T10:00:00`; a bare date stays
+/// `2024-03-01` for DATE columns and becomes `20240301` for the datetime types
+fn date_literal(text: &str, type_name: &str) -> String {
+    let is_date = text.len() >= 10 && text.as_bytes()[4] == b'-' && text.as_bytes()[7] == b'-';
+    match text.split_once(' ') {
+        Some((date, time)) if is_date => format!("{}T{}", date, time),
+        None if is_date && text.len() == 10 && type_name != "DATE" => text.replace('-', ""),
+        _ => text.to_string(),
+    }
+}
+
+/// Plain decimal number such as `-12.50`
+pub fn is_decimal_text(text: &str) -> bool {
+    let text = text.trim();
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    !whole.is_empty() && whole.bytes().all(|b| b.is_ascii_digit()) && fraction.bytes().all(|b| b.is_ascii_digit())
+}
@@ -0,0 +1,101 @@
+//! Exporting a query straight from the server to disk, without holding its rows
+
+use crate::db::QueryExecutor;
+use crate::export::RowWriter;
+use anyhow::{bail, Result};
+use futures::TryStreamExt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tiberius::{Client, QueryItem};
+use tokio::net::TcpStream;
+use tokio::sync::Notify;
+use tokio_util::compat::Compat;
+
+/// Counters of a running export, shared with the UI, and its cancel flag
+#[derive(Debug, Default)]
+pub struct ExportProgress {
+    rows: AtomicU64,
+    bytes: AtomicU64,
+    cancelled: AtomicBool,
+    /// Wakes an export waiting on the server
+    cancel_signal: Notify,
+}
+
+impl ExportProgress {
+    pub fn rows(&self) -> u64 {
+        self.rows.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Ask the export to stop, even while it waits for the next row
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        // notify_one keeps the wakeup for an export that is not waiting yet
+        self.cancel_signal.notify_one();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// How a streamed export ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamOutcome {
+    Finished,
+    /// Stopped by `ExportProgress::cancel`; the rest of the result was not read
+    Cancelled,
+}
+
+/// Run `query` and write each row to `writer` as it arrives; every result set is begun
+/// with its own columns. The writer is finished only when the whole result was read.
+pub async fn stream_query(
+    client: &mut Client<Compat<TcpStream>>,
+    query: &str,
+    writer: &mut dyn RowWriter,
+    progress: &ExportProgress,
+) -> Result<StreamOutcome> {
+    let mut stream = tokio::select! {
+        stream = QueryExecutor::open_stream(client, query) => stream?,
+        _ = progress.cancel_signal.notified() => return Ok(StreamOutcome::Cancelled),
+    };
+    let mut begun = false;
+
+    loop {
+        // A slow query can keep the next item away for minutes; cancel must not wait for it
+        let item = tokio::select! {
+            item = stream.try_next() => item?,
+            _ = progress.cancel_signal.notified() => return Ok(StreamOutcome::Cancelled),
+        };
+        let Some(item) = item else {
+            break;
+        };
+        if progress.is_cancelled() {
+            return Ok(StreamOutcome::Cancelled);
+        }
+        match item {
+            QueryItem::Metadata(meta) => {
+                writer.begin(&QueryExecutor::column_info(meta.columns()))?;
+                begun = true;
+            }
+            QueryItem::Row(row) => {
+                if !begun {
+                    writer.begin(&QueryExecutor::column_info(row.columns()))?;
+                    begun = true;
+                }
+                writer.write_row(&QueryExecutor::row_values(&row))?;
+                progress.rows.fetch_add(1, Ordering::Relaxed);
+                progress.bytes.store(writer.bytes_written(), Ordering::Relaxed);
+            }
+        }
+    }
+
+    if !begun {
+        bail!("the query returned no result set");
+    }
+    writer.finish()?;
+    progress.bytes.store(writer.bytes_written(), Ordering::Relaxed);
+    Ok(StreamOutcome::Finished)
+}
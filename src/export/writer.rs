@@ -0,0 +1,411 @@
+//! Row-by-row writers for the text formats, in the chosen encoding
+
+use crate::db::{quote_ident, CellValue, ColumnInfo};
+use crate::export::{is_decimal_text, sql_literal, ExportFormat, ExportOptions, TextEncoding};
+use anyhow::Result;
+use encoding_rs::{Encoder, EncoderResult};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Destination of an export: `begin` once per result set, then its rows
+pub trait RowWriter: Send {
+    fn begin(&mut self, columns: &[ColumnInfo]) -> Result<()>;
+    fn write_row(&mut self, row: &[CellValue]) -> Result<()>;
+    /// Write any trailer and flush
+    fn finish(&mut self) -> Result<()>;
+    /// Bytes written to the file so far
+    fn bytes_written(&self) -> u64;
+}
+
+/// Writer for a streaming format; `default_table` names the target of SQL INSERTs
+pub fn create_writer(path: &Path, options: &ExportOptions, default_table: Option<&str>) -> Result<Box<dyn RowWriter>> {
+    let file = BufWriter::new(File::create(path)?);
+    if options.format == ExportFormat::Parquet {
+        return Ok(Box::new(crate::export::ParquetWriter::new(file)?));
+    }
+    create_text_writer(Box::new(file), options, default_table)
+}
+
+/// Writer for a text format into any byte sink, such as standard output
+pub fn create_text_writer(
+    out: Box<dyn Write + Send>,
+    options: &ExportOptions,
+    default_table: Option<&str>,
+) -> Result<Box<dyn RowWriter>> {
+    if !options.format.is_text() {
+        anyhow::bail!("{} is not a text format", options.format.label());
+    }
+    let table = match options.table.trim() {
+        "" => default_table.unwrap_or("[ExportedRows]").to_string(),
+        table => table.to_string(),
+    };
+    let mut output = TextOutput::new(out, options.encoding);
+    if options.bom {
+        output.write_bom()?;
+    }
+    Ok(Box::new(TextWriter {
+        output,
+        options: options.clone(),
+        table,
+        columns: Vec::new(),
+        decimal: Vec::new(),
+        sets: 0,
+        rows: 0,
+    }))
+}
+
+/// Encodes UTF-8 text into the target encoding as it is written
+struct TextOutput {
+    out: Box<dyn Write + Send>,
+    encoding: TextEncoding,
+    encoder: Option<Encoder>,
+    buf: Vec<u8>,
+    written: u64,
+}
+
+impl TextOutput {
+    fn new(out: Box<dyn Write + Send>, encoding: TextEncoding) -> Self {
+        let encoder = match encoding {
+            TextEncoding::Windows1256 => Some(encoding_rs::WINDOWS_1256.new_encoder()),
+            TextEncoding::Windows1252 => Some(encoding_rs::WINDOWS_1252.new_encoder()),
+            _ => None,
+        };
+        Self { out, encoding, encoder, buf: Vec::new(), written: 0 }
+    }
+
+    fn write_bom(&mut self) -> Result<()> {
+        let bom = self.encoding.bom();
+        self.out.write_all(bom)?;
+        self.written += bom.len() as u64;
+        Ok(())
+    }
+
+    fn write_str(&mut self, text: &str) -> Result<()> {
+        self.buf.clear();
+        match (&mut self.encoder, self.encoding) {
+            (_, TextEncoding::Utf16Le) => {
+                for unit in text.encode_utf16() {
+                    self.buf.extend_from_slice(&unit.to_le_bytes());
+                }
+            }
+            (Some(encoder), _) => encode_legacy(encoder, text, &mut self.buf),
+            (None, _) => self.buf.extend_from_slice(text.as_bytes()),
+        }
+        self.out.write_all(&self.buf)?;
+        self.written += self.buf.len() as u64;
+        Ok(())
+    }
+}
+
+/// Single-byte code page; characters it cannot hold become `?`
+fn encode_legacy(encoder: &mut Encoder, text: &str, out: &mut Vec<u8>) {
+    let mut src = text;
+    let mut buf = [0u8; 4096];
+    loop {
+        let (result, read, written) = encoder.encode_from_utf8_without_replacement(src, &mut buf, false);
+        out.extend_from_slice(&buf[..written]);
+        src = &src[read..];
+        match result {
+            EncoderResult::InputEmpty => break,
+            EncoderResult::OutputFull => {}
+            EncoderResult::Unmappable(_) => out.push(b'?'),
+        }
+    }
+}
+
+/// CSV, TSV, JSON, JSON Lines, Markdown, HTML and SQL INSERT
+struct TextWriter {
+    output: TextOutput,
+    options: ExportOptions,
+    table: String,
+    columns: Vec<ColumnInfo>,
+    /// DECIMAL/NUMERIC columns, whose text is written as a number where the format allows
+    decimal: Vec<bool>,
+    /// Result sets begun so far
+    sets: usize,
+    /// Rows written in the whole file
+    rows: usize,
+}
+
+impl RowWriter for TextWriter {
+    fn begin(&mut self, columns: &[ColumnInfo]) -> Result<()> {
+        let first = self.sets == 0;
+        self.sets += 1;
+        self.columns = columns.to_vec();
+        self.decimal = columns.iter().map(|c| c.type_name == "DECIMAL" || c.type_name == "NUMERIC").collect();
+
+        let mut text = String::new();
+        match self.options.format {
+            ExportFormat::Csv | ExportFormat::Tsv => {
+                if !first {
+                    text.push('\n');
+                }
+                if self.options.headers {
+                    let fields: Vec<String> = columns.iter().map(|c| self.delimited_field(&c.name)).collect();
+                    text.push_str(&fields.join(&self.options.delimiter.to_string()));
+                    text.push('\n');
+                }
+            }
+            ExportFormat::Json if first => text.push('['),
+            ExportFormat::Markdown => {
+                if !first {
+                    text.push('\n');
+                }
+                let names: Vec<String> = columns
+                    .iter()
+                    .map(|c| if self.options.headers { markdown_escape(&c.name) } else { String::new() })
+                    .collect();
+                text.push_str(&format!("| {} |\n", names.join(" | ")));
+                text.push_str(&format!("|{}\n", " --- |".repeat(columns.len())));
+            }
+            ExportFormat::Html => {
+                if first {
+                    text.push_str(&html_prelude(self.options.encoding));
+                } else {
+                    text.push_str("</tbody></table>\n");
+                }
+                text.push_str("<table>\n");
+                if self.options.headers {
+                    text.push_str("<thead><tr>");
+                    for c in columns {
+                        text.push_str(&format!("<th>{}</th>", html_escape(&c.name)));
+                    }
+                    text.push_str("</tr></thead>\n");
+                }
+                text.push_str("<tbody>\n");
+            }
+            _ => {}
+        }
+        self.output.write_str(&text)
+    }
+
+    fn write_row(&mut self, row: &[CellValue]) -> Result<()> {
+        let mut text = String::new();
+        match self.options.format {
+            ExportFormat::Csv | ExportFormat::Tsv => {
+                let fields: Vec<String> = row
+                    .iter()
+                    .map(|cell| match cell {
+                        CellValue::Null => self.options.null_text.clone(),
+                        cell => self.delimited_field(&plain_text(cell)),
+                    })
+                    .collect();
+                text.push_str(&fields.join(&self.options.delimiter.to_string()));
+                text.push('\n');
+            }
+            ExportFormat::Json => {
+                text.push_str(if self.rows == 0 { "\n  " } else { ",\n  " });
+                text.push_str(&self.json_object(row));
+            }
+            ExportFormat::JsonLines => {
+                text.push_str(&self.json_object(row));
+                text.push('\n');
+            }
+            ExportFormat::Markdown => {
+                let fields: Vec<String> = row.iter().map(|cell| markdown_escape(&self.display_text(cell))).collect();
+                text.push_str(&format!("| {} |\n", fields.join(" | ")));
+            }
+            ExportFormat::Html => {
+                text.push_str("<tr>");
+                for cell in row {
+                    text.push_str(&format!("<td>{}</td>", html_escape(&self.display_text(cell))));
+                }
+                text.push_str("</tr>\n");
+            }
+            ExportFormat::Sql => {
+                let columns: Vec<String> = self.columns.iter().map(|c| quote_ident(&c.name)).collect();
+                let values: Vec<String> = self
+                    .columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, cell)| sql_literal(cell, &column.type_name))
+                    .collect();
+                text.push_str(&format!(
+                    "INSERT INTO {} ({}) VALUES ({});\n",
+                    self.table,
+                    columns.join(", "),
+                    values.join(", ")
+                ));
+            }
+            ExportFormat::Parquet | ExportFormat::Xlsx => {}
+        }
+        self.rows += 1;
+        self.output.write_str(&text)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        match self.options.format {
+            // With no result set at all `begin` never opened the array
+            ExportFormat::Json if self.sets == 0 => self.output.write_str("[]\n")?,
+            ExportFormat::Json => self.output.write_str("\n]\n")?,
+            ExportFormat::Html if self.sets > 0 => self.output.write_str("</tbody></table>\n</body>\n</html>\n")?,
+            _ => {}
+        }
+        self.output.out.flush()?;
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.output.written
+    }
+}
+
+impl TextWriter {
+    /// Quote a CSV/TSV field when it holds the delimiter, the quote, a line break or edge spaces
+    fn delimited_field(&self, text: &str) -> String {
+        let Some(quote) = self.options.quote else {
+            return text.to_string();
+        };
+        let needs_quotes = text.contains(self.options.delimiter)
+            || text.contains(quote)
+            || text.contains(['\n', '\r'])
+            || text.starts_with(' ')
+            || text.ends_with(' ');
+        if needs_quotes {
+            let doubled = text.replace(quote, &format!("{}{}", quote, quote));
+            format!("{}{}{}", quote, doubled, quote)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Cell text for Markdown and HTML
+    fn display_text(&self, cell: &CellValue) -> String {
+        match cell {
+            CellValue::Null => self.options.null_text.clone(),
+            cell => plain_text(cell),
+        }
+    }
+
+    /// JSON object with typed values: numbers, booleans and null stay unquoted
+    fn json_object(&self, row: &[CellValue]) -> String {
+        let fields: Vec<String> = self
+            .columns
+            .iter()
+            .zip(row)
+            .enumerate()
+            .map(|(i, (column, cell))| {
+                let value = match cell {
+                    CellValue::Null => "null".to_string(),
+                    CellValue::Bool(v) => v.to_string(),
+                    CellValue::Int(v) => v.to_string(),
+                    CellValue::Float(v) if v.is_finite() => v.to_string(),
+                    CellValue::String(v) if self.decimal[i] && is_decimal_text(v) => v.trim().to_string(),
+                    cell => json_string(&plain_text(cell)),
+                };
+                format!("{}: {}", json_string(&column.name), value)
+            })
+            .collect();
+        format!("{{{}}}", fields.join(", "))
+    }
+}
+
+/// Cell text without display formatting: full float precision, binary as 0x hex
+pub fn plain_text(cell: &CellValue) -> String {
+    match cell {
+        CellValue::Null => String::new(),
+        CellValue::Float(v) => v.to_string(),
+        cell => cell.to_string(),
+    }
+}
+
+fn json_string(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+/// Cell text safe inside a Markdown table: pipes escaped, line breaks as `<br>`
+pub(crate) fn markdown_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|").replace("\r\n", "<br>").replace(['\n', '\r'], "<br>")
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Document head of an HTML export, declaring the file's encoding
+fn html_prelude(encoding: TextEncoding) -> String {
+    let charset = match encoding {
+        TextEncoding::Utf8 => "utf-8",
+        TextEncoding::Utf16Le => "utf-16le",
+        TextEncoding::Windows1256 => "windows-1256",
+        TextEncoding::Windows1252 => "windows-1252",
+    };
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"{}\">\n<title>Export</title>\n<style>\n\
+         table {{ border-collapse: collapse; font-family: sans-serif; font-size: 13px; margin-bottom: 1em; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 3px 6px; }}\n\
+         th {{ background: #006633; color: #fff; }}\n\
+         </style>\n</head>\n<body>\n",
+        charset
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Byte sink the test can read after the writer is done with it
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn column(name: &str, type_name: &str) -> ColumnInfo {
+        ColumnInfo { name: name.to_string(), type_name: type_name.to_string(), max_width: 0 }
+    }
+
+    /// JSON export of the given result sets, parsed back
+    fn export_json(sets: &[(Vec<ColumnInfo>, Vec<Vec<CellValue>>)]) -> serde_json::Value {
+        let sink = Shared::default();
+        let options = ExportOptions { format: ExportFormat::Json, ..ExportOptions::default() };
+        let mut writer = create_text_writer(Box::new(sink.clone()), &options, None).unwrap();
+        for (columns, rows) in sets {
+            writer.begin(columns).unwrap();
+            for row in rows {
+                writer.write_row(row).unwrap();
+            }
+        }
+        writer.finish().unwrap();
+        let text = String::from_utf8(sink.0.lock().unwrap().clone()).unwrap();
+        serde_json::from_str(&text).unwrap_or_else(|e| panic!("invalid JSON {:?}: {}", text, e))
+    }
+
+    #[test]
+    fn json_without_result_sets() {
+        assert_eq!(export_json(&[]), serde_json::json!([]));
+    }
+
+    #[test]
+    fn json_with_columns_but_no_rows() {
+        let sets = [(vec![column("id", "INT")], Vec::new())];
+        assert_eq!(export_json(&sets), serde_json::json!([]));
+    }
+
+    #[test]
+    fn json_with_one_row() {
+        let columns = vec![column("id", "INT"), column("name", "NVARCHAR"), column("price", "DECIMAL")];
+        let rows = vec![vec![CellValue::Int(1), CellValue::Null, CellValue::String("-12.50".to_string())]];
+        assert_eq!(export_json(&[(columns, rows)]), serde_json::json!([{"id": 1, "name": null, "price": -12.50}]));
+    }
+
+    #[test]
+    fn json_rows_of_every_result_set_share_one_array() {
+        let sets = [
+            (vec![column("a", "INT")], vec![vec![CellValue::Int(1)], vec![CellValue::Int(2)]]),
+            (vec![column("b", "BIT")], Vec::new()),
+            (vec![column("c", "NVARCHAR")], vec![vec![CellValue::String("x \"y\"".to_string())]]),
+        ];
+        assert_eq!(export_json(&sets), serde_json::json!([{"a": 1}, {"a": 2}, {"c": "x \"y\""}]));
+    }
+}
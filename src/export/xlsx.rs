@@ -0,0 +1,516 @@
+//! Excel (.xlsx) workbook writer that keeps cell types
+
+use crate::db::{CellValue, ColumnInfo, QueryResult};
+use crate::export::zip::ZipWriter;
+use crate::utils::display_width;
+use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Data rows per sheet; longer result sets continue on another sheet
+const MAX_SHEET_ROWS: usize = 1_048_575;
+
+/// Longest text Excel keeps in a cell
+const MAX_CELL_CHARS: usize = 32_767;
+
+/// Widest automatic column, in characters
+const MAX_COLUMN_WIDTH: usize = 60;
+
+/// Cell style indexes in `STYLES`
+const STYLE_HEADER: u8 = 1;
+const STYLE_DATETIME: u8 = 2;
+const STYLE_DATE: u8 = 3;
+const STYLE_TIME: u8 = 4;
+const STYLE_MONEY: u8 = 5;
+
+/// One worksheet: a name and a result set
+pub struct Sheet<'a> {
+    pub name: String,
+    pub columns: &'a [ColumnInfo],
+    pub rows: &'a [Vec<CellValue>],
+}
+
+/// One sheet per result set of `result`, named "Result" or "Result 1", "Result 2"...
+pub fn result_sheets(result: &QueryResult) -> Vec<Sheet<'_>> {
+    let sets = result.result_sets();
+    let numbered = sets.len() > 1;
+    sets.into_iter()
+        .enumerate()
+        .map(|(i, (columns, rows))| Sheet {
+            name: if numbered { format!("Result {}", i + 1) } else { "Result".to_string() },
+            columns,
+            rows,
+        })
+        .collect()
+}
+
+/// Write a workbook with a bold, frozen header row and auto-sized columns on each sheet
+pub fn write_xlsx(path: &Path, sheets: &[Sheet]) -> Result<()> {
+    // Split sheets that exceed Excel's row limit
+    let mut parts: Vec<Sheet> = Vec::new();
+    for sheet in sheets {
+        let chunks: Vec<&[Vec<CellValue>]> = if sheet.rows.is_empty() {
+            vec![sheet.rows]
+        } else {
+            sheet.rows.chunks(MAX_SHEET_ROWS).collect()
+        };
+        let continued = chunks.len() > 1;
+        for (i, rows) in chunks.into_iter().enumerate() {
+            let name = if continued { format!("{} ({})", sheet.name, i + 1) } else { sheet.name.clone() };
+            parts.push(Sheet { name, columns: sheet.columns, rows });
+        }
+    }
+    let names = sheet_names(parts.iter().map(|part| part.name.as_str()));
+
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+    zip.add("[Content_Types].xml", content_types(parts.len()).as_bytes())?;
+    zip.add("_rels/.rels", ROOT_RELS.as_bytes())?;
+    zip.add("xl/workbook.xml", workbook(&names).as_bytes())?;
+    zip.add("xl/_rels/workbook.xml.rels", workbook_rels(parts.len()).as_bytes())?;
+    zip.add("xl/styles.xml", STYLES.as_bytes())?;
+    for (i, part) in parts.iter().enumerate() {
+        zip.add(&format!("xl/worksheets/sheet{}.xml", i + 1), worksheet(part.columns, part.rows).as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// Sheet XML: column widths, frozen header, then the rows
+fn worksheet(columns: &[ColumnInfo], rows: &[Vec<CellValue>]) -> String {
+    let kinds: Vec<NumberKind> = columns.iter().map(|c| NumberKind::of(&c.type_name)).collect();
+    let mut widths: Vec<usize> = columns.iter().map(|c| display_width(&c.name) + 2).collect();
+    let mut data = String::new();
+
+    data.push_str("<row r=\"1\">");
+    for (col, column) in columns.iter().enumerate() {
+        push_text_cell(&mut data, &cell_ref(col, 1), &column.name, Some(STYLE_HEADER));
+    }
+    data.push_str("</row>");
+
+    for (i, row) in rows.iter().enumerate() {
+        let r = i + 2;
+        let _ = write!(data, "<row r=\"{}\">", r);
+        for (col, cell) in row.iter().enumerate().take(columns.len()) {
+            let width = push_cell(&mut data, &cell_ref(col, r), cell, kinds[col]);
+            widths[col] = widths[col].max(width);
+        }
+        data.push_str("</row>");
+    }
+
+    let mut xml = String::with_capacity(data.len() + 1024);
+    xml.push_str(XML_DECL);
+    xml.push_str("<worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\">");
+    xml.push_str(
+        "<sheetViews><sheetView workbookViewId=\"0\">\
+         <pane ySplit=\"1\" topLeftCell=\"A2\" activePane=\"bottomLeft\" state=\"frozen\"/>\
+         <selection pane=\"bottomLeft\" activeCell=\"A2\" sqref=\"A2\"/>\
+         </sheetView></sheetViews>",
+    );
+    if !columns.is_empty() {
+        xml.push_str("<cols>");
+        for (col, width) in widths.iter().enumerate() {
+            let _ = write!(
+                xml,
+                "<col min=\"{0}\" max=\"{0}\" width=\"{1}\" customWidth=\"1\"/>",
+                col + 1,
+                (*width).clamp(8, MAX_COLUMN_WIDTH) + 1
+            );
+        }
+        xml.push_str("</cols>");
+    }
+    xml.push_str("<sheetData>");
+    xml.push_str(&data);
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// How a column's numbers are written
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum NumberKind {
+    Plain,
+    /// MONEY/SMALLMONEY: thousands separators and two decimals
+    Money,
+    /// DECIMAL/NUMERIC: arrive as text
+    Decimal,
+}
+
+impl NumberKind {
+    fn of(type_name: &str) -> Self {
+        if type_name.contains("MONEY") {
+            NumberKind::Money
+        } else if type_name == "DECIMAL" || type_name == "NUMERIC" {
+            NumberKind::Decimal
+        } else {
+            NumberKind::Plain
+        }
+    }
+}
+
+/// Append a typed cell and return its display width for column sizing
+fn push_cell(xml: &mut String, r: &str, cell: &CellValue, kind: NumberKind) -> usize {
+    match cell {
+        CellValue::Null => 0,
+        CellValue::Bool(v) => {
+            let _ = write!(xml, "<c r=\"{}\" t=\"b\"><v>{}</v></c>", r, *v as u8);
+            5
+        }
+        // Excel keeps 15 digits; longer BIGINTs such as card numbers stay text
+        CellValue::Int(v) if is_exact_decimal(&v.to_string()) => push_number(xml, r, &v.to_string(), None),
+        CellValue::Int(v) => push_text_cell(xml, r, &v.to_string(), None),
+        CellValue::Float(v) if v.is_finite() && kind == NumberKind::Money => {
+            push_number(xml, r, &v.to_string(), Some(STYLE_MONEY)) + 3
+        }
+        CellValue::Float(v) if v.is_finite() => push_number(xml, r, &v.to_string(), None),
+        CellValue::Float(v) => push_text_cell(xml, r, &v.to_string(), None),
+        // Keep decimals numeric unless Excel would lose digits
+        CellValue::String(v) if kind == NumberKind::Decimal && is_exact_decimal(v) => push_number(xml, r, v.trim(), None),
+        CellValue::String(v) => push_text_cell(xml, r, v, None),
+        CellValue::DateTime(v) => match excel_date(v) {
+            Some((serial, style)) => {
+                let _ = write!(xml, "<c r=\"{}\" s=\"{}\"><v>{}</v></c>", r, style, serial);
+                v.len()
+            }
+            None => push_text_cell(xml, r, v, None),
+        },
+        CellValue::Binary(_) => push_text_cell(xml, r, &cell.to_string(), None),
+    }
+}
+
+fn push_number(xml: &mut String, r: &str, value: &str, style: Option<u8>) -> usize {
+    match style {
+        Some(s) => {
+            let _ = write!(xml, "<c r=\"{}\" s=\"{}\"><v>{}</v></c>", r, s, value);
+        }
+        None => {
+            let _ = write!(xml, "<c r=\"{}\"><v>{}</v></c>", r, value);
+        }
+    }
+    value.len()
+}
+
+/// Inline string cell; avoids a shared strings part
+fn push_text_cell(xml: &mut String, r: &str, text: &str, style: Option<u8>) -> usize {
+    let text: String = text.chars().take(MAX_CELL_CHARS).collect();
+    let _ = write!(xml, "<c r=\"{}\" t=\"inlineStr\"", r);
+    if let Some(s) = style {
+        let _ = write!(xml, " s=\"{}\"", s);
+    }
+    let preserve = text.starts_with(char::is_whitespace) || text.ends_with(char::is_whitespace);
+    let space = if preserve { " xml:space=\"preserve\"" } else { "" };
+    let _ = write!(xml, "><is><t{}>{}</t></is></c>", space, escape_xml(&text));
+    text.lines().map(display_width).max().unwrap_or(0)
+}
+
+/// Decimal text with at most 15 significant digits, the precision of an Excel number
+fn is_exact_decimal(text: &str) -> bool {
+    let text = text.trim();
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let mut parts = digits.splitn(2, '.');
+    let whole = parts.next().unwrap_or("");
+    let fraction = parts.next().unwrap_or("");
+    if whole.is_empty() || !whole.bytes().all(|b| b.is_ascii_digit()) || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    let significant = format!("{}{}", whole, fraction.trim_end_matches('0'));
+    significant.trim_start_matches('0').len() <= 15
+}
+
+/// Excel serial date and style of a formatted DateTime cell
+fn excel_date(text: &str) -> Option<(f64, u8)> {
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30)?.and_hms_opt(0, 0, 0)?;
+    // Serial numbers before March 1900 are off by Excel's leap-year bug
+    let first = NaiveDate::from_ymd_opt(1900, 3, 1)?;
+    let serial = |dt: NaiveDateTime| {
+        let seconds = (dt - epoch).num_seconds() as f64 + dt.nanosecond() as f64 / 1e9;
+        seconds / 86_400.0
+    };
+
+    if let Ok(dt) = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f") {
+        return (dt.date() >= first).then(|| (serial(dt), STYLE_DATETIME));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        let midnight = date.and_hms_opt(0, 0, 0)?;
+        return (date >= first).then(|| (serial(midnight), STYLE_DATE));
+    }
+    if let Ok(time) = NaiveTime::parse_from_str(text, "%H:%M:%S%.f") {
+        let seconds = time.num_seconds_from_midnight() as f64 + time.nanosecond() as f64 / 1e9;
+        return Some((seconds / 86_400.0, STYLE_TIME));
+    }
+    None
+}
+
+/// A1-style reference of a zero-based column and one-based row
+fn cell_ref(col: usize, row: usize) -> String {
+    let mut letters = Vec::new();
+    let mut n = col + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        letters.push(b'A' + rem as u8);
+        n = (n - 1) / 26;
+    }
+    letters.reverse();
+    format!("{}{}", String::from_utf8_lossy(&letters), row)
+}
+
+/// Escape XML text, dropping control characters XML cannot hold
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if (c as u32) < 0x20 || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Valid, unique sheet names: at most 31 characters, none of `[]:*?/\`
+fn sheet_names<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for name in names {
+        let clean: String = name
+            .chars()
+            .map(|c| if "[]:*?/\\".contains(c) { '_' } else { c })
+            .take(31)
+            .collect();
+        let base = if clean.trim().is_empty() { "Sheet".to_string() } else { clean };
+        let mut candidate = base.clone();
+        let mut n = 2;
+        while result.iter().any(|r| r.eq_ignore_ascii_case(&candidate)) {
+            let suffix = format!(" {}", n);
+            let keep = 31usize.saturating_sub(suffix.chars().count());
+            candidate = format!("{}{}", base.chars().take(keep).collect::<String>(), suffix);
+            n += 1;
+        }
+        result.push(candidate);
+    }
+    result
+}
+
+fn content_types(sheets: usize) -> String {
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(
+        "<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+         <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+         <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+         <Override PartName=\"/xl/workbook.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>\
+         <Override PartName=\"/xl/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml\"/>",
+    );
+    for i in 1..=sheets {
+        let _ = write!(
+            xml,
+            "<Override PartName=\"/xl/worksheets/sheet{}.xml\" \
+             ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>",
+            i
+        );
+    }
+    xml.push_str("</Types>");
+    xml
+}
+
+fn workbook(names: &[String]) -> String {
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(
+        "<workbook xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" \
+         xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\"><sheets>",
+    );
+    for (i, name) in names.iter().enumerate() {
+        let _ = write!(xml, "<sheet name=\"{}\" sheetId=\"{}\" r:id=\"rId{}\"/>", escape_xml(name), i + 1, i + 1);
+    }
+    xml.push_str("</sheets></workbook>");
+    xml
+}
+
+fn workbook_rels(sheets: usize) -> String {
+    let mut xml = String::from(XML_DECL);
+    xml.push_str("<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">");
+    for i in 1..=sheets {
+        let _ = write!(
+            xml,
+            "<Relationship Id=\"rId{0}\" \
+             Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet\" \
+             Target=\"worksheets/sheet{0}.xml\"/>",
+            i
+        );
+    }
+    let _ = write!(
+        xml,
+        "<Relationship Id=\"rId{}\" \
+         Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles\" \
+         Target=\"styles.xml\"/></Relationships>",
+        sheets + 1
+    );
+    xml
+}
+
+const XML_DECL: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+const ROOT_RELS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+<Relationship Id=\"rId1\" \
+Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" \
+Target=\"xl/workbook.xml\"/></Relationships>";
+
+/// Default, bold header, date-time, date, time and money cell formats
+const STYLES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<styleSheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\">\
+<numFmts count=\"3\">\
+<numFmt numFmtId=\"164\" formatCode=\"yyyy-mm-dd hh:mm:ss\"/>\
+<numFmt numFmtId=\"165\" formatCode=\"yyyy-mm-dd\"/>\
+<numFmt numFmtId=\"166\" formatCode=\"hh:mm:ss\"/>\
+</numFmts>\
+<fonts count=\"2\"><font><sz val=\"11\"/><name val=\"Calibri\"/></font>\
+<font><b/><sz val=\"11\"/><name val=\"Calibri\"/></font></fonts>\
+<fills count=\"2\"><fill><patternFill patternType=\"none\"/></fill>\
+<fill><patternFill patternType=\"gray125\"/></fill></fills>\
+<borders count=\"1\"><border><left/><right/><top/><bottom/><diagonal/></border></borders>\
+<cellStyleXfs count=\"1\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\"/></cellStyleXfs>\
+<cellXfs count=\"6\">\
+<xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\"/>\
+<xf numFmtId=\"0\" fontId=\"1\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyFont=\"1\"/>\
+<xf numFmtId=\"164\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyNumberFormat=\"1\"/>\
+<xf numFmtId=\"165\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyNumberFormat=\"1\"/>\
+<xf numFmtId=\"166\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyNumberFormat=\"1\"/>\
+<xf numFmtId=\"4\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyNumberFormat=\"1\"/>\
+</cellXfs>\
+<cellStyles count=\"1\"><cellStyle name=\"Normal\" xfId=\"0\" builtinId=\"0\"/></cellStyles>\
+</styleSheet>";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calamine::{open_workbook, Data, Reader, Xlsx};
+    use std::io::Read;
+
+    fn column(name: &str, type_name: &str) -> ColumnInfo {
+        ColumnInfo { name: name.to_string(), type_name: type_name.to_string(), max_width: 0 }
+    }
+
+    #[test]
+    fn cell_ref_rolls_over_after_z() {
+        assert_eq!(cell_ref(0, 1), "A1");
+        assert_eq!(cell_ref(25, 2), "Z2");
+        assert_eq!(cell_ref(26, 3), "AA3");
+        assert_eq!(cell_ref(51, 1), "AZ1");
+        assert_eq!(cell_ref(52, 1), "BA1");
+        assert_eq!(cell_ref(701, 1), "ZZ1");
+        assert_eq!(cell_ref(702, 1), "AAA1");
+    }
+
+    #[test]
+    fn sheet_names_are_valid_and_unique() {
+        let long = "A very long result set name that goes on";
+        let names = sheet_names(["Result", "result", "a/b:c", "  ", long, long].into_iter());
+        assert_eq!(names[0], "Result");
+        assert_eq!(names[1], "result 2");
+        assert_eq!(names[2], "a_b_c");
+        assert_eq!(names[3], "Sheet");
+        assert_eq!(names[4], long.chars().take(31).collect::<String>());
+        assert_eq!(names[5], format!("{} 2", long.chars().take(29).collect::<String>()));
+        assert!(names.iter().all(|n| n.chars().count() <= 31));
+    }
+
+    #[test]
+    fn excel_date_serials() {
+        assert_eq!(excel_date("1900-03-01"), Some((61.0, STYLE_DATE)));
+        assert_eq!(excel_date("2024-01-15 12:00:00"), Some((45306.5, STYLE_DATETIME)));
+        assert_eq!(excel_date("2024-01-15 00:00:00.000"), Some((45306.0, STYLE_DATETIME)));
+        assert_eq!(excel_date("18:00:00"), Some((0.75, STYLE_TIME)));
+        // Before March 1900 Excel's serials are off by its 1900 leap-year bug, so they stay text
+        assert_eq!(excel_date("1900-02-28"), None);
+        assert_eq!(excel_date("1899-12-31 10:00:00"), None);
+        assert_eq!(excel_date("yesterday"), None);
+    }
+
+    #[test]
+    fn exact_decimals_fit_fifteen_digits() {
+        assert!(is_exact_decimal("123.45"));
+        assert!(is_exact_decimal("-0.5"));
+        assert!(is_exact_decimal(" 42 "));
+        assert!(is_exact_decimal("12.3400000000000000"));
+        assert!(is_exact_decimal("0.000000000000001"));
+        assert!(is_exact_decimal("123456789012345"));
+        assert!(!is_exact_decimal("1234567890123456"));
+        assert!(!is_exact_decimal("1234567890.123456"));
+        assert!(!is_exact_decimal("1e5"));
+        assert!(!is_exact_decimal("-"));
+        assert!(!is_exact_decimal(""));
+    }
+
+    #[test]
+    fn workbook_opens_with_typed_cells() {
+        let path = std::env::temp_dir().join(format!("xlsx-test-{}.xlsx", std::process::id()));
+        let columns = vec![
+            column("Id", "INT"),
+            column("Name", "NVARCHAR"),
+            column("Active", "BIT"),
+            column("Opened", "DATETIME"),
+            column("Balance", "DECIMAL"),
+            column("Card", "BIGINT"),
+        ];
+        let rows = vec![
+            vec![
+                CellValue::Int(1),
+                CellValue::String("Riyadh <HQ> & co".to_string()),
+                CellValue::Bool(true),
+                CellValue::DateTime("2024-01-15 12:00:00".to_string()),
+                CellValue::String("1234.50".to_string()),
+                CellValue::Int(123_456_789_012_345),
+            ],
+            vec![
+                CellValue::Int(2),
+                CellValue::Null,
+                CellValue::Bool(false),
+                CellValue::DateTime("1900-01-01".to_string()),
+                CellValue::String("12345678901234567.89".to_string()),
+                CellValue::Int(4_847_352_989_263_094),
+            ],
+        ];
+        let other = vec![column("n", "INT")];
+        let sheets = [
+            Sheet { name: "Result 1".to_string(), columns: &columns, rows: &rows },
+            Sheet { name: "Result 1".to_string(), columns: &other, rows: &[] },
+        ];
+        write_xlsx(&path, &sheets).unwrap();
+
+        // Every entry decompresses with a matching CRC
+        let mut archive = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut names = Vec::new();
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).unwrap();
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content).unwrap();
+            names.push(entry.name().to_string());
+        }
+        assert!(names.contains(&"[Content_Types].xml".to_string()));
+        assert!(names.contains(&"xl/worksheets/sheet2.xml".to_string()));
+
+        let mut workbook: Xlsx<_> = open_workbook(&path).unwrap();
+        assert_eq!(workbook.sheet_names(), vec!["Result 1".to_string(), "Result 1 2".to_string()]);
+        let range = workbook.worksheet_range("Result 1").unwrap();
+        assert_eq!(range.get_value((0, 0)), Some(&Data::String("Id".to_string())));
+        assert_eq!(range.get_value((1, 0)), Some(&Data::Float(1.0)));
+        assert_eq!(range.get_value((1, 1)), Some(&Data::String("Riyadh <HQ> & co".to_string())));
+        assert_eq!(range.get_value((1, 2)), Some(&Data::Bool(true)));
+        match range.get_value((1, 3)) {
+            Some(Data::DateTime(date)) => assert_eq!(date.as_f64(), 45306.5),
+            other => panic!("expected a date, got {:?}", other),
+        }
+        assert_eq!(range.get_value((1, 4)), Some(&Data::Float(1234.5)));
+        assert_eq!(range.get_value((2, 1)), Some(&Data::Empty));
+        assert_eq!(range.get_value((2, 3)), Some(&Data::String("1900-01-01".to_string())));
+        assert_eq!(range.get_value((2, 4)), Some(&Data::String("12345678901234567.89".to_string())));
+        assert_eq!(range.get_value((1, 5)), Some(&Data::Float(123_456_789_012_345.0)));
+        assert_eq!(range.get_value((2, 5)), Some(&Data::String("4847352989263094".to_string())));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
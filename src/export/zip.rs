@@ -0,0 +1,147 @@
+//! Minimal zip container writer (deflate, no zip64) for .xlsx packages
+
+use anyhow::{bail, Result};
+use chrono::{Datelike, Local, Timelike};
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::io::Write;
+
+/// Central directory record of a written entry
+struct Entry {
+    name: String,
+    crc: u32,
+    compressed: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes deflated entries and the central directory
+pub(crate) struct ZipWriter<W: Write> {
+    out: W,
+    written: u64,
+    entries: Vec<Entry>,
+    time: u16,
+    date: u16,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(out: W) -> Self {
+        let now = Local::now();
+        let time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+        let date = (((now.year().clamp(1980, 2107) - 1980) as u32) << 9 | (now.month() << 5) | now.day()) as u16;
+        Self { out, written: 0, entries: Vec::new(), time, date }
+    }
+
+    /// Compress and write one file
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+
+        let (Ok(size), Ok(compressed_len), Ok(offset)) =
+            (u32::try_from(data.len()), u32::try_from(compressed.len()), u32::try_from(self.written))
+        else {
+            bail!("{} is too large for a workbook (4 GB limit)", name);
+        };
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        self.push_common(&mut header, crc.sum(), compressed_len, size, name);
+        header.extend_from_slice(name.as_bytes());
+        self.write(&header)?;
+        self.write(&compressed)?;
+
+        self.entries.push(Entry {
+            name: name.to_string(),
+            crc: crc.sum(),
+            compressed: compressed_len,
+            size,
+            offset,
+        });
+        Ok(())
+    }
+
+    /// Write the central directory and return the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        let Ok(start) = u32::try_from(self.written) else {
+            bail!("workbook is too large (4 GB limit)");
+        };
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            let mut record = Vec::with_capacity(46 + entry.name.len());
+            record.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            record.extend_from_slice(&20u16.to_le_bytes()); // version made by
+            self.push_common(&mut record, entry.crc, entry.compressed, entry.size, &entry.name);
+            record.extend_from_slice(&0u16.to_le_bytes()); // comment length
+            record.extend_from_slice(&0u16.to_le_bytes()); // disk number
+            record.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+            record.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+            record.extend_from_slice(&entry.offset.to_le_bytes());
+            record.extend_from_slice(entry.name.as_bytes());
+            self.write(&record)?;
+        }
+        let size = (self.written - start as u64) as u32;
+
+        let mut end = Vec::with_capacity(22);
+        end.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        end.extend_from_slice(&size.to_le_bytes());
+        end.extend_from_slice(&start.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        self.write(&end)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Fields shared by local headers and central directory records, up to the name
+    fn push_common(&self, buf: &mut Vec<u8>, crc: u32, compressed: u32, size: u32, name: &str) {
+        buf.extend_from_slice(&20u16.to_le_bytes()); // version needed: deflate
+        buf.extend_from_slice(&0x0800u16.to_le_bytes()); // UTF-8 names
+        buf.extend_from_slice(&8u16.to_le_bytes()); // deflate
+        buf.extend_from_slice(&self.time.to_le_bytes());
+        buf.extend_from_slice(&self.date.to_le_bytes());
+        buf.extend_from_slice(&crc.to_le_bytes());
+        buf.extend_from_slice(&compressed.to_le_bytes());
+        buf.extend_from_slice(&size.to_le_bytes());
+        buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.out.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn archive_reads_back() {
+        let big = "row,value\n".repeat(2000);
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add("empty.txt", b"").unwrap();
+        zip.add("dir/big.csv", big.as_bytes()).unwrap();
+        zip.add("tiny.xml", b"<a/>").unwrap();
+        let bytes = zip.finish().unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), 3);
+        let mut read = |name: &str| {
+            let mut content = String::new();
+            archive.by_name(name).unwrap().read_to_string(&mut content).unwrap();
+            content
+        };
+        assert_eq!(read("empty.txt"), "");
+        assert_eq!(read("dir/big.csv"), big);
+        assert_eq!(read("tiny.xml"), "<a/>");
+    }
+}
@@ -0,0 +1,641 @@
+//! Loading a CSV file into a table in one transaction: TDS bulk copy, or batched
+//! INSERTs when the table has types this driver build cannot bulk copy
+
+use crate::db::{
+    begin_transaction, end_transaction, format_data_type, quote_ident, CellValue, ColumnDef, ColumnInfo, QueryExecutor,
+};
+use crate::export::{generate_script, is_decimal_text, ScriptKind, ScriptOptions, MAX_VALUES_ROWS};
+use crate::import::{open_csv, parse_bool, parse_date, parse_datetime, CsvSettings};
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use csv::StringRecord;
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tiberius::numeric::Numeric;
+use tiberius::time::{DateTime, SmallDateTime};
+use tiberius::xml::XmlData;
+use tiberius::{Client, ColumnData, TokenRow, Uuid};
+use tokio::net::TcpStream;
+use tokio_util::compat::Compat;
+
+/// Row errors kept for the report; further ones are only counted
+pub const MAX_REPORTED_ERRORS: usize = 200;
+
+/// Column types bulk copy can read and send without the TDS 7.3 date/time types
+const BULK_TYPES: [&str; 21] = [
+    "tinyint", "smallint", "int", "bigint", "bit", "real", "float", "decimal", "numeric",
+    "uniqueidentifier", "binary", "varbinary", "timestamp", "datetime", "smalldatetime",
+    "char", "varchar", "nchar", "nvarchar", "sysname", "xml",
+];
+
+/// How rows reach the table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadMethod {
+    /// TDS bulk copy (`INSERT BULK`)
+    BulkCopy,
+    /// Multi-row `INSERT ... VALUES` statements with typed literals
+    Insert,
+}
+
+impl LoadMethod {
+    /// Bulk copy reads every column of the table, loaded or not, so one
+    /// unsupported type anywhere means INSERTs
+    pub fn for_table(columns: &[ColumnDef]) -> Self {
+        if columns.iter().all(|c| BULK_TYPES.contains(&c.data_type.as_str())) {
+            LoadMethod::BulkCopy
+        } else {
+            LoadMethod::Insert
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LoadMethod::BulkCopy => "Bulk copy",
+            LoadMethod::Insert => "INSERT batches",
+        }
+    }
+}
+
+/// A table column the import fills and the CSV field feeding it (`None` sends NULL)
+#[derive(Clone, Debug)]
+pub struct ImportColumn {
+    pub target: ColumnDef,
+    pub source: Option<usize>,
+}
+
+/// Identity, computed and rowversion columns are filled by the server
+pub fn is_loadable(column: &ColumnDef) -> bool {
+    !column.is_identity && !column.is_computed && column.data_type != "timestamp"
+}
+
+/// Everything the load needs besides the file
+#[derive(Clone, Debug)]
+pub struct ImportPlan {
+    /// Quoted target, e.g. `[dbo].[Branches]`
+    pub table: String,
+    /// `CREATE TABLE` run inside the load's transaction, for a new table
+    pub create_sql: Option<String>,
+    /// Every loadable column of the table, in table order
+    pub columns: Vec<ImportColumn>,
+    pub settings: CsvSettings,
+    pub method: LoadMethod,
+}
+
+/// Counters of a running import, shared with the UI, and its cancel flag
+#[derive(Debug, Default)]
+pub struct ImportProgress {
+    checked: AtomicU64,
+    loaded: AtomicU64,
+    loading: AtomicBool,
+    cancelled: AtomicBool,
+}
+
+impl ImportProgress {
+    /// Rows converted in the checking pass
+    pub fn checked(&self) -> u64 {
+        self.checked.load(Ordering::Relaxed)
+    }
+
+    /// Rows sent to the server
+    pub fn loaded(&self) -> u64 {
+        self.loaded.load(Ordering::Relaxed)
+    }
+
+    /// Every row passed the checks and the transaction is open
+    pub fn is_loading(&self) -> bool {
+        self.loading.load(Ordering::Relaxed)
+    }
+
+    /// Stop after the current row; anything sent is rolled back
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// A CSV row that cannot be loaded
+#[derive(Clone, Debug)]
+pub struct RowError {
+    /// Line in the file, 1-based
+    pub line: u64,
+    pub message: String,
+}
+
+/// How an import ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportStatus {
+    Committed,
+    /// Some rows failed the checks; nothing was sent
+    Rejected,
+    /// Stopped by `ImportProgress::cancel`; anything sent was rolled back
+    Cancelled,
+}
+
+#[derive(Clone, Debug)]
+pub struct ImportReport {
+    pub status: ImportStatus,
+    /// Rows committed
+    pub rows: u64,
+    /// The first `MAX_REPORTED_ERRORS` row errors
+    pub errors: Vec<RowError>,
+    pub error_count: u64,
+}
+
+/// Check every row of the file against the plan, then load it in one transaction.
+/// Rows failing the checks are reported and nothing is sent; a server error rolls back.
+pub async fn import_csv(
+    client: &mut Client<Compat<TcpStream>>,
+    path: &Path,
+    plan: &ImportPlan,
+    progress: &ImportProgress,
+) -> Result<ImportReport> {
+    let mut report = ImportReport { status: ImportStatus::Rejected, rows: 0, errors: Vec::new(), error_count: 0 };
+    for record in open_csv(path, plan.settings)?.records() {
+        if progress.is_cancelled() {
+            report.status = ImportStatus::Cancelled;
+            return Ok(report);
+        }
+        let converted = match record {
+            Ok(record) => convert_record(&record, &plan.columns).map_err(|message| (line_of(&record), message)),
+            Err(e) => match e.kind() {
+                csv::ErrorKind::Utf8 { .. } => {
+                    let line = e.position().map_or(0, |p| p.line());
+                    Err((line, "not valid UTF-8; save the file as UTF-8".to_string()))
+                }
+                _ => return Err(e).context("cannot read the file"),
+            },
+        };
+        if let Err((line, message)) = converted {
+            report.error_count += 1;
+            if report.errors.len() < MAX_REPORTED_ERRORS {
+                report.errors.push(RowError { line, message });
+            }
+        }
+        progress.checked.fetch_add(1, Ordering::Relaxed);
+    }
+    if report.error_count > 0 {
+        return Ok(report);
+    }
+
+    progress.loading.store(true, Ordering::Relaxed);
+    let xact_abort_was_on = begin_transaction(client).await?;
+    let loaded = match plan.create_sql.as_deref() {
+        Some(sql) => match client.simple_query(sql).await {
+            Ok(stream) => stream.into_results().await.map(|_| ()).map_err(anyhow::Error::from),
+            Err(e) => Err(e.into()),
+        },
+        None => Ok(()),
+    };
+    let loaded = match loaded {
+        Ok(()) => match plan.method {
+            LoadMethod::BulkCopy => bulk_copy(client, path, plan, progress).await,
+            LoadMethod::Insert => insert_batches(client, path, plan, progress).await,
+        },
+        Err(e) => Err(e.context("CREATE TABLE failed")),
+    };
+
+    match loaded {
+        Ok(rows) if !progress.is_cancelled() => {
+            if let Err(e) = end_transaction(client, true, xact_abort_was_on).await {
+                let _ = end_transaction(client, false, xact_abort_was_on).await;
+                return Err(e.context("Import rolled back"));
+            }
+            report.status = ImportStatus::Committed;
+            report.rows = rows;
+            Ok(report)
+        }
+        outcome => {
+            let _ = end_transaction(client, false, xact_abort_was_on).await;
+            match outcome {
+                Ok(_) => {
+                    report.status = ImportStatus::Cancelled;
+                    Ok(report)
+                }
+                Err(e) => Err(e.context("Import rolled back")),
+            }
+        }
+    }
+}
+
+fn line_of(record: &StringRecord) -> u64 {
+    record.position().map_or(0, |p| p.line())
+}
+
+/// Send every row with `INSERT BULK`; returns the rows the server took
+async fn bulk_copy(
+    client: &mut Client<Compat<TcpStream>>,
+    path: &Path,
+    plan: &ImportPlan,
+    progress: &ImportProgress,
+) -> Result<u64> {
+    let mut reader = open_csv(path, plan.settings)?;
+    let mut request = client.bulk_insert(&plan.table).await?;
+    for record in reader.records() {
+        // Finalizing a partial load keeps the connection usable; the caller rolls it back
+        if progress.is_cancelled() {
+            break;
+        }
+        let record = record?;
+        let line = line_of(&record);
+        let cells = convert_record(&record, &plan.columns).map_err(|e| anyhow::anyhow!("line {}: {}", line, e))?;
+        let mut row = TokenRow::with_capacity(cells.len());
+        for (cell, column) in cells.into_iter().zip(&plan.columns) {
+            row.push(column_data(cell, &column.target));
+        }
+        request.send(row).await.with_context(|| format!("line {}", line))?;
+        progress.loaded.fetch_add(1, Ordering::Relaxed);
+    }
+    Ok(request.finalize().await?.total())
+}
+
+/// Send the rows as `INSERT ... VALUES` statements of up to `MAX_VALUES_ROWS` rows,
+/// naming only the mapped columns so the others take their defaults
+async fn insert_batches(
+    client: &mut Client<Compat<TcpStream>>,
+    path: &Path,
+    plan: &ImportPlan,
+    progress: &ImportProgress,
+) -> Result<u64> {
+    let mapped: Vec<usize> = (0..plan.columns.len()).filter(|&i| plan.columns[i].source.is_some()).collect();
+    let columns: Vec<ColumnInfo> = mapped
+        .iter()
+        .map(|&i| ColumnInfo {
+            name: plan.columns[i].target.name.clone(),
+            type_name: plan.columns[i].target.data_type.to_uppercase(),
+            max_width: 0,
+        })
+        .collect();
+    let options = ScriptOptions {
+        table: plan.table.clone(),
+        kind: ScriptKind::Insert,
+        batch_size: MAX_VALUES_ROWS,
+        identity_insert: false,
+        keys: Vec::new(),
+    };
+
+    let mut reader = open_csv(path, plan.settings)?;
+    let mut batch: Vec<Vec<CellValue>> = Vec::with_capacity(MAX_VALUES_ROWS);
+    let mut records = reader.records();
+    let mut total = 0;
+    loop {
+        let record = if progress.is_cancelled() { None } else { records.next().transpose()? };
+        if let Some(record) = record.as_ref() {
+            let cells = convert_record(record, &plan.columns)
+                .map_err(|e| anyhow::anyhow!("line {}: {}", line_of(record), e))?;
+            batch.push(mapped.iter().map(|&i| cells[i].clone()).collect());
+        }
+        if batch.len() == MAX_VALUES_ROWS || (record.is_none() && !batch.is_empty()) {
+            let rows: Vec<&[CellValue]> = batch.iter().map(Vec::as_slice).collect();
+            let sql = generate_script(&columns, &rows, &options);
+            client.simple_query(sql).await?.into_results().await?;
+            total += batch.len() as u64;
+            progress.loaded.store(total, Ordering::Relaxed);
+            batch.clear();
+        }
+        if record.is_none() {
+            return Ok(total);
+        }
+    }
+}
+
+/// A cell per plan column, or the first field that does not fit its column
+fn convert_record(record: &StringRecord, columns: &[ImportColumn]) -> Result<Vec<CellValue>, String> {
+    columns
+        .iter()
+        .map(|column| {
+            let Some(index) = column.source else {
+                return Ok(CellValue::Null);
+            };
+            let Some(text) = record.get(index) else {
+                return Err(format!("expected at least {} fields, found {}", index + 1, record.len()));
+            };
+            parse_value(text, &column.target).map_err(|e| format!("{}: {}", quote_ident(&column.target.name), e))
+        })
+        .collect()
+}
+
+/// Text of a CSV field as a value of the column's type. Empty is NULL, or an
+/// empty string for a NOT NULL text column.
+pub fn parse_value(text: &str, column: &ColumnDef) -> Result<CellValue, String> {
+    let data_type = column.data_type.as_str();
+    let is_text = matches!(data_type, "char" | "varchar" | "nchar" | "nvarchar" | "sysname" | "text" | "ntext");
+    let trimmed = text.trim();
+    if text.is_empty() || (!is_text && trimmed.is_empty()) {
+        return match column.is_nullable {
+            true => Ok(CellValue::Null),
+            false if is_text => Ok(CellValue::String(String::new())),
+            false => Err("empty value in a NOT NULL column".to_string()),
+        };
+    }
+
+    let not_a = |what: &str| format!("'{}' is not {}", trimmed, what);
+    match data_type {
+        "tinyint" => integer(trimmed, 0, u8::MAX as i64),
+        "smallint" => integer(trimmed, i16::MIN as i64, i16::MAX as i64),
+        "int" => integer(trimmed, i32::MIN as i64, i32::MAX as i64),
+        "bigint" => integer(trimmed, i64::MIN, i64::MAX),
+        "bit" => parse_bool(trimmed).map(CellValue::Bool).ok_or_else(|| not_a("true/false or 1/0")),
+        "real" | "float" => match trimmed.parse::<f64>() {
+            Ok(v) if v.is_finite() => Ok(CellValue::Float(v)),
+            _ => Err(not_a("a number")),
+        },
+        "decimal" | "numeric" => {
+            let scale = column.scale.unwrap_or(0) as usize;
+            decimal(trimmed, column.precision.unwrap_or(18) as usize, scale)
+        }
+        "money" => decimal(trimmed, 19, 4),
+        "smallmoney" => decimal(trimmed, 10, 4),
+        "date" => parse_date(trimmed)
+            .map(|d| CellValue::DateTime(d.format("%Y-%m-%d").to_string()))
+            .ok_or_else(|| not_a("a date (YYYY-MM-DD)")),
+        "datetime" | "smalldatetime" | "datetime2" => {
+            let value = parse_datetime(trimmed).ok_or_else(|| not_a("a date and time (YYYY-MM-DD hh:mm:ss)"))?;
+            let (from, to) = match data_type {
+                "datetime" => (date(1753, 1, 1), date(9999, 12, 31)),
+                "smalldatetime" => (date(1900, 1, 1), date(2079, 6, 6)),
+                _ => (date(1, 1, 1), date(9999, 12, 31)),
+            };
+            if value.date() < from || value.date() > to {
+                return Err(format!("'{}' is outside the {} range", trimmed, data_type.to_uppercase()));
+            }
+            Ok(CellValue::DateTime(value.format("%Y-%m-%d %H:%M:%S%.f").to_string()))
+        }
+        "time" => NaiveTime::parse_from_str(trimmed, "%H:%M:%S%.f")
+            .or_else(|_| NaiveTime::parse_from_str(trimmed, "%H:%M"))
+            .map(|t| CellValue::String(t.format("%H:%M:%S%.f").to_string()))
+            .map_err(|_| not_a("a time (hh:mm:ss)")),
+        "uniqueidentifier" => Uuid::parse_str(trimmed)
+            .map(|u| CellValue::String(u.to_string().to_uppercase()))
+            .map_err(|_| not_a("a GUID")),
+        "binary" | "varbinary" | "image" => {
+            let bytes = hex(trimmed).ok_or_else(|| not_a("hex such as 0x0A1B"))?;
+            match column.max_length.filter(|&n| n > 0) {
+                Some(max) if bytes.len() > max as usize => Err(format!("{} bytes; the column holds {}", bytes.len(), max)),
+                _ => Ok(CellValue::Binary(bytes)),
+            }
+        }
+        _ => {
+            // nchar/nvarchar lengths are in bytes, two per UTF-16 unit
+            let (length, max) = match data_type {
+                "nchar" | "nvarchar" | "sysname" => (text.encode_utf16().count(), column.max_length.map(|n| n / 2)),
+                "char" | "varchar" => (text.chars().count(), column.max_length),
+                _ => (0, None),
+            };
+            match max.filter(|&n| n > 0) {
+                Some(max) if length > max as usize => {
+                    Err(format!("{} characters; the column holds {}", length, max))
+                }
+                _ => Ok(CellValue::String(text.to_string())),
+            }
+        }
+    }
+}
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap_or_default()
+}
+
+fn integer(text: &str, min: i64, max: i64) -> Result<CellValue, String> {
+    match text.parse::<i64>() {
+        Ok(v) if (min..=max).contains(&v) => Ok(CellValue::Int(v)),
+        Ok(_) => Err(format!("{} is outside {}..{}", text, min, max)),
+        Err(_) => Err(format!("'{}' is not a whole number", text)),
+    }
+}
+
+/// A decimal fitting `precision` digits with at most `scale` after the point
+fn decimal(text: &str, precision: usize, scale: usize) -> Result<CellValue, String> {
+    if !is_decimal_text(text) {
+        return Err(format!("'{}' is not a number", text));
+    }
+    let digits = text.trim_start_matches('-');
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if fraction.trim_end_matches('0').len() > scale {
+        return Err(format!("'{}' has more than {} decimal places", text, scale));
+    }
+    if whole.trim_start_matches('0').len() > precision.saturating_sub(scale) {
+        return Err(format!("'{}' is too large for DECIMAL({}, {})", text, precision, scale));
+    }
+    Ok(CellValue::String(text.to_string()))
+}
+
+/// `0x0A1B` or `0A1B` as bytes
+fn hex(text: &str) -> Option<Vec<u8>> {
+    let digits = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).unwrap_or(text);
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A checked cell as the wire value bulk copy expects for the column's type
+fn column_data(cell: CellValue, column: &ColumnDef) -> ColumnData<'static> {
+    let int = match &cell {
+        CellValue::Int(v) => Some(*v),
+        _ => None,
+    };
+    let text = match &cell {
+        CellValue::String(v) | CellValue::DateTime(v) => Some(v.clone()),
+        _ => None,
+    };
+    match column.data_type.as_str() {
+        "tinyint" => ColumnData::U8(int.map(|v| v as u8)),
+        "smallint" => ColumnData::I16(int.map(|v| v as i16)),
+        "int" => ColumnData::I32(int.map(|v| v as i32)),
+        "bigint" => ColumnData::I64(int),
+        "bit" => ColumnData::Bit(match cell {
+            CellValue::Bool(v) => Some(v),
+            _ => None,
+        }),
+        "real" => ColumnData::F32(match cell {
+            CellValue::Float(v) => Some(v as f32),
+            _ => None,
+        }),
+        "float" => ColumnData::F64(match cell {
+            CellValue::Float(v) => Some(v),
+            _ => None,
+        }),
+        "decimal" | "numeric" => {
+            // The wire scale must be the column's
+            let scale = column.scale.unwrap_or(0) as u8;
+            ColumnData::Numeric(text.map(|t| Numeric::new_with_scale(scaled(&t, scale), scale)))
+        }
+        "uniqueidentifier" => ColumnData::Guid(text.and_then(|t| Uuid::parse_str(&t).ok())),
+        "binary" | "varbinary" => ColumnData::Binary(match cell {
+            CellValue::Binary(v) => Some(Cow::Owned(v)),
+            _ => None,
+        }),
+        "datetime" => ColumnData::DateTime(text.and_then(|t| parse_datetime(&t)).map(|dt| {
+            let (days, fragments) = days_and_fragments(dt);
+            DateTime::new(days, fragments)
+        })),
+        "smalldatetime" => ColumnData::SmallDateTime(text.and_then(|t| parse_datetime(&t)).map(|dt| {
+            // Minutes, rounding 29.999 seconds and up to the next minute
+            let rounded = dt + chrono::Duration::milliseconds(30_001);
+            let (days, _) = days_and_fragments(rounded);
+            SmallDateTime::new(days as u16, (rounded.hour() * 60 + rounded.minute()) as u16)
+        })),
+        "xml" => ColumnData::Xml(text.map(|t| Cow::Owned(XmlData::new(t)))),
+        _ => ColumnData::String(text.map(Cow::Owned)),
+    }
+}
+
+/// Days since 1900-01-01 and 1/300 s ticks since midnight, as DATETIME stores them
+fn days_and_fragments(value: NaiveDateTime) -> (i32, u32) {
+    let nanos = value.num_seconds_from_midnight() as u64 * 1_000_000_000 + value.nanosecond() as u64;
+    let fragments = ((nanos * 300 + 500_000_000) / 1_000_000_000) as u32;
+    let days = (value.date() - date(1900, 1, 1)).num_days() as i32;
+    match fragments {
+        25_920_000.. => (days + 1, 0),
+        _ => (days, fragments),
+    }
+}
+
+/// `-12.5` at scale 2 as -1250
+fn scaled(text: &str, scale: u8) -> i128 {
+    let negative = text.starts_with('-');
+    let digits = text.trim_start_matches('-');
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let mut fraction: String = fraction.chars().take(scale as usize).collect();
+    while fraction.len() < scale as usize {
+        fraction.push('0');
+    }
+    let value: i128 = format!("{}{}", whole, fraction).parse().unwrap_or(0);
+    if negative { -value } else { value }
+}
+
+/// Target from what the user typed: `Branches`, `dbo.Branches` or `[stg].[Branches]`;
+/// returns the schema (default `dbo`) and table names
+pub fn split_table_name(name: &str) -> (String, String) {
+    let (schema, table) = QueryExecutor::parse_table_name(name.trim());
+    (schema.unwrap_or_else(|| "dbo".to_string()), table)
+}
+
+/// `[schema].[table]`
+pub fn qualified_table(schema: &str, table: &str) -> String {
+    format!("{}.{}", quote_ident(schema), quote_ident(table))
+}
+
+/// A column for `CREATE TABLE` from a type as written in SQL, e.g. `NVARCHAR(50)`,
+/// `DECIMAL(18, 2)`; lengths are kept in bytes as `sys.columns` has them
+pub fn column_def(name: &str, sql_type: &str) -> ColumnDef {
+    let (base, args) = match sql_type.split_once('(') {
+        Some((base, rest)) => (base.trim(), rest.trim_end_matches(')')),
+        None => (sql_type.trim(), ""),
+    };
+    let data_type = base.to_lowercase();
+    let numbers: Vec<Option<i32>> = args.split(',').map(|a| a.trim().parse().ok()).collect();
+    let first = numbers.first().copied().flatten();
+    let is_max = args.trim().eq_ignore_ascii_case("max");
+    let (max_length, precision, scale) = match data_type.as_str() {
+        "nvarchar" | "nchar" if is_max => (Some(-1), None, None),
+        "nvarchar" | "nchar" => (first.map(|n| n * 2), None, None),
+        "varchar" | "char" | "varbinary" | "binary" if is_max => (Some(-1), None, None),
+        "varchar" | "char" | "varbinary" | "binary" => (first, None, None),
+        "decimal" | "numeric" => (None, first.or(Some(18)), numbers.get(1).copied().flatten().or(Some(0))),
+        "float" => (None, Some(53), None),
+        _ => (None, None, None),
+    };
+    ColumnDef {
+        name: name.to_string(),
+        data_type,
+        is_nullable: true,
+        is_primary_key: false,
+        max_length,
+        precision,
+        scale,
+        is_identity: false,
+        is_computed: false,
+    }
+}
+
+/// `CREATE TABLE` for the columns, every one nullable
+pub fn create_table_sql(table: &str, columns: &[ColumnDef]) -> String {
+    let lines: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            let data_type = format_data_type(
+                &c.data_type,
+                c.max_length.unwrap_or(0),
+                c.precision.unwrap_or(0),
+                c.scale.unwrap_or(0),
+            );
+            format!("    {} {} NULL", quote_ident(&c.name), data_type)
+        })
+        .collect();
+    format!("CREATE TABLE {} (\n{}\n);", table, lines.join(",\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+    }
+
+    #[test]
+    fn scaled_pads_and_truncates_to_the_scale() {
+        assert_eq!(scaled("-12.5", 2), -1250);
+        assert_eq!(scaled("12", 2), 1200);
+        assert_eq!(scaled("-0.05", 2), -5);
+        assert_eq!(scaled("0.125", 2), 12);
+        assert_eq!(scaled("7.9", 0), 7);
+        assert_eq!(scaled("99999999999999999999999999999999999999", 0), 99999999999999999999999999999999999999);
+    }
+
+    #[test]
+    fn days_and_fragments_round_to_ticks() {
+        assert_eq!(days_and_fragments(datetime("1900-01-01 00:00:00")), (0, 0));
+        assert_eq!(days_and_fragments(datetime("1899-12-31 00:00:00")), (-1, 0));
+        assert_eq!(days_and_fragments(datetime("2024-01-15 12:00:00")), (45304, 12_960_000));
+        // 1/300 s ticks: 10 ms is three of them, 5 ms rounds up to two
+        assert_eq!(days_and_fragments(datetime("1900-01-01 00:00:00.010")), (0, 3));
+        assert_eq!(days_and_fragments(datetime("1900-01-01 00:00:00.005")), (0, 2));
+        // The last tick of the day rounds into the next one
+        assert_eq!(days_and_fragments(datetime("2024-01-15 23:59:59.999")), (45305, 0));
+        assert_eq!(days_and_fragments(datetime("2024-01-15 23:59:59.997")), (45304, 25_919_999));
+    }
+
+    #[test]
+    fn parse_value_checks_the_column_type() {
+        let parse = |text: &str, sql_type: &str| parse_value(text, &column_def("c", sql_type));
+        assert_eq!(parse("42", "INT"), Ok(CellValue::Int(42)));
+        assert_eq!(parse(" -7 ", "SMALLINT"), Ok(CellValue::Int(-7)));
+        assert!(parse("256", "TINYINT").is_err());
+        assert!(parse("2147483648", "INT").is_err());
+        assert_eq!(parse("1", "BIT"), Ok(CellValue::Bool(true)));
+        assert_eq!(parse("False", "BIT"), Ok(CellValue::Bool(false)));
+        assert!(parse("2", "BIT").is_err());
+        assert_eq!(parse("-12.50", "DECIMAL(5, 2)"), Ok(CellValue::String("-12.50".to_string())));
+        assert!(parse("1.234", "DECIMAL(5, 2)").is_err());
+        assert!(parse("1234.5", "DECIMAL(5, 2)").is_err());
+        assert_eq!(parse("2024-01-15", "DATE"), Ok(CellValue::DateTime("2024-01-15".to_string())));
+        assert!(parse("1752-12-31 00:00:00", "DATETIME").is_err());
+        assert_eq!(parse("0742", "NVARCHAR(10)"), Ok(CellValue::String("0742".to_string())));
+        assert!(parse("abcdef", "NVARCHAR(5)").is_err());
+        assert_eq!(parse("0x0A1B", "VARBINARY(2)"), Ok(CellValue::Binary(vec![0x0A, 0x1B])));
+        assert!(parse("0x0A1B", "VARBINARY(1)").is_err());
+    }
+
+    #[test]
+    fn empty_values_follow_nullability() {
+        let mut int = column_def("c", "INT");
+        let mut text = column_def("c", "NVARCHAR(10)");
+        assert_eq!(parse_value("", &int), Ok(CellValue::Null));
+        assert_eq!(parse_value("  ", &int), Ok(CellValue::Null));
+        assert_eq!(parse_value("", &text), Ok(CellValue::Null));
+        assert_eq!(parse_value("  ", &text), Ok(CellValue::String("  ".to_string())));
+        int.is_nullable = false;
+        text.is_nullable = false;
+        assert!(parse_value("", &int).is_err());
+        assert_eq!(parse_value("", &text), Ok(CellValue::String(String::new())));
+    }
+}
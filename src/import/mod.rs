@@ -0,0 +1,7 @@
+//! Loading CSV files into tables
+
+mod load;
+mod source;
+
+pub use load::*;
+pub use source::*;
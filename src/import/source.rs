@@ -0,0 +1,289 @@
+//! Reading a CSV file and inferring SQL types for its columns
+
+use crate::export::is_decimal_text;
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::fs::File;
+use std::path::Path;
+
+/// Rows read to infer types; the preview shows the first few
+pub const SAMPLE_ROWS: usize = 10_000;
+
+/// Types offered for a column of a new table; the inferred one is added when missing
+pub const TYPE_CHOICES: [&str; 12] = [
+    "NVARCHAR(50)",
+    "NVARCHAR(255)",
+    "NVARCHAR(4000)",
+    "NVARCHAR(MAX)",
+    "INT",
+    "BIGINT",
+    "DECIMAL(18, 2)",
+    "FLOAT",
+    "BIT",
+    "DATE",
+    "DATETIME",
+    "UNIQUEIDENTIFIER",
+];
+
+/// How the file is split into fields
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CsvSettings {
+    pub delimiter: char,
+    /// The first record holds column names
+    pub has_headers: bool,
+}
+
+/// Column names, sampled rows and an SQL type per column
+#[derive(Clone, Debug)]
+pub struct CsvSample {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub types: Vec<String>,
+    /// The file has more rows than were sampled
+    pub truncated: bool,
+}
+
+/// Reader over the file's records; the header record, if any, is skipped
+pub fn open_csv(path: &Path, settings: CsvSettings) -> Result<csv::Reader<File>> {
+    if !settings.delimiter.is_ascii() {
+        anyhow::bail!("the delimiter must be an ASCII character");
+    }
+    csv::ReaderBuilder::new()
+        .delimiter(settings.delimiter as u8)
+        .has_headers(settings.has_headers)
+        .flexible(true)
+        .from_path(path)
+        .with_context(|| format!("cannot open {}", path.display()))
+}
+
+/// Read the header and up to `SAMPLE_ROWS` rows, inferring each column's type
+pub fn read_sample(path: &Path, settings: CsvSettings) -> Result<CsvSample> {
+    let mut reader = open_csv(path, settings)?;
+    let mut rows = Vec::new();
+    let mut truncated = false;
+    for record in reader.records() {
+        if rows.len() == SAMPLE_ROWS {
+            truncated = true;
+            break;
+        }
+        let record = record.context("cannot read the file as UTF-8 CSV")?;
+        rows.push(record.iter().map(str::to_string).collect::<Vec<_>>());
+    }
+
+    let named: Vec<String> = if settings.has_headers {
+        reader.headers()?.iter().map(|h| h.trim().to_string()).collect()
+    } else {
+        Vec::new()
+    };
+    let width = rows.iter().map(Vec::len).chain([named.len()]).max().unwrap_or(0);
+    if width == 0 {
+        anyhow::bail!("{} has no columns", path.display());
+    }
+    let headers = unique_names(&named, width);
+
+    let types = (0..width)
+        .map(|i| {
+            let mut guess = TypeGuess::new();
+            for row in &rows {
+                guess.observe(row.get(i).map_or("", String::as_str));
+            }
+            guess.sql_type()
+        })
+        .collect();
+
+    Ok(CsvSample { headers, rows, types, truncated })
+}
+
+/// Header names with blanks filled as `ColumnN` and repeats suffixed `_2`, `_3`, ...
+fn unique_names(named: &[String], width: usize) -> Vec<String> {
+    let mut names: Vec<String> = Vec::with_capacity(width);
+    for i in 0..width {
+        let base = match named.get(i).filter(|n| !n.is_empty()) {
+            Some(name) => name.clone(),
+            None => format!("Column{}", i + 1),
+        };
+        let mut name = base.clone();
+        let mut n = 2;
+        while names.iter().any(|existing| existing.eq_ignore_ascii_case(&name)) {
+            name = format!("{}_{}", base, n);
+            n += 1;
+        }
+        names.push(name);
+    }
+    names
+}
+
+/// `true`/`false`, `yes`/`no`, `y`/`n` or `1`/`0`, in any case
+pub fn parse_bool(text: &str) -> Option<bool> {
+    match text.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" => Some(true),
+        "false" | "no" | "n" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// ISO date: `2024-03-01` or `2024/03/01`
+pub fn parse_date(text: &str) -> Option<NaiveDate> {
+    ["%Y-%m-%d", "%Y/%m/%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+}
+
+/// ISO date with an optional time, e.g. `2024-03-01 10:00`, `2024-03-01T10:00:00.123`
+pub fn parse_datetime(text: &str) -> Option<NaiveDateTime> {
+    const FORMATS: [&str; 4] = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"];
+    FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .or_else(|| parse_date(text).and_then(|date| date.and_hms_opt(0, 0, 0)))
+}
+
+/// Codes such as `0742` keep their leading zero, so they stay text
+fn has_leading_zero(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    digits.len() > 1 && digits.starts_with('0') && !digits[1..].starts_with('.')
+}
+
+/// Narrowest type every value seen so far fits
+struct TypeGuess {
+    values: usize,
+    bit: bool,
+    int: bool,
+    bigint: bool,
+    decimal: bool,
+    float: bool,
+    date: bool,
+    datetime: bool,
+    guid: bool,
+    whole_digits: usize,
+    scale: usize,
+    max_chars: usize,
+}
+
+impl TypeGuess {
+    fn new() -> Self {
+        Self {
+            values: 0,
+            bit: true,
+            int: true,
+            bigint: true,
+            decimal: true,
+            float: true,
+            date: true,
+            datetime: true,
+            guid: true,
+            whole_digits: 0,
+            scale: 0,
+            max_chars: 0,
+        }
+    }
+
+    /// Empty values are NULL and fit any type
+    fn observe(&mut self, value: &str) {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        self.values += 1;
+        self.max_chars = self.max_chars.max(value.chars().count());
+
+        let number = !has_leading_zero(value);
+        self.bit &= matches!(value.to_ascii_lowercase().as_str(), "true" | "false");
+        self.int &= number && value.parse::<i32>().is_ok();
+        self.bigint &= number && value.parse::<i64>().is_ok();
+        self.float &= number && value.parse::<f64>().is_ok_and(f64::is_finite);
+        if self.decimal && number && is_decimal_text(value) {
+            let digits = value.trim_start_matches('-');
+            let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+            self.whole_digits = self.whole_digits.max(whole.trim_start_matches('0').len());
+            self.scale = self.scale.max(fraction.len());
+        } else {
+            self.decimal = false;
+        }
+        self.date &= parse_date(value).is_some();
+        self.datetime &= parse_datetime(value).is_some();
+        self.guid &= value.len() == 36 && tiberius::Uuid::parse_str(value).is_ok();
+    }
+
+    fn sql_type(&self) -> String {
+        let precision = (self.whole_digits + self.scale).max(18);
+        if self.values == 0 {
+            "NVARCHAR(50)".to_string()
+        } else if self.bit {
+            "BIT".to_string()
+        } else if self.int {
+            "INT".to_string()
+        } else if self.bigint {
+            "BIGINT".to_string()
+        } else if self.decimal && precision <= 38 {
+            format!("DECIMAL({}, {})", precision, self.scale)
+        } else if self.float {
+            "FLOAT".to_string()
+        } else if self.date {
+            "DATE".to_string()
+        } else if self.datetime {
+            "DATETIME".to_string()
+        } else if self.guid {
+            "UNIQUEIDENTIFIER".to_string()
+        } else {
+            match self.max_chars {
+                0..=50 => "NVARCHAR(50)",
+                51..=255 => "NVARCHAR(255)",
+                256..=4000 => "NVARCHAR(4000)",
+                _ => "NVARCHAR(MAX)",
+            }
+            .to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guess(values: &[&str]) -> String {
+        let mut guess = TypeGuess::new();
+        for value in values {
+            guess.observe(value);
+        }
+        guess.sql_type()
+    }
+
+    #[test]
+    fn bit_only_for_true_and_false() {
+        assert_eq!(guess(&["true", "FALSE", ""]), "BIT");
+        assert_eq!(guess(&["1", "0", "1"]), "INT");
+        assert_eq!(guess(&["true", "1"]), "NVARCHAR(50)");
+    }
+
+    #[test]
+    fn integers_widen_to_bigint() {
+        assert_eq!(guess(&["-5", "2147483647"]), "INT");
+        assert_eq!(guess(&["-5", "2147483648"]), "BIGINT");
+    }
+
+    #[test]
+    fn leading_zeros_stay_text() {
+        assert_eq!(guess(&["0742"]), "NVARCHAR(50)");
+        assert_eq!(guess(&["12", "0742"]), "NVARCHAR(50)");
+        assert_eq!(guess(&["0", "0.75"]), "DECIMAL(18, 2)");
+    }
+
+    #[test]
+    fn decimals_keep_the_widest_scale() {
+        assert_eq!(guess(&["-12.5", "3.125", "7"]), "DECIMAL(18, 3)");
+        assert_eq!(guess(&["12345678901234567890.5"]), "DECIMAL(21, 1)");
+        assert_eq!(guess(&["1.5", "1e3"]), "FLOAT");
+    }
+
+    #[test]
+    fn dates_guids_and_text() {
+        assert_eq!(guess(&[]), "NVARCHAR(50)");
+        assert_eq!(guess(&["", "  "]), "NVARCHAR(50)");
+        assert_eq!(guess(&["2024-01-15", "1999-12-31"]), "DATE");
+        assert_eq!(guess(&["2024-01-15", "2024-01-15 08:30:00"]), "DATETIME");
+        assert_eq!(guess(&["6F9619FF-8B86-D011-B42D-00C04FC964FF"]), "UNIQUEIDENTIFIER");
+        assert_eq!(guess(&[&"x".repeat(51)]), "NVARCHAR(255)");
+        assert_eq!(guess(&[&"x".repeat(4001)]), "NVARCHAR(MAX)");
+    }
+}
@@ -2,6 +2,9 @@
 //! High-performance database client for enterprise use
 
 pub mod app;
+pub mod cli;
 pub mod db;
+pub mod export;
+pub mod import;
 pub mod ui;
 pub mod utils;
@@ -3,25 +3,53 @@
 
 use anyhow::Result;
 use alrajhi_sql_tui::app::App;
+use alrajhi_sql_tui::cli::{self, Command, EXIT_USAGE, USAGE};
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::prelude::*;
 use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
+    // Optional .sql file to open (atui report.sql), or a subcommand (atui exec -q "...")
+    let command = match cli::parse_args(std::env::args_os().skip(1)) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("Error: {}\nRun with --help for usage.", message);
+            return Ok(ExitCode::from(EXIT_USAGE));
+        }
+    };
+
+    match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Exec(args) => Ok(cli::run_exec(args).await),
+        Command::Repl(args) => Ok(cli::run_repl(args).await),
+        Command::Diagnose(args) => Ok(cli::run_diagnose(args).await),
+        Command::Tui(query_file) => {
+            run_tui(query_file).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+async fn run_tui(query_file: Option<PathBuf>) -> Result<()> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Create app and run
-    let mut app = App::new().await?;
+    let mut app = App::new(query_file).await?;
     let res = app.run(&mut terminal).await;
 
     // Restore terminal
@@ -29,7 +57,8 @@ async fn main() -> Result<()> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
@@ -1,7 +1,8 @@
 //! Layout management
 
-use crate::app::{App, ActivePanel, SPINNER_FRAMES};
-use crate::ui::{AlrajhiTheme, draw_query_editor, draw_results_table, draw_schema_explorer, draw_history_panel};
+use crate::app::{App, ActivePanel, CopyFormat, DiffSetupStage, ExportField, ExportSource, FileBrowserMode, ImportField, ImportStep, InputMode, InspectorFormat, ScriptField, SNIPPET_FORM_FIELDS, SPINNER_FRAMES};
+use crate::utils::{display_width, to_visual, truncate_with_ellipsis};
+use crate::ui::{AlrajhiTheme, draw_query_editor, draw_results_table, draw_schema_explorer, draw_history_panel, draw_snippets_panel};
 use ratatui::prelude::*;
 use ratatui::widgets::{Block, Borders, Paragraph, Clear};
 
@@ -106,12 +107,13 @@ fn draw_content(f: &mut Frame, app: &mut App, area: Rect) {
         ])
         .split(h_chunks[0]);
 
-    // Right side: Schema explorer + History
+    // Right side: Schema explorer + History + Snippets
     let right_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Percentage(60),  // Schema explorer
-            Constraint::Percentage(40),  // History
+            Constraint::Percentage(50),  // Schema explorer
+            Constraint::Percentage(25),  // History
+            Constraint::Percentage(25),  // Snippets
         ])
         .split(h_chunks[1]);
 
@@ -120,25 +122,89 @@ fn draw_content(f: &mut Frame, app: &mut App, area: Rect) {
     let is_results_active = app.active_panel == ActivePanel::Results;
     let is_schema_active = app.active_panel == ActivePanel::SchemaExplorer;
     let is_history_active = app.active_panel == ActivePanel::History;
+    let is_snippets_active = app.active_panel == ActivePanel::Snippets;
 
     draw_query_editor(f, app, left_chunks[0], is_query_active);
     draw_results_table(f, app, left_chunks[1], is_results_active);
     draw_schema_explorer(f, app, right_chunks[0], is_schema_active);
     draw_history_panel(f, app, right_chunks[1], is_history_active);
+    draw_snippets_panel(f, app, right_chunks[2], is_snippets_active);
 }
 
 /// Draw the status bar
 fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
+    // Vim mode label, with any half-typed command after it
+    let mode = app.vim_enabled.then(|| {
+        let style = match app.input_mode {
+            InputMode::Normal => AlrajhiTheme::mode_normal(),
+            InputMode::Insert => AlrajhiTheme::mode_insert(),
+            InputMode::Visual | InputMode::VisualLine => AlrajhiTheme::mode_visual(),
+            InputMode::Command => AlrajhiTheme::mode_command(),
+        };
+        let pending = app.vim.pending_keys();
+        let mut spans = vec![Span::styled(format!(" {} ", app.input_mode.label()), style)];
+        if !pending.is_empty() {
+            spans.push(Span::styled(format!(" {}", pending), AlrajhiTheme::warning()));
+        }
+        Line::from(spans)
+    });
+    let mode_width = mode.as_ref().map(|m| m.width() as u16 + 1).unwrap_or(0);
+
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
+            Constraint::Length(mode_width), // Vim mode
             Constraint::Min(20),      // Messages
             Constraint::Length(50),   // Status info
             Constraint::Length(40),   // Keyboard hints
         ])
         .split(area);
 
-    // Messages (error or success)
+    if let Some(mode) = mode {
+        f.render_widget(Paragraph::new(mode).style(AlrajhiTheme::status_bar()), chunks[0]);
+    }
+
+    // `:` command line replaces the message while it is open
+    if app.vim_enabled && app.input_mode == InputMode::Command {
+        let command = format!(":{}", app.command_buffer);
+        let cursor_x = chunks[1].x + display_width(&command) as u16;
+        f.render_widget(
+            Paragraph::new(Span::styled(command, AlrajhiTheme::normal_text())).style(AlrajhiTheme::status_bar()),
+            chunks[1],
+        );
+        f.set_cursor(cursor_x.min(chunks[1].right().saturating_sub(1)), chunks[1].y);
+    } else {
+        draw_status_message(f, app, chunks[1]);
+    }
+
+    // Status info
+    let status_info = format!(
+        " {} | Rows: {} | History: {} ",
+        app.status,
+        app.result.row_count,
+        app.history.len()
+    );
+    let status = Paragraph::new(status_info)
+        .style(AlrajhiTheme::status_bar())
+        .alignment(Alignment::Center);
+    f.render_widget(status, chunks[2]);
+
+    // Simplified keyboard hints
+    let hints = match app.input_mode {
+        _ if !app.vim_enabled => "Enter:Run  Shift+Enter:Newline  Ctrl+F:Format  Ctrl+Z:Undo  Tab:Indent",
+        InputMode::Normal => "Enter:Run  i:Insert  v:Visual  ::Command  u:Undo  F3:Vim off",
+        InputMode::Insert => "Esc:Normal  Enter:Newline  F5:Run",
+        InputMode::Visual | InputMode::VisualLine => "d:Delete  y:Yank  c:Change  >/<:Indent  Esc:Cancel",
+        InputMode::Command => "Enter:Execute  Esc:Cancel",
+    };
+    let hints_widget = Paragraph::new(hints)
+        .style(AlrajhiTheme::status_bar())
+        .alignment(Alignment::Right);
+    f.render_widget(hints_widget, chunks[3]);
+}
+
+/// Error, success message or spinner in the status bar
+fn draw_status_message(f: &mut Frame, app: &App, area: Rect) {
     let message = if let Some(ref err) = app.error {
         Paragraph::new(Span::styled(
             format!("❌ {}", err),
@@ -159,26 +225,7 @@ fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
         Paragraph::new(Span::styled("Type query, press Enter to run", AlrajhiTheme::dim_text()))
     };
 
-    f.render_widget(message.style(AlrajhiTheme::status_bar()), chunks[0]);
-
-    // Status info
-    let status_info = format!(
-        " {} | Rows: {} | History: {} ",
-        app.status,
-        app.result.row_count,
-        app.history.len()
-    );
-    let status = Paragraph::new(status_info)
-        .style(AlrajhiTheme::status_bar())
-        .alignment(Alignment::Center);
-    f.render_widget(status, chunks[1]);
-
-    // Simplified keyboard hints
-    let hints = "Enter:Run  Shift+Enter:Newline  Ctrl+F:Format  Tab:Indent";
-    let hints_widget = Paragraph::new(hints)
-        .style(AlrajhiTheme::status_bar())
-        .alignment(Alignment::Right);
-    f.render_widget(hints_widget, chunks[2]);
+    f.render_widget(message.style(AlrajhiTheme::status_bar()), area);
 }
 
 /// Draw help popup
@@ -193,30 +240,79 @@ pub fn draw_help_popup(f: &mut Frame, area: Rect) {
         Line::from(""),
         Line::from(Span::styled("═══ QUERY EDITOR ═══", AlrajhiTheme::info())),
         Line::from("  Enter           Run query"),
-        Line::from("  Shift+Enter     New line in query"),
-        Line::from("  Tab             Insert indentation (4 spaces)"),
+        Line::from("  Shift+Enter     New line (keeps indentation)"),
+        Line::from("  Tab / Shift+Tab Indent / outdent (selected lines)"),
         Line::from("  Ctrl+F          Format SQL (beautify)"),
         Line::from("  F5              Run query"),
-        Line::from("  Esc             Clear query"),
+        Line::from("  Ctrl+O / Ctrl+S Open / save .sql file (Alt+S: save as)"),
+        Line::from("  Ctrl+Z / Ctrl+Y Undo / redo"),
+        Line::from("  Shift+arrows    Select (Ctrl+A: select all)"),
+        Line::from("  Ctrl+←/→        Word left/right"),
+        Line::from("  Ctrl+C/X/V      Copy/cut/paste selection"),
+        Line::from("  Ctrl+K / Ctrl+D Delete / duplicate line"),
+        Line::from("  Ctrl+W          Delete word before cursor"),
+        Line::from("  Ctrl+R          Search query history"),
+        Line::from("  Ctrl+E          Export query: re-run and stream rows to a file"),
+        Line::from("  Esc             Clear selection, then query"),
         Line::from("  ←/→/↑/↓         Move cursor"),
         Line::from("  Home/End        Jump to start/end"),
         Line::from(""),
+        Line::from(Span::styled("═══ VIM KEYS (F3 to toggle) ═══", AlrajhiTheme::info())),
+        Line::from("  i a I A o O     Insert mode (Esc back to normal)"),
+        Line::from("  hjkl w b e 0 ^ $ gg G f t % ; ,   Motions (with counts)"),
+        Line::from("  d c y > <       Operators (dd, cw, yiw, ci(, di\", >ap...)"),
+        Line::from("  x D C s S Y p P J r u Ctrl+R      Edit / put / undo"),
+        Line::from("  v / V           Visual / visual line (u/U: case)"),
+        Line::from("  Enter           Run query (normal mode)"),
+        Line::from("  :w file :e file :run :export [query] [csv|json|parquet...] :import [file] :db NAME :q"),
+        Line::from(""),
         Line::from(Span::styled("═══ RESULTS TABLE ═══", AlrajhiTheme::info())),
         Line::from("  ↑/↓ or j/k      Navigate rows"),
         Line::from("  ←/→ or h/l      Navigate columns"),
         Line::from("  PageUp/Down     Fast scroll (20 rows)"),
         Line::from("  Home/End        First/Last row"),
-        Line::from("  Ctrl+Y          Copy cell value"),
-        Line::from("  Ctrl+E          Export to CSV"),
-        Line::from("  Ctrl+S          Export to JSON"),
+        Line::from("  Ctrl+Y          Copy cell value (selection as TSV)"),
+        Line::from("  Shift+arrows    Select a range (or drag with the mouse)"),
+        Line::from("  V / C / Ctrl+A  Select whole rows / whole columns / everything"),
+        Line::from("  Y               Copy selection as TSV, CSV, Markdown or IN (...) list"),
+        Line::from("  Ctrl+E          Export... (path, format, encoding; remembers settings)"),
+        Line::from("  Ctrl+S / Ctrl+X Export as JSON / Excel (.xlsx)"),
         Line::from("  Ctrl+I          Copy row as INSERT"),
+        Line::from("  I               INSERT/MERGE script (batched, typed literals)"),
+        Line::from("  i               Inspect cell (JSON/XML/hex, r:raw, w:save)"),
+        Line::from("  v               Record view (row as field list)"),
+        Line::from("  s               Sort by column (asc/desc/off)"),
+        Line::from("  f               Filter column (> 1000, ~ 'abc', is null)"),
+        Line::from("  /  n/N          Quick search, next/previous match"),
+        Line::from("  x               Clear sort, filters and search"),
+        Line::from("  < / >           Narrow/widen column (or drag header edge)"),
+        Line::from("  P               Pin columns up to cursor (toggle)"),
+        Line::from("  H / U           Hide column / show all columns"),
+        Line::from("  Alt+←/→         Move column left/right"),
+        Line::from("  [ / ]           Previous/next result set (no re-run)"),
+        Line::from("  D               Compare with an earlier result set (4: Diff tab)"),
+        Line::from("  Diff: f x       Show added/removed/changed only, close (Ctrl+E: export)"),
+        Line::from("  e               Edit mode (single-table results)"),
         Line::from("  Enter/Esc       Back to query"),
         Line::from(""),
+        Line::from(Span::styled("═══ EDIT MODE ═══", AlrajhiTheme::info())),
+        Line::from("  Enter/F2        Edit cell"),
+        Line::from("  o/Insert        Insert row"),
+        Line::from("  d/Delete        Toggle row delete"),
+        Line::from("  u               Revert row"),
+        Line::from("  p               Preview & commit changes"),
+        Line::from("  Esc             Leave edit mode"),
+        Line::from(""),
         Line::from(Span::styled("═══ PANELS ═══", AlrajhiTheme::info())),
         Line::from("  Ctrl+Tab        Next panel"),
         Line::from("  Shift+Tab       Previous panel"),
         Line::from("  Schema: Enter   Expand/Insert table"),
-        Line::from("  History: Enter  Load query"),
+        Line::from("  Schema: i       Import a CSV file into the table (or a new one)"),
+        Line::from("  History: Enter  Load query (Ctrl+R or /: search)"),
+        Line::from("  History: D T S M  Filter database / date / status / duration (x clears)"),
+        Line::from("  History: p d y r  Pin / delete / copy query / show kept result (▣)"),
+        Line::from("  Snippets: Enter Insert (Tab: next placeholder)"),
+        Line::from("  Snippets: / a e u d y   Search, save query, edit, update, delete, copy"),
         Line::from(""),
         Line::from(Span::styled("═══ GLOBAL ═══", AlrajhiTheme::info())),
         Line::from("  Ctrl+Q          Quit application"),
@@ -238,6 +334,823 @@ pub fn draw_help_popup(f: &mut Frame, area: Rect) {
     f.render_widget(help, popup_area);
 }
 
+/// Draw the new/edit snippet form
+pub fn draw_snippet_form_popup(f: &mut Frame, app: &App, area: Rect) {
+    let Some(form) = app.snippet_form.as_ref() else {
+        return;
+    };
+
+    let popup_area = centered_rect(60, 50, area);
+    f.render_widget(Clear, popup_area);
+
+    let title = if form.editing.is_some() { " Edit snippet " } else { " Save query as snippet " };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(AlrajhiTheme::popup_border())
+        .title(Span::styled(title, AlrajhiTheme::title()))
+        .title_bottom(Span::styled(" Tab/↑/↓:Field  Enter:Save  Esc:Cancel ", AlrajhiTheme::dim_text()))
+        .style(AlrajhiTheme::popup());
+    let inner = block.inner(popup_area);
+    f.render_widget(block, popup_area);
+
+    let label_width = 13;
+    let mut lines: Vec<Line> = Vec::new();
+    for (i, (label, value)) in SNIPPET_FORM_FIELDS.iter().zip(form.fields.iter()).enumerate() {
+        let style = if i == form.field { AlrajhiTheme::selected() } else { AlrajhiTheme::normal_text() };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:<width$}", format!("{}:", label), width = label_width), AlrajhiTheme::dim_text()),
+            Span::styled(value.clone(), style),
+        ]));
+    }
+    lines.push(match &form.error {
+        Some(error) => Line::from(Span::styled(error.clone(), AlrajhiTheme::warning())),
+        None => Line::from(Span::styled(
+            "Tags are comma-separated; placeholders: ${1:default} ${name} $0",
+            AlrajhiTheme::dim_text(),
+        )),
+    });
+    lines.push(Line::from(""));
+    for line in form.body.lines() {
+        lines.push(Line::from(Span::styled(to_visual(line), AlrajhiTheme::info())));
+    }
+    f.render_widget(Paragraph::new(lines), inner);
+
+    let cursor_x = inner.x + label_width as u16 + display_width(&form.fields[form.field]) as u16;
+    f.set_cursor(cursor_x.min(inner.right().saturating_sub(1)), inner.y + form.field as u16);
+}
+
+/// Draw the compare-results popup: pick a kept result, then the key columns
+pub fn draw_diff_setup_popup(f: &mut Frame, app: &App, area: Rect) {
+    let Some(setup) = app.diff_setup.as_ref() else {
+        return;
+    };
+
+    let popup_area = centered_rect(60, 60, area);
+    f.render_widget(Clear, popup_area);
+
+    let (title, hints) = match setup.stage {
+        DiffSetupStage::Result => (" Compare with result ", " ↑/↓:Select  Enter:Choose  Esc:Cancel "),
+        DiffSetupStage::Keys { .. } => (" Key columns ", " ↑/↓:Select  Space:Toggle key  Enter:Compare  Esc:Cancel "),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(AlrajhiTheme::popup_border())
+        .title(Span::styled(title, AlrajhiTheme::title()))
+        .title_bottom(Span::styled(hints, AlrajhiTheme::dim_text()))
+        .style(AlrajhiTheme::popup());
+    let inner = block.inner(popup_area);
+    f.render_widget(block, popup_area);
+
+    let status = match (&setup.error, &setup.stage) {
+        (Some(error), _) => Span::styled(error.clone(), AlrajhiTheme::warning()),
+        (None, DiffSetupStage::Result) => Span::styled("Rows of the older result show as removed, the newer as added", AlrajhiTheme::dim_text()),
+        (None, DiffSetupStage::Keys { .. }) => Span::styled("Rows are matched on the key; no key compares whole rows", AlrajhiTheme::dim_text()),
+    };
+    let mut lines: Vec<Line> = vec![Line::from(status), Line::from("")];
+
+    let height = inner.height.saturating_sub(2) as usize;
+    let offset = setup.selected.saturating_sub(height.saturating_sub(1));
+    let width = inner.width as usize;
+    let items: Vec<String> = match &setup.stage {
+        DiffSetupStage::Result => setup
+            .candidates
+            .iter()
+            .map(|&i| {
+                let item = &app.result_history.items()[i];
+                let query = item.query.split_whitespace().collect::<Vec<_>>().join(" ");
+                format!(
+                    "{:>3}  {}  {:>8} rows{}  {}",
+                    i + 1,
+                    item.timestamp.format("%H:%M:%S"),
+                    item.row_count,
+                    if item.on_disk() { " ▤" } else { "  " },
+                    query
+                )
+            })
+            .collect(),
+        DiffSetupStage::Keys { columns, keys, .. } => columns
+            .iter()
+            .zip(keys)
+            .map(|(name, on)| format!("[{}] {}", if *on { "x" } else { " " }, name))
+            .collect(),
+    };
+    for (i, item) in items.iter().enumerate().skip(offset).take(height) {
+        let style = if i == setup.selected { AlrajhiTheme::selected() } else { AlrajhiTheme::normal_text() };
+        lines.push(Line::from(Span::styled(to_visual(&truncate_with_ellipsis(item, width)), style)));
+    }
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Draw the export dialog: one line per field that applies to the format
+pub fn draw_export_dialog_popup(f: &mut Frame, app: &App, area: Rect) {
+    let Some(dialog) = app.export_dialog.as_ref() else {
+        return;
+    };
+
+    let popup_area = centered_rect(60, 50, area);
+    f.render_widget(Clear, popup_area);
+
+    let hints = if dialog.field.is_text() {
+        " ↑/↓:Field  Tab:Complete  Enter:Export  Esc:Cancel "
+    } else {
+        " ↑/↓:Field  ←/→/Space:Change  Enter:Export  Esc:Cancel "
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(AlrajhiTheme::popup_border())
+        .title(Span::styled(
+            match dialog.source {
+                ExportSource::Grid => format!(" Export {} rows ", app.result.rows.len()),
+                ExportSource::Query => " Export query to file ".to_string(),
+            },
+            AlrajhiTheme::title(),
+        ))
+        .title_bottom(Span::styled(hints, AlrajhiTheme::dim_text()))
+        .style(AlrajhiTheme::popup());
+    let inner = block.inner(popup_area);
+    f.render_widget(block, popup_area);
+
+    let label_width = 11;
+    let value_width = (inner.width as usize).saturating_sub(label_width);
+    let fields = dialog.fields();
+    let mut lines: Vec<Line> = Vec::new();
+    for field in &fields {
+        let style = if *field == dialog.field { AlrajhiTheme::selected() } else { AlrajhiTheme::normal_text() };
+        let value = dialog.value(*field);
+        // Keep the end of a long path, where the cursor is
+        let value = if *field == ExportField::Path && display_width(&value) > value_width {
+            let skip = value.chars().count().saturating_sub(value_width.saturating_sub(1));
+            format!("…{}", value.chars().skip(skip).collect::<String>())
+        } else if field.is_text() {
+            value
+        } else {
+            format!("‹ {} ›", value)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:<width$}", format!("{}:", field.label()), width = label_width), AlrajhiTheme::dim_text()),
+            Span::styled(to_visual(&value), style),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(match &dialog.error {
+        Some(error) => Line::from(Span::styled(error.clone(), AlrajhiTheme::warning())),
+        None if dialog.source == ExportSource::Query => Line::from(Span::styled(
+            "Rows go straight from the server to the file; Esc cancels",
+            AlrajhiTheme::dim_text(),
+        )),
+        None if dialog.options.format.is_text() => Line::from(Span::styled(
+            "Excel reads UTF-8 CSV only with a BOM; Windows-1256 suits older Arabic tools",
+            AlrajhiTheme::dim_text(),
+        )),
+        None => Line::from(Span::styled("Typed columns; the file is binary", AlrajhiTheme::dim_text())),
+    });
+    f.render_widget(Paragraph::new(lines), inner);
+
+    if dialog.field.is_text() {
+        let row = fields.iter().position(|field| *field == dialog.field).unwrap_or(0);
+        let width = display_width(&dialog.value(dialog.field)).min(value_width);
+        let cursor_x = inner.x + (label_width + width) as u16;
+        f.set_cursor(cursor_x.min(inner.right().saturating_sub(1)), inner.y + row as u16);
+    }
+}
+
+/// Draw the INSERT/MERGE script dialog
+pub fn draw_script_dialog_popup(f: &mut Frame, app: &App, area: Rect) {
+    let Some(dialog) = app.script_dialog.as_ref() else {
+        return;
+    };
+
+    let popup_area = centered_rect(60, 45, area);
+    f.render_widget(Clear, popup_area);
+
+    let hints = if dialog.field.is_text() {
+        " ↑/↓:Field  Enter:Generate  Esc:Cancel "
+    } else {
+        " ↑/↓:Field  ←/→/Space:Change  Enter:Generate  Esc:Cancel "
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(AlrajhiTheme::popup_border())
+        .title(Span::styled(" Generate script ", AlrajhiTheme::title()))
+        .title_bottom(Span::styled(hints, AlrajhiTheme::dim_text()))
+        .style(AlrajhiTheme::popup());
+    let inner = block.inner(popup_area);
+    f.render_widget(block, popup_area);
+
+    let label_width = 17;
+    let fields = dialog.fields();
+    let mut lines: Vec<Line> = Vec::new();
+    for field in &fields {
+        let style = if *field == dialog.field { AlrajhiTheme::selected() } else { AlrajhiTheme::normal_text() };
+        let value = dialog.value(*field);
+        let value = if field.is_text() { value } else { format!("‹ {} ›", value) };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:<width$}", format!("{}:", field.label()), width = label_width), AlrajhiTheme::dim_text()),
+            Span::styled(to_visual(&value), style),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(match &dialog.error {
+        Some(error) => Line::from(Span::styled(error.clone(), AlrajhiTheme::warning())),
+        None if dialog.field == ScriptField::Keys => Line::from(Span::styled(
+            "Comma-separated columns matched between the script rows and the table",
+            AlrajhiTheme::dim_text(),
+        )),
+        None => Line::from(Span::styled(
+            "Typed literals: N'' for Unicode, ISO dates, 0x binary; up to 1000 rows per VALUES",
+            AlrajhiTheme::dim_text(),
+        )),
+    });
+    f.render_widget(Paragraph::new(lines), inner);
+
+    if dialog.field.is_text() {
+        let row = fields.iter().position(|field| *field == dialog.field).unwrap_or(0);
+        let cursor_x = inner.x + (label_width + display_width(&dialog.value(dialog.field))) as u16;
+        f.set_cursor(cursor_x.min(inner.right().saturating_sub(1)), inner.y + row as u16);
+    }
+}
+
+/// Draw the copy-as menu for the grid selection
+pub fn draw_copy_menu_popup(f: &mut Frame, app: &App, area: Rect) {
+    let Some(current) = app.copy_menu else {
+        return;
+    };
+
+    let popup_width = 46.min(area.width);
+    let popup_height = (CopyFormat::ALL.len() as u16 + 4).min(area.height);
+    let popup_area = Rect {
+        x: (area.width.saturating_sub(popup_width)) / 2,
+        y: (area.height.saturating_sub(popup_height)) / 2,
+        width: popup_width,
+        height: popup_height,
+    };
+    f.render_widget(Clear, popup_area);
+
+    let (rows, cols) = app.selection_bounds();
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} row(s) × {} column(s)", rows.count(), cols.count()),
+            AlrajhiTheme::dim_text(),
+        )),
+        Line::from(""),
+    ];
+    for format in CopyFormat::ALL {
+        let style = if format == current { AlrajhiTheme::selected() } else { AlrajhiTheme::normal_text() };
+        lines.push(Line::from(vec![
+            Span::styled(format!(" {} ", format.key()), AlrajhiTheme::info()),
+            Span::styled(format!(" {} ", format.label()), style),
+        ]));
+    }
+
+    let popup = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(AlrajhiTheme::popup_border())
+            .title(Span::styled(" Copy as ", AlrajhiTheme::title()))
+            .title_bottom(Span::styled(" ↑/↓ Enter or key  Esc:Cancel ", AlrajhiTheme::dim_text()))
+            .style(AlrajhiTheme::popup()),
+    );
+    f.render_widget(popup, popup_area);
+}
+
+/// Draw the CSV import wizard (hidden while the load runs)
+pub fn draw_import_wizard_popup(f: &mut Frame, app: &App, area: Rect) {
+    let Some(wizard) = app.import_wizard.as_ref() else {
+        return;
+    };
+    if app.pending_import.as_ref().is_some_and(|job| job.progress.is_some()) {
+        return;
+    }
+
+    let popup_area = centered_rect(70, 60, area);
+    f.render_widget(Clear, popup_area);
+
+    let (title, hints) = match wizard.step {
+        ImportStep::File => (" Import CSV · 1/3 File ", " ↑/↓:Field  Tab:Complete  ←/→:Change  Enter:Preview  Esc:Close "),
+        ImportStep::Target => (" Import CSV · 2/3 Preview and target ", " ↑/↓:Field  ←/→:Change  Enter:Next  Esc:Back "),
+        ImportStep::Mapping => (" Import CSV · 3/3 Columns ", " ↑/↓:Column  ←/→:Change  Enter:Load  Esc:Back "),
+        ImportStep::Report => (" Import CSV · Rows rejected ", " ↑/↓:Scroll  c:Copy report  Enter:Close  Esc:Back "),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(AlrajhiTheme::popup_border())
+        .title(Span::styled(title, AlrajhiTheme::title()))
+        .title_bottom(Span::styled(hints, AlrajhiTheme::dim_text()))
+        .style(AlrajhiTheme::popup());
+    let inner = block.inner(popup_area);
+    f.render_widget(block, popup_area);
+
+    let label_width = 12;
+    let value_width = (inner.width as usize).saturating_sub(label_width);
+    let height = inner.height as usize;
+    let mut lines: Vec<Line> = Vec::new();
+    for field in wizard.fields() {
+        let style = if *field == wizard.field { AlrajhiTheme::selected() } else { AlrajhiTheme::normal_text() };
+        let value = wizard.value(*field);
+        // Keep the end of a long path, where the cursor is
+        let value = if *field == ImportField::Path && display_width(&value) > value_width {
+            let skip = value.chars().count().saturating_sub(value_width.saturating_sub(1));
+            format!("…{}", value.chars().skip(skip).collect::<String>())
+        } else if field.is_text() {
+            value
+        } else {
+            format!("‹ {} ›", value)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:<width$}", format!("{}:", field.label()), width = label_width), AlrajhiTheme::dim_text()),
+            Span::styled(to_visual(&value), style),
+        ]));
+    }
+
+    // Room left above the blank line and the message line
+    let room = height.saturating_sub(lines.len() + 3);
+    match wizard.step {
+        ImportStep::File => {}
+        ImportStep::Target => {
+            if let Some(sample) = wizard.sample() {
+                lines.push(Line::from(""));
+                let widths: Vec<usize> = (0..sample.headers.len())
+                    .map(|i| {
+                        let values = sample.rows.iter().take(room).filter_map(|r| r.get(i));
+                        values
+                            .chain([&sample.headers[i], &sample.types[i]])
+                            .map(|v| display_width(v))
+                            .max()
+                            .unwrap_or(0)
+                            .min(18)
+                    })
+                    .collect();
+                let row_line = |cells: Vec<&str>, style: Style| {
+                    let text: Vec<String> = cells
+                        .iter()
+                        .zip(&widths)
+                        .map(|(cell, &width)| {
+                            let cell = truncate_with_ellipsis(cell, width);
+                            format!("{}{}", cell, " ".repeat(width.saturating_sub(display_width(&cell))))
+                        })
+                        .collect();
+                    Line::from(Span::styled(to_visual(&text.join("  ")), style))
+                };
+                lines.push(row_line(sample.headers.iter().map(String::as_str).collect(), AlrajhiTheme::info()));
+                lines.push(row_line(sample.types.iter().map(String::as_str).collect(), AlrajhiTheme::dim_text()));
+                for row in sample.rows.iter().take(room.saturating_sub(3)) {
+                    let cells = (0..widths.len()).map(|i| row.get(i).map_or("", String::as_str)).collect();
+                    lines.push(row_line(cells, AlrajhiTheme::normal_text()));
+                }
+            }
+        }
+        ImportStep::Mapping => {
+            let rows = wizard.mapping_rows();
+            let name_width = rows.iter().map(|(name, _)| display_width(name)).max().unwrap_or(0).min(40) + 2;
+            let visible = room.saturating_sub(1).max(1);
+            let start = wizard.selected.saturating_sub(visible - 1);
+            for (i, (name, value)) in rows.iter().enumerate().skip(start).take(visible) {
+                let style = if i == wizard.selected { AlrajhiTheme::selected() } else { AlrajhiTheme::normal_text() };
+                let name = truncate_with_ellipsis(name, name_width - 2);
+                let padding = " ".repeat(name_width.saturating_sub(display_width(&name)));
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{}{}", to_visual(&name), padding), AlrajhiTheme::dim_text()),
+                    Span::styled(format!("‹ {} ›", to_visual(value)), style),
+                ]));
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(wizard.plan_summary(), AlrajhiTheme::info())));
+        }
+        ImportStep::Report => {
+            if let Some(report) = wizard.report.as_ref() {
+                let more = report.error_count.saturating_sub(report.errors.len() as u64);
+                lines.push(Line::from(Span::styled(
+                    match more {
+                        0 => format!("{} rows failed the checks; nothing was loaded", report.error_count),
+                        more => format!("{} rows failed the checks (first {} shown, {} more); nothing was loaded", report.error_count, report.errors.len(), more),
+                    },
+                    AlrajhiTheme::warning(),
+                )));
+                lines.push(Line::from(""));
+                for error in report.errors.iter().skip(wizard.selected).take(room.saturating_sub(2)) {
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("line {:<7} ", error.line), AlrajhiTheme::dim_text()),
+                        Span::styled(to_visual(&error.message), AlrajhiTheme::normal_text()),
+                    ]));
+                }
+            }
+        }
+    }
+
+    lines.push(Line::from(""));
+    lines.push(match &wizard.error {
+        Some(error) => Line::from(Span::styled(error.clone(), AlrajhiTheme::warning())),
+        None => Line::from(Span::styled(
+            match wizard.step {
+                ImportStep::File => "UTF-8 text; empty fields load as NULL",
+                ImportStep::Target => "Types are inferred from up to 10,000 rows; codes with leading zeros stay text",
+                ImportStep::Mapping => "Every row is checked first; any failure loads nothing",
+                ImportStep::Report => "Fix the file or the mapping and load again",
+            },
+            AlrajhiTheme::dim_text(),
+        )),
+    });
+    f.render_widget(Paragraph::new(lines), inner);
+
+    if wizard.field.is_text() && wizard.fields().contains(&wizard.field) {
+        let row = wizard.fields().iter().position(|field| *field == wizard.field).unwrap_or(0);
+        let width = display_width(&wizard.value(wizard.field)).min(value_width);
+        let cursor_x = inner.x + (label_width + width) as u16;
+        f.set_cursor(cursor_x.min(inner.right().saturating_sub(1)), inner.y + row as u16);
+    }
+}
+
+/// Draw the cell editor popup for edit mode
+pub fn draw_cell_input_popup(f: &mut Frame, app: &App, area: Rect) {
+    let Some(session) = app.edit_session.as_ref() else {
+        return;
+    };
+    let Some(input) = session.input.as_ref() else {
+        return;
+    };
+
+    let popup_area = centered_rect(60, 20, area);
+    f.render_widget(Clear, popup_area);
+
+    let (name, type_name) = session
+        .columns
+        .get(app.selected_col().unwrap_or(usize::MAX))
+        .and_then(|c| c.as_ref())
+        .map(|c| (c.name.as_str(), c.data_type.as_str()))
+        .unwrap_or(("?", "?"));
+
+    let text = vec![
+        Line::from(vec![
+            Span::styled(format!(" [{}] ", name), AlrajhiTheme::info()),
+            Span::styled(type_name.to_uppercase(), AlrajhiTheme::dim_text()),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(format!(" {}", to_visual(input)), AlrajhiTheme::normal_text())),
+        Line::from(""),
+        Line::from(Span::styled(
+            " Enter:Stage  Esc:Cancel  (type NULL for NULL, 0x.. for binary)",
+            AlrajhiTheme::dim_text(),
+        )),
+    ];
+
+    let popup = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(AlrajhiTheme::popup_border())
+            .title(Span::styled(format!(" Edit row {} ", app.selected_row().map(|r| r + 1).unwrap_or(0)), AlrajhiTheme::title()))
+            .style(AlrajhiTheme::popup()),
+    );
+    f.render_widget(popup, popup_area);
+
+    // Place the terminal cursor at the end of the input
+    let cursor_x = popup_area.x + 2 + unicode_width::UnicodeWidthStr::width(input.as_str()) as u16;
+    if cursor_x < popup_area.x + popup_area.width.saturating_sub(1) {
+        f.set_cursor(cursor_x, popup_area.y + 3);
+    }
+}
+
+/// Draw the generated DML preview for edit mode
+pub fn draw_edit_preview_popup(f: &mut Frame, app: &App, area: Rect) {
+    let Some(session) = app.edit_session.as_ref() else {
+        return;
+    };
+    let Some(statements) = session.preview.as_ref() else {
+        return;
+    };
+
+    let popup_area = centered_rect(80, 70, area);
+    f.render_widget(Clear, popup_area);
+
+    let mut lines: Vec<Line> = vec![
+        Line::from(Span::styled("BEGIN TRANSACTION;", AlrajhiTheme::info())),
+        Line::from(""),
+    ];
+    for statement in statements.iter().skip(session.preview_scroll) {
+        for line in statement.preview().lines() {
+            let style = if line.trim_start().starts_with("--") {
+                Style::default().fg(AlrajhiTheme::COMMENT)
+            } else {
+                AlrajhiTheme::normal_text()
+            };
+            lines.push(Line::from(Span::styled(line.to_string(), style)));
+        }
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(Span::styled("COMMIT TRANSACTION;", AlrajhiTheme::info())));
+
+    let title = format!(
+        " Pending changes │ {} statement(s) │ Enter/y:Execute  Esc/n:Back ",
+        statements.len()
+    );
+    let popup = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(AlrajhiTheme::popup_border())
+                .title(Span::styled(title, AlrajhiTheme::title()))
+                .style(AlrajhiTheme::popup()),
+        )
+        .wrap(ratatui::widgets::Wrap { trim: false });
+    f.render_widget(popup, popup_area);
+}
+
+/// Draw the full-value cell inspector
+pub fn draw_cell_inspector_popup(f: &mut Frame, app: &mut App, area: Rect) {
+    let Some(inspector) = app.inspector.as_mut() else {
+        return;
+    };
+
+    let popup_area = centered_rect(85, 80, area);
+    f.render_widget(Clear, popup_area);
+    // Wrapped here rather than by the paragraph, so scrolling and the title count screen rows
+    inspector.wrap(popup_area.width.saturating_sub(2) as usize);
+
+    let format = if inspector.pretty { inspector.format } else { InspectorFormat::Text };
+    let lines: Vec<Line> = inspector
+        .rows
+        .iter()
+        .map(|line| match format {
+            InspectorFormat::Json => highlight_json_line(line),
+            InspectorFormat::Xml => highlight_xml_line(line),
+            InspectorFormat::Hex => highlight_hex_line(line),
+            InspectorFormat::Text => Line::from(Span::styled(to_visual(line), AlrajhiTheme::normal_text())),
+        })
+        .collect();
+
+    let size = match &inspector.value {
+        crate::db::CellValue::Binary(bytes) => format!("{} bytes", bytes.len()),
+        other => format!("{} chars", other.to_string().chars().count()),
+    };
+    let mode = if inspector.pretty { inspector.format.label() } else { "RAW" };
+    let title = format!(
+        " {} ({}) │ {} │ {} │ line {}/{} ",
+        inspector.column,
+        inspector.type_name,
+        mode,
+        size,
+        inspector.scroll + 1,
+        inspector.rows.len()
+    );
+
+    let popup = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(AlrajhiTheme::popup_border())
+                .title(Span::styled(title, AlrajhiTheme::title()))
+                .title_bottom(Span::styled(
+                    " ↑/↓:Scroll  r:Raw/Pretty  y:Copy  w:Save to file  Esc:Close ",
+                    AlrajhiTheme::dim_text(),
+                ))
+                .style(AlrajhiTheme::popup()),
+        )
+        .scroll((inspector.scroll.min(u16::MAX as usize) as u16, 0));
+    f.render_widget(popup, popup_area);
+}
+
+/// Draw the open/save-as file browser
+pub fn draw_file_browser_popup(f: &mut Frame, app: &App, area: Rect) {
+    let Some(browser) = app.file_browser.as_ref() else {
+        return;
+    };
+
+    let popup_area = centered_rect(60, 70, area);
+    f.render_widget(Clear, popup_area);
+
+    let title = match browser.mode {
+        FileBrowserMode::Open => " Open SQL file ",
+        FileBrowserMode::SaveAs => " Save query as ",
+    };
+    let hints = match browser.mode {
+        FileBrowserMode::Open => " ↑/↓:Select  Enter:Open  ←/Backspace:Up  Tab:All files  Esc:Cancel ",
+        FileBrowserMode::SaveAs => " Type name  ↑/↓:Pick  Enter:Save  Tab:All files  Esc:Cancel ",
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(AlrajhiTheme::popup_border())
+        .title(Span::styled(title, AlrajhiTheme::title()))
+        .title_bottom(Span::styled(hints, AlrajhiTheme::dim_text()))
+        .style(AlrajhiTheme::popup());
+    let inner = block.inner(popup_area);
+    f.render_widget(block, popup_area);
+
+    // Directory line, optional name input, status line, then the listing
+    let input_height = if browser.mode == FileBrowserMode::SaveAs { 1 } else { 0 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(input_height),
+            Constraint::Length(1),
+            Constraint::Min(1),
+        ])
+        .split(inner);
+
+    let dir = Paragraph::new(Span::styled(format!("📁 {}", browser.dir.display()), AlrajhiTheme::info()));
+    f.render_widget(dir, chunks[0]);
+
+    if browser.mode == FileBrowserMode::SaveAs {
+        let input = Line::from(vec![
+            Span::styled("Name: ", AlrajhiTheme::dim_text()),
+            Span::styled(browser.input.clone(), AlrajhiTheme::normal_text()),
+        ]);
+        f.render_widget(Paragraph::new(input), chunks[1]);
+        let cursor_x = chunks[1].x + 6 + display_width(&browser.input) as u16;
+        f.set_cursor(cursor_x.min(chunks[1].right().saturating_sub(1)), chunks[1].y);
+    }
+
+    let status = match &browser.error {
+        Some(error) => Span::styled(error.clone(), AlrajhiTheme::warning()),
+        None => Span::styled(
+            if browser.show_all { "All files" } else { "*.sql files" },
+            AlrajhiTheme::dim_text(),
+        ),
+    };
+    f.render_widget(Paragraph::new(status), chunks[2]);
+
+    let list_area = chunks[3];
+    let height = list_area.height as usize;
+    let offset = browser.selected.saturating_sub(height.saturating_sub(1));
+    let name_width = (list_area.width as usize).saturating_sub(14);
+    let lines: Vec<Line> = browser
+        .entries
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(height)
+        .map(|(i, entry)| {
+            let (icon, size) = if entry.is_dir {
+                ("📁", String::new())
+            } else {
+                ("📄", format_file_size(entry.size))
+            };
+            let name = truncate_with_ellipsis(&entry.name, name_width.saturating_sub(3));
+            let padding = name_width.saturating_sub(display_width(&name) + 3);
+            let text = format!("{} {}{} {:>9}", icon, to_visual(&name), " ".repeat(padding), size);
+            let style = if i == browser.selected {
+                AlrajhiTheme::selected()
+            } else if entry.is_dir {
+                AlrajhiTheme::info()
+            } else {
+                AlrajhiTheme::normal_text()
+            };
+            Line::from(Span::styled(text, style))
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), list_area);
+}
+
+fn format_file_size(bytes: u64) -> String {
+    match bytes {
+        b if b < 1024 => format!("{} B", b),
+        b if b < 1024 * 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+/// Ask whether to reload the open file after it changed on disk
+pub fn draw_reload_prompt(f: &mut Frame, app: &App, area: Rect) {
+    let Some(file) = app.query_file.as_ref().filter(|f| f.changed_on_disk) else {
+        return;
+    };
+
+    let popup_width = 60.min(area.width);
+    let popup_height = 7.min(area.height);
+    let popup_area = Rect {
+        x: (area.width.saturating_sub(popup_width)) / 2,
+        y: (area.height.saturating_sub(popup_height)) / 2,
+        width: popup_width,
+        height: popup_height,
+    };
+    f.render_widget(Clear, popup_area);
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("{} changed on disk.", file.name()),
+            AlrajhiTheme::warning(),
+        )),
+    ];
+    if app.is_query_modified() {
+        lines.push(Line::from(Span::styled(
+            "Reloading replaces your unsaved edits (Ctrl+Z restores them).",
+            AlrajhiTheme::dim_text(),
+        )));
+    }
+    lines.push(Line::from(Span::styled("Reload it?  y:Reload  n:Keep mine", AlrajhiTheme::normal_text())));
+
+    let popup = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(AlrajhiTheme::popup_border())
+                .title(Span::styled(" File changed ", AlrajhiTheme::title()))
+                .style(AlrajhiTheme::popup()),
+        )
+        .alignment(Alignment::Center);
+    f.render_widget(popup, popup_area);
+}
+
+/// Colour keys, strings, numbers and literals in a pretty-printed JSON line
+fn highlight_json_line(line: &str) -> Line<'static> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut spans: Vec<Span> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '"' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            let text: String = chars[start..i].iter().collect();
+            let is_key = chars[i..].iter().find(|c| !c.is_whitespace()) == Some(&':');
+            let style = if is_key {
+                Style::default().fg(AlrajhiTheme::INFO)
+            } else {
+                Style::default().fg(AlrajhiTheme::STRING)
+            };
+            spans.push(Span::styled(text, style));
+        } else if c == '-' || c.is_ascii_digit() || c.is_ascii_alphabetic() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '-' | '+' | '.')) {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let style = if matches!(text.as_str(), "true" | "false" | "null") {
+                Style::default().fg(AlrajhiTheme::KEYWORD)
+            } else {
+                Style::default().fg(AlrajhiTheme::NUMBER)
+            };
+            spans.push(Span::styled(text, style));
+        } else {
+            spans.push(Span::styled(c.to_string(), Style::default().fg(AlrajhiTheme::OPERATOR)));
+            i += 1;
+        }
+    }
+
+    Line::from(spans)
+}
+
+/// Colour tags, attributes and text in a pretty-printed XML line
+fn highlight_xml_line(line: &str) -> Line<'static> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut rest = line;
+
+    while !rest.is_empty() {
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map(|e| e + 3).unwrap_or(rest.len());
+            spans.push(Span::styled(rest[..end].to_string(), Style::default().fg(AlrajhiTheme::COMMENT)));
+            rest = &rest[end..];
+        } else if rest.starts_with('<') {
+            let end = rest.find('>').map(|e| e + 1).unwrap_or(rest.len());
+            let tag = &rest[..end];
+            // Tag name, then attribute names and quoted values
+            let name_end = tag
+                .find(|c: char| c.is_whitespace() || c == '>')
+                .unwrap_or(tag.len());
+            spans.push(Span::styled(tag[..name_end].to_string(), Style::default().fg(AlrajhiTheme::KEYWORD)));
+            let mut attrs = &tag[name_end..];
+            while !attrs.is_empty() {
+                if let Some(quote) = attrs.chars().next().filter(|c| *c == '"' || *c == '\'') {
+                    let close = attrs[1..].find(quote).map(|p| p + 2).unwrap_or(attrs.len());
+                    spans.push(Span::styled(attrs[..close].to_string(), Style::default().fg(AlrajhiTheme::STRING)));
+                    attrs = &attrs[close..];
+                } else {
+                    let next = attrs.find(['"', '\'']).unwrap_or(attrs.len());
+                    spans.push(Span::styled(attrs[..next].to_string(), Style::default().fg(AlrajhiTheme::FUNCTION)));
+                    attrs = &attrs[next..];
+                }
+            }
+            rest = &rest[end..];
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            spans.push(Span::styled(rest[..end].to_string(), AlrajhiTheme::normal_text()));
+            rest = &rest[end..];
+        }
+    }
+
+    Line::from(spans)
+}
+
+/// Dim the offset and ASCII columns of a hex dump line
+fn highlight_hex_line(line: &str) -> Line<'static> {
+    match (line.split_once("  "), line.find('|')) {
+        (Some((offset, _)), Some(bar)) if offset.len() < bar => Line::from(vec![
+            Span::styled(offset.to_string(), AlrajhiTheme::dim_text()),
+            Span::styled(line[offset.len()..bar].to_string(), Style::default().fg(AlrajhiTheme::NUMBER)),
+            Span::styled(line[bar..].to_string(), Style::default().fg(AlrajhiTheme::STRING)),
+        ]),
+        _ => Line::from(Span::styled(line.to_string(), AlrajhiTheme::normal_text())),
+    }
+}
+
 /// Helper to create a centered rect
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
@@ -9,6 +9,7 @@ pub use layout::*;
 pub use widgets::*;
 
 use crate::app::{App, SPINNER_FRAMES};
+use crate::utils::{format_bytes, format_duration, format_number, truncate};
 use ratatui::prelude::*;
 use ratatui::widgets::{Block, Borders, Clear, Paragraph};
 
@@ -19,8 +20,35 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     // Draw main layout
     draw_layout(f, app, size);
 
-    // Draw loading popup if active
-    if app.is_loading {
+    // Draw edit mode popups
+    draw_cell_input_popup(f, app, size);
+    draw_edit_preview_popup(f, app, size);
+
+    // Draw cell inspector
+    draw_cell_inspector_popup(f, app, size);
+
+    // Draw file browser and the changed-on-disk prompt
+    draw_file_browser_popup(f, app, size);
+    draw_reload_prompt(f, app, size);
+
+    // Draw snippet form
+    draw_snippet_form_popup(f, app, size);
+
+    // Draw compare-results popup
+    draw_diff_setup_popup(f, app, size);
+
+    // Draw export dialog and script generator
+    draw_export_dialog_popup(f, app, size);
+    draw_script_dialog_popup(f, app, size);
+    draw_import_wizard_popup(f, app, size);
+    draw_copy_menu_popup(f, app, size);
+
+    // Draw loading popup if active, with progress while an export streams or an import loads
+    if app.pending_export.is_some() {
+        draw_export_progress_popup(f, app, size);
+    } else if app.pending_import.as_ref().is_some_and(|job| job.progress.is_some()) {
+        draw_import_progress_popup(f, app, size);
+    } else if app.is_loading {
         draw_loading_popup(f, app, size);
     }
 
@@ -69,3 +97,106 @@ fn draw_loading_popup(f: &mut Frame, app: &App, area: Rect) {
 
     f.render_widget(loading, popup_area);
 }
+
+/// Draw the progress of a query export streaming to disk
+fn draw_export_progress_popup(f: &mut Frame, app: &App, area: Rect) {
+    let Some(job) = app.pending_export.as_ref() else {
+        return;
+    };
+    let popup_width = 56;
+    let popup_height = 8;
+
+    let popup_area = Rect {
+        x: (area.width.saturating_sub(popup_width)) / 2,
+        y: (area.height.saturating_sub(popup_height)) / 2,
+        width: popup_width.min(area.width),
+        height: popup_height.min(area.height),
+    };
+
+    f.render_widget(Clear, popup_area);
+
+    let spinner = SPINNER_FRAMES[app.spinner_frame];
+    let status = if job.progress.is_cancelled() { "Cancelling..." } else { "Exporting query..." };
+    let file = job.path.display().to_string();
+    let lines = vec![
+        Line::from(Span::styled(
+            format!("{}  {}", spinner, status),
+            Style::default().fg(AlrajhiTheme::GOLD).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(truncate(&file, popup_width as usize - 4), AlrajhiTheme::dim_text())),
+        Line::from(""),
+        Line::from(format!(
+            "{} rows  ·  {}  ·  {} rows/s",
+            format_number(job.progress.rows() as i64),
+            format_bytes(job.progress.bytes()),
+            format_number(job.rate() as i64)
+        )),
+        Line::from(Span::styled(
+            format!("{} elapsed  ·  Esc: cancel", format_duration(job.started.elapsed())),
+            AlrajhiTheme::dim_text(),
+        )),
+    ];
+
+    let progress = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(AlrajhiTheme::PRIMARY))
+                .style(Style::default().bg(AlrajhiTheme::BG_PANEL)),
+        )
+        .alignment(Alignment::Center);
+
+    f.render_widget(progress, popup_area);
+}
+
+/// Draw the progress of a CSV import: the checking pass, then the load
+fn draw_import_progress_popup(f: &mut Frame, app: &App, area: Rect) {
+    let Some((job, progress)) = app.pending_import.as_ref().and_then(|job| Some((job, job.progress.as_ref()?))) else {
+        return;
+    };
+    let popup_width = 56;
+    let popup_height = 8;
+
+    let popup_area = Rect {
+        x: (area.width.saturating_sub(popup_width)) / 2,
+        y: (area.height.saturating_sub(popup_height)) / 2,
+        width: popup_width.min(area.width),
+        height: popup_height.min(area.height),
+    };
+
+    f.render_widget(Clear, popup_area);
+
+    let spinner = SPINNER_FRAMES[app.spinner_frame];
+    let (status, counts) = if progress.is_cancelled() {
+        ("Cancelling, rolling back...", String::new())
+    } else if progress.is_loading() {
+        ("Loading rows...", format!("{} of {} rows sent", format_number(progress.loaded() as i64), format_number(progress.checked() as i64)))
+    } else {
+        ("Checking rows...", format!("{} rows checked", format_number(progress.checked() as i64)))
+    };
+    let table = app.import_wizard.as_ref().map(|w| w.table.trim().to_string()).unwrap_or_default();
+    let lines = vec![
+        Line::from(Span::styled(
+            format!("{}  {}", spinner, status),
+            Style::default().fg(AlrajhiTheme::GOLD).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(truncate(&table, popup_width as usize - 4), AlrajhiTheme::dim_text())),
+        Line::from(""),
+        Line::from(counts),
+        Line::from(Span::styled(
+            format!("{} elapsed  ·  Esc: cancel and roll back", format_duration(job.started.elapsed())),
+            AlrajhiTheme::dim_text(),
+        )),
+    ];
+
+    let progress = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(AlrajhiTheme::PRIMARY))
+                .style(Style::default().bg(AlrajhiTheme::BG_PANEL)),
+        )
+        .alignment(Alignment::Center);
+
+    f.render_widget(progress, popup_area);
+}
@@ -124,6 +124,13 @@ impl AlrajhiTheme {
             .add_modifier(Modifier::BOLD)
     }
 
+    pub fn mode_visual() -> Style {
+        Style::default()
+            .fg(Color::Black)
+            .bg(Self::WARNING)
+            .add_modifier(Modifier::BOLD)
+    }
+
     pub fn mode_command() -> Style {
         Style::default()
             .fg(Color::Black)
@@ -191,6 +198,43 @@ impl AlrajhiTheme {
         Style::default().fg(Color::Rgb(255, 200, 100)) // Yellow for bool
     }
 
+    // Staged edits in the data grid
+    pub fn edited_cell() -> Style {
+        Style::default()
+            .fg(Color::Black)
+            .bg(Self::WARNING)
+    }
+
+    pub fn inserted_row() -> Style {
+        Style::default()
+            .fg(Self::SUCCESS)
+            .bg(Self::BG_HIGHLIGHT)
+    }
+
+    pub fn search_match() -> Style {
+        Style::default()
+            .fg(Color::Black)
+            .bg(Self::GOLD_LIGHT)
+    }
+
+    pub fn deleted_row() -> Style {
+        Style::default()
+            .fg(Self::ERROR)
+            .add_modifier(Modifier::CROSSED_OUT)
+    }
+
+    // Range selected in the data grid
+    pub fn cell_range() -> Style {
+        Style::default()
+            .fg(Self::TEXT)
+            .bg(Self::PRIMARY_DARK)
+    }
+
+    // Selected text in the query editor
+    pub fn text_selection() -> Style {
+        Style::default().bg(Self::PRIMARY_DARK)
+    }
+
     // Row number column
     pub fn row_number() -> Style {
         Style::default()
@@ -1,11 +1,13 @@
 //! UI widgets for the application
 
-use crate::app::{App, SchemaNodeType, ResultsTab};
+use crate::app::{format_duration_ms, App, DiffKind, EditorBuffer, GridInputKind, InputMode, QueryStatus, SchemaNodeType, ResultsTab, SnippetRow, SortDirection};
 use crate::db::CellValue;
 use crate::ui::AlrajhiTheme;
+use crate::utils::{display_width, grapheme_width, mirror_bracket, to_visual, truncate_to_width, truncate_with_ellipsis, visual_graphemes};
 use ratatui::prelude::*;
 use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, Row, Table, Scrollbar, ScrollbarOrientation, ScrollbarState, Cell};
 use ratatui::layout::Margin;
+use unicode_segmentation::UnicodeSegmentation;
 
 /// Line number gutter width (4 chars + 1 separator)
 const LINE_NUMBER_WIDTH: u16 = 5;
@@ -18,7 +20,13 @@ pub fn draw_query_editor(f: &mut Frame, app: &mut App, area: Rect, active: bool)
         AlrajhiTheme::inactive_border()
     };
 
-    let title = if active { " Query [1] ▪ " } else { " Query [1] " };
+    // Open file name, with ● while it has unsaved changes
+    let file = app
+        .query_file
+        .as_ref()
+        .map(|f| format!("─ {}{} ", f.name(), if app.is_query_modified() { " ●" } else { "" }))
+        .unwrap_or_default();
+    let title = format!(" Query [1] {}{}", file, if active { "▪ " } else { "" });
 
     // Create outer block
     let block = Block::default()
@@ -51,10 +59,10 @@ pub fn draw_query_editor(f: &mut Frame, app: &mut App, area: Rect, active: bool)
         app.update_scroll(visible_width, visible_height);
 
         // Get lines from query
-        let query_lines: Vec<&str> = if app.query.is_empty() {
+        let query_lines: Vec<&str> = if app.editor.is_empty() {
             vec![""]
         } else {
-            app.query.split('\n').collect()
+            app.editor.text().split('\n').collect()
         };
 
         // Draw line numbers (with vertical scroll)
@@ -76,7 +84,7 @@ pub fn draw_query_editor(f: &mut Frame, app: &mut App, area: Rect, active: bool)
 
         // Draw syntax-highlighted code with scrolling
         let highlighted_lines = highlight_sql_with_scroll(
-            &app.query,
+            &app.editor,
             app.query_scroll_x,
             app.query_scroll_y,
             visible_width,
@@ -85,8 +93,8 @@ pub fn draw_query_editor(f: &mut Frame, app: &mut App, area: Rect, active: bool)
         let code_widget = Paragraph::new(highlighted_lines);
         f.render_widget(code_widget, code_area);
 
-        // Show cursor when query editor is active
-        if active {
+        // Show cursor when query editor is active (the `:` line owns it in command mode)
+        if active && app.input_mode != InputMode::Command {
             let (cursor_x, cursor_y) = calculate_cursor_position_with_scroll(
                 app,
                 code_area,
@@ -97,7 +105,7 @@ pub fn draw_query_editor(f: &mut Frame, app: &mut App, area: Rect, active: bool)
 }
 
 /// Draw the results table panel with tabs
-pub fn draw_results_table(f: &mut Frame, app: &App, area: Rect, active: bool) {
+pub fn draw_results_table(f: &mut Frame, app: &mut App, area: Rect, active: bool) {
     let border_style = if active {
         AlrajhiTheme::active_border()
     } else {
@@ -146,9 +154,11 @@ pub fn draw_results_table(f: &mut Frame, app: &App, area: Rect, active: bool) {
 
     // Draw content based on selected tab
     match app.results_tab {
+        ResultsTab::Data if app.record_view => draw_record_view(f, app, content_area, active),
         ResultsTab::Data => draw_results_data(f, app, content_area, active),
         ResultsTab::Columns => draw_results_columns(f, app, content_area, active),
         ResultsTab::Stats => draw_results_stats(f, app, content_area, active),
+        ResultsTab::Diff => draw_results_diff(f, app, content_area, active),
     }
 }
 
@@ -158,6 +168,7 @@ fn draw_results_tabs(f: &mut Frame, app: &App, area: Rect, active: bool) {
         ("1:Data", ResultsTab::Data),
         ("2:Columns", ResultsTab::Columns),
         ("3:Stats", ResultsTab::Stats),
+        ("4:Diff", ResultsTab::Diff),
     ];
 
     let mut spans: Vec<Span> = vec![Span::raw(" ")];
@@ -186,6 +197,14 @@ fn draw_results_tabs(f: &mut Frame, app: &App, area: Rect, active: bool) {
         spans.push(Span::styled(info, AlrajhiTheme::dim_text()));
     }
 
+    // Position in the result history; an older result is highlighted
+    if let Some(position) = app.result_history.position().filter(|_| app.result_history.len() > 1) {
+        let latest = position + 1 == app.result_history.len();
+        let info = format!("│ [ result {}/{} ] ", position + 1, app.result_history.len());
+        let style = if latest { AlrajhiTheme::dim_text() } else { AlrajhiTheme::warning() };
+        spans.push(Span::styled(info, style));
+    }
+
     let tabs_line = Line::from(spans);
     let tabs_widget = Paragraph::new(tabs_line)
         .style(Style::default().bg(AlrajhiTheme::BG_PANEL));
@@ -193,7 +212,7 @@ fn draw_results_tabs(f: &mut Frame, app: &App, area: Rect, active: bool) {
 }
 
 /// Draw the data tab (table rows)
-fn draw_results_data(f: &mut Frame, app: &App, area: Rect, active: bool) {
+fn draw_results_data(f: &mut Frame, app: &mut App, area: Rect, active: bool) {
     let border_style = if active {
         AlrajhiTheme::active_border()
     } else {
@@ -202,65 +221,138 @@ fn draw_results_data(f: &mut Frame, app: &App, area: Rect, active: bool) {
 
     // Build title with stats
     let exec_time_ms = app.result.execution_time.as_secs_f64() * 1000.0;
-    let title = format!(
-        " Data │ {} rows │ {} cols │ {:.1}ms ",
-        app.result.row_count,
-        app.result.columns.len(),
-        exec_time_ms
-    );
+    let title = match &app.edit_session {
+        Some(session) => format!(
+            " Data │ EDIT {} │ {} staged change(s) ",
+            session.table_name(),
+            session.change_count(app.result.rows.len())
+        ),
+        None if app.result_view.is_active() => format!(
+            " Data │ {} of {} rows │ {} cols │ {:.1}ms ",
+            app.result_view.len(),
+            app.result.row_count,
+            app.result.columns.len(),
+            exec_time_ms
+        ),
+        None => format!(
+            " Data │ {} rows │ {} cols │ {:.1}ms ",
+            app.result.row_count,
+            app.result.columns.len(),
+            exec_time_ms
+        ),
+    };
 
     // Calculate available width for columns
     let available_width = area.width.saturating_sub(2) as usize; // minus borders
     let row_num_width = (app.result.rows.len().to_string().len() + 2).max(4) as u16;
 
-    // Calculate which columns to show based on horizontal scroll
-    // Each column gets a fixed width for consistent display
-    let col_width: u16 = 20; // Fixed column width
-    let cols_that_fit = ((available_width as u16).saturating_sub(row_num_width) / col_width).max(1) as usize;
+    // Pinned columns are always drawn first; the rest scroll horizontally
+    let layout = &app.column_layout;
+    let pinned = layout.pinned_count();
+    let selected_col = app.selected_col();
+    let selection = app.grid_selection.map(|_| app.selection_bounds());
+    let mut budget = (available_width as u16).saturating_sub(row_num_width + 1);
+    let mut visible_cols: Vec<usize> = Vec::new();
+
+    for &col in layout.order.iter().take(pinned) {
+        let needed = layout.width(col) + 1;
+        if needed > budget && !visible_cols.is_empty() {
+            break;
+        }
+        budget = budget.saturating_sub(needed);
+        visible_cols.push(col);
+    }
+
+    // Separator between pinned and scrolling columns
+    let separator_at = (pinned > 0 && pinned < layout.len()).then_some(visible_cols.len());
+    if separator_at.is_some() {
+        budget = budget.saturating_sub(2);
+    }
 
-    // Calculate column scroll offset to keep selected column visible
-    let col_scroll = if app.results_col_selected >= cols_that_fit {
-        app.results_col_selected.saturating_sub(cols_that_fit - 1)
+    // Scroll just far enough that the selected column is visible
+    let scroll_start = if app.results_col_selected < pinned {
+        pinned
     } else {
-        0
+        let mut start = app.results_col_selected.min(layout.len().saturating_sub(1));
+        let mut used = layout.col_at(start).map(|c| layout.width(c) + 1).unwrap_or(0);
+        while start > pinned {
+            let needed = layout.width(layout.order[start - 1]) + 1;
+            if used + needed > budget {
+                break;
+            }
+            used += needed;
+            start -= 1;
+        }
+        start
     };
 
-    // Get visible columns range
-    let visible_cols_start = col_scroll;
-    let visible_cols_end = (col_scroll + cols_that_fit).min(app.result.columns.len());
+    let scroll_first = visible_cols.len();
+    for &col in layout.order.iter().skip(scroll_start) {
+        let needed = layout.width(col) + 1;
+        if needed > budget && visible_cols.len() > scroll_first {
+            break;
+        }
+        budget = budget.saturating_sub(needed);
+        visible_cols.push(col);
+    }
 
-    // Build column widths
+    // Build column widths, remembering header cell positions for mouse resize
     let mut widths: Vec<Constraint> = vec![Constraint::Length(row_num_width)];
-    for _ in visible_cols_start..visible_cols_end {
-        widths.push(Constraint::Length(col_width));
+    let mut header_bounds = Vec::with_capacity(visible_cols.len());
+    let mut x = area.x + 1 + row_num_width + 1;
+    for (i, &col) in visible_cols.iter().enumerate() {
+        if separator_at == Some(i) {
+            widths.push(Constraint::Length(1));
+            x += 2;
+        }
+        let width = layout.width(col);
+        widths.push(Constraint::Length(width));
+        header_bounds.push((x, x + width, col));
+        x += width + 1;
     }
 
+    // Columns inside the range selection
+    let in_range: Vec<bool> = visible_cols
+        .iter()
+        .map(|&col| {
+            let pos = layout.position_of(col);
+            selection.as_ref().zip(pos).is_some_and(|((_, cols), pos)| cols.contains(&pos))
+        })
+        .collect();
+
     // Create header row with row number column and type indicators
     let mut header_cells: Vec<Cell> = vec![
         Cell::from(" # ").style(AlrajhiTheme::table_header())
     ];
-    header_cells.extend(
-        app.result
-            .columns
-            .iter()
-            .enumerate()
-            .skip(visible_cols_start)
-            .take(visible_cols_end - visible_cols_start)
-            .map(|(i, c)| {
-                // Get type indicator
-                let type_indicator = get_type_indicator(&c.type_name);
-                // Truncate column name to fit
-                let name: String = c.name.chars().take(col_width as usize - 4).collect();
-                let header_text = format!("{} {}", type_indicator, name);
-
-                let style = if active && i == app.results_col_selected {
-                    AlrajhiTheme::selected()
-                } else {
-                    AlrajhiTheme::table_header()
-                };
-                Cell::from(header_text).style(style)
-            })
-    );
+    for (i, &col) in visible_cols.iter().enumerate() {
+        if separator_at == Some(i) {
+            header_cells.push(Cell::from("│").style(AlrajhiTheme::inactive_border()));
+        }
+        let c = &app.result.columns[col];
+        // Get type indicator
+        let type_indicator = get_type_indicator(&c.type_name);
+        // Sort arrow and filter mark
+        let sort_mark = match app.result_view.sort {
+            Some((sorted, SortDirection::Ascending)) if sorted == col => "▲",
+            Some((sorted, SortDirection::Descending)) if sorted == col => "▼",
+            _ => "",
+        };
+        let filter_mark = if app.result_view.filters.contains_key(&col) { "⚑" } else { "" };
+        let marks = format!("{}{}", sort_mark, filter_mark);
+        // Truncate column name to fit
+        let name_width = (layout.width(col) as usize).saturating_sub(4 + marks.chars().count());
+        let name = to_visual(&truncate_to_width(&c.name, name_width));
+        let header_text = format!("{} {}{}", type_indicator, name, marks);
+
+        let style = if active && Some(col) == selected_col {
+            AlrajhiTheme::selected()
+        } else if in_range[i] {
+            AlrajhiTheme::cell_range()
+        } else {
+            AlrajhiTheme::table_header()
+        };
+        header_cells.push(Cell::from(header_text).style(style));
+    }
     let header = Row::new(header_cells).height(1);
 
     // Create data rows with row numbers
@@ -272,50 +364,81 @@ fn draw_results_data(f: &mut Frame, app: &App, area: Rect, active: bool) {
     };
 
     let rows: Vec<Row> = app
-        .result
-        .rows
+        .result_view
+        .order
         .iter()
         .enumerate()
         .skip(scroll_offset)
         .take(visible_height)
-        .map(|(row_idx, row)| {
-            // Row number cell
-            let row_num_style = if active && row_idx == app.results_selected {
+        .filter_map(|(display_idx, &row_idx)| app.result.rows.get(row_idx).map(|row| (display_idx, row_idx, row)))
+        .map(|(display_idx, row_idx, row)| {
+            let session = app.edit_session.as_ref();
+            let is_deleted = session.map(|s| s.is_deleted(row_idx)).unwrap_or(false);
+            let is_inserted = session.map(|s| s.is_inserted(row_idx)).unwrap_or(false);
+            let row_in_range = selection.as_ref().is_some_and(|(rows, _)| rows.contains(&display_idx));
+
+            // Row number cell (marked + / - for staged inserts and deletes)
+            let row_num_style = if active && display_idx == app.results_selected {
                 AlrajhiTheme::selected()
+            } else if row_in_range {
+                AlrajhiTheme::cell_range()
+            } else if is_deleted {
+                AlrajhiTheme::deleted_row()
+            } else if is_inserted {
+                AlrajhiTheme::inserted_row()
             } else {
                 AlrajhiTheme::row_number()
             };
+            let row_label = if is_deleted {
+                "-".to_string()
+            } else if is_inserted {
+                "+".to_string()
+            } else {
+                (row_idx + 1).to_string()
+            };
             let mut cells: Vec<Cell> = vec![
-                Cell::from(format!("{:>width$} ", row_idx + 1, width = row_num_width as usize - 1))
+                Cell::from(format!("{:>width$} ", row_label, width = row_num_width as usize - 1))
                     .style(row_num_style)
             ];
 
             // Data cells - only visible columns
-            cells.extend(
-                row.iter()
-                    .enumerate()
-                    .skip(visible_cols_start)
-                    .take(visible_cols_end - visible_cols_start)
-                    .map(|(col_idx, cell)| {
-                        let (value, is_null) = format_cell_value(cell);
-                        // Truncate value to fit column
-                        let display_value: String = value.chars().take(col_width as usize - 2).collect();
-
-                        let style = if active && row_idx == app.results_selected && col_idx == app.results_col_selected {
-                            AlrajhiTheme::selected()
-                        } else if active && row_idx == app.results_selected {
-                            AlrajhiTheme::highlighted()
-                        } else if is_null {
-                            AlrajhiTheme::null_value()
-                        } else if row_idx % 2 == 1 {
-                            AlrajhiTheme::table_row_alt()
-                        } else {
-                            AlrajhiTheme::normal_text()
-                        };
-
-                        Cell::from(format!(" {} ", display_value)).style(style)
-                    })
-            );
+            for (i, &col_idx) in visible_cols.iter().enumerate() {
+                if separator_at == Some(i) {
+                    cells.push(Cell::from("│").style(AlrajhiTheme::inactive_border()));
+                }
+                let Some(cell) = row.get(col_idx) else {
+                    continue;
+                };
+                let cell = session.map(|s| s.display_value(row_idx, col_idx, cell)).unwrap_or(cell);
+                let is_edited = session.map(|s| s.is_edited(row_idx, col_idx)).unwrap_or(false);
+                let (value, is_null) = format_cell_value(cell);
+                // Truncate value to fit column
+                let display_value = to_visual(&truncate_to_width(&value, (layout.width(col_idx) as usize).saturating_sub(2)));
+
+                let style = if active && display_idx == app.results_selected && Some(col_idx) == selected_col {
+                    AlrajhiTheme::selected()
+                } else if row_in_range && in_range[i] {
+                    AlrajhiTheme::cell_range()
+                } else if app.result_view.is_search_match(cell) {
+                    AlrajhiTheme::search_match()
+                } else if is_deleted {
+                    AlrajhiTheme::deleted_row()
+                } else if is_edited {
+                    AlrajhiTheme::edited_cell()
+                } else if is_inserted {
+                    AlrajhiTheme::inserted_row()
+                } else if active && display_idx == app.results_selected {
+                    AlrajhiTheme::highlighted()
+                } else if is_null {
+                    AlrajhiTheme::null_value()
+                } else if display_idx % 2 == 1 {
+                    AlrajhiTheme::table_row_alt()
+                } else {
+                    AlrajhiTheme::normal_text()
+                };
+
+                cells.push(Cell::from(format!(" {} ", display_value)).style(style));
+            }
             Row::new(cells)
         })
         .collect();
@@ -330,16 +453,22 @@ fn draw_results_data(f: &mut Frame, app: &App, area: Rect, active: bool) {
         )
         .highlight_style(AlrajhiTheme::highlighted());
 
+    let drawn_rows = app.result_view.len().saturating_sub(scroll_offset).min(visible_height) as u16;
     f.render_widget(table, area);
+    app.grid_header_bounds = header_bounds;
+    app.grid_header_y = area.y + 1;
+    app.grid_rows_y = area.y + 2..area.y + 2 + drawn_rows;
+    app.grid_row_number_x = area.x + 1..area.x + 1 + row_num_width;
+    app.results_scroll = scroll_offset;
 
     // Draw scrollbar if needed
-    if app.result.rows.len() > visible_height {
+    if app.result_view.len() > visible_height {
         let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
             .begin_symbol(Some("▲"))
             .end_symbol(Some("▼"))
             .track_symbol(Some("│"));
 
-        let mut scrollbar_state = ScrollbarState::new(app.result.rows.len())
+        let mut scrollbar_state = ScrollbarState::new(app.result_view.len())
             .position(app.results_selected);
 
         f.render_stateful_widget(
@@ -349,16 +478,50 @@ fn draw_results_data(f: &mut Frame, app: &App, area: Rect, active: bool) {
         );
     }
 
+    // Draw filter/search prompt along the bottom border
+    if let Some(input) = &app.grid_input {
+        let label = match input.kind {
+            GridInputKind::Filter(col) => format!(
+                " Filter {} (> 1000, ~ 'abc', is null): ",
+                app.result.columns.get(col).map(|c| c.name.as_str()).unwrap_or("?")
+            ),
+            GridInputKind::Search => " Search: ".to_string(),
+        };
+        let prompt = Line::from(vec![
+            Span::styled(label, AlrajhiTheme::info()),
+            Span::styled(format!("{} ", input.buffer), AlrajhiTheme::normal_text()),
+        ]);
+        let prompt_width = (prompt.width() as u16).min(area.width.saturating_sub(2));
+        let prompt_area = Rect::new(area.x + 1, area.y + area.height.saturating_sub(1), prompt_width, 1);
+        f.render_widget(Paragraph::new(prompt).style(AlrajhiTheme::popup()), prompt_area);
+        f.set_cursor(
+            (prompt_area.x + prompt_width).saturating_sub(1),
+            prompt_area.y,
+        );
+        return;
+    }
+
     // Draw position indicator at bottom right
     if !app.result.rows.is_empty() {
+        let search_info = if app.result_view.search.is_some() {
+            format!(" {} match(es) │", app.search_match_count())
+        } else {
+            String::new()
+        };
+        let selection_info = match selection {
+            Some((rows, cols)) => format!(" {}×{} selected │", rows.count(), cols.count()),
+            None => String::new(),
+        };
         let pos_text = format!(
-            " Row {}/{} Col {}/{} ",
+            "{}{} Row {}/{} Col {}/{} ",
+            search_info,
+            selection_info,
             app.results_selected + 1,
-            app.result.rows.len(),
+            app.result_view.len(),
             app.results_col_selected + 1,
-            app.result.columns.len()
+            app.column_layout.len()
         );
-        let pos_len = pos_text.len() as u16;
+        let pos_len = pos_text.chars().count() as u16;
         let pos_x = area.x + area.width.saturating_sub(pos_len + 2);
         let pos_y = area.y + area.height.saturating_sub(1);
 
@@ -372,6 +535,145 @@ fn draw_results_data(f: &mut Frame, app: &App, area: Rect, active: bool) {
     }
 }
 
+/// Draw the selected row as a vertical name/type/value list
+fn draw_record_view(f: &mut Frame, app: &mut App, area: Rect, active: bool) {
+    let border_style = if active {
+        AlrajhiTheme::active_border()
+    } else {
+        AlrajhiTheme::inactive_border()
+    };
+
+    let row_idx = app.selected_row();
+    let row = row_idx.and_then(|r| app.result.rows.get(r));
+    let title = format!(
+        " Record {}/{} │ ←/→:Prev/Next  ↑/↓:Field  Ctrl+Y:Copy  v:Grid ",
+        if row.is_some() { app.results_selected + 1 } else { 0 },
+        app.result_view.len()
+    );
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style)
+        .title(Span::styled(title, AlrajhiTheme::title()));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let Some(row) = row else {
+        f.render_widget(
+            Paragraph::new(Span::styled("No record selected", AlrajhiTheme::dim_text())),
+            inner,
+        );
+        return;
+    };
+    let row_idx = row_idx.unwrap_or(0);
+
+    // Name and type columns sized to content, value takes the rest
+    let name_width = app
+        .result
+        .columns
+        .iter()
+        .map(|c| display_width(&c.name))
+        .max()
+        .unwrap_or(4)
+        .clamp(4, 32);
+    let type_width = app
+        .result
+        .columns
+        .iter()
+        .map(|c| display_width(&c.type_name))
+        .max()
+        .unwrap_or(4)
+        .clamp(4, 16);
+    let value_width = (inner.width as usize)
+        .saturating_sub(name_width + type_width + 5)
+        .max(10);
+
+    let mut lines: Vec<Line> = Vec::new();
+    let mut selected_start = 0;
+    let mut selected_end = 0;
+
+    // Fields follow the grid's column order and skip hidden columns
+    for (field_idx, &col_idx) in app.column_layout.order.iter().enumerate() {
+        let (Some(col), Some(cell)) = (app.result.columns.get(col_idx), row.get(col_idx)) else {
+            continue;
+        };
+        let cell = app
+            .edit_session
+            .as_ref()
+            .map(|s| s.display_value(row_idx, col_idx, cell))
+            .unwrap_or(cell);
+        let is_selected = field_idx == app.results_col_selected;
+        let is_null = matches!(cell, CellValue::Null);
+
+        let name_style = if is_selected && active {
+            AlrajhiTheme::selected()
+        } else {
+            AlrajhiTheme::info()
+        };
+        let value_style = if is_null {
+            AlrajhiTheme::null_value()
+        } else if is_selected {
+            AlrajhiTheme::highlighted()
+        } else {
+            AlrajhiTheme::normal_text()
+        };
+
+        // Full value, wrapped; embedded newlines are kept
+        let text = cell.to_string();
+        let mut wrapped: Vec<String> = text
+            .split('\n')
+            .flat_map(|line| {
+                textwrap::wrap(line, value_width)
+                    .into_iter()
+                    .map(|l| to_visual(&l))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        if wrapped.is_empty() {
+            wrapped.push(String::new());
+        }
+
+        if is_selected {
+            selected_start = lines.len();
+            selected_end = lines.len() + wrapped.len();
+        }
+
+        // Pad by display width so wide and RTL names keep the columns aligned
+        let name = truncate_to_width(&col.name, name_width);
+        let name = format!("{}{}", to_visual(&name), " ".repeat(name_width - display_width(&name)));
+        let type_name: String = col.type_name.chars().take(type_width).collect();
+        for (i, part) in wrapped.into_iter().enumerate() {
+            let (name_cell, type_cell) = if i == 0 {
+                (format!(" {}", name), format!(" {:<width$} ", type_name, width = type_width))
+            } else {
+                (" ".repeat(name_width + 1), " ".repeat(type_width + 2))
+            };
+            lines.push(Line::from(vec![
+                Span::styled(name_cell, if i == 0 { name_style } else { Style::default() }),
+                Span::styled(type_cell, AlrajhiTheme::dim_text()),
+                Span::styled("│ ", AlrajhiTheme::muted_text()),
+                Span::styled(part, value_style),
+            ]));
+        }
+    }
+
+    // Scroll so the whole selected field is visible when possible; a taller one
+    // starts at its first row and pages through with record_field_scroll
+    let visible = inner.height as usize;
+    let overflow = (selected_end - selected_start).saturating_sub(visible);
+    let scroll = if overflow > 0 {
+        selected_start + app.record_field_scroll.min(overflow)
+    } else if selected_end > visible {
+        (selected_end - visible).min(selected_start)
+    } else {
+        0
+    };
+    app.record_field_overflow = overflow;
+    app.record_page_rows = visible;
+
+    f.render_widget(Paragraph::new(lines).scroll((scroll as u16, 0)), inner);
+}
+
 /// Draw the columns tab (column info)
 fn draw_results_columns(f: &mut Frame, app: &App, area: Rect, active: bool) {
     let border_style = if active {
@@ -460,6 +762,153 @@ fn draw_results_columns(f: &mut Frame, app: &App, area: Rect, active: bool) {
     }
 }
 
+/// Draw the diff tab: added, removed and changed rows of the last comparison
+fn draw_results_diff(f: &mut Frame, app: &App, area: Rect, active: bool) {
+    let border_style = if active {
+        AlrajhiTheme::active_border()
+    } else {
+        AlrajhiTheme::inactive_border()
+    };
+
+    let Some(diff) = app.result_diff.as_ref() else {
+        let help_text = vec![
+            Line::from(""),
+            Line::from(Span::styled("No comparison yet", AlrajhiTheme::dim_text())),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("Press ", AlrajhiTheme::dim_text()),
+                Span::styled("D", AlrajhiTheme::info()),
+                Span::styled(" to compare this result with an earlier result set", AlrajhiTheme::dim_text()),
+            ]),
+        ];
+        let empty_msg = Paragraph::new(help_text)
+            .block(Block::default().borders(Borders::ALL).border_style(border_style).title(Span::styled(" Diff ", AlrajhiTheme::title())))
+            .alignment(Alignment::Center);
+        f.render_widget(empty_msg, area);
+        return;
+    };
+
+    let filter = diff.filter.map(|kind| format!(" │ only {}", kind.label())).unwrap_or_default();
+    let title = format!(
+        " Diff │ {} → {} │ key: {} │ +{} -{} ~{} ={}{} ",
+        diff.before_label,
+        diff.after_label,
+        diff.key_summary(),
+        diff.count(DiffKind::Added),
+        diff.count(DiffKind::Removed),
+        diff.count(DiffKind::Changed),
+        diff.unchanged,
+        filter
+    );
+
+    // Key columns stay on the left; the rest scroll with h/l
+    let order = diff.display_columns();
+    let key_count = diff.keys.len();
+    let mut budget = area.width.saturating_sub(2 + 4);
+    let mut visible_cols: Vec<usize> = Vec::new();
+    for &col in order.iter().take(key_count).chain(order.iter().skip(key_count + diff.col_offset)) {
+        let needed = diff.widths[col] + 1;
+        if needed > budget && !visible_cols.is_empty() {
+            break;
+        }
+        budget = budget.saturating_sub(needed);
+        visible_cols.push(col);
+    }
+
+    let mut widths: Vec<Constraint> = vec![Constraint::Length(3)];
+    widths.extend(visible_cols.iter().map(|&col| Constraint::Length(diff.widths[col])));
+
+    let mut header_cells: Vec<Cell> = vec![Cell::from("   ").style(AlrajhiTheme::table_header())];
+    for &col in &visible_cols {
+        let c = &diff.columns[col];
+        let key_mark = if diff.keys.contains(&col) { "🔑" } else { get_type_indicator(&c.type_name) };
+        let name = to_visual(&truncate_to_width(&c.name, (diff.widths[col] as usize).saturating_sub(4)));
+        header_cells.push(Cell::from(format!("{} {}", key_mark, name)).style(AlrajhiTheme::table_header()));
+    }
+    let header = Row::new(header_cells).height(1);
+
+    let visible_height = area.height.saturating_sub(3) as usize;
+    let scroll_offset = if app.results_selected >= visible_height {
+        app.results_selected.saturating_sub(visible_height - 1)
+    } else {
+        0
+    };
+
+    let rows: Vec<Row> = diff
+        .view
+        .iter()
+        .enumerate()
+        .skip(scroll_offset)
+        .take(visible_height)
+        .filter_map(|(display_idx, &row_idx)| diff.rows.get(row_idx).map(|row| (display_idx, row)))
+        .map(|(display_idx, row)| {
+            let is_selected = active && display_idx == app.results_selected;
+            let row_style = match row.kind {
+                DiffKind::Added => AlrajhiTheme::inserted_row(),
+                DiffKind::Removed => AlrajhiTheme::deleted_row(),
+                DiffKind::Changed if is_selected => AlrajhiTheme::highlighted(),
+                DiffKind::Changed if display_idx % 2 == 1 => AlrajhiTheme::table_row_alt(),
+                DiffKind::Changed => AlrajhiTheme::normal_text(),
+            };
+            let marker_style = if is_selected { AlrajhiTheme::selected() } else { row_style };
+            let mut cells: Vec<Cell> = vec![Cell::from(format!(" {} ", row.kind.marker())).style(marker_style)];
+
+            for &col in &visible_cols {
+                let Some(cell) = row.cells.get(col) else {
+                    continue;
+                };
+                let (value, is_null) = format_cell_value(cell);
+                let (text, style) = match row.old_value(col) {
+                    Some(old) => (format!("{} → {}", format_cell_value(old).0, value), AlrajhiTheme::edited_cell()),
+                    None if is_null && row.kind == DiffKind::Changed => (value, AlrajhiTheme::null_value()),
+                    None => (value, row_style),
+                };
+                let display_value = to_visual(&truncate_to_width(&text, (diff.widths[col] as usize).saturating_sub(2)));
+                cells.push(Cell::from(format!(" {} ", display_value)).style(style));
+            }
+            Row::new(cells)
+        })
+        .collect();
+
+    // Columns that could not be compared are listed along the bottom border
+    let mut skipped = Vec::new();
+    if !diff.before_only_columns.is_empty() {
+        skipped.push(format!("only before: {}", diff.before_only_columns.join(", ")));
+    }
+    if !diff.after_only_columns.is_empty() {
+        skipped.push(format!("only after: {}", diff.after_only_columns.join(", ")));
+    }
+    if diff.duplicate_keys > 0 {
+        skipped.push(format!("{} duplicate key(s)", diff.duplicate_keys));
+    }
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style)
+        .title(Span::styled(title, AlrajhiTheme::title()));
+    if !skipped.is_empty() {
+        block = block.title_bottom(Span::styled(format!(" {} ", skipped.join(" │ ")), AlrajhiTheme::warning()));
+    }
+
+    let table = Table::new(rows, widths).header(header).block(block);
+    f.render_widget(table, area);
+
+    if diff.view.len() > visible_height {
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some("▲"))
+            .end_symbol(Some("▼"))
+            .track_symbol(Some("│"));
+
+        let mut scrollbar_state = ScrollbarState::new(diff.view.len())
+            .position(app.results_selected);
+
+        f.render_stateful_widget(
+            scrollbar,
+            area.inner(&Margin { vertical: 1, horizontal: 0 }),
+            &mut scrollbar_state,
+        );
+    }
+}
+
 /// Draw the stats tab (query statistics)
 fn draw_results_stats(f: &mut Frame, app: &App, area: Rect, active: bool) {
     let border_style = if active {
@@ -543,11 +992,11 @@ fn draw_results_stats(f: &mut Frame, app: &App, area: Rect, active: bool) {
     stats_lines.push(Line::from(""));
     stats_lines.push(Line::from(vec![
         Span::styled("  Ctrl+E  ", AlrajhiTheme::info()),
-        Span::styled("Export to CSV", AlrajhiTheme::dim_text()),
+        Span::styled("Export (CSV, JSON, Parquet, Excel...)", AlrajhiTheme::dim_text()),
     ]));
     stats_lines.push(Line::from(vec![
         Span::styled("  Ctrl+S  ", AlrajhiTheme::info()),
-        Span::styled("Export to JSON", AlrajhiTheme::dim_text()),
+        Span::styled("Export as JSON", AlrajhiTheme::dim_text()),
     ]));
     stats_lines.push(Line::from(vec![
         Span::styled("  Ctrl+I  ", AlrajhiTheme::info()),
@@ -555,7 +1004,7 @@ fn draw_results_stats(f: &mut Frame, app: &App, area: Rect, active: bool) {
     ]));
     stats_lines.push(Line::from(vec![
         Span::styled("  Ctrl+Y  ", AlrajhiTheme::info()),
-        Span::styled("Copy cell value", AlrajhiTheme::dim_text()),
+        Span::styled("Copy cell value (selection as TSV; Y: other formats)", AlrajhiTheme::dim_text()),
     ]));
 
     let stats_widget = Paragraph::new(stats_lines)
@@ -593,11 +1042,7 @@ fn format_cell_value(cell: &CellValue) -> (String, bool) {
         CellValue::Float(v) => (format!("{:.4}", v), false),
         CellValue::String(v) => {
             // Truncate long strings
-            if v.len() > 50 {
-                (format!("{}…", &v[..47]), false)
-            } else {
-                (v.clone(), false)
-            }
+            (truncate_with_ellipsis(v, 50), false)
         }
         CellValue::DateTime(v) => (v.clone(), false),
         CellValue::Binary(v) => (format!("0x{}…", &hex_encode(&v[..v.len().min(8)])), false),
@@ -679,7 +1124,7 @@ pub fn draw_schema_explorer(f: &mut Frame, app: &App, area: Rect, active: bool)
     f.render_widget(list, area);
 }
 
-/// Draw the history panel
+/// Draw the history panel: search line, column header and filtered entries
 pub fn draw_history_panel(f: &mut Frame, app: &App, area: Rect, active: bool) {
     let border_style = if active {
         AlrajhiTheme::active_border()
@@ -688,139 +1133,219 @@ pub fn draw_history_panel(f: &mut Frame, app: &App, area: Rect, active: bool) {
     };
 
     let title = if active { " History [4] ▪ " } else { " History [4] " };
+    let filter = &app.history_filter;
+    let rows = app.history_rows();
+    let count = if rows.len() == app.history.len() {
+        format!("({})", rows.len())
+    } else {
+        format!("({}/{})", rows.len(), app.history.len())
+    };
 
-    let entries = app.history.entries();
-    let items: Vec<ListItem> = entries
+    let filters = if filter.has_filters() { format!("{} ", filter.summary()) } else { String::new() };
+
+    // Error of the selected failed entry, or where it ran, along the bottom border
+    let details = app
+        .selected_history_index()
+        .and_then(|i| app.history.get(i))
+        .filter(|_| active)
+        .map(|entry| match entry.status {
+            QueryStatus::Success => {
+                let mut parts = vec![entry.profile.clone(), entry.server.clone()];
+                if let Some(affected) = entry.affected_rows {
+                    parts.push(format!("{} affected", affected));
+                }
+                let parts: Vec<String> = parts.into_iter().filter(|p| !p.is_empty()).collect();
+                (parts.join(" · "), AlrajhiTheme::dim_text())
+            }
+            QueryStatus::Error => (
+                format!("✗ {}", entry.error.as_deref().unwrap_or("error").lines().next().unwrap_or_default()),
+                AlrajhiTheme::error(),
+            ),
+            QueryStatus::Cancelled => ("✗ cancelled".to_string(), AlrajhiTheme::error()),
+        })
+        .filter(|(text, _)| !text.is_empty())
+        .map(|(text, style)| Span::styled(
+            format!(" {} ", truncate_with_ellipsis(&text, area.width.saturating_sub(4) as usize)),
+            style,
+        ))
+        .unwrap_or_default();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style)
+        .title(Line::from(vec![
+            Span::styled(format!("{}{} ", title, count), AlrajhiTheme::title()),
+            Span::styled(filters, AlrajhiTheme::warning()),
+        ]))
+        .title_bottom(details);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let show_search = app.history_searching || !filter.text.is_empty();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(show_search as u16),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+
+    if show_search {
+        let style = if app.history_searching { AlrajhiTheme::warning() } else { AlrajhiTheme::dim_text() };
+        f.render_widget(Paragraph::new(Span::styled(format!("/{}", filter.text), style)), chunks[0]);
+        if active && app.history_searching {
+            let x = chunks[0].x + 1 + display_width(&filter.text) as u16;
+            f.set_cursor(x.min(chunks[0].right().saturating_sub(1)), chunks[0].y);
+        }
+    }
+
+    // Pin, kept-result marker, time, duration, rows and database columns, then the query
+    let header = format!("    {:<5} {:>6} {:>6} {:<10} {}", "Time", "Dur", "Rows", "Database", "Query");
+    f.render_widget(Paragraph::new(Span::styled(header, AlrajhiTheme::dim_text())), chunks[1]);
+
+    let list_area = chunks[2];
+    let height = list_area.height as usize;
+    let offset = app.history_selected.saturating_sub(height.saturating_sub(1));
+    let width = list_area.width as usize;
+    let today = chrono::Local::now().date_naive();
+    let lines: Vec<Line> = rows
         .iter()
-        .rev()
         .enumerate()
+        .skip(offset)
+        .take(height)
+        .filter_map(|(idx, &i)| Some((idx, app.history.get(i)?)))
         .map(|(idx, entry)| {
-            let time = entry.timestamp.format("%H:%M:%S").to_string();
-            let query_preview: String = entry
-                .query
-                .chars()
-                .take(50)
-                .filter(|c| !c.is_control())
-                .collect();
-            let query_preview = if entry.query.len() > 50 {
-                format!("{}...", query_preview)
+            let time = if entry.timestamp.date_naive() == today {
+                entry.timestamp.format("%H:%M").to_string()
             } else {
-                query_preview
+                entry.timestamp.format("%m-%d").to_string()
             };
+            let rows = match entry.status {
+                QueryStatus::Success => entry.row_count.map(|r| r.to_string()).unwrap_or_else(|| "-".to_string()),
+                QueryStatus::Error => "error".to_string(),
+                QueryStatus::Cancelled => "cancel".to_string(),
+            };
+            let query: String = entry
+                .query
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let text = format!(
+                "{}{} {:<5} {:>6} {:>6} {:<10} {}",
+                if entry.pinned { "📌" } else { "  " },
+                if app.result_history.index_of(entry.id).is_some() { "▣" } else { " " },
+                time,
+                format_duration_ms(entry.execution_time_ms),
+                rows,
+                truncate_with_ellipsis(&entry.database, 10),
+                query
+            );
 
-            let row_info = entry.row_count.map(|r| format!(" ({} rows)", r)).unwrap_or_default();
-
+            // Failed and cancelled runs stand out in the error style
             let style = if active && idx == app.history_selected {
                 AlrajhiTheme::selected()
+            } else if !entry.is_success() {
+                AlrajhiTheme::error()
             } else {
                 AlrajhiTheme::normal_text()
             };
-
-            ListItem::new(format!("{} │ {}{}", time, query_preview, row_info)).style(style)
+            Line::from(Span::styled(truncate_with_ellipsis(&text, width), style))
         })
         .collect();
 
-    let list = List::new(items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(border_style)
-                .title(Span::styled(
-                    format!("{} ({}) ", title, app.history.len()),
-                    AlrajhiTheme::title(),
-                )),
-        );
-
-    f.render_widget(list, area);
+    if lines.is_empty() && !app.history.is_empty() {
+        f.render_widget(Paragraph::new(Span::styled("No matches (x clears filters)", AlrajhiTheme::dim_text())), list_area);
+    } else {
+        f.render_widget(Paragraph::new(lines), list_area);
+    }
 }
 
-/// SQL syntax highlighting
-fn highlight_sql(sql: &str) -> Vec<Line<'static>> {
-    let keywords = [
-        "SELECT", "FROM", "WHERE", "AND", "OR", "NOT", "IN", "LIKE", "BETWEEN",
-        "ORDER", "BY", "ASC", "DESC", "GROUP", "HAVING", "JOIN", "INNER", "LEFT",
-        "RIGHT", "OUTER", "FULL", "CROSS", "ON", "AS", "DISTINCT", "TOP", "WITH",
-        "INSERT", "INTO", "VALUES", "UPDATE", "SET", "DELETE", "CREATE", "TABLE",
-        "ALTER", "DROP", "INDEX", "VIEW", "PROCEDURE", "FUNCTION", "TRIGGER",
-        "BEGIN", "END", "IF", "ELSE", "WHILE", "RETURN", "DECLARE", "EXEC", "EXECUTE",
-        "NULL", "IS", "CASE", "WHEN", "THEN", "UNION", "ALL", "EXISTS", "COUNT",
-        "SUM", "AVG", "MIN", "MAX", "CAST", "CONVERT", "COALESCE", "ISNULL",
-    ];
-
-    let mut lines: Vec<Line> = Vec::new();
-
-    for line in sql.lines() {
-        let mut spans: Vec<Span> = Vec::new();
-        let mut current_word = String::new();
-        let mut in_string = false;
-        let mut string_char = ' ';
-        let mut in_comment = false;
-
-        let chars: Vec<char> = line.chars().collect();
-        let mut i = 0;
+/// Draw the snippets panel: search line, folders and snippets, selected description
+pub fn draw_snippets_panel(f: &mut Frame, app: &App, area: Rect, active: bool) {
+    let border_style = if active {
+        AlrajhiTheme::active_border()
+    } else {
+        AlrajhiTheme::inactive_border()
+    };
 
-        while i < chars.len() {
-            let c = chars[i];
+    let title = if active { " Snippets [5] ▪ " } else { " Snippets [5] " };
+    let panel = &app.snippet_panel;
 
-            // Check for line comment
-            if !in_string && i + 1 < chars.len() && chars[i] == '-' && chars[i + 1] == '-' {
-                if !current_word.is_empty() {
-                    spans.push(colorize_word(&current_word, &keywords));
-                    current_word.clear();
-                }
-                // Rest of line is comment
-                let comment: String = chars[i..].iter().collect();
-                spans.push(Span::styled(comment, Style::default().fg(AlrajhiTheme::COMMENT)));
-                break;
-            }
+    // Description and tags of the selected snippet along the bottom border
+    let details = app
+        .selected_snippet()
+        .and_then(|i| app.snippets.get(i))
+        .map(|s| {
+            let tags: Vec<String> = s.tags.iter().map(|t| format!("#{}", t)).collect();
+            format!(" {} {} ", s.description, tags.join(" ")).replace("  ", " ")
+        })
+        .filter(|d| active && !d.trim().is_empty())
+        .unwrap_or_default();
 
-            // Handle strings
-            if (c == '\'' || c == '"') && !in_comment {
-                if in_string && c == string_char {
-                    current_word.push(c);
-                    spans.push(Span::styled(
-                        current_word.clone(),
-                        Style::default().fg(AlrajhiTheme::STRING),
-                    ));
-                    current_word.clear();
-                    in_string = false;
-                } else if !in_string {
-                    if !current_word.is_empty() {
-                        spans.push(colorize_word(&current_word, &keywords));
-                        current_word.clear();
-                    }
-                    in_string = true;
-                    string_char = c;
-                    current_word.push(c);
-                } else {
-                    current_word.push(c);
-                }
-            } else if in_string {
-                current_word.push(c);
-            } else if c.is_whitespace() || "(),;.=<>+-*/".contains(c) {
-                if !current_word.is_empty() {
-                    spans.push(colorize_word(&current_word, &keywords));
-                    current_word.clear();
-                }
-                spans.push(Span::styled(
-                    c.to_string(),
-                    Style::default().fg(AlrajhiTheme::OPERATOR),
-                ));
-            } else {
-                current_word.push(c);
-            }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style)
+        .title(Span::styled(
+            format!("{} ({}) ", title, app.snippets.snippets().len()),
+            AlrajhiTheme::title(),
+        ))
+        .title_bottom(Span::styled(
+            truncate_with_ellipsis(&details, area.width.saturating_sub(2) as usize),
+            AlrajhiTheme::dim_text(),
+        ));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
 
-            i += 1;
+    let show_search = panel.searching || !panel.filter.is_empty();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(show_search as u16), Constraint::Min(0)])
+        .split(inner);
+
+    if show_search {
+        let style = if panel.searching { AlrajhiTheme::warning() } else { AlrajhiTheme::dim_text() };
+        f.render_widget(Paragraph::new(Span::styled(format!("/{}", panel.filter), style)), chunks[0]);
+        if active && panel.searching {
+            let x = chunks[0].x + 1 + display_width(&panel.filter) as u16;
+            f.set_cursor(x.min(chunks[0].right().saturating_sub(1)), chunks[0].y);
         }
+    }
 
-        if !current_word.is_empty() {
-            spans.push(colorize_word(&current_word, &keywords));
-        }
+    let rows = app.snippet_rows();
+    let list_area = chunks[1];
+    let height = list_area.height as usize;
+    let offset = panel.selected.saturating_sub(height.saturating_sub(1));
+    let width = list_area.width as usize;
+    let lines: Vec<Line> = rows
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(height)
+        .map(|(idx, row)| {
+            let selected = active && idx == panel.selected;
+            let (text, style) = match row {
+                SnippetRow::Folder { name, count, collapsed } => (
+                    format!("{} 📁 {} ({})", if *collapsed { "▶" } else { "▼" }, name, count),
+                    AlrajhiTheme::info(),
+                ),
+                SnippetRow::Snippet(i) => {
+                    let snippet = &app.snippets.snippets()[*i];
+                    let indent = if snippet.folder.is_empty() { "" } else { "    " };
+                    (format!("{}✎ {}", indent, snippet.name), AlrajhiTheme::normal_text())
+                }
+            };
+            let style = if selected { AlrajhiTheme::selected() } else { style };
+            Line::from(Span::styled(to_visual(&truncate_with_ellipsis(&text, width)), style))
+        })
+        .collect();
 
-        lines.push(Line::from(spans));
+    if lines.is_empty() {
+        let hint = if panel.filter.is_empty() { "No snippets: press a to save the query" } else { "No matches" };
+        f.render_widget(Paragraph::new(Span::styled(hint, AlrajhiTheme::dim_text())), list_area);
+    } else {
+        f.render_widget(Paragraph::new(lines), list_area);
     }
-
-    lines
 }
 
 fn colorize_word(word: &str, keywords: &[&str]) -> Span<'static> {
@@ -850,7 +1375,7 @@ fn colorize_word(word: &str, keywords: &[&str]) -> Span<'static> {
 
 /// Calculate cursor position with scroll offset
 fn calculate_cursor_position_with_scroll(app: &App, code_area: Rect) -> (u16, u16) {
-    let (line, col) = app.get_cursor_line_col();
+    let (line, col) = app.editor.visual_line_col();
 
     // Adjust for scroll offset
     let visible_line = line.saturating_sub(app.query_scroll_y);
@@ -863,13 +1388,125 @@ fn calculate_cursor_position_with_scroll(app: &App, code_area: Rect) -> (u16, u1
 }
 
 /// SQL syntax highlighting with scroll support
+///
+/// Each line is highlighted in logical order, then laid out for the screen:
+/// selection applied, right-to-left runs reordered and the result cropped to
+/// the visible display columns.
 fn highlight_sql_with_scroll(
-    sql: &str,
+    editor: &EditorBuffer,
     scroll_x: usize,
     scroll_y: usize,
     visible_width: usize,
     visible_height: usize,
 ) -> Vec<Line<'static>> {
+    let mut lines: Vec<Line> = editor
+        .text()
+        .split('\n')
+        .enumerate()
+        .skip(scroll_y)
+        .take(visible_height)
+        .map(|(line_idx, line_content)| {
+            let spans = highlight_sql_line(line_content);
+            layout_editor_line(line_content, &spans, editor.selection_on_line(line_idx), scroll_x, visible_width)
+        })
+        .collect();
+
+    // Pad with empty lines if needed
+    while lines.len() < visible_height {
+        lines.push(Line::from(""));
+    }
+
+    lines
+}
+
+/// Place a highlighted line on screen, grapheme by grapheme
+fn layout_editor_line(
+    line: &str,
+    spans: &[Span<'static>],
+    selection: Option<(usize, usize)>,
+    scroll_x: usize,
+    visible_width: usize,
+) -> Line<'static> {
+    // Byte ranges styled by the highlighter
+    let mut styled: Vec<(std::ops::Range<usize>, Style)> = Vec::with_capacity(spans.len());
+    let mut offset = 0;
+    for span in spans {
+        styled.push((offset..offset + span.content.len(), span.style));
+        offset += span.content.len();
+    }
+    let style_at = |byte: usize| {
+        styled
+            .iter()
+            .find(|(range, _)| range.contains(&byte))
+            .map(|(_, style)| *style)
+            .unwrap_or_default()
+    };
+
+    // Logical start column of each grapheme, which is what the selection uses
+    let logical_cols: Vec<usize> = line
+        .graphemes(true)
+        .scan(0, |col, g| {
+            let start = *col;
+            *col += grapheme_width(g);
+            Some(start)
+        })
+        .collect();
+
+    let view_end = scroll_x + visible_width;
+    let mut out: Vec<Span<'static>> = Vec::new();
+    let mut col = 0;
+
+    for g in visual_graphemes(line) {
+        let (start, end) = (col, col + g.width);
+        col = end;
+        if end <= scroll_x || g.width == 0 && start < scroll_x {
+            continue;
+        }
+        if start >= view_end {
+            break;
+        }
+
+        let mut style = style_at(g.byte);
+        if let Some((from, to)) = selection {
+            let logical = logical_cols[g.index];
+            if logical >= from && logical < to {
+                style = style.patch(AlrajhiTheme::text_selection());
+            }
+        }
+
+        let text = if start < scroll_x || end > view_end {
+            // Wide character cut by the viewport edge
+            " ".repeat(end.min(view_end) - start.max(scroll_x))
+        } else if g.text.chars().any(char::is_control) {
+            " ".to_string()
+        } else if g.rtl {
+            mirror_bracket(g.text).to_string()
+        } else {
+            g.text.to_string()
+        };
+        push_span(&mut out, text, style);
+    }
+
+    // Selection running past the end of the line (the newline itself)
+    if let Some((_, to)) = selection {
+        if to > display_width(line) && col >= scroll_x && col < view_end {
+            push_span(&mut out, " ".to_string(), AlrajhiTheme::text_selection());
+        }
+    }
+
+    Line::from(out)
+}
+
+/// Append text, merging it into the previous span when the style matches
+fn push_span(spans: &mut Vec<Span<'static>>, text: String, style: Style) {
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.content.to_mut().push_str(&text),
+        _ => spans.push(Span::styled(text, style)),
+    }
+}
+
+/// Syntax-highlight one full line of SQL
+fn highlight_sql_line(line_content: &str) -> Vec<Span<'static>> {
     let keywords = [
         "SELECT", "FROM", "WHERE", "AND", "OR", "NOT", "IN", "LIKE", "BETWEEN",
         "ORDER", "BY", "ASC", "DESC", "GROUP", "HAVING", "JOIN", "INNER", "LEFT",
@@ -881,89 +1518,70 @@ fn highlight_sql_with_scroll(
         "SUM", "AVG", "MIN", "MAX", "CAST", "CONVERT", "COALESCE", "ISNULL",
     ];
 
-    let source_lines: Vec<&str> = sql.split('\n').collect();
-    let mut lines: Vec<Line> = Vec::new();
-
-    for (line_idx, line_content) in source_lines.iter().enumerate().skip(scroll_y).take(visible_height) {
-        // Apply horizontal scroll
-        let display_content: String = line_content
-            .chars()
-            .skip(scroll_x)
-            .take(visible_width)
-            .collect();
-
-        let mut spans: Vec<Span> = Vec::new();
-        let mut current_word = String::new();
-        let mut in_string = false;
-        let mut string_char = ' ';
+    let mut spans: Vec<Span> = Vec::new();
+    let mut current_word = String::new();
+    let mut in_string = false;
+    let mut string_char = ' ';
 
-        let chars: Vec<char> = display_content.chars().collect();
-        let mut i = 0;
+    let chars: Vec<char> = line_content.chars().collect();
+    let mut i = 0;
 
-        while i < chars.len() {
-            let c = chars[i];
-
-            // Check for line comment
-            if !in_string && i + 1 < chars.len() && chars[i] == '-' && chars[i + 1] == '-' {
-                if !current_word.is_empty() {
-                    spans.push(colorize_word(&current_word, &keywords));
-                    current_word.clear();
-                }
-                let comment: String = chars[i..].iter().collect();
-                spans.push(Span::styled(comment, Style::default().fg(AlrajhiTheme::COMMENT)));
-                break;
-            }
+    while i < chars.len() {
+        let c = chars[i];
 
-            // Handle strings
-            if (c == '\'' || c == '"') && !in_string {
-                if !current_word.is_empty() {
-                    spans.push(colorize_word(&current_word, &keywords));
-                    current_word.clear();
-                }
-                in_string = true;
-                string_char = c;
-                current_word.push(c);
-            } else if in_string && c == string_char {
-                current_word.push(c);
-                spans.push(Span::styled(
-                    current_word.clone(),
-                    Style::default().fg(AlrajhiTheme::STRING),
-                ));
+        // Check for line comment
+        if !in_string && i + 1 < chars.len() && chars[i] == '-' && chars[i + 1] == '-' {
+            if !current_word.is_empty() {
+                spans.push(colorize_word(&current_word, &keywords));
                 current_word.clear();
-                in_string = false;
-            } else if in_string {
-                current_word.push(c);
-            } else if c.is_whitespace() || "(),;.=<>+-*/[]".contains(c) {
-                if !current_word.is_empty() {
-                    spans.push(colorize_word(&current_word, &keywords));
-                    current_word.clear();
-                }
-                spans.push(Span::styled(
-                    c.to_string(),
-                    Style::default().fg(AlrajhiTheme::OPERATOR),
-                ));
-            } else {
-                current_word.push(c);
             }
-
-            i += 1;
+            let comment: String = chars[i..].iter().collect();
+            spans.push(Span::styled(comment, Style::default().fg(AlrajhiTheme::COMMENT)));
+            break;
         }
 
-        if !current_word.is_empty() {
-            if in_string {
-                spans.push(Span::styled(current_word, Style::default().fg(AlrajhiTheme::STRING)));
-            } else {
+        // Handle strings
+        if (c == '\'' || c == '"') && !in_string {
+            if !current_word.is_empty() {
+                spans.push(colorize_word(&current_word, &keywords));
+                current_word.clear();
+            }
+            in_string = true;
+            string_char = c;
+            current_word.push(c);
+        } else if in_string && c == string_char {
+            current_word.push(c);
+            spans.push(Span::styled(
+                current_word.clone(),
+                Style::default().fg(AlrajhiTheme::STRING),
+            ));
+            current_word.clear();
+            in_string = false;
+        } else if in_string {
+            current_word.push(c);
+        } else if c.is_whitespace() || "(),;.=<>+-*/[]".contains(c) {
+            if !current_word.is_empty() {
                 spans.push(colorize_word(&current_word, &keywords));
+                current_word.clear();
             }
+            spans.push(Span::styled(
+                c.to_string(),
+                Style::default().fg(AlrajhiTheme::OPERATOR),
+            ));
+        } else {
+            current_word.push(c);
         }
 
-        lines.push(Line::from(spans));
+        i += 1;
     }
 
-    // Pad with empty lines if needed
-    while lines.len() < visible_height {
-        lines.push(Line::from(""));
+    if !current_word.is_empty() {
+        if in_string {
+            spans.push(Span::styled(current_word, Style::default().fg(AlrajhiTheme::STRING)));
+        } else {
+            spans.push(colorize_word(&current_word, &keywords));
+        }
     }
 
-    lines
+    spans
 }
@@ -1,5 +1,9 @@
 //! Utility functions
 
+mod text;
+
+pub use text::*;
+
 use std::time::Duration;
 
 /// Format duration for display
@@ -19,10 +23,10 @@ pub fn format_duration(d: Duration) -> String {
 
 /// Truncate string with ellipsis
 pub fn truncate(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
+    if display_width(s) <= max_len {
         s.to_string()
     } else {
-        format!("{}...", &s[..max_len.saturating_sub(3)])
+        format!("{}...", truncate_to_width(s, max_len.saturating_sub(3)))
     }
 }
 
@@ -0,0 +1,143 @@
+//! Unicode-aware text measurement and right-to-left display ordering
+
+use unicode_bidi::{bidi_class, BidiClass, ParagraphBidiInfo};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Terminal columns taken by one grapheme; control chars (tabs) are drawn as one blank
+pub fn grapheme_width(grapheme: &str) -> usize {
+    if grapheme.chars().any(|c| c.is_control()) {
+        1
+    } else {
+        grapheme.width()
+    }
+}
+
+/// Terminal columns taken by a string
+pub fn display_width(s: &str) -> usize {
+    s.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Longest prefix of whole graphemes that fits in `max` columns
+pub fn truncate_to_width(s: &str, max: usize) -> String {
+    let mut width = 0;
+    let mut end = 0;
+    for (i, g) in s.grapheme_indices(true) {
+        let w = grapheme_width(g);
+        if width + w > max {
+            break;
+        }
+        width += w;
+        end = i + g.len();
+    }
+    s[..end].to_string()
+}
+
+/// Truncate to `max` columns, marking cut text with '…'
+pub fn truncate_with_ellipsis(s: &str, max: usize) -> String {
+    if display_width(s) <= max {
+        s.to_string()
+    } else {
+        format!("{}…", truncate_to_width(s, max.saturating_sub(1)))
+    }
+}
+
+/// Whether the text contains any right-to-left characters
+pub fn contains_rtl(s: &str) -> bool {
+    s.chars().any(|c| {
+        matches!(
+            bidi_class(c),
+            BidiClass::R | BidiClass::AL | BidiClass::RLE | BidiClass::RLO | BidiClass::RLI
+        )
+    })
+}
+
+/// A grapheme placed at its on-screen position
+#[derive(Clone, Debug)]
+pub struct VisualGrapheme<'a> {
+    /// Index of the grapheme in logical order
+    pub index: usize,
+    /// Byte offset in the line
+    pub byte: usize,
+    pub text: &'a str,
+    pub width: usize,
+    /// Part of a right-to-left run
+    pub rtl: bool,
+}
+
+/// Graphemes of a single line in display (left-to-right screen) order
+pub fn visual_graphemes(line: &str) -> Vec<VisualGrapheme<'_>> {
+    let graphemes: Vec<(usize, &str)> = line.grapheme_indices(true).collect();
+    let make = |index: usize, rtl: bool| {
+        let (byte, text) = graphemes[index];
+        VisualGrapheme { index, byte, text, width: grapheme_width(text), rtl }
+    };
+
+    if !contains_rtl(line) {
+        return (0..graphemes.len()).map(|i| make(i, false)).collect();
+    }
+
+    let info = ParagraphBidiInfo::new(line, None);
+    let byte_levels = info.reordered_levels(0..line.len());
+    let levels: Vec<_> = graphemes.iter().map(|(byte, _)| byte_levels[*byte]).collect();
+    ParagraphBidiInfo::reorder_visual(&levels)
+        .into_iter()
+        .map(|i| make(i, levels[i].is_rtl()))
+        .collect()
+}
+
+/// Screen column of the cursor when it sits before byte `pos` of a line
+pub fn visual_col(line: &str, pos: usize) -> usize {
+    let graphemes = visual_graphemes(line);
+    let mut col = 0;
+    let mut last: Option<(usize, usize, bool)> = None; // (logical index, start col, rtl)
+
+    for g in &graphemes {
+        if g.byte == pos {
+            // The caret before an RTL grapheme sits on its right edge
+            return if g.rtl { col + g.width } else { col };
+        }
+        if last.map(|(index, _, _)| g.index > index).unwrap_or(true) {
+            last = Some((g.index, col, g.rtl));
+        }
+        col += g.width;
+    }
+
+    // End of line: after the last logical grapheme
+    match last {
+        Some((_, start, true)) => start,
+        _ => col,
+    }
+}
+
+/// Swap paired brackets, as RTL runs show them mirrored
+pub fn mirror_bracket(grapheme: &str) -> &str {
+    match grapheme {
+        "(" => ")",
+        ")" => "(",
+        "[" => "]",
+        "]" => "[",
+        "{" => "}",
+        "}" => "{",
+        "<" => ">",
+        ">" => "<",
+        other => other,
+    }
+}
+
+/// Reorder a line of text for display on a terminal that has no bidi support
+pub fn to_visual(text: &str) -> String {
+    if !contains_rtl(text) {
+        return text.to_string();
+    }
+
+    text.split('\n')
+        .map(|line| {
+            visual_graphemes(line)
+                .iter()
+                .map(|g| if g.rtl { mirror_bracket(g.text) } else { g.text })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}